@@ -14,6 +14,28 @@ fn main() -> Result<()> {
 		assemble_x86_64_smp_boot()?;
 	}
 
+	if env::var_os("CARGO_FEATURE_INITRAMFS").is_some() {
+		copy_initramfs()?;
+	}
+
+	Ok(())
+}
+
+/// Copies the archive `HERMIT_INITRAMFS` to `OUT_DIR`, where it is linked into the kernel.
+fn copy_initramfs() -> Result<()> {
+	let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+	let initramfs = out_dir.join("initramfs");
+
+	println!("cargo:rerun-if-env-changed=HERMIT_INITRAMFS");
+	if let Some(path) = env::var_os("HERMIT_INITRAMFS") {
+		println!("cargo:rerun-if-changed={}", Path::new(&path).display());
+		fs::copy(&path, &initramfs)
+			.with_context(|| format!("Unable to copy initramfs {}", Path::new(&path).display()))?;
+	} else {
+		println!("cargo:warning=HERMIT_INITRAMFS is not set, the initramfs is empty");
+		File::create(&initramfs)?;
+	}
+
 	Ok(())
 }
 
@@ -185,7 +185,12 @@ pub(crate) extern "C" fn do_sync(state: &State) {
 			/* read far_el1 register, which holds the faulting virtual address */
 			let far = FAR_EL1.get();
 
-			// add page fault handler
+			/* WnR bit is set, if the abort was caused by a write */
+			#[cfg(feature = "mmap")]
+			if crate::syscalls::handle_page_fault(far as usize, (iss & (1 << 6)) != 0) {
+				GicV3::end_interrupt(irqid);
+				return;
+			}
 
 			error!("Current stack pointer {state:p}");
 			error!("Unable to handle page fault at {:#x}", far);
@@ -350,7 +355,8 @@ pub(crate) fn add_irq_name(irq_number: u8, name: &'static str) {
 	IRQ_NAMES.lock().insert(SPI_START + irq_number, name);
 }
 
-fn get_irq_name(irq_number: u8) -> Option<&'static str> {
+/// Returns the name of the interrupt `irq_number`, if a name was registered
+pub(crate) fn get_irq_name(irq_number: u8) -> Option<&'static str> {
 	IRQ_NAMES.lock().get(&irq_number).copied()
 }
 
@@ -375,6 +381,19 @@ impl IrqStatistics {
 	}
 }
 
+/// Returns a snapshot of the interrupt counters of all cores
+pub(crate) fn irq_counters() -> Vec<(CoreId, [u64; 256])> {
+	IRQ_COUNTERS
+		.lock()
+		.iter()
+		.map(|(core_id, irq_statistics)| {
+			let counters =
+				core::array::from_fn(|i| irq_statistics.counters[i].load(Ordering::Relaxed));
+			(*core_id, counters)
+		})
+		.collect()
+}
+
 pub(crate) fn print_statistics() {
 	info!("Number of interrupts");
 	for (core_id, irg_statistics) in IRQ_COUNTERS.lock().iter() {
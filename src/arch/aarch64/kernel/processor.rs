@@ -254,6 +254,24 @@ pub fn set_oneshot_timer(wakeup_time: Option<u64>) {
 	});
 }
 
+/// Writes the description of a core in the format of `/proc/cpuinfo`
+pub(crate) fn write_cpuinfo(f: &mut impl fmt::Write) -> fmt::Result {
+	let dtb = unsafe {
+		Dtb::from_raw(core::ptr::with_exposed_provenance(
+			env::boot_info().hardware_info.device_tree.unwrap().get() as usize,
+		))
+		.expect(".dtb file has invalid header")
+	};
+
+	let compatible = dtb
+		.get_property("/cpus/cpu@0", "compatible")
+		.and_then(|compatible| str::from_utf8(compatible).ok())
+		.unwrap_or("unknown");
+
+	writeln!(f, "model name\t: {}", compatible.trim_end_matches('\0'))?;
+	writeln!(f, "timer MHz\t: {}", get_frequency())
+}
+
 pub fn print_information() {
 	let dtb = unsafe {
 		Dtb::from_raw(core::ptr::with_exposed_provenance(
@@ -9,6 +9,7 @@ use core::sync::atomic::Ordering;
 use hermit_sync::InterruptTicketMutex;
 
 use crate::arch::riscv64::kernel::CPU_ONLINE;
+use crate::arch::riscv64::kernel::interrupts::{IRQ_COUNTERS, IrqStatistics};
 use crate::executor::task::AsyncTask;
 #[cfg(feature = "smp")]
 use crate::scheduler::SchedulerInput;
@@ -21,6 +22,8 @@ pub struct CoreLocal {
 	scheduler: Cell<*mut PerCoreScheduler>,
 	/// start address of the kernel stack
 	pub kernel_stack: Cell<u64>,
+	/// Interface to the interrupt counters
+	irq_statistics: &'static IrqStatistics,
 	/// Queue of async tasks
 	async_tasks: RefCell<Vec<AsyncTask>>,
 	/// Queues to handle incoming requests from the other cores
@@ -37,10 +40,18 @@ impl CoreLocal {
 
 			let core_id = CPU_ONLINE.load(Ordering::Relaxed);
 
+			let irq_statistics = if core_id == 0 {
+				static FIRST_IRQ_STATISTICS: IrqStatistics = IrqStatistics::new();
+				&FIRST_IRQ_STATISTICS
+			} else {
+				&*Box::leak(Box::new(IrqStatistics::new()))
+			};
+
 			let this = Self {
 				core_id,
 				scheduler: Cell::new(ptr::null_mut()),
 				kernel_stack: Cell::new(0),
+				irq_statistics,
 				async_tasks: RefCell::new(Vec::new()),
 				#[cfg(feature = "smp")]
 				scheduler_input: InterruptTicketMutex::new(SchedulerInput::new()),
@@ -51,6 +62,7 @@ impl CoreLocal {
 				}
 				FIRST_CORE_LOCAL.take().unwrap().insert(this)
 			} else {
+				this.add_irq_counter();
 				Box::leak(Box::new(this))
 			};
 
@@ -67,6 +79,12 @@ impl CoreLocal {
 			&*raw
 		}
 	}
+
+	pub fn add_irq_counter(&self) {
+		IRQ_COUNTERS
+			.lock()
+			.insert(self.core_id, self.irq_statistics);
+	}
 }
 
 #[inline]
@@ -87,3 +105,7 @@ pub fn set_core_scheduler(scheduler: *mut PerCoreScheduler) {
 pub(crate) fn async_tasks() -> RefMut<'static, Vec<AsyncTask>> {
 	CoreLocal::get().async_tasks.borrow_mut()
 }
+
+pub(crate) fn increment_irq_counter(irq_no: u8) {
+	CoreLocal::get().irq_statistics.inc(irq_no);
+}
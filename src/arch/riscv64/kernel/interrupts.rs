@@ -1,19 +1,31 @@
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
 
 use ahash::RandomState;
 use hashbrown::HashMap;
-use hermit_sync::{InterruptTicketMutex, OnceCell, SpinMutex};
+use hermit_sync::{InterruptSpinMutex, InterruptTicketMutex, OnceCell, SpinMutex};
 use riscv::asm::wfi;
 use riscv::interrupt::{Exception, Interrupt, Trap};
 use riscv::register::{scause, sie, sip, sstatus, stval};
 use trapframe::TrapFrame;
 
+use crate::arch::riscv64::kernel::core_local::increment_irq_counter;
 use crate::drivers::InterruptHandlerQueue;
 #[cfg(not(feature = "pci"))]
 use crate::drivers::mmio::get_interrupt_handlers;
 #[cfg(feature = "pci")]
 use crate::drivers::pci::get_interrupt_handlers;
-use crate::scheduler;
+use crate::scheduler::{self, CoreId};
+
+/// Interrupt number of the timer interrupt. The PLIC numbers the external
+/// interrupts from 1, so the interrupts of the scheduler are counted at the
+/// end of the range.
+pub(crate) const TIMER_INTERRUPT_NUMBER: u8 = 255;
+
+/// Interrupt number of the wakeup interrupt
+#[cfg(feature = "smp")]
+pub(crate) const WAKEUP_INTERRUPT_NUMBER: u8 = 254;
 
 /// base address of the PLIC, only one access at the same time is allowed
 static PLIC_BASE: SpinMutex<usize> = SpinMutex::new(0x0);
@@ -35,6 +47,10 @@ pub(crate) fn install() {
 		// Enable external interrupts
 		sie::set_sext();
 	}
+
+	add_irq_name(TIMER_INTERRUPT_NUMBER, "Timer");
+	#[cfg(feature = "smp")]
+	add_irq_name(WAKEUP_INTERRUPT_NUMBER, "Wakeup");
 }
 
 /// Init PLIC
@@ -172,6 +188,12 @@ pub extern "C" fn trap_handler(tf: &mut TrapFrame) {
 		Trap::Interrupt(Interrupt::SupervisorTimer) => {
 			crate::arch::riscv64::kernel::scheduler::timer_handler();
 		}
+		#[cfg(feature = "mmap")]
+		Trap::Exception(exception @ (Exception::LoadPageFault | Exception::StorePageFault))
+			if crate::syscalls::handle_page_fault(
+				stval,
+				matches!(exception, Exception::StorePageFault),
+			) => {}
 		cause => {
 			error!("Interrupt: {cause:?}");
 			error!("tf = {tf:x?} ");
@@ -207,8 +229,10 @@ fn external_handler() {
 		}
 
 		// Call handler
+		let irq = u8::try_from(irq).unwrap();
+		increment_irq_counter(irq);
 		if let Some(handlers) = INTERRUPT_HANDLERS.get() {
-			if let Some(queue) = handlers.get(&u8::try_from(irq).unwrap()) {
+			if let Some(queue) = handlers.get(&irq) {
 				for handler in queue.iter() {
 					handler();
 				}
@@ -239,4 +263,60 @@ fn external_eoi() {
 	}
 }
 
-pub(crate) fn print_statistics() {}
+/// Returns the name of the interrupt `irq_number`, if a name was registered
+pub(crate) fn get_irq_name(irq_number: u8) -> Option<&'static str> {
+	IRQ_NAMES.lock().get(&irq_number).copied()
+}
+
+pub(crate) static IRQ_COUNTERS: InterruptSpinMutex<BTreeMap<CoreId, &IrqStatistics>> =
+	InterruptSpinMutex::new(BTreeMap::new());
+
+pub(crate) struct IrqStatistics {
+	pub counters: [AtomicU64; 256],
+}
+
+impl IrqStatistics {
+	pub const fn new() -> Self {
+		#[allow(clippy::declare_interior_mutable_const)]
+		const NEW_COUNTER: AtomicU64 = AtomicU64::new(0);
+		IrqStatistics {
+			counters: [NEW_COUNTER; 256],
+		}
+	}
+
+	pub fn inc(&self, pos: u8) {
+		self.counters[usize::from(pos)].fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+/// Returns a snapshot of the interrupt counters of all cores
+pub(crate) fn irq_counters() -> Vec<(CoreId, [u64; 256])> {
+	IRQ_COUNTERS
+		.lock()
+		.iter()
+		.map(|(core_id, irq_statistics)| {
+			let counters =
+				core::array::from_fn(|i| irq_statistics.counters[i].load(Ordering::Relaxed));
+			(*core_id, counters)
+		})
+		.collect()
+}
+
+pub(crate) fn print_statistics() {
+	info!("Number of interrupts");
+	for (core_id, irg_statistics) in IRQ_COUNTERS.lock().iter() {
+		for (i, counter) in irg_statistics.counters.iter().enumerate() {
+			let counter = counter.load(Ordering::Relaxed);
+			if counter > 0 {
+				match get_irq_name(i.try_into().unwrap()) {
+					Some(name) => {
+						info!("[{core_id}][{name}]: {counter}");
+					}
+					_ => {
+						info!("[{core_id}][{i}]: {counter}");
+					}
+				}
+			}
+		}
+	}
+}
@@ -147,6 +147,7 @@ pub fn boot_processor_init() {
 	devicetree::init();
 	crate::mm::init();
 	crate::mm::print_information();
+	CoreLocal::get().add_irq_counter();
 	env::init();
 	interrupts::install();
 
@@ -1,5 +1,6 @@
 use core::arch::asm;
 use core::convert::TryInto;
+use core::fmt;
 use core::num::NonZeroU64;
 
 use riscv::register::{sie, sstatus, time};
@@ -242,6 +243,12 @@ pub fn shutdown(error_code: i32) -> ! {
 	}
 }
 
+/// Writes the description of a core in the format of `/proc/cpuinfo`
+pub(crate) fn write_cpuinfo(f: &mut impl fmt::Write) -> fmt::Result {
+	writeln!(f, "isa\t\t: rv64gc")?;
+	writeln!(f, "timebase\t: {}", get_timebase_freq())
+}
+
 pub fn get_timer_ticks() -> u64 {
 	// We simulate a timer with a 1 microsecond resolution by taking the CPU timestamp
 	// and dividing it by the CPU frequency in MHz.
@@ -9,7 +9,10 @@ use core::{mem, ptr};
 use align_address::Align;
 use memory_addresses::{PhysAddr, VirtAddr};
 
-use crate::arch::riscv64::kernel::core_local::core_scheduler;
+use crate::arch::riscv64::kernel::core_local::{core_scheduler, increment_irq_counter};
+use crate::arch::riscv64::kernel::interrupts::TIMER_INTERRUPT_NUMBER;
+#[cfg(feature = "smp")]
+use crate::arch::riscv64::kernel::interrupts::WAKEUP_INTERRUPT_NUMBER;
 use crate::arch::riscv64::kernel::processor::set_oneshot_timer;
 use crate::arch::riscv64::mm::paging::{BasePageSize, PageSize, PageTableEntryFlags};
 #[cfg(not(feature = "common-os"))]
@@ -425,7 +428,7 @@ unsafe extern "C" {
 }
 
 pub fn timer_handler() {
-	//increment_irq_counter(apic::TIMER_INTERRUPT_NUMBER.into());
+	increment_irq_counter(TIMER_INTERRUPT_NUMBER);
 	core_scheduler().handle_waiting_tasks();
 	set_oneshot_timer(None);
 	core_scheduler().scheduler();
@@ -434,7 +437,7 @@ pub fn timer_handler() {
 #[cfg(feature = "smp")]
 pub fn wakeup_handler() {
 	debug!("Received Wakeup Interrupt");
-	//increment_irq_counter(WAKEUP_INTERRUPT_NUMBER.into());
+	increment_irq_counter(WAKEUP_INTERRUPT_NUMBER);
 	let core_scheduler = core_scheduler();
 	core_scheduler.check_input();
 	unsafe {
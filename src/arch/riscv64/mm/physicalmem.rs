@@ -34,12 +34,20 @@ fn detect_from_limits() -> Result<(), ()> {
 
 pub fn init() {
 	detect_from_limits().unwrap();
+
+	// the initial RAM disk of the loader must not be used as free memory
+	mm::reserve_initrd();
 }
 
 pub fn total_memory_size() -> usize {
 	TOTAL_MEMORY.load(Ordering::Relaxed)
 }
 
+/// Returns the size of the unused physical memory in bytes
+pub fn free_memory_size() -> usize {
+	PHYSICAL_FREE_LIST.lock().free_space()
+}
+
 pub fn allocate(size: usize) -> Result<PhysAddr, AllocError> {
 	assert!(size > 0);
 	assert_eq!(
@@ -113,6 +121,28 @@ pub fn deallocate(physical_address: PhysAddr, size: usize) {
 	}
 }
 
+pub fn reserve(physical_address: PhysAddr, size: usize) {
+	assert_eq!(
+		physical_address.as_usize() % BasePageSize::SIZE as usize,
+		0,
+		"Physical address {physical_address:#X} is not a multiple of {:#X}",
+		BasePageSize::SIZE
+	);
+	assert!(size > 0);
+	assert_eq!(
+		size % BasePageSize::SIZE as usize,
+		0,
+		"Size {:#X} is not a multiple of {:#X}",
+		size,
+		BasePageSize::SIZE
+	);
+
+	let range = PageRange::from_start_len(physical_address.as_usize(), size).unwrap();
+
+	// the range might be already outside of the free memory
+	PHYSICAL_FREE_LIST.lock().allocate_at(range).ok();
+}
+
 pub fn print_information() {
 	let free_list = PHYSICAL_FREE_LIST.lock();
 	info!("Physical memory free list:\n{free_list}");
@@ -1,4 +1,5 @@
 use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
 use core::arch::asm;
 use core::sync::atomic::{AtomicU64, Ordering};
 
@@ -346,7 +347,8 @@ pub(crate) fn add_irq_name(irq_number: u8, name: &'static str) {
 	IRQ_NAMES.lock().insert(32 + irq_number, name);
 }
 
-fn get_irq_name(irq_number: u8) -> Option<&'static str> {
+/// Returns the name of the interrupt `irq_number`, if a name was registered
+pub(crate) fn get_irq_name(irq_number: u8) -> Option<&'static str> {
 	IRQ_NAMES.lock().get(&irq_number).copied()
 }
 
@@ -371,6 +373,19 @@ impl IrqStatistics {
 	}
 }
 
+/// Returns a snapshot of the interrupt counters of all cores
+pub(crate) fn irq_counters() -> Vec<(CoreId, [u64; 256])> {
+	IRQ_COUNTERS
+		.lock()
+		.iter()
+		.map(|(core_id, irq_statistics)| {
+			let counters =
+				core::array::from_fn(|i| irq_statistics.counters[i].load(Ordering::Relaxed));
+			(*core_id, counters)
+		})
+		.collect()
+}
+
 pub(crate) fn print_statistics() {
 	panic_println!("Number of interrupts");
 	for (core_id, irg_statistics) in IRQ_COUNTERS.lock().iter() {
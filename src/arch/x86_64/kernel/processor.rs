@@ -986,6 +986,28 @@ pub fn print_information() {
 	infofooter!();
 }
 
+/// Writes the description of a core in the format of `/proc/cpuinfo`
+pub(crate) fn write_cpuinfo(f: &mut impl fmt::Write) -> fmt::Result {
+	let cpuid = CpuId::new();
+
+	if let Some(vendor_info) = cpuid.get_vendor_info() {
+		writeln!(f, "vendor_id\t: {}", vendor_info.as_str())?;
+	}
+	if let Some(brand_string) = cpuid.get_processor_brand_string() {
+		writeln!(f, "model name\t: {}", brand_string.as_str().trim())?;
+	}
+	writeln!(f, "cpu MHz\t\t: {}.000", get_frequency())?;
+
+	let flags = format!("{}", CpuFeaturePrinter::new(&cpuid)).to_lowercase();
+	writeln!(f, "flags\t\t: {}", flags.trim_end())?;
+	writeln!(
+		f,
+		"address sizes\t: {} bits physical, {} bits virtual",
+		get_physical_address_bits(),
+		get_linear_address_bits()
+	)
+}
+
 pub fn seed_entropy() -> Option<[u8; 32]> {
 	let mut buf = [0; 32];
 	if FEATURES.supports_rdseed {
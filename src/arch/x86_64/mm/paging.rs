@@ -292,6 +292,14 @@ pub(crate) extern "x86-interrupt" fn page_fault_handler(
 	stack_frame: ExceptionStackFrame,
 	error_code: PageFaultErrorCode,
 ) {
+	#[cfg(feature = "mmap")]
+	if crate::syscalls::handle_page_fault(
+		Cr2::read().unwrap().as_u64() as usize,
+		error_code.contains(PageFaultErrorCode::CAUSED_BY_WRITE),
+	) {
+		return;
+	}
+
 	error!("Page fault (#PF)!");
 	error!("page_fault_linear_address = {:p}", Cr2::read().unwrap());
 	error!("error_code = {error_code:?}");
@@ -311,6 +319,20 @@ pub(crate) extern "x86-interrupt" fn page_fault_handler(
 			core::arch::asm!("swapgs", options(nostack));
 		}
 	}
+
+	#[cfg(feature = "mmap")]
+	if crate::syscalls::handle_page_fault(
+		Cr2::read().unwrap().as_u64() as usize,
+		error_code.contains(PageFaultErrorCode::CAUSED_BY_WRITE),
+	) {
+		unsafe {
+			if stack_frame.as_mut().read().code_segment != SegmentSelector(0x08) {
+				core::arch::asm!("swapgs", options(nostack));
+			}
+		}
+		return;
+	}
+
 	error!("Page fault (#PF)!");
 	error!("page_fault_linear_address = {:p}", Cr2::read().unwrap());
 	error!("error_code = {error_code:?}");
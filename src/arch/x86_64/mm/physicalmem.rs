@@ -159,12 +159,20 @@ pub fn init() {
 		.or_else(|_e| detect_from_multiboot_info())
 		.or_else(|_e| detect_from_uhyve())
 		.unwrap();
+
+	// the initial RAM disk of the loader must not be used as free memory
+	mm::reserve_initrd();
 }
 
 pub fn total_memory_size() -> usize {
 	TOTAL_MEMORY.load(Ordering::Relaxed)
 }
 
+/// Returns the size of the unused physical memory in bytes
+pub fn free_memory_size() -> usize {
+	PHYSICAL_FREE_LIST.lock().free_space()
+}
+
 pub fn allocate(size: usize) -> Result<PhysAddr, AllocError> {
 	assert!(size > 0);
 	assert_eq!(
@@ -234,8 +242,6 @@ pub fn deallocate(physical_address: PhysAddr, size: usize) {
 	}
 }
 
-#[allow(dead_code)]
-#[cfg(not(feature = "pci"))]
 pub fn reserve(physical_address: PhysAddr, size: usize) {
 	use align_address::Align;
 	assert!(
@@ -3,6 +3,7 @@ use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::str;
 
+use memory_addresses::VirtAddr;
 use pci_types::InterruptLine;
 use virtio::FeatureBits;
 use virtio::fs::ConfigVolatileFieldAccess;
@@ -24,6 +25,9 @@ use crate::drivers::virtio::virtqueue::{
 use crate::fs::fuse::{self, FuseInterface, Rsp, RspHeader};
 use crate::mm::device_alloc::DeviceAlloc;
 
+/// Id of the shared memory region, which is used as DAX window
+pub(crate) const VIRTIO_FS_SHMCAP_ID_CACHE: u8 = 0;
+
 /// A wrapper struct for the raw configuration structure.
 /// Handling the right access to fields, as some are read-only
 /// for the driver.
@@ -45,6 +49,8 @@ pub(crate) struct VirtioFsDriver {
 	pub(super) notif_cfg: NotifCfg,
 	pub(super) vqueues: Vec<Box<dyn Virtq>>,
 	pub(super) irq: InterruptLine,
+	/// Address and length of the DAX window, in which the device maps files
+	pub(super) cache_window: Option<(VirtAddr, usize)>,
 }
 
 // Backend-independent interface for Virtio network driver
@@ -59,6 +65,11 @@ impl VirtioFsDriver {
 		self.com_cfg.set_failed();
 	}
 
+	/// Returns the address and the length of the DAX window, if the device provides one
+	pub fn cache_window(&self) -> Option<(VirtAddr, usize)> {
+		self.cache_window
+	}
+
 	/// Negotiates a subset of features, understood and wanted by both the OS
 	/// and the device.
 	fn negotiate_features(&mut self, driver_features: virtio::fs::F) -> Result<(), VirtioFsError> {
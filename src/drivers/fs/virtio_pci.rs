@@ -1,9 +1,10 @@
 use alloc::vec::Vec;
 
+use memory_addresses::VirtAddr;
 use volatile::VolatileRef;
 
 use crate::arch::pci::PciConfigRegion;
-use crate::drivers::fs::virtio_fs::{FsDevCfg, VirtioFsDriver};
+use crate::drivers::fs::virtio_fs::{FsDevCfg, VIRTIO_FS_SHMCAP_ID_CACHE, VirtioFsDriver};
 use crate::drivers::pci::PciDevice;
 use crate::drivers::virtio::error::{self, VirtioError};
 use crate::drivers::virtio::transport::pci;
@@ -34,8 +35,8 @@ impl VirtioFsDriver {
 			com_cfg,
 			notif_cfg,
 			isr_cfg,
+			sh_mem_cfg_list,
 			dev_cfg_list,
-			..
 		} = caps_coll;
 
 		let Some(dev_cfg) = dev_cfg_list.iter().find_map(VirtioFsDriver::map_cfg) else {
@@ -43,6 +44,16 @@ impl VirtioFsDriver {
 			return Err(error::VirtioFsError::NoDevCfg(device_id));
 		};
 
+		let cache_window = sh_mem_cfg_list
+			.iter()
+			.find(|sh_mem| sh_mem.id() == VIRTIO_FS_SHMCAP_ID_CACHE)
+			.map(|sh_mem| {
+				(
+					VirtAddr::new(sh_mem.mem_addr()),
+					sh_mem.len().try_into().unwrap(),
+				)
+			});
+
 		Ok(VirtioFsDriver {
 			dev_cfg,
 			com_cfg,
@@ -50,6 +61,7 @@ impl VirtioFsDriver {
 			notif_cfg,
 			vqueues: Vec::new(),
 			irq: device.get_irq().unwrap(),
+			cache_window,
 		})
 	}
 
@@ -507,12 +507,15 @@ pub(crate) fn get_vsock_driver() -> Option<&'static InterruptTicketMutex<VirtioV
 		.find_map(|drv| drv.get_vsock_driver())
 }
 
+/// Returns the drivers of all virtio-fs devices
 #[cfg(feature = "fuse")]
-pub(crate) fn get_filesystem_driver() -> Option<&'static InterruptTicketMutex<VirtioFsDriver>> {
+pub(crate) fn get_filesystem_drivers()
+-> impl Iterator<Item = &'static InterruptTicketMutex<VirtioFsDriver>> {
 	PCI_DRIVERS
-		.get()?
-		.iter()
-		.find_map(|drv| drv.get_filesystem_driver())
+		.get()
+		.into_iter()
+		.flatten()
+		.filter_map(|drv| drv.get_filesystem_driver())
 }
 
 pub(crate) fn init() {
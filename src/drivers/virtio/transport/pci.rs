@@ -567,7 +567,6 @@ impl IsrStatus {
 pub struct ShMemCfg {
 	mem_addr: u64,
 	length: u64,
-	sh_mem: ShMem,
 	/// Shared memory regions are identified via an ID
 	/// See Virtio specification v1.1. - 4.1.4.7
 	id: u8,
@@ -583,67 +582,27 @@ impl ShMemCfg {
 			return None;
 		}
 
-		let offset = cap.cap.offset.to_ne();
-		let length = cap.cap.length.to_ne();
-
-		let virt_addr_raw = cap.bar.mem_addr + offset;
-		let raw_ptr = ptr::with_exposed_provenance_mut::<u8>(virt_addr_raw.try_into().unwrap());
-
-		// Zero initialize shared memory area
-		unsafe {
-			for i in 0..usize::try_from(length).unwrap() {
-				*(raw_ptr.add(i)) = 0;
-			}
-		};
-
-		// Currently in place in order to ensure a safe cast below
-		// "len: cap.bar.length as usize"
-		// In order to remove this assert a safe conversion from
-		// kernel PciBar struct into usize must be made
-		assert!(mem::size_of::<usize>() == 8);
-
+		// The region isn't initialized, because its content is defined by the device.
+		// For instance, the DAX window of virtio-fs isn't backed by memory until
+		// the device maps a file into the window.
 		Some(ShMemCfg {
-			mem_addr: virt_addr_raw,
+			mem_addr: cap.bar.mem_addr + cap.offset(),
 			length: cap.len(),
-			sh_mem: ShMem {
-				ptr: raw_ptr,
-				len: cap.bar.length as usize,
-			},
 			id: cap.cap.id,
 		})
 	}
-}
-
-/// Defines a shared memory locate at location ptr with a length of len.
-/// The shared memories Drop implementation does not dealloc the memory
-/// behind the pointer but sets it to zero, to prevent leakage of data.
-struct ShMem {
-	ptr: *mut u8,
-	len: usize,
-}
-
-impl core::ops::Deref for ShMem {
-	type Target = [u8];
 
-	fn deref(&self) -> &[u8] {
-		unsafe { core::slice::from_raw_parts(self.ptr, self.len) }
+	/// Returns the virtual address of the shared memory region
+	pub fn mem_addr(&self) -> u64 {
+		self.mem_addr
 	}
-}
 
-impl core::ops::DerefMut for ShMem {
-	fn deref_mut(&mut self) -> &mut [u8] {
-		unsafe { core::slice::from_raw_parts_mut(self.ptr, self.len) }
+	pub fn len(&self) -> u64 {
+		self.length
 	}
-}
 
-// Upon drop the shared memory region is "deleted" with zeros.
-impl Drop for ShMem {
-	fn drop(&mut self) {
-		for i in 0..self.len {
-			unsafe {
-				*(self.ptr.add(i)) = 0;
-			}
-		}
+	pub fn id(&self) -> u8 {
+		self.id
 	}
 }
 
@@ -687,7 +646,9 @@ fn read_caps(device: &PciDevice<PciConfigRegion>) -> Result<Vec<PciCap>, PciErro
 		.filter(|cap| cap.cfg_type != CapCfgType::Pci)
 		.map(|cap| {
 			let slot = cap.bar;
-			let (addr, size) = device.memory_map_bar(slot, true).unwrap();
+			// shared memory regions are used like main memory and can be cached
+			let no_cache = cap.cfg_type != CapCfgType::SharedMemory;
+			let (addr, size) = device.memory_map_bar(slot, no_cache).unwrap();
 			PciCap {
 				bar: VirtioPciBar::new(slot, addr.as_u64(), size.try_into().unwrap()),
 				dev_id: device_id,
@@ -32,7 +32,6 @@ pub fn init() {
 
 #[derive(Debug)]
 struct Cli {
-	#[allow(dead_code)]
 	image_path: Option<String>,
 	#[cfg(not(target_arch = "riscv64"))]
 	freq: Option<u16>,
@@ -40,6 +39,8 @@ struct Cli {
 	args: Vec<String>,
 	#[allow(dead_code)]
 	mmio: Vec<String>,
+	#[allow(dead_code)]
+	mounts: Vec<String>,
 }
 
 /// Whether Hermit is running under the "uhyve" hypervisor.
@@ -70,6 +71,17 @@ pub fn rsdp() -> Option<core::num::NonZero<usize>> {
 	core::num::NonZero::new(rsdp)
 }
 
+/// Returns the physical address range of the initial RAM disk, which was
+/// passed by the loader as boot module.
+pub fn initrd() -> Option<core::ops::Range<u64>> {
+	let fdt = fdt()?;
+	let chosen = fdt.find_node("/chosen")?;
+	let start = chosen.property("linux,initrd-start")?.as_usize()?;
+	let end = chosen.property("linux,initrd-end")?.as_usize()?;
+
+	(start < end).then(|| start as u64..end as u64)
+}
+
 pub fn fdt_args() -> Option<&'static str> {
 	fdt().and_then(|fdt| fdt.chosen().bootargs())
 }
@@ -96,6 +108,7 @@ impl Default for Cli {
 
 		let mut args = Vec::new();
 		let mut mmio = Vec::new();
+		let mut mounts = Vec::new();
 		while let Some(word) = words.next() {
 			if word.as_str().starts_with("virtio_mmio.device=") {
 				let v: Vec<&str> = word.as_str().split('=').collect();
@@ -122,8 +135,14 @@ impl Default for Cli {
 					env_vars.insert(String::from("HERMIT_GATEWAY"), gateway);
 				}
 				"-mount" => {
-					let gateway = expect_arg(words.next(), word.as_str());
-					env_vars.insert(String::from("UHYVE_MOUNT"), gateway);
+					let mount = expect_arg(words.next(), word.as_str());
+					// uhyve passes its own mount point, otherwise virtio-fs
+					// devices are mounted with `tag:/path[:ro]`
+					if is_uhyve() {
+						env_vars.insert(String::from("UHYVE_MOUNT"), mount);
+					} else {
+						mounts.push(mount);
+					}
 				}
 				"--" => args.extend(&mut words),
 				word if word.contains('=') => {
@@ -153,6 +172,8 @@ impl Default for Cli {
 			args,
 			#[allow(dead_code)]
 			mmio,
+			#[allow(dead_code)]
+			mounts,
 		}
 	}
 }
@@ -172,11 +193,29 @@ pub fn vars() -> Iter<'static, String, String> {
 	CLI.get().unwrap().env_vars.iter()
 }
 
+/// Returns the name of the application, which is passed as `argv[0]`.
+/// This is the file name of the image path, if the loader passed one.
+pub fn application_name() -> &'static str {
+	CLI.get()
+		.unwrap()
+		.image_path
+		.as_deref()
+		.and_then(|path| path.rsplit('/').next())
+		.filter(|name| !name.is_empty())
+		.unwrap_or("bin")
+}
+
 /// Returns the cmdline argument passed in after "--"
 pub fn args() -> &'static [String] {
 	CLI.get().unwrap().args.as_slice()
 }
 
+/// Returns the mount points of the virtio-fs devices, as given by `-mount tag:/path[:ro]`
+#[allow(dead_code)]
+pub fn mounts() -> &'static [String] {
+	CLI.get().unwrap().mounts.as_slice()
+}
+
 /// Returns the configuration of all mmio devices
 #[allow(dead_code)]
 pub fn mmio() -> &'static [String] {
@@ -0,0 +1,639 @@
+//! Scalable readiness notification, as provided by `epoll`.
+//!
+//! Every entry of the interest list polls its object with an own waker. If
+//! the object wakes the entry, the entry is moved to the ready list, so that
+//! `epoll_wait` only polls the objects on the ready list instead of all
+//! objects. Ready entries stay on the ready list. Level-triggered entries
+//! are reported, while their objects are ready. Edge-triggered entries are
+//! reported, if their objects woke them or if new events became available,
+//! since they were reported last.
+//!
+//! An epoll instance, which is polled as an object, e.g. by the entry of
+//! another instance, keeps the waker of the caller as watcher. In contrast to
+//! the tasks in `epoll_wait`, watchers aren't removed, when the caller stops
+//! polling, but when the entry of the outer instance is removed or closed.
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::{Arc, Weak};
+use alloc::task::Wake;
+use alloc::vec::Vec;
+use core::future::{self, Future};
+use core::mem;
+use core::task::{Context, Poll, Waker};
+
+use async_trait::async_trait;
+use hermit_sync::{InterruptTicketMutex, TicketMutex};
+
+use crate::fd::{FileDescriptor, ObjectInterface, PollEvent};
+use crate::io;
+
+/// Maximum depth of nested epoll instances, as on Linux
+const EP_MAX_NESTS: usize = 4;
+
+bitflags! {
+	/// Flags of `epoll_create1`
+	#[derive(Debug, Copy, Clone, Default)]
+	pub struct EpollFlags: i32 {
+		const EPOLL_CLOEXEC = 0o2_000_000;
+	}
+}
+
+bitflags! {
+	/// Events and input flags of an entry. The events have the values of
+	/// the corresponding events of `poll`.
+	#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+	pub struct EpollEvents: u32 {
+		const EPOLLIN = 0x001;
+		const EPOLLPRI = 0x002;
+		const EPOLLOUT = 0x004;
+		const EPOLLERR = 0x008;
+		const EPOLLHUP = 0x010;
+		const EPOLLRDNORM = 0x040;
+		const EPOLLRDBAND = 0x080;
+		const EPOLLWRNORM = 0x100;
+		const EPOLLWRBAND = 0x200;
+		const EPOLLMSG = 0x400;
+		const EPOLLRDHUP = 0x2000;
+		const EPOLLEXCLUSIVE = 1 << 28;
+		const EPOLLWAKEUP = 1 << 29;
+		const EPOLLONESHOT = 1 << 30;
+		const EPOLLET = 1 << 31;
+	}
+}
+
+impl From<EpollEvents> for PollEvent {
+	fn from(events: EpollEvents) -> Self {
+		PollEvent::from_bits_truncate((events.bits() & 0x7fff) as i16)
+	}
+}
+
+impl From<PollEvent> for EpollEvents {
+	fn from(events: PollEvent) -> Self {
+		EpollEvents::from_bits_truncate(u32::from(events.bits() as u16))
+	}
+}
+
+/// Operations of `epoll_ctl`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
+pub enum EpollOp {
+	Add = 1,
+	Del = 2,
+	Mod = 3,
+}
+
+/// Event of `epoll_ctl` and `epoll_wait`, which is packed on x86_64 as on Linux
+#[cfg_attr(target_arch = "x86_64", repr(C, packed))]
+#[cfg_attr(not(target_arch = "x86_64"), repr(C))]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct EpollEvent {
+	pub events: u32,
+	pub data: u64,
+}
+
+/// An entry is identified by the file descriptor and by the address of the
+/// object, so that a closed descriptor can be reused for another object.
+type Key = (FileDescriptor, usize);
+
+#[derive(Debug)]
+struct Entry {
+	/// The entry is removed, after the object was closed
+	obj: Weak<dyn ObjectInterface>,
+	events: EpollEvents,
+	data: u64,
+	/// `false`, after a one-shot entry was reported
+	armed: bool,
+	/// `true`, while the entry is on the ready list
+	queued: bool,
+	/// `true`, if the object woke the entry, since it was reported last
+	woken: bool,
+	/// Events, which were available, when the entry was reported last
+	reported: EpollEvents,
+	waker: Waker,
+}
+
+#[derive(Debug, Default)]
+struct EpollState {
+	interest: BTreeMap<Key, Entry>,
+	/// Entries, whose objects might be ready
+	ready: VecDeque<Key>,
+	/// Tasks, which wait for an entry on the ready list
+	waiters: Vec<Waker>,
+	/// Wakers of callers of `poll`, which are woken with the waiters
+	watchers: Vec<Waker>,
+}
+
+impl EpollState {
+	/// Moves the entry `key` to the ready list
+	fn enqueue(&mut self, key: Key) {
+		if let Some(entry) = self.interest.get_mut(&key) {
+			if entry.armed && !entry.queued {
+				entry.queued = true;
+				self.ready.push_back(key);
+			}
+		}
+	}
+
+	/// Removes the waiters and the watchers, which have to be woken
+	fn take_waiters(&mut self) -> Vec<Waker> {
+		let mut waiters = mem::take(&mut self.waiters);
+		waiters.append(&mut self.watchers);
+		waiters
+	}
+}
+
+/// Wakes the waiters of an epoll instance, after the lock was released
+fn wake_all(waiters: Vec<Waker>) {
+	for waker in waiters {
+		waker.wake();
+	}
+}
+
+/// Waker of an entry, which moves the entry to the ready list
+struct EntryWaker {
+	state: Weak<InterruptTicketMutex<EpollState>>,
+	key: Key,
+}
+
+impl Wake for EntryWaker {
+	fn wake(self: Arc<Self>) {
+		self.wake_by_ref();
+	}
+
+	fn wake_by_ref(self: &Arc<Self>) {
+		let Some(state) = self.state.upgrade() else {
+			return;
+		};
+
+		let waiters = {
+			let mut guard = state.lock();
+			if let Some(entry) = guard.interest.get_mut(&self.key) {
+				entry.woken = true;
+			}
+			guard.enqueue(self.key);
+			guard.take_waiters()
+		};
+		wake_all(waiters);
+	}
+}
+
+/// Registration of a waiting task, which is removed, when the task stops waiting
+struct Waiter<'a> {
+	state: &'a InterruptTicketMutex<EpollState>,
+	waker: Option<Waker>,
+}
+
+impl Waiter<'_> {
+	fn register(&mut self, waker: &Waker) {
+		let mut guard = self.state.lock();
+		if !guard.waiters.iter().any(|w| w.will_wake(waker)) {
+			guard.waiters.push(waker.clone());
+		}
+		self.waker = Some(waker.clone());
+	}
+}
+
+impl Drop for Waiter<'_> {
+	fn drop(&mut self) {
+		if let Some(waker) = self.waker.take() {
+			self.state.lock().waiters.retain(|w| !w.will_wake(&waker));
+		}
+	}
+}
+
+/// All epoll instances by the addresses of their objects, which are used to
+/// detect loops of nested instances
+static INSTANCES: TicketMutex<BTreeMap<usize, Weak<InterruptTicketMutex<EpollState>>>> =
+	TicketMutex::new(BTreeMap::new());
+
+/// Removes `waker` from the watchers, if the object at `addr` is an epoll instance
+fn unwatch(addr: usize, waker: &Waker) {
+	let state = INSTANCES.lock().get(&addr).and_then(Weak::upgrade);
+	if let Some(state) = state {
+		state.lock().watchers.retain(|w| !w.will_wake(waker));
+	}
+}
+
+/// Fails with `ELOOP`, if the epoll instance `target` can be reached from the
+/// object at `addr` or if the instances are nested too deeply
+fn check_nesting(
+	instances: &BTreeMap<usize, Weak<InterruptTicketMutex<EpollState>>>,
+	addr: usize,
+	target: usize,
+	depth: usize,
+) -> io::Result<()> {
+	let Some(state) = instances.get(&addr).and_then(Weak::upgrade) else {
+		// the object isn't an epoll instance
+		return Ok(());
+	};
+
+	if addr == target || depth >= EP_MAX_NESTS {
+		return Err(io::Error::ELOOP);
+	}
+
+	let children: Vec<usize> = state
+		.lock()
+		.interest
+		.keys()
+		.map(|(_, addr)| *addr)
+		.collect();
+	for child in children {
+		check_nesting(instances, child, target, depth + 1)?;
+	}
+
+	Ok(())
+}
+
+#[derive(Debug)]
+pub(crate) struct Epoll {
+	state: Arc<InterruptTicketMutex<EpollState>>,
+}
+
+impl Epoll {
+	pub fn new(flags: EpollFlags) -> Arc<Self> {
+		debug!("Create epoll instance with {:?}", flags);
+		let epoll = Arc::new(Self {
+			state: Arc::default(),
+		});
+		INSTANCES
+			.lock()
+			.insert(epoll.addr(), Arc::downgrade(&epoll.state));
+
+		epoll
+	}
+
+	fn addr(&self) -> usize {
+		core::ptr::from_ref(self).addr()
+	}
+
+	/// Polls the objects on the ready list and stores their events in
+	/// `events`. Returns the number of stored events. If `consume` isn't set,
+	/// the entries are reported again by the next call.
+	fn collect(&self, events: &mut [EpollEvent], consume: bool) -> usize {
+		let candidates = {
+			let mut guard = self.state.lock();
+			let keys = mem::take(&mut guard.ready);
+			let mut candidates = Vec::with_capacity(keys.len());
+			for key in keys {
+				let Some(entry) = guard.interest.get_mut(&key) else {
+					continue;
+				};
+				entry.queued = false;
+				match entry.obj.upgrade() {
+					Some(obj) if entry.armed => {
+						candidates.push((key, obj, entry.events, entry.waker.clone()));
+					}
+					Some(_) => {}
+					None => {
+						// the object was closed
+						guard.interest.remove(&key);
+					}
+				}
+			}
+			candidates
+		};
+
+		let mut count = 0;
+		for (key, obj, interest, waker) in candidates {
+			if count == events.len() {
+				// the entry is checked by the next call
+				self.state.lock().enqueue(key);
+				continue;
+			}
+
+			// errors and hang-ups are always reported
+			let interest =
+				(interest & !EpollEvents::EPOLLET) | EpollEvents::EPOLLERR | EpollEvents::EPOLLHUP;
+			// the object isn't polled with the lock, because it may wake the entry
+			let available = {
+				let mut cx = Context::from_waker(&waker);
+				let mut pinned = core::pin::pin!(obj.poll(interest.into()));
+				match pinned.as_mut().poll(&mut cx) {
+					Poll::Ready(Ok(available)) => EpollEvents::from(available) & interest,
+					Poll::Ready(Err(_)) => EpollEvents::EPOLLERR,
+					Poll::Pending => EpollEvents::empty(),
+				}
+			};
+
+			let mut guard = self.state.lock();
+			let Some(entry) = guard.interest.get_mut(&key) else {
+				continue;
+			};
+			if available.is_empty() {
+				// the object wakes the entry, when it becomes ready
+				entry.reported = EpollEvents::empty();
+				continue;
+			}
+
+			let report = !entry.events.contains(EpollEvents::EPOLLET)
+				|| entry.woken
+				|| !entry.reported.contains(available);
+			if report {
+				events[count] = EpollEvent {
+					events: available.bits(),
+					data: entry.data,
+				};
+				count += 1;
+
+				if consume {
+					entry.woken = false;
+					entry.reported = available;
+					if entry.events.contains(EpollEvents::EPOLLONESHOT) {
+						entry.armed = false;
+					}
+				}
+			}
+			guard.enqueue(key);
+		}
+
+		count
+	}
+}
+
+impl Drop for Epoll {
+	fn drop(&mut self) {
+		INSTANCES.lock().remove(&self.addr());
+
+		let interest = mem::take(&mut self.state.lock().interest);
+		for ((_, addr), entry) in interest {
+			unwatch(addr, &entry.waker);
+		}
+	}
+}
+
+#[async_trait]
+impl ObjectInterface for Epoll {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		future::poll_fn(|cx| {
+			{
+				let mut guard = self.state.lock();
+				if !guard.watchers.iter().any(|w| w.will_wake(cx.waker())) {
+					guard.watchers.push(cx.waker().clone());
+				}
+			}
+			let mut events = [EpollEvent::default()];
+			let ret = if self.collect(&mut events, false) > 0 {
+				event & (PollEvent::POLLIN | PollEvent::POLLRDNORM)
+			} else {
+				PollEvent::empty()
+			};
+
+			if ret.is_empty() {
+				Poll::Pending
+			} else {
+				Poll::Ready(Ok(ret))
+			}
+		})
+		.await
+	}
+
+	async fn epoll_ctl(
+		&self,
+		op: EpollOp,
+		fd: FileDescriptor,
+		obj: Arc<dyn ObjectInterface>,
+		event: Option<EpollEvent>,
+	) -> io::Result<()> {
+		let addr = Arc::as_ptr(&obj).cast::<()>().addr();
+		let key = (fd, addr);
+
+		let waiters = match op {
+			EpollOp::Add => {
+				let event = event.ok_or(io::Error::EFAULT)?;
+				check_nesting(&INSTANCES.lock(), addr, self.addr(), 0)?;
+
+				let mut guard = self.state.lock();
+				if guard
+					.interest
+					.get(&key)
+					.is_some_and(|entry| entry.obj.strong_count() > 0)
+				{
+					return Err(io::Error::EEXIST);
+				}
+
+				let waker = Waker::from(Arc::new(EntryWaker {
+					state: Arc::downgrade(&self.state),
+					key,
+				}));
+				let entry = Entry {
+					obj: Arc::downgrade(&obj),
+					events: EpollEvents::from_bits_truncate(event.events),
+					data: event.data,
+					armed: true,
+					queued: false,
+					woken: true,
+					reported: EpollEvents::empty(),
+					waker,
+				};
+				guard.interest.insert(key, entry);
+				guard.enqueue(key);
+				guard.take_waiters()
+			}
+			EpollOp::Mod => {
+				let event = event.ok_or(io::Error::EFAULT)?;
+
+				let mut guard = self.state.lock();
+				let entry = guard.interest.get_mut(&key).ok_or(io::Error::ENOENT)?;
+				entry.events = EpollEvents::from_bits_truncate(event.events);
+				entry.data = event.data;
+				entry.armed = true;
+				entry.woken = true;
+				entry.reported = EpollEvents::empty();
+				guard.enqueue(key);
+				guard.take_waiters()
+			}
+			EpollOp::Del => {
+				let mut guard = self.state.lock();
+				let entry = guard.interest.remove(&key).ok_or(io::Error::ENOENT)?;
+				drop(guard);
+				unwatch(addr, &entry.waker);
+				Vec::new()
+			}
+		};
+		wake_all(waiters);
+
+		Ok(())
+	}
+
+	async fn epoll_wait(&self, events: &mut [EpollEvent]) -> io::Result<usize> {
+		let mut waiter = Waiter {
+			state: &self.state,
+			waker: None,
+		};
+
+		future::poll_fn(|cx| {
+			// the task is registered first, so that no wakeup is lost
+			waiter.register(cx.waker());
+			let count = self.collect(events, true);
+			if count > 0 {
+				Poll::Ready(Ok(count))
+			} else {
+				Poll::Pending
+			}
+		})
+		.await
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+
+	/// Object, whose readiness is set by the test. As real objects, it only
+	/// registers the waker, if it isn't ready.
+	#[derive(Debug, Default)]
+	struct Mock {
+		available: TicketMutex<PollEvent>,
+		waker: TicketMutex<Option<Waker>>,
+	}
+
+	impl Mock {
+		fn set(&self, available: PollEvent) {
+			*self.available.lock() = available;
+			let waker = self.waker.lock().take();
+			if let Some(waker) = waker {
+				waker.wake();
+			}
+		}
+	}
+
+	#[async_trait]
+	impl ObjectInterface for Mock {
+		async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+			future::poll_fn(|cx| {
+				let ret = *self.available.lock() & event;
+				if ret.is_empty() {
+					*self.waker.lock() = Some(cx.waker().clone());
+					Poll::Pending
+				} else {
+					Poll::Ready(Ok(ret))
+				}
+			})
+			.await
+		}
+	}
+
+	fn setup(events: EpollEvents) -> (Arc<Epoll>, Arc<Mock>) {
+		let epoll = Epoll::new(EpollFlags::empty());
+		let mock = Arc::new(Mock::default());
+		ctl(&epoll, EpollOp::Add, mock.clone(), events);
+		(epoll, mock)
+	}
+
+	fn ctl(epoll: &Epoll, op: EpollOp, obj: Arc<dyn ObjectInterface>, events: EpollEvents) {
+		let event = EpollEvent {
+			events: events.bits(),
+			data: 42,
+		};
+		let mut future = core::pin::pin!(epoll.epoll_ctl(op, 3, obj, Some(event)));
+		let mut cx = Context::from_waker(Waker::noop());
+		assert!(matches!(future.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+	}
+
+	/// Returns the reported events
+	fn wait(epoll: &Epoll) -> Option<EpollEvents> {
+		let mut events = [EpollEvent::default(); 2];
+		match epoll.collect(&mut events, true) {
+			0 => None,
+			1 => {
+				assert_eq!({ events[0].data }, 42);
+				Some(EpollEvents::from_bits_truncate(events[0].events))
+			}
+			_ => panic!("entry is reported twice"),
+		}
+	}
+
+	#[test]
+	fn level_triggered() {
+		let (epoll, mock) = setup(EpollEvents::EPOLLIN);
+		assert_eq!(wait(&epoll), None);
+
+		mock.set(PollEvent::POLLIN);
+		assert_eq!(wait(&epoll), Some(EpollEvents::EPOLLIN));
+		// the entry is reported, while the object is ready
+		assert_eq!(wait(&epoll), Some(EpollEvents::EPOLLIN));
+
+		mock.set(PollEvent::POLLIN | PollEvent::POLLHUP);
+		assert_eq!(
+			wait(&epoll),
+			Some(EpollEvents::EPOLLIN | EpollEvents::EPOLLHUP)
+		);
+
+		mock.set(PollEvent::empty());
+		assert_eq!(wait(&epoll), None);
+		assert_eq!(wait(&epoll), None);
+	}
+
+	#[test]
+	fn edge_triggered() {
+		let (epoll, mock) = setup(EpollEvents::EPOLLIN | EpollEvents::EPOLLET);
+		assert_eq!(wait(&epoll), None);
+
+		mock.set(PollEvent::POLLIN);
+		// `poll` of the epoll instance doesn't consume the edge
+		let mut events = [EpollEvent::default()];
+		assert_eq!(epoll.collect(&mut events, false), 1);
+		assert_eq!(wait(&epoll), Some(EpollEvents::EPOLLIN));
+		// the object is still ready, but didn't change
+		assert_eq!(wait(&epoll), None);
+
+		// new events are reported
+		mock.set(PollEvent::POLLIN | PollEvent::POLLHUP);
+		assert_eq!(
+			wait(&epoll),
+			Some(EpollEvents::EPOLLIN | EpollEvents::EPOLLHUP)
+		);
+		assert_eq!(wait(&epoll), None);
+
+		// after the object was drained, the next data is reported
+		mock.set(PollEvent::empty());
+		assert_eq!(wait(&epoll), None);
+		mock.set(PollEvent::POLLIN);
+		assert_eq!(wait(&epoll), Some(EpollEvents::EPOLLIN));
+		assert_eq!(wait(&epoll), None);
+	}
+
+	#[test]
+	fn oneshot() {
+		let (epoll, mock) = setup(EpollEvents::EPOLLIN | EpollEvents::EPOLLONESHOT);
+		mock.set(PollEvent::POLLIN);
+		assert_eq!(wait(&epoll), Some(EpollEvents::EPOLLIN));
+		assert_eq!(wait(&epoll), None);
+
+		mock.set(PollEvent::empty());
+		mock.set(PollEvent::POLLIN);
+		assert_eq!(wait(&epoll), None);
+
+		// the entry is armed again by `EPOLL_CTL_MOD`
+		ctl(
+			&epoll,
+			EpollOp::Mod,
+			mock.clone(),
+			EpollEvents::EPOLLIN | EpollEvents::EPOLLONESHOT,
+		);
+		assert_eq!(wait(&epoll), Some(EpollEvents::EPOLLIN));
+		assert_eq!(wait(&epoll), None);
+	}
+
+	#[test]
+	fn nested() {
+		let (inner, mock) = setup(EpollEvents::EPOLLIN);
+		let outer = Epoll::new(EpollFlags::empty());
+		ctl(&outer, EpollOp::Add, inner.clone(), EpollEvents::EPOLLIN);
+		assert_eq!(wait(&outer), None);
+
+		// the inner instance wakes the entry of the outer instance
+		mock.set(PollEvent::POLLIN);
+		assert_eq!(outer.state.lock().ready.len(), 1);
+		assert_eq!(wait(&outer), Some(EpollEvents::EPOLLIN));
+
+		mock.set(PollEvent::empty());
+		assert_eq!(wait(&outer), None);
+		mock.set(PollEvent::POLLIN);
+		assert_eq!(wait(&outer), Some(EpollEvents::EPOLLIN));
+
+		// the watcher is removed with the entry
+		ctl(&outer, EpollOp::Del, inner.clone(), EpollEvents::empty());
+		assert!(inner.state.lock().watchers.is_empty());
+	}
+}
@@ -1,45 +1,61 @@
 use alloc::boxed::Box;
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::future::{self, Future};
 use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicI32, Ordering};
 use core::task::Poll::{Pending, Ready};
 use core::time::Duration;
 
 use async_trait::async_trait;
+#[cfg(feature = "mmap")]
+use memory_addresses::VirtAddr;
 #[cfg(any(feature = "tcp", feature = "udp"))]
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
 use crate::arch::kernel::core_local::core_scheduler;
 use crate::executor::block_on;
-use crate::fs::{DirectoryEntry, FileAttr, SeekWhence};
+use crate::fd::epoll::{Epoll, EpollEvent, EpollFlags, EpollOp};
+use crate::fd::timerfd::{TimerClock, TimerFd, TimerFdFlags, TimerSetFlags};
+use crate::fs::lock::{self, FileLock, LockOwner, LockType};
+use crate::fs::notify::{self, Inotify, InotifyFlags, InotifyMask};
+use crate::fs::{self, DirectoryEntry, FileAttr, SeekWhence, SetAttr, XattrFlags};
 use crate::io;
+use crate::time::itimerspec;
 
+pub(crate) mod epoll;
 mod eventfd;
-#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+mod pipe;
+#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 pub(crate) mod socket;
 pub(crate) mod stdio;
+pub(crate) mod timerfd;
 
 pub(crate) const STDIN_FILENO: FileDescriptor = 0;
 pub(crate) const STDOUT_FILENO: FileDescriptor = 1;
 pub(crate) const STDERR_FILENO: FileDescriptor = 2;
 
-#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 #[derive(Debug)]
 pub(crate) enum Endpoint {
 	#[cfg(any(feature = "tcp", feature = "udp"))]
 	Ip(IpEndpoint),
 	#[cfg(feature = "vsock")]
 	Vsock(socket::vsock::VsockEndpoint),
+	#[cfg(feature = "unix")]
+	Unix(socket::unix::UnixEndpoint),
 }
 
-#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 #[derive(Debug)]
 pub(crate) enum ListenEndpoint {
 	#[cfg(any(feature = "tcp", feature = "udp"))]
 	Ip(IpListenEndpoint),
 	#[cfg(feature = "vsock")]
 	Vsock(socket::vsock::VsockListenEndpoint),
+	#[cfg(feature = "unix")]
+	Unix(socket::unix::UnixEndpoint),
 }
 
 #[allow(dead_code)]
@@ -56,6 +72,83 @@ pub(crate) enum IoCtl {
 
 pub(crate) type FileDescriptor = i32;
 
+bitflags! {
+	/// Flags of a file descriptor
+	#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+	pub struct FdFlags: i32 {
+		const FD_CLOEXEC = 1;
+	}
+}
+
+/// Access mode and status flags of an open file description. They are
+/// shared by all descriptors, which were duplicated from the same open.
+#[derive(Debug)]
+pub(crate) struct StatusFlags(AtomicI32);
+
+impl StatusFlags {
+	pub fn get(&self) -> OpenOption {
+		OpenOption::from_bits_retain(self.0.load(Ordering::Relaxed))
+	}
+
+	pub fn set(&self, status: OpenOption) {
+		self.0.store(status.bits(), Ordering::Relaxed);
+	}
+}
+
+/// Entry of the object map, which holds the object of a file descriptor
+/// together with the status flags and the flags of the descriptor
+#[derive(Debug, Clone)]
+pub(crate) struct Descriptor {
+	pub object: Arc<dyn ObjectInterface>,
+	/// Access mode and status flags like `O_APPEND` and `O_NONBLOCK`
+	pub status: Arc<StatusFlags>,
+	pub flags: FdFlags,
+}
+
+impl Descriptor {
+	/// Status flags, which are kept after opening the object
+	const STATUS_FLAGS: OpenOption = OpenOption::O_WRONLY
+		.union(OpenOption::O_RDWR)
+		.union(OpenOption::O_APPEND)
+		.union(OpenOption::O_NONBLOCK)
+		.union(OpenOption::O_DIRECT);
+
+	/// Status flags, which can be changed by `F_SETFL`
+	const SETTABLE_FLAGS: OpenOption = OpenOption::O_APPEND
+		.union(OpenOption::O_NONBLOCK)
+		.union(OpenOption::O_DIRECT);
+
+	/// Creates a descriptor of `object`, which was opened with `flags`
+	pub fn new(object: Arc<dyn ObjectInterface>, flags: OpenOption) -> Self {
+		Self {
+			object,
+			status: Arc::new(StatusFlags(AtomicI32::new(
+				(flags & Self::STATUS_FLAGS).bits(),
+			))),
+			flags: if flags.contains(OpenOption::O_CLOEXEC) {
+				FdFlags::FD_CLOEXEC
+			} else {
+				FdFlags::empty()
+			},
+		}
+	}
+}
+
+/// Source of the pages of a memory mapping
+#[cfg(feature = "mmap")]
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum MmapSource {
+	/// The file content is copied with `pread` and changes of shared
+	/// mappings are written back with `pwrite`
+	Copy,
+	/// The file content is stored in page-aligned memory, which is never
+	/// changed. It is mapped without copy.
+	Static(&'static [u8]),
+	/// The pages are mapped from the shared memory window of the device,
+	/// see [`ObjectInterface::map_window`]
+	Window,
+}
+
 bitflags! {
 	/// Options for opening files
 	#[derive(Debug, Copy, Clone, Default)]
@@ -70,6 +163,7 @@ bitflags! {
 		const O_NONBLOCK = 0o4000;
 		const O_DIRECT = 0o40000;
 		const O_DIRECTORY = 0o200_000;
+		const O_CLOEXEC = 0o2_000_000;
 	}
 }
 
@@ -101,6 +195,15 @@ pub struct PollFd {
 	pub revents: PollEvent,
 }
 
+bitflags! {
+	#[derive(Debug, Default, Copy, Clone)]
+	pub struct FallocateFlags: i32 {
+		const FALLOC_FL_KEEP_SIZE = 0x01;
+		const FALLOC_FL_PUNCH_HOLE = 0x02;
+		const FALLOC_FL_ZERO_RANGE = 0x10;
+	}
+}
+
 bitflags! {
 	#[derive(Debug, Default, Copy, Clone)]
 	pub struct EventFlags: i16 {
@@ -163,74 +266,190 @@ pub(crate) trait ObjectInterface: Sync + Send + core::fmt::Debug {
 		Err(io::Error::ENOSYS)
 	}
 
+	/// `append` writes `buf` at the end of the file and moves the offset of
+	/// the file descriptor behind it, as `write` does with `O_APPEND`. Objects
+	/// without a file offset simply write `buf`.
+	async fn append(&self, buf: &[u8]) -> io::Result<usize> {
+		self.write(buf).await
+	}
+
 	/// `lseek` function repositions the offset of the file descriptor fildes
 	async fn lseek(&self, _offset: isize, _whence: SeekWhence) -> io::Result<isize> {
 		Err(io::Error::EINVAL)
 	}
 
+	/// `pread` attempts to read `len` bytes at position `offset` from the object
+	/// without changing the offset of the file descriptor
+	async fn pread(&self, _buf: &mut [MaybeUninit<u8>], _offset: usize) -> io::Result<usize> {
+		Err(io::Error::ESPIPE)
+	}
+
+	/// `pwrite` attempts to write `len` bytes at position `offset` to the object
+	/// without changing the offset of the file descriptor
+	async fn pwrite(&self, _buf: &[u8], _offset: usize) -> io::Result<usize> {
+		Err(io::Error::ESPIPE)
+	}
+
+	/// `truncate` sets the size of the file to `size` bytes
+	async fn truncate(&self, _size: usize) -> io::Result<()> {
+		Err(io::Error::EINVAL)
+	}
+
+	/// `fallocate` manipulates the allocated space of the byte range
+	/// starting at `offset` with `len` bytes
+	async fn fallocate(
+		&self,
+		_mode: FallocateFlags,
+		_offset: usize,
+		_len: usize,
+	) -> io::Result<()> {
+		Err(io::Error::EOPNOTSUPP)
+	}
+
+	/// `flush` is called, whenever a file descriptor of the object is closed
+	async fn flush(&self) -> io::Result<()> {
+		Ok(())
+	}
+
+	/// `fsync` transfers all modified data of the object to the storage device.
+	/// If `datasync` is set, metadata is only transferred, if it is required
+	/// to read the data.
+	async fn fsync(&self, _datasync: bool) -> io::Result<()> {
+		Err(io::Error::EINVAL)
+	}
+
+	/// `syncfs` transfers all modified data of the file system, which
+	/// contains the object, to the storage device
+	async fn syncfs(&self) -> io::Result<()> {
+		Ok(())
+	}
+
 	/// `fstat`
 	async fn fstat(&self) -> io::Result<FileAttr> {
 		Err(io::Error::EINVAL)
 	}
 
-	/// 'readdir' returns a pointer to a dirent structure
-	/// representing the next directory entry in the directory stream
-	/// pointed to by the file descriptor
-	async fn readdir(&self) -> io::Result<Vec<DirectoryEntry>> {
+	/// `setattr` changes the attributes of the file
+	async fn setattr(&self, _attr: SetAttr) -> io::Result<()> {
 		Err(io::Error::EINVAL)
 	}
 
+	/// `getxattr` returns the value of the extended attribute `name`
+	async fn getxattr(&self, _name: &str) -> io::Result<Vec<u8>> {
+		Err(io::Error::EOPNOTSUPP)
+	}
+
+	/// `setxattr` creates or replaces the extended attribute `name`
+	async fn setxattr(&self, _name: &str, _value: &[u8], _flags: XattrFlags) -> io::Result<()> {
+		Err(io::Error::EOPNOTSUPP)
+	}
+
+	/// `listxattr` returns the names of all extended attributes
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		Err(io::Error::EOPNOTSUPP)
+	}
+
+	/// `removexattr` removes the extended attribute `name`
+	async fn removexattr(&self, _name: &str) -> io::Result<()> {
+		Err(io::Error::EOPNOTSUPP)
+	}
+
+	/// `getlk` returns a lock of another owner, which conflicts with `lock`
+	async fn getlk(&self, lock: FileLock) -> io::Result<Option<FileLock>> {
+		let attr = self.fstat().await?;
+		Ok(lock::getlk(&attr, &lock))
+	}
+
+	/// `setlk` acquires, converts or releases `lock`. If a conflicting lock
+	/// exists, `setlk` waits for its release, if `wait` is set, and fails
+	/// with `EAGAIN` otherwise.
+	async fn setlk(&self, lock: FileLock, wait: bool) -> io::Result<()> {
+		let attr = self.fstat().await?;
+		lock::setlk(&attr, lock, wait).await
+	}
+
+	/// `mmap` prepares a memory mapping of the file and returns how its
+	/// pages are supplied
+	#[cfg(feature = "mmap")]
+	async fn mmap(&self) -> io::Result<MmapSource> {
+		Err(io::Error::ENODEV)
+	}
+
+	/// `map_window` returns the address of the page at the page-aligned `offset`
+	/// in the shared memory window of the device. The page stays mapped
+	/// until it is released by `unmap_window`.
+	#[cfg(feature = "mmap")]
+	async fn map_window(&self, _offset: usize) -> io::Result<VirtAddr> {
+		Err(io::Error::ENODEV)
+	}
+
+	/// `unmap_window` releases a page, which was returned by `map_window`
+	#[cfg(feature = "mmap")]
+	async fn unmap_window(&self, _offset: usize) {}
+
+	/// `readdir` returns the next directory entries of the directory stream,
+	/// which follow the position `offset`. The beginning of the stream has
+	/// the position 0 and an empty vector marks its end.
+	async fn readdir(&self, _offset: u64) -> io::Result<Vec<DirectoryEntry>> {
+		Err(io::Error::EINVAL)
+	}
+
+	/// `getpath` returns the absolute path of an opened directory
+	async fn getpath(&self) -> io::Result<String> {
+		Err(io::Error::ENOTDIR)
+	}
+
 	/// `accept` a connection on a socket
-	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 	async fn accept(&self) -> io::Result<(Arc<dyn ObjectInterface>, Endpoint)> {
 		Err(io::Error::EINVAL)
 	}
 
 	/// initiate a connection on a socket
-	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 	async fn connect(&self, _endpoint: Endpoint) -> io::Result<()> {
 		Err(io::Error::EINVAL)
 	}
 
 	/// `bind` a name to a socket
-	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 	async fn bind(&self, _name: ListenEndpoint) -> io::Result<()> {
 		Err(io::Error::EINVAL)
 	}
 
 	/// `listen` for connections on a socket
-	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 	async fn listen(&self, _backlog: i32) -> io::Result<()> {
 		Err(io::Error::EINVAL)
 	}
 
 	/// `setsockopt` sets options on sockets
-	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 	async fn setsockopt(&self, _opt: SocketOption, _optval: bool) -> io::Result<()> {
 		Err(io::Error::ENOTSOCK)
 	}
 
 	/// `getsockopt` gets options on sockets
-	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 	async fn getsockopt(&self, _opt: SocketOption) -> io::Result<bool> {
 		Err(io::Error::ENOTSOCK)
 	}
 
 	/// `getsockname` gets socket name
-	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 	async fn getsockname(&self) -> io::Result<Option<Endpoint>> {
 		Ok(None)
 	}
 
 	/// `getpeername` get address of connected peer
-	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 	#[allow(dead_code)]
 	async fn getpeername(&self) -> io::Result<Option<Endpoint>> {
 		Ok(None)
 	}
 
 	/// receive a message from a socket
-	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 	async fn recvfrom(&self, _buffer: &mut [MaybeUninit<u8>]) -> io::Result<(usize, Endpoint)> {
 		Err(io::Error::ENOSYS)
 	}
@@ -242,13 +461,13 @@ pub(crate) trait ObjectInterface: Sync + Send + core::fmt::Debug {
 	/// If a peer address has been prespecified, either the message shall
 	/// be sent to the address specified by dest_addr (overriding the pre-specified peer
 	/// address).
-	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 	async fn sendto(&self, _buffer: &[u8], _endpoint: Endpoint) -> io::Result<usize> {
 		Err(io::Error::ENOSYS)
 	}
 
 	/// shut down part of a full-duplex connection
-	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+	#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 	async fn shutdown(&self, _how: i32) -> io::Result<()> {
 		Err(io::Error::ENOSYS)
 	}
@@ -263,6 +482,49 @@ pub(crate) trait ObjectInterface: Sync + Send + core::fmt::Debug {
 	async fn isatty(&self) -> io::Result<bool> {
 		Ok(false)
 	}
+
+	/// `inotify_add_watch` adds a watch of `path` to an inotify instance or
+	/// changes the events of an existing watch and returns its descriptor
+	async fn inotify_add_watch(&self, _path: &str, _mask: u32) -> io::Result<i32> {
+		Err(io::Error::EINVAL)
+	}
+
+	/// `inotify_rm_watch` removes the watch `wd` from an inotify instance
+	async fn inotify_rm_watch(&self, _wd: i32) -> io::Result<()> {
+		Err(io::Error::EINVAL)
+	}
+
+	/// `epoll_ctl` adds, modifies or removes the entry of the object `obj`
+	/// with the file descriptor `fd` in the interest list of an epoll instance
+	async fn epoll_ctl(
+		&self,
+		_op: EpollOp,
+		_fd: FileDescriptor,
+		_obj: Arc<dyn ObjectInterface>,
+		_event: Option<EpollEvent>,
+	) -> io::Result<()> {
+		Err(io::Error::EINVAL)
+	}
+
+	/// `epoll_wait` waits for events of an epoll instance and stores them
+	/// in `events`
+	async fn epoll_wait(&self, _events: &mut [EpollEvent]) -> io::Result<usize> {
+		Err(io::Error::EINVAL)
+	}
+
+	/// `timerfd_settime` arms or disarms a timer and returns its previous setting
+	async fn timerfd_settime(
+		&self,
+		_flags: TimerSetFlags,
+		_value: itimerspec,
+	) -> io::Result<itimerspec> {
+		Err(io::Error::EINVAL)
+	}
+
+	/// `timerfd_gettime` returns the current setting of a timer
+	async fn timerfd_gettime(&self) -> io::Result<itimerspec> {
+		Err(io::Error::EINVAL)
+	}
 }
 
 pub(crate) fn read(fd: FileDescriptor, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
@@ -272,6 +534,10 @@ pub(crate) fn read(fd: FileDescriptor, buf: &mut [MaybeUninit<u8>]) -> io::Resul
 		return Ok(0);
 	}
 
+	// the object may be locked, while the buffer is accessed
+	#[cfg(feature = "mmap")]
+	crate::syscalls::prefault(buf.as_ptr().addr(), buf.len())?;
+
 	block_on(obj.read(buf), None)
 }
 
@@ -282,13 +548,140 @@ pub(crate) fn lseek(fd: FileDescriptor, offset: isize, whence: SeekWhence) -> io
 }
 
 pub(crate) fn write(fd: FileDescriptor, buf: &[u8]) -> io::Result<usize> {
+	let descriptor = get_descriptor(fd)?;
+	let obj = descriptor.object;
+
+	if buf.is_empty() {
+		return Ok(0);
+	}
+
+	#[cfg(feature = "mmap")]
+	crate::syscalls::prefault(buf.as_ptr().addr(), buf.len())?;
+
+	let len = if descriptor.status.get().contains(OpenOption::O_APPEND) {
+		block_on(obj.append(buf), None)?
+	} else {
+		block_on(obj.write(buf), None)?
+	};
+	notify::file_changed(&obj, InotifyMask::IN_MODIFY);
+	Ok(len)
+}
+
+pub(crate) fn pread(
+	fd: FileDescriptor,
+	buf: &mut [MaybeUninit<u8>],
+	offset: usize,
+) -> io::Result<usize> {
 	let obj = get_object(fd)?;
 
 	if buf.is_empty() {
 		return Ok(0);
 	}
 
-	block_on(obj.write(buf), None)
+	#[cfg(feature = "mmap")]
+	crate::syscalls::prefault(buf.as_ptr().addr(), buf.len())?;
+
+	block_on(obj.pread(buf, offset), None)
+}
+
+pub(crate) fn pwrite(fd: FileDescriptor, buf: &[u8], offset: usize) -> io::Result<usize> {
+	let obj = get_object(fd)?;
+
+	if buf.is_empty() {
+		return Ok(0);
+	}
+
+	#[cfg(feature = "mmap")]
+	crate::syscalls::prefault(buf.as_ptr().addr(), buf.len())?;
+
+	let len = block_on(obj.pwrite(buf, offset), None)?;
+	notify::file_changed(&obj, InotifyMask::IN_MODIFY);
+	Ok(len)
+}
+
+pub(crate) fn ftruncate(fd: FileDescriptor, size: usize) -> io::Result<()> {
+	let obj = get_object(fd)?;
+
+	block_on(obj.truncate(size), None)?;
+	notify::file_changed(&obj, InotifyMask::IN_MODIFY);
+	Ok(())
+}
+
+pub(crate) fn fsync(fd: FileDescriptor, datasync: bool) -> io::Result<()> {
+	let obj = get_object(fd)?;
+
+	block_on(obj.fsync(datasync), None)
+}
+
+pub(crate) fn syncfs(fd: FileDescriptor) -> io::Result<()> {
+	let obj = get_object(fd)?;
+
+	block_on(obj.syncfs(), None)
+}
+
+/// Flushes the object and removes the file descriptor from the object map.
+/// The record locks of the process on the file are released and the locks
+/// of the open file are released with its last descriptor.
+pub(crate) fn close(fd: FileDescriptor) -> io::Result<()> {
+	let obj = get_object(fd)?;
+	let ret = block_on(obj.flush(), None);
+	// The locks of files, which are forwarded to the server of a FUSE file
+	// system, aren't known locally. Consequently, the unlock is always sent.
+	let unlock = FileLock {
+		kind: LockType::Unlock,
+		owner: LockOwner::Process,
+		flock: false,
+		start: 0,
+		end: u64::MAX,
+		pid: 0,
+	};
+	// objects, which don't support locks, cannot hold them
+	let _ = block_on(obj.setlk(unlock, false), None);
+	let owner = lock_owner(&obj);
+	drop(obj);
+
+	let obj = remove_object(fd)?;
+	if Arc::strong_count(&obj) == 1 {
+		lock::release(owner);
+		notify::file_closed(&obj);
+	}
+
+	ret
+}
+
+/// Returns the owner of the locks of `flock` and of open file description locks
+pub(crate) fn lock_owner(obj: &Arc<dyn ObjectInterface>) -> LockOwner {
+	LockOwner::File(Arc::as_ptr(obj).cast::<()>().addr())
+}
+
+pub(crate) fn getlk(fd: FileDescriptor, lock: FileLock) -> io::Result<Option<FileLock>> {
+	let obj = get_object(fd)?;
+	block_on(obj.getlk(lock), None)
+}
+
+pub(crate) fn setlk(fd: FileDescriptor, lock: FileLock, wait: bool) -> io::Result<()> {
+	let obj = get_object(fd)?;
+	block_on(obj.setlk(lock, wait), None)
+}
+
+/// Applies or removes a lock of `flock` on the open file `fd`
+pub(crate) fn flock(fd: FileDescriptor, kind: LockType, wait: bool) -> io::Result<()> {
+	let obj = get_object(fd)?;
+	let lock = FileLock::flock(kind, lock_owner(&obj));
+	block_on(obj.setlk(lock, wait), None)
+}
+
+pub(crate) fn fallocate(
+	fd: FileDescriptor,
+	mode: FallocateFlags,
+	offset: usize,
+	len: usize,
+) -> io::Result<()> {
+	let obj = get_object(fd)?;
+
+	block_on(obj.fallocate(mode, offset, len), None)?;
+	notify::file_changed(&obj, InotifyMask::IN_MODIFY);
+	Ok(())
 }
 
 async fn poll_fds(fds: &mut [PollFd]) -> io::Result<u64> {
@@ -344,6 +737,49 @@ pub fn fstat(fd: FileDescriptor) -> io::Result<FileAttr> {
 	block_on(obj.fstat(), None)
 }
 
+pub(crate) fn fsetattr(fd: FileDescriptor, attr: SetAttr) -> io::Result<()> {
+	let obj = get_object(fd)?;
+	block_on(obj.setattr(attr), None)?;
+	notify::file_changed(&obj, InotifyMask::IN_ATTRIB);
+	Ok(())
+}
+
+/// Returns the value of the extended attribute `name` of the file `fd`
+pub(crate) fn fgetxattr(fd: FileDescriptor, name: &str) -> io::Result<Vec<u8>> {
+	fs::check_xattr_name(name)?;
+	let obj = get_object(fd)?;
+	block_on(obj.getxattr(name), None)
+}
+
+/// Creates or replaces the extended attribute `name` of the file `fd`
+pub(crate) fn fsetxattr(
+	fd: FileDescriptor,
+	name: &str,
+	value: &[u8],
+	flags: XattrFlags,
+) -> io::Result<()> {
+	fs::check_xattr(name, value)?;
+	let obj = get_object(fd)?;
+	block_on(obj.setxattr(name, value, flags), None)?;
+	notify::file_changed(&obj, InotifyMask::IN_ATTRIB);
+	Ok(())
+}
+
+/// Returns the names of the extended attributes of the file `fd`
+pub(crate) fn flistxattr(fd: FileDescriptor) -> io::Result<Vec<String>> {
+	let obj = get_object(fd)?;
+	block_on(obj.listxattr(), None)
+}
+
+/// Removes the extended attribute `name` of the file `fd`
+pub(crate) fn fremovexattr(fd: FileDescriptor, name: &str) -> io::Result<()> {
+	fs::check_xattr_name(name)?;
+	let obj = get_object(fd)?;
+	block_on(obj.removexattr(name), None)?;
+	notify::file_changed(&obj, InotifyMask::IN_ATTRIB);
+	Ok(())
+}
+
 /// Wait for some event on a file descriptor.
 ///
 /// `eventfd` creates an linux-like "eventfd object" that can be used
@@ -365,31 +801,218 @@ pub fn fstat(fd: FileDescriptor) -> io::Result<FileAttr> {
 pub fn eventfd(initval: u64, flags: EventFlags) -> io::Result<FileDescriptor> {
 	let obj = self::eventfd::EventFd::new(initval, flags);
 
-	let fd = block_on(core_scheduler().insert_object(Arc::new(obj)), None)?;
+	let mut status = OpenOption::O_RDWR;
+	if flags.contains(EventFlags::EFD_NONBLOCK) {
+		status |= OpenOption::O_NONBLOCK;
+	}
+	if flags.contains(EventFlags::EFD_CLOEXEC) {
+		status |= OpenOption::O_CLOEXEC;
+	}
 
-	Ok(fd)
+	insert_object(Arc::new(obj), status)
+}
+
+/// Creates a pipe and returns the file descriptors of its read end and its
+/// write end. `flags` may contain `O_NONBLOCK` and `O_CLOEXEC`.
+pub(crate) fn pipe(flags: OpenOption) -> io::Result<(FileDescriptor, FileDescriptor)> {
+	if !(OpenOption::O_NONBLOCK | OpenOption::O_CLOEXEC).contains(flags) {
+		return Err(io::Error::EINVAL);
+	}
+
+	let (reader, writer) = self::pipe::pipe(flags.contains(OpenOption::O_NONBLOCK));
+	let read_fd = insert_object(Arc::new(reader), OpenOption::O_RDONLY | flags)?;
+	match insert_object(Arc::new(writer), OpenOption::O_WRONLY | flags) {
+		Ok(write_fd) => Ok((read_fd, write_fd)),
+		Err(err) => {
+			let _ = remove_object(read_fd);
+			Err(err)
+		}
+	}
+}
+
+/// Creates an inotify instance, which reports changes of the file system
+pub(crate) fn inotify_init(flags: InotifyFlags) -> io::Result<FileDescriptor> {
+	let mut status = OpenOption::O_RDONLY;
+	if flags.contains(InotifyFlags::IN_NONBLOCK) {
+		status |= OpenOption::O_NONBLOCK;
+	}
+	if flags.contains(InotifyFlags::IN_CLOEXEC) {
+		status |= OpenOption::O_CLOEXEC;
+	}
+
+	insert_object(Inotify::new(flags), status)
+}
+
+/// Adds a watch of `path` to the inotify instance `fd`
+pub(crate) fn inotify_add_watch(fd: FileDescriptor, path: &str, mask: u32) -> io::Result<i32> {
+	let obj = get_object(fd)?;
+	block_on(obj.inotify_add_watch(path, mask), None)
+}
+
+/// Removes the watch `wd` from the inotify instance `fd`
+pub(crate) fn inotify_rm_watch(fd: FileDescriptor, wd: i32) -> io::Result<()> {
+	let obj = get_object(fd)?;
+	block_on(obj.inotify_rm_watch(wd), None)
+}
+
+/// Creates an epoll instance, which reports the readiness of file descriptors
+pub(crate) fn epoll_create(flags: EpollFlags) -> io::Result<FileDescriptor> {
+	let status = if flags.contains(EpollFlags::EPOLL_CLOEXEC) {
+		OpenOption::O_RDONLY | OpenOption::O_CLOEXEC
+	} else {
+		OpenOption::O_RDONLY
+	};
+
+	insert_object(Epoll::new(flags), status)
+}
+
+/// Adds, modifies or removes the entry of `fd` in the epoll instance `epfd`
+pub(crate) fn epoll_ctl(
+	epfd: FileDescriptor,
+	op: EpollOp,
+	fd: FileDescriptor,
+	event: Option<EpollEvent>,
+) -> io::Result<()> {
+	if epfd == fd {
+		return Err(io::Error::EINVAL);
+	}
+
+	let epoll = get_object(epfd)?;
+	let obj = get_object(fd)?;
+	block_on(epoll.epoll_ctl(op, fd, obj, event), None)
+}
+
+/// Waits for events of the epoll instance `epfd` and returns the number of
+/// events stored in `events`. A return value of zero indicates that the
+/// call timed out.
+pub(crate) fn epoll_wait(
+	epfd: FileDescriptor,
+	events: &mut [EpollEvent],
+	timeout: Option<Duration>,
+) -> io::Result<usize> {
+	if events.is_empty() {
+		return Err(io::Error::EINVAL);
+	}
+
+	let epoll = get_object(epfd)?;
+	match block_on(epoll.epoll_wait(events), timeout) {
+		Err(io::Error::ETIME) => Ok(0),
+		result => result,
+	}
+}
+
+/// Creates a timer, which reports its expirations through a file descriptor
+pub(crate) fn timerfd_create(clock: TimerClock, flags: TimerFdFlags) -> io::Result<FileDescriptor> {
+	let mut status = OpenOption::O_RDONLY;
+	if flags.contains(TimerFdFlags::TFD_NONBLOCK) {
+		status |= OpenOption::O_NONBLOCK;
+	}
+	if flags.contains(TimerFdFlags::TFD_CLOEXEC) {
+		status |= OpenOption::O_CLOEXEC;
+	}
+
+	insert_object(Arc::new(TimerFd::new(clock, flags)), status)
+}
+
+/// Arms or disarms the timer `fd` and returns its previous setting
+pub(crate) fn timerfd_settime(
+	fd: FileDescriptor,
+	flags: TimerSetFlags,
+	value: itimerspec,
+) -> io::Result<itimerspec> {
+	let obj = get_object(fd)?;
+	block_on(obj.timerfd_settime(flags, value), None)
+}
+
+/// Returns the current setting of the timer `fd`
+pub(crate) fn timerfd_gettime(fd: FileDescriptor) -> io::Result<itimerspec> {
+	let obj = get_object(fd)?;
+	block_on(obj.timerfd_gettime(), None)
 }
 
 pub(crate) fn get_object(fd: FileDescriptor) -> io::Result<Arc<dyn ObjectInterface>> {
 	block_on(core_scheduler().get_object(fd), None)
 }
 
-pub(crate) fn insert_object(obj: Arc<dyn ObjectInterface>) -> io::Result<FileDescriptor> {
-	block_on(core_scheduler().insert_object(obj), None)
+/// Returns the object of `fd` together with its flags
+pub(crate) fn get_descriptor(fd: FileDescriptor) -> io::Result<Descriptor> {
+	block_on(core_scheduler().get_descriptor(fd), None)
+}
+
+/// Inserts the object `obj`, which was opened with `flags`, and returns
+/// its file descriptor. `flags` contains the access mode, the status flags
+/// and optionally `O_CLOEXEC`.
+pub(crate) fn insert_object(
+	obj: Arc<dyn ObjectInterface>,
+	flags: OpenOption,
+) -> io::Result<FileDescriptor> {
+	block_on(
+		core_scheduler().insert_object(Descriptor::new(obj, flags), 0),
+		None,
+	)
 }
 
 // The dup system call allocates a new file descriptor that refers
 // to the same open file description as the descriptor oldfd. The new
 // file descriptor number is guaranteed to be the lowest-numbered
-// file descriptor that was unused in the calling process.
-pub(crate) fn dup_object(fd: FileDescriptor) -> io::Result<FileDescriptor> {
-	block_on(core_scheduler().dup_object(fd), None)
+// file descriptor that was unused in the calling process and which
+// is greater than or equal to `min_fd`.
+pub(crate) fn dup_object(
+	fd: FileDescriptor,
+	min_fd: FileDescriptor,
+	flags: FdFlags,
+) -> io::Result<FileDescriptor> {
+	if min_fd < 0 {
+		return Err(io::Error::EINVAL);
+	}
+
+	block_on(core_scheduler().dup_object(fd, min_fd, flags), None)
 }
 
 pub(crate) fn dup_object2(fd1: FileDescriptor, fd2: FileDescriptor) -> io::Result<FileDescriptor> {
 	block_on(core_scheduler().dup_object2(fd1, fd2), None)
 }
 
+/// Returns the access mode and the status flags of `fd`
+pub(crate) fn get_status_flags(fd: FileDescriptor) -> io::Result<OpenOption> {
+	Ok(get_descriptor(fd)?.status.get())
+}
+
+/// Sets the status flags of `fd`. Only `O_APPEND`, `O_NONBLOCK` and
+/// `O_DIRECT` can be changed, all other flags are ignored.
+pub(crate) fn set_status_flags(fd: FileDescriptor, flags: OpenOption) -> io::Result<()> {
+	let descriptor = get_descriptor(fd)?;
+	let flags = flags & Descriptor::SETTABLE_FLAGS;
+
+	let status = descriptor.status.get();
+	let nonblocking = flags.contains(OpenOption::O_NONBLOCK);
+	if nonblocking != status.contains(OpenOption::O_NONBLOCK) {
+		// objects without a blocking mode, e.g. regular files, are always ready
+		match block_on(
+			descriptor.object.ioctl(IoCtl::NonBlocking, nonblocking),
+			None,
+		) {
+			Ok(()) | Err(io::Error::ENOSYS) => {}
+			Err(err) => return Err(err),
+		}
+	}
+
+	descriptor
+		.status
+		.set((status & !Descriptor::SETTABLE_FLAGS) | flags);
+	Ok(())
+}
+
+/// Returns the flags of the file descriptor `fd`
+pub(crate) fn get_fd_flags(fd: FileDescriptor) -> io::Result<FdFlags> {
+	Ok(get_descriptor(fd)?.flags)
+}
+
+/// Sets the flags of the file descriptor `fd`
+pub(crate) fn set_fd_flags(fd: FileDescriptor, flags: FdFlags) -> io::Result<()> {
+	block_on(core_scheduler().set_fd_flags(fd, flags), None)
+}
+
 pub(crate) fn remove_object(fd: FileDescriptor) -> io::Result<Arc<dyn ObjectInterface>> {
 	block_on(core_scheduler().remove_object(fd), None)
 }
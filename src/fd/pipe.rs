@@ -0,0 +1,319 @@
+//! Anonymous pipes, which connect a read end with a write end
+
+use alloc::boxed::Box;
+use alloc::collections::vec_deque::VecDeque;
+use alloc::sync::Arc;
+use core::future::{self, Future};
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Poll, Waker, ready};
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+
+use crate::executor::block_on;
+use crate::fd::{AccessPermission, IoCtl, ObjectInterface, PollEvent};
+use crate::fs::{ANON_DEV, FileAttr};
+use crate::io;
+
+/// Capacity of the buffer of a pipe
+const PIPE_CAPACITY: usize = 0x1_0000;
+
+/// Writes of at most `PIPE_BUF` bytes are not interleaved with other writes
+const PIPE_BUF: usize = 0x1000;
+
+#[derive(Debug)]
+struct PipeState {
+	buffer: VecDeque<u8>,
+	/// `true`, while the read end is open. Besides dropping it, the read end
+	/// is closed by `PipeReader::close`, while the object is still in use.
+	reader: bool,
+	/// `true`, while the write end is open. Besides dropping it, the write end
+	/// is closed by `PipeWriter::close`, while the object is still in use.
+	writer: bool,
+	read_queue: VecDeque<Waker>,
+	write_queue: VecDeque<Waker>,
+}
+
+impl PipeState {
+	fn new() -> Self {
+		Self {
+			buffer: VecDeque::new(),
+			reader: true,
+			writer: true,
+			read_queue: VecDeque::new(),
+			write_queue: VecDeque::new(),
+		}
+	}
+
+	fn free(&self) -> usize {
+		PIPE_CAPACITY - self.buffer.len()
+	}
+
+	/// Returns `true`, if reads reach the end of file. The read end has to
+	/// check its own state as well, because it may have been closed by
+	/// `shutdown` of a Unix domain socket, while the object is still in use.
+	fn read_closed(&self) -> bool {
+		!self.writer || !self.reader
+	}
+
+	/// Returns `true`, if writes fail with `EPIPE`. As for reads, the write
+	/// end may have been closed, while the object is still in use.
+	fn write_closed(&self) -> bool {
+		!self.reader || !self.writer
+	}
+
+	/// Wakes all tasks, which wait for data
+	fn wake_readers(&mut self) {
+		for waker in self.read_queue.drain(..) {
+			waker.wake();
+		}
+	}
+
+	/// Wakes all tasks, which wait for free space
+	fn wake_writers(&mut self) {
+		for waker in self.write_queue.drain(..) {
+			waker.wake();
+		}
+	}
+}
+
+/// Returns the attributes of both ends of a pipe
+fn pipe_attr() -> FileAttr {
+	FileAttr {
+		st_dev: ANON_DEV,
+		st_nlink: 1,
+		st_mode: AccessPermission::S_IFIFO | AccessPermission::from_bits(0o600).unwrap(),
+		st_blksize: PIPE_BUF as i64,
+		..Default::default()
+	}
+}
+
+/// Read end of a pipe
+#[derive(Debug)]
+pub(crate) struct PipeReader {
+	state: Arc<Mutex<PipeState>>,
+	nonblocking: AtomicBool,
+}
+
+/// Write end of a pipe
+#[derive(Debug)]
+pub(crate) struct PipeWriter {
+	state: Arc<Mutex<PipeState>>,
+	nonblocking: AtomicBool,
+}
+
+/// Creates the read end and the write end of a new pipe
+pub(crate) fn pipe(nonblocking: bool) -> (PipeReader, PipeWriter) {
+	let state = Arc::new(Mutex::new(PipeState::new()));
+	let reader = PipeReader {
+		state: state.clone(),
+		nonblocking: AtomicBool::new(nonblocking),
+	};
+	let writer = PipeWriter {
+		state,
+		nonblocking: AtomicBool::new(nonblocking),
+	};
+
+	(reader, writer)
+}
+
+#[async_trait]
+impl ObjectInterface for PipeReader {
+	async fn read(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		future::poll_fn(|cx| {
+			let mut pinned = core::pin::pin!(self.state.lock());
+			let mut guard = ready!(pinned.as_mut().poll(cx));
+			if !guard.buffer.is_empty() {
+				let len = buf.len().min(guard.buffer.len());
+				for (dst, src) in buf.iter_mut().zip(guard.buffer.drain(..len)) {
+					dst.write(src);
+				}
+				guard.wake_writers();
+				Poll::Ready(Ok(len))
+			} else if guard.read_closed() {
+				// end of file
+				Poll::Ready(Ok(0))
+			} else if self.nonblocking.load(Ordering::Relaxed) {
+				Poll::Ready(Err(io::Error::EAGAIN))
+			} else {
+				guard.read_queue.push_back(cx.waker().clone());
+				Poll::Pending
+			}
+		})
+		.await
+	}
+
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		future::poll_fn(|cx| {
+			let mut pinned = core::pin::pin!(self.state.lock());
+			let mut guard = ready!(pinned.as_mut().poll(cx));
+			let mut available = PollEvent::empty();
+			if !guard.buffer.is_empty() {
+				available.insert(PollEvent::POLLIN | PollEvent::POLLRDNORM);
+			}
+			if guard.read_closed() {
+				available.insert(PollEvent::POLLHUP);
+			}
+
+			// POLLHUP is reported, even if it wasn't requested
+			let ret = (event & available) | (available & PollEvent::POLLHUP);
+			if ret.is_empty() {
+				guard.read_queue.push_back(cx.waker().clone());
+				Poll::Pending
+			} else {
+				Poll::Ready(Ok(ret))
+			}
+		})
+		.await
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(pipe_attr())
+	}
+
+	async fn ioctl(&self, cmd: IoCtl, value: bool) -> io::Result<()> {
+		match cmd {
+			IoCtl::NonBlocking => {
+				self.nonblocking.store(value, Ordering::Relaxed);
+				Ok(())
+			}
+		}
+	}
+}
+
+impl PipeReader {
+	/// Closes the read end, while the object is still alive. Afterwards,
+	/// reads reach the end of file and the write end fails with `EPIPE`.
+	pub(crate) async fn close(&self) {
+		let mut guard = self.state.lock().await;
+		guard.reader = false;
+		guard.buffer.clear();
+		guard.wake_readers();
+		guard.wake_writers();
+	}
+}
+
+impl Drop for PipeReader {
+	fn drop(&mut self) {
+		// the other end notices that this end was closed
+		let _ = block_on(
+			async {
+				self.close().await;
+				Ok(())
+			},
+			None,
+		);
+	}
+}
+
+#[async_trait]
+impl ObjectInterface for PipeWriter {
+	async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		let mut written = 0;
+		future::poll_fn(|cx| {
+			let mut pinned = core::pin::pin!(self.state.lock());
+			let mut guard = ready!(pinned.as_mut().poll(cx));
+			if guard.write_closed() {
+				// there is no signal, which terminates the writer
+				return if written > 0 {
+					Poll::Ready(Ok(written))
+				} else {
+					Poll::Ready(Err(io::Error::EPIPE))
+				};
+			}
+
+			let remaining = &buf[written..];
+			let free = guard.free();
+			// small writes are atomic and wait for enough space
+			if free > 0 && (free >= remaining.len() || buf.len() > PIPE_BUF) {
+				let len = free.min(remaining.len());
+				guard.buffer.extend(&remaining[..len]);
+				guard.wake_readers();
+				written += len;
+			}
+
+			if written == buf.len() {
+				Poll::Ready(Ok(written))
+			} else if self.nonblocking.load(Ordering::Relaxed) {
+				if written > 0 {
+					Poll::Ready(Ok(written))
+				} else {
+					Poll::Ready(Err(io::Error::EAGAIN))
+				}
+			} else {
+				guard.write_queue.push_back(cx.waker().clone());
+				Poll::Pending
+			}
+		})
+		.await
+	}
+
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		future::poll_fn(|cx| {
+			let mut pinned = core::pin::pin!(self.state.lock());
+			let mut guard = ready!(pinned.as_mut().poll(cx));
+			let mut available = PollEvent::empty();
+			if guard.write_closed() {
+				available.insert(PollEvent::POLLERR);
+			} else if guard.free() >= PIPE_BUF {
+				available.insert(PollEvent::POLLOUT | PollEvent::POLLWRNORM);
+			}
+
+			// POLLERR is reported, even if it wasn't requested
+			let ret = (event & available) | (available & PollEvent::POLLERR);
+			if ret.is_empty() {
+				guard.write_queue.push_back(cx.waker().clone());
+				Poll::Pending
+			} else {
+				Poll::Ready(Ok(ret))
+			}
+		})
+		.await
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(pipe_attr())
+	}
+
+	async fn ioctl(&self, cmd: IoCtl, value: bool) -> io::Result<()> {
+		match cmd {
+			IoCtl::NonBlocking => {
+				self.nonblocking.store(value, Ordering::Relaxed);
+				Ok(())
+			}
+		}
+	}
+}
+
+impl PipeWriter {
+	/// Closes the write end, while the object is still alive. Afterwards,
+	/// writes fail with `EPIPE` and the read end reaches the end of file.
+	pub(crate) async fn close(&self) {
+		let mut guard = self.state.lock().await;
+		guard.writer = false;
+		guard.wake_readers();
+		guard.wake_writers();
+	}
+}
+
+impl Drop for PipeWriter {
+	fn drop(&mut self) {
+		// the other end notices that this end was closed
+		let _ = block_on(
+			async {
+				self.close().await;
+				Ok(())
+			},
+			None,
+		);
+	}
+}
@@ -2,5 +2,7 @@
 pub(crate) mod tcp;
 #[cfg(feature = "udp")]
 pub(crate) mod udp;
+#[cfg(feature = "unix")]
+pub(crate) mod unix;
 #[cfg(feature = "vsock")]
 pub(crate) mod vsock;
@@ -0,0 +1,986 @@
+//! Unix domain sockets, which connect sockets of the same application.
+//!
+//! Sockets are bound to the path of a socket file (`S_IFSOCK`) or to a name
+//! in the abstract namespace, which isn't visible in the file system. A
+//! stream connection consists of two pipes, one for each direction.
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::future::{self, Future};
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::{Poll, Waker, ready};
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+use hermit_sync::TicketMutex;
+
+use crate::executor::block_on;
+use crate::fd::pipe::{self, PipeReader, PipeWriter};
+use crate::fd::{AccessPermission, Endpoint, IoCtl, ListenEndpoint, ObjectInterface, PollEvent};
+use crate::fs::{self, ANON_DEV, FileAttr, FileType};
+use crate::io;
+
+/// further receives will be disallowed
+pub const SHUT_RD: i32 = 0;
+/// further sends will be disallowed
+pub const SHUT_WR: i32 = 1;
+/// further sends and receives will be disallowed
+pub const SHUT_RDWR: i32 = 2;
+/// Maximum length of the queue of pending connections
+pub const SOMAXCONN: usize = 4096;
+
+/// Maximum number of bytes, which are queued at a datagram socket
+const DATAGRAM_CAPACITY: usize = 0x1_0000;
+
+const READ_EVENTS: PollEvent = PollEvent::POLLIN.union(PollEvent::POLLRDNORM);
+const WRITE_EVENTS: PollEvent = PollEvent::POLLOUT.union(PollEvent::POLLWRNORM);
+
+/// Address of a Unix domain socket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum UnixEndpoint {
+	/// The socket isn't bound to a name
+	Unnamed,
+	/// Path of a socket file
+	Path(String),
+	/// Name in the abstract namespace without the leading null byte
+	Abstract(Vec<u8>),
+}
+
+/// Key of a bound socket
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Name {
+	/// Device and inode of the socket file, so that renamed files and
+	/// hard links refer to the same socket
+	Inode(u64, u64),
+	Abstract(Vec<u8>),
+}
+
+/// Reference from a name to the bound socket
+#[derive(Debug, Clone)]
+enum Binding {
+	Stream(Weak<Mutex<ListenState>>),
+	Datagram(Weak<Mutex<DatagramQueue>>),
+}
+
+/// Names of all bound sockets
+static NAMES: TicketMutex<BTreeMap<Name, Binding>> = TicketMutex::new(BTreeMap::new());
+/// Counter, which generates the names of automatically bound sockets
+static NEXT_AUTOBIND: AtomicU32 = AtomicU32::new(0);
+
+/// Binds `endpoint` to `binding` and returns its key. A socket file is
+/// created for a path and an unnamed endpoint is replaced by a unique name
+/// in the abstract namespace.
+fn bind_name(endpoint: &mut UnixEndpoint, binding: Binding) -> io::Result<Name> {
+	let name = match endpoint {
+		UnixEndpoint::Unnamed => {
+			let mut names = NAMES.lock();
+			// Linux uses five hexadecimal digits
+			for _ in 0..=0xf_ffff {
+				let id = NEXT_AUTOBIND.fetch_add(1, Ordering::Relaxed) & 0xf_ffff;
+				let bytes = format!("{id:05x}").into_bytes();
+				let name = Name::Abstract(bytes.clone());
+				if !names.contains_key(&name) {
+					names.insert(name.clone(), binding);
+					*endpoint = UnixEndpoint::Abstract(bytes);
+					return Ok(name);
+				}
+			}
+
+			return Err(io::Error::ENOSPC);
+		}
+		UnixEndpoint::Path(path) => {
+			let mode = AccessPermission::S_IFSOCK
+				| AccessPermission::S_IRWXU
+				| AccessPermission::S_IRWXG
+				| AccessPermission::S_IRWXO;
+			return bind_path(
+				path,
+				binding,
+				|path| {
+					fs::mknod(path, mode)?;
+					fs::read_lstat(path).inspect_err(|_| {
+						let _ = fs::unlink(path);
+					})
+				},
+				|path| {
+					let _ = fs::unlink(path);
+				},
+			);
+		}
+		UnixEndpoint::Abstract(bytes) => Name::Abstract(bytes.clone()),
+	};
+
+	let mut names = NAMES.lock();
+	if names.contains_key(&name) {
+		return Err(io::Error::EADDRINUSE);
+	}
+	names.insert(name.clone(), binding);
+
+	Ok(name)
+}
+
+/// Creates the socket file at `path` with `create`, which returns the
+/// attributes of the new file, and registers `binding` under its inode. If
+/// the inode is still bound, the file is deleted again with `remove`.
+fn bind_path(
+	path: &str,
+	binding: Binding,
+	create: impl FnOnce(&str) -> io::Result<FileAttr>,
+	remove: impl FnOnce(&str),
+) -> io::Result<Name> {
+	let attr = create(path).map_err(|err| {
+		if err == io::Error::EEXIST {
+			io::Error::EADDRINUSE
+		} else {
+			err
+		}
+	})?;
+	let name = Name::Inode(attr.st_dev, attr.st_ino);
+
+	let mut names = NAMES.lock();
+	if names.contains_key(&name) {
+		drop(names);
+		remove(path);
+		return Err(io::Error::EADDRINUSE);
+	}
+	names.insert(name.clone(), binding);
+
+	Ok(name)
+}
+
+/// Returns the binding of the socket, which is bound to `endpoint`
+fn lookup(endpoint: &UnixEndpoint) -> io::Result<Binding> {
+	let name = match endpoint {
+		UnixEndpoint::Unnamed => return Err(io::Error::EINVAL),
+		UnixEndpoint::Path(path) => {
+			let attr = fs::read_stat(path)?;
+			if FileType::from(attr.st_mode) != FileType::Socket {
+				return Err(io::Error::ECONNREFUSED);
+			}
+			Name::Inode(attr.st_dev, attr.st_ino)
+		}
+		UnixEndpoint::Abstract(bytes) => Name::Abstract(bytes.clone()),
+	};
+
+	NAMES
+		.lock()
+		.get(&name)
+		.cloned()
+		.ok_or(io::Error::ECONNREFUSED)
+}
+
+/// Returns the attributes of a socket
+fn socket_attr() -> FileAttr {
+	FileAttr {
+		st_dev: ANON_DEV,
+		st_nlink: 1,
+		st_mode: AccessPermission::S_IFSOCK | AccessPermission::from_bits(0o777).unwrap(),
+		..Default::default()
+	}
+}
+
+fn wake_all(queue: &mut VecDeque<Waker>) {
+	for waker in queue.drain(..) {
+		waker.wake();
+	}
+}
+
+/// Connection of a stream socket
+#[derive(Debug)]
+struct Connection {
+	/// Data, which is sent by the peer
+	rx: PipeReader,
+	/// Data, which is sent to the peer
+	tx: PipeWriter,
+	peer: UnixEndpoint,
+}
+
+impl Connection {
+	/// Connects the sockets with the addresses `first` and `second` and
+	/// returns the connection of `first` and the connection of `second`
+	fn pair(first: &UnixEndpoint, second: &UnixEndpoint) -> (Self, Self) {
+		let (first_rx, second_tx) = pipe::pipe(false);
+		let (second_rx, first_tx) = pipe::pipe(false);
+
+		(
+			Self {
+				rx: first_rx,
+				tx: first_tx,
+				peer: second.clone(),
+			},
+			Self {
+				rx: second_rx,
+				tx: second_tx,
+				peer: first.clone(),
+			},
+		)
+	}
+
+	async fn set_nonblocking(&self, value: bool) -> io::Result<()> {
+		self.rx.ioctl(IoCtl::NonBlocking, value).await?;
+		self.tx.ioctl(IoCtl::NonBlocking, value).await
+	}
+
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		future::poll_fn(|cx| {
+			let mut ret = PollEvent::empty();
+			let mut hangup = true;
+
+			let mut pinned = core::pin::pin!(self.rx.poll(event & READ_EVENTS));
+			if let Poll::Ready(available) = pinned.as_mut().poll(cx) {
+				let available = available?;
+				ret |= available & READ_EVENTS;
+				if available.contains(PollEvent::POLLHUP) {
+					// the end of file can be read
+					ret |= event & (READ_EVENTS | PollEvent::POLLRDHUP);
+				} else {
+					hangup = false;
+				}
+			} else {
+				hangup = false;
+			}
+
+			let mut pinned = core::pin::pin!(self.tx.poll(event & WRITE_EVENTS));
+			if let Poll::Ready(available) = pinned.as_mut().poll(cx) {
+				let available = available?;
+				ret |= available & WRITE_EVENTS;
+				if available.contains(PollEvent::POLLERR) {
+					// writes fail immediately with `EPIPE`
+					ret |= event & WRITE_EVENTS;
+				} else {
+					hangup = false;
+				}
+			} else {
+				hangup = false;
+			}
+
+			// POLLHUP is reported, if both directions are shut down
+			if hangup {
+				ret |= PollEvent::POLLHUP;
+			}
+
+			if ret.is_empty() {
+				Poll::Pending
+			} else {
+				Poll::Ready(Ok(ret))
+			}
+		})
+		.await
+	}
+}
+
+/// State of a bound stream socket, which may accept connections
+#[derive(Debug)]
+struct ListenState {
+	/// Address of the socket
+	local: UnixEndpoint,
+	/// `true`, after `listen` was called
+	listening: bool,
+	/// Maximum number of pending connections
+	backlog: usize,
+	/// Connections, which weren't accepted yet
+	pending: VecDeque<Socket>,
+	accept_queue: VecDeque<Waker>,
+	connect_queue: VecDeque<Waker>,
+}
+
+impl ListenState {
+	fn new(local: UnixEndpoint) -> Self {
+		Self {
+			local,
+			listening: false,
+			backlog: 0,
+			pending: VecDeque::new(),
+			accept_queue: VecDeque::new(),
+			connect_queue: VecDeque::new(),
+		}
+	}
+}
+
+/// Stream socket (`SOCK_STREAM`)
+#[derive(Debug)]
+pub(crate) struct Socket {
+	local: UnixEndpoint,
+	/// Key of the name, to which the socket is bound
+	name: Option<Name>,
+	/// State of a bound socket
+	listener: Option<Arc<Mutex<ListenState>>>,
+	connection: Option<Connection>,
+	nonblocking: bool,
+}
+
+impl Socket {
+	pub fn new() -> Self {
+		Self {
+			local: UnixEndpoint::Unnamed,
+			name: None,
+			listener: None,
+			connection: None,
+			nonblocking: false,
+		}
+	}
+
+	/// Creates two connected sockets, as `socketpair` does
+	pub fn pair() -> (Self, Self) {
+		let (first, second) = Connection::pair(&UnixEndpoint::Unnamed, &UnixEndpoint::Unnamed);
+
+		(
+			Self::connected(UnixEndpoint::Unnamed, first),
+			Self::connected(UnixEndpoint::Unnamed, second),
+		)
+	}
+
+	fn connected(local: UnixEndpoint, connection: Connection) -> Self {
+		Self {
+			local,
+			name: None,
+			listener: None,
+			connection: Some(connection),
+			nonblocking: false,
+		}
+	}
+
+	fn connection(&self) -> io::Result<&Connection> {
+		self.connection.as_ref().ok_or(io::Error::ENOTCONN)
+	}
+
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		if let Some(connection) = &self.connection {
+			return connection.poll(event).await;
+		}
+
+		let Some(listener) = &self.listener else {
+			// an unconnected socket isn't ready for anything, but writes
+			// fail immediately
+			let ret = event & WRITE_EVENTS;
+			return if ret.is_empty() {
+				future::pending().await
+			} else {
+				Ok(ret)
+			};
+		};
+
+		future::poll_fn(|cx| {
+			let mut pinned = core::pin::pin!(listener.lock());
+			let mut guard = ready!(pinned.as_mut().poll(cx));
+			let ret = if guard.pending.is_empty() {
+				PollEvent::empty()
+			} else {
+				event & READ_EVENTS
+			};
+
+			if ret.is_empty() {
+				guard.accept_queue.push_back(cx.waker().clone());
+				Poll::Pending
+			} else {
+				Poll::Ready(Ok(ret))
+			}
+		})
+		.await
+	}
+
+	async fn bind(&mut self, endpoint: ListenEndpoint) -> io::Result<()> {
+		match endpoint {
+			ListenEndpoint::Unix(mut endpoint) => {
+				if self.name.is_some() || self.connection.is_some() {
+					return Err(io::Error::EINVAL);
+				}
+
+				let listener = Arc::new(Mutex::new(ListenState::new(UnixEndpoint::Unnamed)));
+				let name = bind_name(&mut endpoint, Binding::Stream(Arc::downgrade(&listener)))?;
+				listener.lock().await.local = endpoint.clone();
+				self.local = endpoint;
+				self.name = Some(name);
+				self.listener = Some(listener);
+
+				Ok(())
+			}
+			#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+			_ => Err(io::Error::EINVAL),
+		}
+	}
+
+	async fn listen(&mut self, backlog: i32) -> io::Result<()> {
+		if self.connection.is_some() {
+			return Err(io::Error::EINVAL);
+		}
+
+		if self.listener.is_none() {
+			self.bind(ListenEndpoint::Unix(UnixEndpoint::Unnamed))
+				.await?;
+		}
+
+		let mut guard = self.listener.as_ref().unwrap().lock().await;
+		guard.listening = true;
+		guard.backlog =
+			usize::try_from(backlog).map_or(SOMAXCONN, |backlog| backlog.clamp(1, SOMAXCONN));
+		wake_all(&mut guard.connect_queue);
+
+		Ok(())
+	}
+
+	async fn accept(&self) -> io::Result<(Socket, Endpoint)> {
+		let listener = self.listener.as_ref().ok_or(io::Error::EINVAL)?;
+
+		future::poll_fn(|cx| {
+			let mut pinned = core::pin::pin!(listener.lock());
+			let mut guard = ready!(pinned.as_mut().poll(cx));
+			if !guard.listening {
+				Poll::Ready(Err(io::Error::EINVAL))
+			} else if let Some(socket) = guard.pending.pop_front() {
+				wake_all(&mut guard.connect_queue);
+				let peer = socket.connection.as_ref().unwrap().peer.clone();
+				Poll::Ready(Ok((socket, Endpoint::Unix(peer))))
+			} else if self.nonblocking {
+				Poll::Ready(Err(io::Error::EAGAIN))
+			} else {
+				guard.accept_queue.push_back(cx.waker().clone());
+				Poll::Pending
+			}
+		})
+		.await
+	}
+
+	async fn connect(&mut self, endpoint: Endpoint) -> io::Result<()> {
+		match endpoint {
+			Endpoint::Unix(endpoint) => {
+				if self.connection.is_some() {
+					return Err(io::Error::EISCONN);
+				}
+				if let Some(listener) = &self.listener {
+					if listener.lock().await.listening {
+						return Err(io::Error::EINVAL);
+					}
+				}
+
+				let Binding::Stream(listener) = lookup(&endpoint)? else {
+					return Err(io::Error::EPROTOTYPE);
+				};
+				let listener = listener.upgrade().ok_or(io::Error::ECONNREFUSED)?;
+
+				let connection = future::poll_fn(|cx| {
+					let mut pinned = core::pin::pin!(listener.lock());
+					let mut guard = ready!(pinned.as_mut().poll(cx));
+					if !guard.listening {
+						Poll::Ready(Err(io::Error::ECONNREFUSED))
+					} else if guard.pending.len() < guard.backlog {
+						let (connection, accepted) = Connection::pair(&self.local, &guard.local);
+						let accepted = Socket::connected(guard.local.clone(), accepted);
+						guard.pending.push_back(accepted);
+						wake_all(&mut guard.accept_queue);
+						Poll::Ready(Ok(connection))
+					} else if self.nonblocking {
+						Poll::Ready(Err(io::Error::EAGAIN))
+					} else {
+						guard.connect_queue.push_back(cx.waker().clone());
+						Poll::Pending
+					}
+				})
+				.await?;
+
+				if self.nonblocking {
+					connection.set_nonblocking(true).await?;
+				}
+				self.connection = Some(connection);
+
+				Ok(())
+			}
+			#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+			_ => Err(io::Error::EINVAL),
+		}
+	}
+
+	async fn read(&self, buffer: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		self.connection()?.rx.read(buffer).await
+	}
+
+	async fn write(&self, buffer: &[u8]) -> io::Result<usize> {
+		self.connection()?.tx.write(buffer).await
+	}
+
+	async fn recvfrom(&self, buffer: &mut [MaybeUninit<u8>]) -> io::Result<(usize, Endpoint)> {
+		// the address of the sender isn't reported for streams
+		let len = self.read(buffer).await?;
+		Ok((len, Endpoint::Unix(UnixEndpoint::Unnamed)))
+	}
+
+	async fn sendto(&self, _buffer: &[u8], _endpoint: Endpoint) -> io::Result<usize> {
+		if self.connection.is_some() {
+			Err(io::Error::EISCONN)
+		} else {
+			Err(io::Error::EOPNOTSUPP)
+		}
+	}
+
+	async fn getsockname(&self) -> io::Result<Option<Endpoint>> {
+		Ok(Some(Endpoint::Unix(self.local.clone())))
+	}
+
+	async fn getpeername(&self) -> io::Result<Option<Endpoint>> {
+		Ok(Some(Endpoint::Unix(self.connection()?.peer.clone())))
+	}
+
+	async fn shutdown(&self, how: i32) -> io::Result<()> {
+		let connection = self.connection()?;
+
+		match how {
+			SHUT_RD => connection.rx.close().await,
+			SHUT_WR => connection.tx.close().await,
+			SHUT_RDWR => {
+				connection.rx.close().await;
+				connection.tx.close().await;
+			}
+			_ => return Err(io::Error::EINVAL),
+		}
+
+		Ok(())
+	}
+
+	async fn ioctl(&mut self, cmd: IoCtl, value: bool) -> io::Result<()> {
+		match cmd {
+			IoCtl::NonBlocking => {
+				self.nonblocking = value;
+				if let Some(connection) = &self.connection {
+					connection.set_nonblocking(value).await?;
+				}
+
+				Ok(())
+			}
+		}
+	}
+}
+
+impl Drop for Socket {
+	fn drop(&mut self) {
+		if let Some(name) = self.name.take() {
+			NAMES.lock().remove(&name);
+		}
+
+		if let Some(listener) = self.listener.take() {
+			// pending connections are closed after the lock was released,
+			// because dropping their pipes blocks on other locks
+			let pending = block_on(
+				async {
+					let mut guard = listener.lock().await;
+					guard.listening = false;
+					wake_all(&mut guard.accept_queue);
+					wake_all(&mut guard.connect_queue);
+					Ok(core::mem::take(&mut guard.pending))
+				},
+				None,
+			);
+			drop(pending);
+		}
+	}
+}
+
+#[async_trait]
+impl ObjectInterface for async_lock::RwLock<Socket> {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		self.read().await.poll(event).await
+	}
+
+	async fn read(&self, buffer: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		self.read().await.read(buffer).await
+	}
+
+	async fn write(&self, buffer: &[u8]) -> io::Result<usize> {
+		self.read().await.write(buffer).await
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(socket_attr())
+	}
+
+	async fn bind(&self, endpoint: ListenEndpoint) -> io::Result<()> {
+		self.write().await.bind(endpoint).await
+	}
+
+	async fn connect(&self, endpoint: Endpoint) -> io::Result<()> {
+		self.write().await.connect(endpoint).await
+	}
+
+	async fn accept(&self) -> io::Result<(Arc<dyn ObjectInterface>, Endpoint)> {
+		let (socket, endpoint) = self.read().await.accept().await?;
+		Ok((Arc::new(async_lock::RwLock::new(socket)), endpoint))
+	}
+
+	async fn listen(&self, backlog: i32) -> io::Result<()> {
+		self.write().await.listen(backlog).await
+	}
+
+	async fn recvfrom(&self, buffer: &mut [MaybeUninit<u8>]) -> io::Result<(usize, Endpoint)> {
+		self.read().await.recvfrom(buffer).await
+	}
+
+	async fn sendto(&self, buffer: &[u8], endpoint: Endpoint) -> io::Result<usize> {
+		self.read().await.sendto(buffer, endpoint).await
+	}
+
+	async fn getsockname(&self) -> io::Result<Option<Endpoint>> {
+		self.read().await.getsockname().await
+	}
+
+	async fn getpeername(&self) -> io::Result<Option<Endpoint>> {
+		self.read().await.getpeername().await
+	}
+
+	async fn shutdown(&self, how: i32) -> io::Result<()> {
+		self.read().await.shutdown(how).await
+	}
+
+	async fn ioctl(&self, cmd: IoCtl, value: bool) -> io::Result<()> {
+		self.write().await.ioctl(cmd, value).await
+	}
+}
+
+/// Messages, which were sent to a datagram socket
+#[derive(Debug, Default)]
+struct DatagramQueue {
+	/// Messages and the addresses of their senders
+	messages: VecDeque<(Vec<u8>, UnixEndpoint)>,
+	/// Number of queued bytes
+	len: usize,
+	/// `true`, after the receiving socket was closed
+	closed: bool,
+	read_queue: VecDeque<Waker>,
+	write_queue: VecDeque<Waker>,
+}
+
+impl DatagramQueue {
+	/// Returns `true`, if a message of `len` bytes can be queued
+	fn has_space(&self, len: usize) -> bool {
+		self.messages.is_empty() || self.len + len <= DATAGRAM_CAPACITY
+	}
+}
+
+/// Datagram socket (`SOCK_DGRAM`)
+#[derive(Debug)]
+pub(crate) struct DatagramSocket {
+	local: UnixEndpoint,
+	/// Key of the name, to which the socket is bound
+	name: Option<Name>,
+	/// Messages, which were sent to this socket
+	queue: Arc<Mutex<DatagramQueue>>,
+	/// Address and queue of the connected peer
+	peer: Option<(UnixEndpoint, Weak<Mutex<DatagramQueue>>)>,
+	nonblocking: bool,
+}
+
+impl DatagramSocket {
+	pub fn new() -> Self {
+		Self {
+			local: UnixEndpoint::Unnamed,
+			name: None,
+			queue: Arc::new(Mutex::new(DatagramQueue::default())),
+			peer: None,
+			nonblocking: false,
+		}
+	}
+
+	/// Creates two connected sockets, as `socketpair` does
+	pub fn pair() -> (Self, Self) {
+		let mut first = Self::new();
+		let mut second = Self::new();
+		first.peer = Some((UnixEndpoint::Unnamed, Arc::downgrade(&second.queue)));
+		second.peer = Some((UnixEndpoint::Unnamed, Arc::downgrade(&first.queue)));
+
+		(first, second)
+	}
+
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		let peer = self.peer.as_ref().map(|(_, queue)| queue.upgrade());
+
+		future::poll_fn(|cx| {
+			let mut ret = PollEvent::empty();
+
+			let mut pinned = core::pin::pin!(self.queue.lock());
+			let mut guard = ready!(pinned.as_mut().poll(cx));
+			if !guard.messages.is_empty() {
+				ret |= event & READ_EVENTS;
+			} else if event.intersects(READ_EVENTS) {
+				guard.read_queue.push_back(cx.waker().clone());
+			}
+			drop(guard);
+
+			match &peer {
+				Some(Some(queue)) => {
+					let mut pinned = core::pin::pin!(queue.lock());
+					if let Poll::Ready(mut guard) = pinned.as_mut().poll(cx) {
+						if guard.closed || guard.has_space(1) {
+							ret |= event & WRITE_EVENTS;
+						} else if event.intersects(WRITE_EVENTS) {
+							guard.write_queue.push_back(cx.waker().clone());
+						}
+					}
+				}
+				// sends fail immediately or the destination is chosen by `sendto`
+				_ => ret |= event & WRITE_EVENTS,
+			}
+
+			if ret.is_empty() {
+				Poll::Pending
+			} else {
+				Poll::Ready(Ok(ret))
+			}
+		})
+		.await
+	}
+
+	async fn bind(&mut self, endpoint: ListenEndpoint) -> io::Result<()> {
+		match endpoint {
+			ListenEndpoint::Unix(mut endpoint) => {
+				if self.name.is_some() {
+					return Err(io::Error::EINVAL);
+				}
+
+				let binding = Binding::Datagram(Arc::downgrade(&self.queue));
+				self.name = Some(bind_name(&mut endpoint, binding)?);
+				self.local = endpoint;
+
+				Ok(())
+			}
+			#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+			_ => Err(io::Error::EINVAL),
+		}
+	}
+
+	async fn connect(&mut self, endpoint: Endpoint) -> io::Result<()> {
+		match endpoint {
+			Endpoint::Unix(endpoint) => {
+				let Binding::Datagram(queue) = lookup(&endpoint)? else {
+					return Err(io::Error::EPROTOTYPE);
+				};
+				self.peer = Some((endpoint, queue));
+
+				Ok(())
+			}
+			#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+			_ => Err(io::Error::EINVAL),
+		}
+	}
+
+	/// Appends a message to `queue`
+	async fn send(&self, buffer: &[u8], queue: &Weak<Mutex<DatagramQueue>>) -> io::Result<usize> {
+		if buffer.len() > DATAGRAM_CAPACITY {
+			return Err(io::Error::EMSGSIZE);
+		}
+		let queue = queue.upgrade().ok_or(io::Error::ECONNREFUSED)?;
+
+		future::poll_fn(|cx| {
+			let mut pinned = core::pin::pin!(queue.lock());
+			let mut guard = ready!(pinned.as_mut().poll(cx));
+			if guard.closed {
+				Poll::Ready(Err(io::Error::ECONNREFUSED))
+			} else if guard.has_space(buffer.len()) {
+				guard
+					.messages
+					.push_back((buffer.to_vec(), self.local.clone()));
+				guard.len += buffer.len();
+				wake_all(&mut guard.read_queue);
+				Poll::Ready(Ok(buffer.len()))
+			} else if self.nonblocking {
+				Poll::Ready(Err(io::Error::EAGAIN))
+			} else {
+				guard.write_queue.push_back(cx.waker().clone());
+				Poll::Pending
+			}
+		})
+		.await
+	}
+
+	async fn write(&self, buffer: &[u8]) -> io::Result<usize> {
+		let (_, queue) = self.peer.as_ref().ok_or(io::Error::ENOTCONN)?;
+		self.send(buffer, queue).await
+	}
+
+	async fn sendto(&self, buffer: &[u8], endpoint: Endpoint) -> io::Result<usize> {
+		match endpoint {
+			Endpoint::Unix(endpoint) => {
+				let Binding::Datagram(queue) = lookup(&endpoint)? else {
+					return Err(io::Error::EPROTOTYPE);
+				};
+				self.send(buffer, &queue).await
+			}
+			#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+			_ => Err(io::Error::EINVAL),
+		}
+	}
+
+	/// Receives the next message. The part of the message, which doesn't
+	/// fit into `buffer`, is discarded.
+	async fn recvfrom(&self, buffer: &mut [MaybeUninit<u8>]) -> io::Result<(usize, Endpoint)> {
+		future::poll_fn(|cx| {
+			let mut pinned = core::pin::pin!(self.queue.lock());
+			let mut guard = ready!(pinned.as_mut().poll(cx));
+			if let Some((message, sender)) = guard.messages.pop_front() {
+				guard.len -= message.len();
+				wake_all(&mut guard.write_queue);
+				let len = buffer.len().min(message.len());
+				buffer[..len].write_copy_of_slice(&message[..len]);
+				Poll::Ready(Ok((len, Endpoint::Unix(sender))))
+			} else if self.nonblocking {
+				Poll::Ready(Err(io::Error::EAGAIN))
+			} else {
+				guard.read_queue.push_back(cx.waker().clone());
+				Poll::Pending
+			}
+		})
+		.await
+	}
+
+	async fn read(&self, buffer: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		let (len, _) = self.recvfrom(buffer).await?;
+		Ok(len)
+	}
+
+	async fn getsockname(&self) -> io::Result<Option<Endpoint>> {
+		Ok(Some(Endpoint::Unix(self.local.clone())))
+	}
+
+	async fn getpeername(&self) -> io::Result<Option<Endpoint>> {
+		let (peer, _) = self.peer.as_ref().ok_or(io::Error::ENOTCONN)?;
+		Ok(Some(Endpoint::Unix(peer.clone())))
+	}
+
+	async fn shutdown(&self, how: i32) -> io::Result<()> {
+		match how {
+			SHUT_RD | SHUT_WR | SHUT_RDWR => Ok(()),
+			_ => Err(io::Error::EINVAL),
+		}
+	}
+
+	async fn ioctl(&mut self, cmd: IoCtl, value: bool) -> io::Result<()> {
+		match cmd {
+			IoCtl::NonBlocking => {
+				self.nonblocking = value;
+				Ok(())
+			}
+		}
+	}
+}
+
+impl Drop for DatagramSocket {
+	fn drop(&mut self) {
+		if let Some(name) = self.name.take() {
+			NAMES.lock().remove(&name);
+		}
+
+		// the senders notice that the socket was closed
+		let _ = block_on(
+			async {
+				let mut guard = self.queue.lock().await;
+				guard.closed = true;
+				guard.messages.clear();
+				guard.len = 0;
+				wake_all(&mut guard.write_queue);
+				Ok(())
+			},
+			None,
+		);
+	}
+}
+
+#[async_trait]
+impl ObjectInterface for async_lock::RwLock<DatagramSocket> {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		self.read().await.poll(event).await
+	}
+
+	async fn read(&self, buffer: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		self.read().await.read(buffer).await
+	}
+
+	async fn write(&self, buffer: &[u8]) -> io::Result<usize> {
+		self.read().await.write(buffer).await
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(socket_attr())
+	}
+
+	async fn bind(&self, endpoint: ListenEndpoint) -> io::Result<()> {
+		self.write().await.bind(endpoint).await
+	}
+
+	async fn connect(&self, endpoint: Endpoint) -> io::Result<()> {
+		self.write().await.connect(endpoint).await
+	}
+
+	async fn recvfrom(&self, buffer: &mut [MaybeUninit<u8>]) -> io::Result<(usize, Endpoint)> {
+		self.read().await.recvfrom(buffer).await
+	}
+
+	async fn sendto(&self, buffer: &[u8], endpoint: Endpoint) -> io::Result<usize> {
+		self.read().await.sendto(buffer, endpoint).await
+	}
+
+	async fn getsockname(&self) -> io::Result<Option<Endpoint>> {
+		self.read().await.getsockname().await
+	}
+
+	async fn getpeername(&self) -> io::Result<Option<Endpoint>> {
+		self.read().await.getpeername().await
+	}
+
+	async fn shutdown(&self, how: i32) -> io::Result<()> {
+		self.read().await.shutdown(how).await
+	}
+
+	async fn ioctl(&self, cmd: IoCtl, value: bool) -> io::Result<()> {
+		self.write().await.ioctl(cmd, value).await
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use core::cell::RefCell;
+
+	use super::*;
+
+	#[test]
+	fn bind_same_path() {
+		// inodes of a device number, which isn't used by other tests
+		const DEV: u64 = u64::MAX;
+		let files = RefCell::new(BTreeMap::<String, u64>::new());
+		let create = |path: &str| {
+			let mut files = files.borrow_mut();
+			if files.contains_key(path) {
+				return Err(io::Error::EEXIST);
+			}
+			let ino = files.len() as u64;
+			files.insert(path.into(), ino);
+			Ok(FileAttr {
+				st_dev: DEV,
+				st_ino: ino,
+				..Default::default()
+			})
+		};
+		let remove = |path: &str| {
+			files.borrow_mut().remove(path);
+		};
+
+		let name = bind_path("/tmp/a", Binding::Datagram(Weak::new()), create, remove).unwrap();
+		assert_eq!(name, Name::Inode(DEV, 0));
+		assert_eq!(
+			bind_path("/tmp/a", Binding::Datagram(Weak::new()), create, remove).unwrap_err(),
+			io::Error::EADDRINUSE
+		);
+		assert!(files.borrow().contains_key("/tmp/a"));
+
+		// The file of the bound socket is removed and its inode is reused.
+		files.borrow_mut().clear();
+		assert_eq!(
+			bind_path("/tmp/b", Binding::Datagram(Weak::new()), create, remove).unwrap_err(),
+			io::Error::EADDRINUSE
+		);
+		assert!(!files.borrow().contains_key("/tmp/b"));
+
+		NAMES.lock().remove(&name);
+	}
+}
@@ -149,7 +149,7 @@ impl Socket {
 				}
 				VSOCK_MAP.lock().bind(ep.port)
 			}
-			#[cfg(any(feature = "tcp", feature = "udp"))]
+			#[cfg(any(feature = "tcp", feature = "udp", feature = "unix"))]
 			_ => Err(io::Error::EINVAL),
 		}
 	}
@@ -206,7 +206,7 @@ impl Socket {
 				})
 				.await
 			}
-			#[cfg(any(feature = "tcp", feature = "udp"))]
+			#[cfg(any(feature = "tcp", feature = "udp", feature = "unix"))]
 			_ => Err(io::Error::EINVAL),
 		}
 	}
@@ -0,0 +1,370 @@
+//! Timers, which report their expirations through a file descriptor.
+//!
+//! The expiration time is kept in timer ticks. A waiting task registers its
+//! waker at the timer, which registers itself at the wakeup timers of the
+//! scheduler. This timer of the scheduler is replaced, if the timer is
+//! rearmed, and removed, if the timer is disarmed or closed.
+
+use alloc::boxed::Box;
+use alloc::sync::{Arc, Weak};
+use alloc::task::Wake;
+use alloc::vec::Vec;
+use core::future;
+use core::mem::{self, MaybeUninit};
+use core::task::{Poll, Waker};
+
+use async_trait::async_trait;
+use hermit_sync::InterruptTicketMutex;
+
+use crate::arch::core_local::core_id;
+use crate::arch::kernel::core_local::core_scheduler;
+use crate::fd::{IoCtl, ObjectInterface, PollEvent};
+use crate::scheduler::CoreId;
+use crate::scheduler::task::TimerKey;
+use crate::time::{itimerspec, timespec};
+use crate::{arch, io};
+
+bitflags! {
+	/// Flags of `timerfd_create`
+	#[derive(Debug, Copy, Clone, Default)]
+	pub struct TimerFdFlags: i32 {
+		const TFD_NONBLOCK = 0o4000;
+		const TFD_CLOEXEC = 0o2_000_000;
+	}
+}
+
+bitflags! {
+	/// Flags of `timerfd_settime`
+	#[derive(Debug, Copy, Clone, Default)]
+	pub struct TimerSetFlags: i32 {
+		const TFD_TIMER_ABSTIME = 1;
+		/// The real-time clock can't be set, so this flag has no effect
+		const TFD_TIMER_CANCEL_ON_SET = 2;
+	}
+}
+
+/// Clock, which is used to interpret absolute expiration times
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimerClock {
+	Monotonic,
+	Realtime,
+}
+
+/// Converts `time` into microseconds. Fractions of a microsecond are
+/// rounded up, so that a nonzero time arms the timer. Times, which exceed
+/// `i64::MAX` microseconds, are rejected.
+fn micros(time: &timespec) -> io::Result<u64> {
+	if time.tv_sec < 0 || !(0..1_000_000_000).contains(&time.tv_nsec) {
+		return Err(io::Error::EINVAL);
+	}
+
+	u64::try_from(time.tv_sec)
+		.unwrap()
+		.checked_mul(1_000_000)
+		.and_then(|usec| usec.checked_add(u64::try_from(time.tv_nsec).unwrap().div_ceil(1000)))
+		.filter(|usec| i64::try_from(*usec).is_ok())
+		.ok_or(io::Error::EINVAL)
+}
+
+/// Converts microseconds into a `timespec`, which saturates at `i64::MAX`
+fn from_micros(usec: u64) -> timespec {
+	timespec::from_usec(usec.try_into().unwrap_or(i64::MAX))
+}
+
+#[derive(Debug)]
+struct TimerState {
+	/// Next expiration in timer ticks, `None` if the timer is disarmed
+	deadline: Option<u64>,
+	/// Interval of a periodic timer in microseconds, zero for a one-shot timer
+	interval: u64,
+	/// Number of expirations since the last read
+	expirations: u64,
+	/// Timer of the scheduler, which is registered for the next expiration,
+	/// together with the core, whose scheduler holds it
+	registered: Option<(CoreId, TimerKey)>,
+	nonblocking: bool,
+	wakers: Vec<Waker>,
+}
+
+impl TimerState {
+	/// Counts the expirations until `now` and computes the next expiration
+	fn expire(&mut self, now: u64) {
+		let Some(deadline) = self.deadline.filter(|deadline| *deadline <= now) else {
+			return;
+		};
+
+		if self.interval > 0 {
+			let expirations = ((now - deadline) / self.interval).saturating_add(1);
+			self.expirations = self.expirations.saturating_add(expirations);
+			self.deadline =
+				Some(deadline.saturating_add(expirations.saturating_mul(self.interval)));
+		} else {
+			self.expirations = self.expirations.saturating_add(1);
+			self.deadline = None;
+		}
+	}
+
+	/// Returns the interval and the time until the next expiration
+	fn get(&self, now: u64) -> itimerspec {
+		itimerspec {
+			it_interval: from_micros(self.interval),
+			it_value: self.deadline.map_or_else(timespec::default, |deadline| {
+				from_micros(deadline.saturating_sub(now))
+			}),
+		}
+	}
+}
+
+/// Waker of a timer of the scheduler, which wakes the waiting tasks
+struct ExpirationWaker {
+	state: Weak<InterruptTicketMutex<TimerState>>,
+}
+
+impl Wake for ExpirationWaker {
+	fn wake(self: Arc<Self>) {
+		self.wake_by_ref();
+	}
+
+	fn wake_by_ref(self: &Arc<Self>) {
+		let Some(state) = self.state.upgrade() else {
+			return;
+		};
+
+		let wakers = mem::take(&mut state.lock().wakers);
+		for waker in wakers {
+			waker.wake();
+		}
+	}
+}
+
+#[derive(Debug)]
+pub(crate) struct TimerFd {
+	state: Arc<InterruptTicketMutex<TimerState>>,
+	clock: TimerClock,
+}
+
+impl TimerFd {
+	pub fn new(clock: TimerClock, flags: TimerFdFlags) -> Self {
+		debug!("Create TimerFd {:?}, {:?}", clock, flags);
+		Self {
+			state: Arc::new(InterruptTicketMutex::new(TimerState {
+				deadline: None,
+				interval: 0,
+				expirations: 0,
+				registered: None,
+				nonblocking: flags.contains(TimerFdFlags::TFD_NONBLOCK),
+				wakers: Vec::new(),
+			})),
+			clock,
+		}
+	}
+
+	/// Registers a timer of the scheduler for the next expiration and
+	/// removes the timer of a previous expiration
+	fn schedule(&self, state: &mut TimerState) {
+		let Some(deadline) = state.deadline else {
+			Self::unschedule(state);
+			return;
+		};
+
+		if state.registered.map(|(_, (time, _))| time) != Some(deadline) {
+			Self::unschedule(state);
+			let waker = Waker::from(Arc::new(ExpirationWaker {
+				state: Arc::downgrade(&self.state),
+			}));
+			let key = core_scheduler().add_timer(deadline, waker);
+			state.registered = Some((core_id(), key));
+		}
+	}
+
+	/// Removes the registered timer of the scheduler. A timer on another core
+	/// can't be removed, it only wakes the waiting tasks spuriously.
+	fn unschedule(state: &mut TimerState) {
+		if let Some((core, key)) = state.registered.take() {
+			if core == core_id() {
+				core_scheduler().remove_timer(key);
+			}
+		}
+	}
+
+	/// Registers `waker`, which is woken at the next expiration
+	fn register(&self, state: &mut TimerState, waker: &Waker) {
+		if !state.wakers.iter().any(|w| w.will_wake(waker)) {
+			state.wakers.push(waker.clone());
+		}
+		self.schedule(state);
+	}
+}
+
+impl Drop for TimerFd {
+	fn drop(&mut self) {
+		Self::unschedule(&mut self.state.lock());
+	}
+}
+
+#[async_trait]
+impl ObjectInterface for TimerFd {
+	async fn read(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		let len = mem::size_of::<u64>();
+
+		if buf.len() < len {
+			return Err(io::Error::EINVAL);
+		}
+
+		future::poll_fn(|cx| {
+			let mut guard = self.state.lock();
+			guard.expire(arch::processor::get_timer_ticks());
+
+			if guard.expirations > 0 {
+				let expirations = mem::take(&mut guard.expirations);
+				buf[..len].write_copy_of_slice(&u64::to_ne_bytes(expirations));
+				Poll::Ready(Ok(len))
+			} else if guard.nonblocking {
+				Poll::Ready(Err(io::Error::EAGAIN))
+			} else {
+				self.register(&mut guard, cx.waker());
+				Poll::Pending
+			}
+		})
+		.await
+	}
+
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		future::poll_fn(|cx| {
+			let mut guard = self.state.lock();
+			guard.expire(arch::processor::get_timer_ticks());
+
+			if guard.expirations > 0 {
+				Poll::Ready(Ok(event & (PollEvent::POLLIN | PollEvent::POLLRDNORM)))
+			} else if event.intersects(PollEvent::POLLIN | PollEvent::POLLRDNORM) {
+				self.register(&mut guard, cx.waker());
+				Poll::Pending
+			} else {
+				Poll::Ready(Ok(PollEvent::empty()))
+			}
+		})
+		.await
+	}
+
+	async fn ioctl(&self, cmd: IoCtl, value: bool) -> io::Result<()> {
+		match cmd {
+			IoCtl::NonBlocking => {
+				self.state.lock().nonblocking = value;
+				Ok(())
+			}
+		}
+	}
+
+	async fn timerfd_settime(
+		&self,
+		flags: TimerSetFlags,
+		value: itimerspec,
+	) -> io::Result<itimerspec> {
+		let interval = micros(&value.it_interval)?;
+		let initial = micros(&value.it_value)?;
+
+		let now = arch::processor::get_timer_ticks();
+		let deadline = if initial == 0 {
+			None
+		} else if !flags.contains(TimerSetFlags::TFD_TIMER_ABSTIME) {
+			Some(now.saturating_add(initial))
+		} else {
+			match self.clock {
+				TimerClock::Monotonic => Some(initial),
+				TimerClock::Realtime => {
+					// the real-time clock is the boot time plus the timer ticks
+					let boot_time = arch::kernel::systemtime::now_micros() - now;
+					Some(initial.saturating_sub(boot_time))
+				}
+			}
+		};
+
+		let mut guard = self.state.lock();
+		guard.expire(now);
+		let old = guard.get(now);
+
+		guard.deadline = deadline;
+		guard.interval = interval;
+		guard.expirations = 0;
+		// an expiration time in the past expires immediately
+		guard.expire(now);
+
+		if guard.expirations > 0 {
+			Self::unschedule(&mut guard);
+			let wakers = mem::take(&mut guard.wakers);
+			drop(guard);
+			for waker in wakers {
+				waker.wake();
+			}
+		} else if guard.wakers.is_empty() {
+			Self::unschedule(&mut guard);
+		} else {
+			self.schedule(&mut guard);
+		}
+
+		Ok(old)
+	}
+
+	async fn timerfd_gettime(&self) -> io::Result<itimerspec> {
+		let now = arch::processor::get_timer_ticks();
+		let mut guard = self.state.lock();
+		guard.expire(now);
+
+		Ok(guard.get(now))
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+
+	fn state(deadline: Option<u64>, interval: u64) -> TimerState {
+		TimerState {
+			deadline,
+			interval,
+			expirations: 0,
+			registered: None,
+			nonblocking: false,
+			wakers: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn expire() {
+		let mut oneshot = state(Some(100), 0);
+		oneshot.expire(99);
+		assert_eq!((oneshot.deadline, oneshot.expirations), (Some(100), 0));
+		oneshot.expire(250);
+		assert_eq!((oneshot.deadline, oneshot.expirations), (None, 1));
+		oneshot.expire(1000);
+		assert_eq!(oneshot.expirations, 1);
+
+		let mut periodic = state(Some(100), 50);
+		periodic.expire(100);
+		assert_eq!((periodic.deadline, periodic.expirations), (Some(150), 1));
+		// overruns are counted and the next expiration stays on the grid
+		periodic.expire(260);
+		assert_eq!((periodic.deadline, periodic.expirations), (Some(300), 4));
+
+		// large intervals saturate instead of overflowing
+		let mut large = state(Some(u64::MAX - 10), u64::MAX / 2);
+		large.expire(u64::MAX);
+		assert_eq!((large.deadline, large.expirations), (Some(u64::MAX), 1));
+	}
+
+	#[test]
+	fn get() {
+		let periodic = state(Some(3_500_000), 1_000_000);
+		let value = periodic.get(1_000_000);
+		assert_eq!(
+			(value.it_value.tv_sec, value.it_value.tv_nsec),
+			(2, 500_000_000)
+		);
+		assert_eq!(value.it_interval.tv_sec, 1);
+
+		let far = state(Some(u64::MAX), u64::MAX);
+		let value = far.get(0);
+		assert_eq!(value.it_value.tv_sec, i64::MAX / 1_000_000);
+		assert_eq!(value.it_interval.tv_sec, i64::MAX / 1_000_000);
+	}
+}
@@ -0,0 +1,380 @@
+//! Implements the device file system, which is mounted at `/dev`.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+
+use async_trait::async_trait;
+
+use crate::entropy::{self, Flags};
+use crate::fd::stdio::{GenericStdin, GenericStdout, UhyveStdin, UhyveStdout};
+use crate::fd::{AccessPermission, ObjectInterface, OpenOption, PollEvent};
+use crate::fs::{
+	DEVFS_DEV, DirectoryEntry, DirectoryReader, FileAttr, NodeKind, SeekWhence, StatFs, VfsNode,
+};
+use crate::time::timespec;
+use crate::{arch, env, io};
+
+/// Magic number of the device file system, as reported by `statfs`
+const DEVFS_SUPER_MAGIC: i64 = 0x1373;
+
+/// Character devices, which are provided by the kernel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Device {
+	Null,
+	Zero,
+	Random,
+	Urandom,
+	Tty,
+	Console,
+}
+
+impl Device {
+	/// Returns the major and minor number of the device, as used by Linux
+	fn number(self) -> (u64, u64) {
+		match self {
+			Device::Null => (1, 3),
+			Device::Zero => (1, 5),
+			Device::Random => (1, 8),
+			Device::Urandom => (1, 9),
+			Device::Tty => (5, 0),
+			Device::Console => (5, 1),
+		}
+	}
+
+	fn mode(self) -> AccessPermission {
+		let mode = if self == Device::Console {
+			0o600
+		} else {
+			0o666
+		};
+		AccessPermission::S_IFCHR | AccessPermission::from_bits(mode).unwrap()
+	}
+
+	fn is_terminal(self) -> bool {
+		matches!(self, Device::Tty | Device::Console)
+	}
+}
+
+/// Encodes a device number in the same way as glibc's `makedev`
+const fn makedev(major: u64, minor: u64) -> u64 {
+	((major & 0xffff_f000) << 32)
+		| ((major & 0x0fff) << 8)
+		| ((minor & 0xffff_ff00) << 12)
+		| (minor & 0xff)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DevNode {
+	Device(Device),
+	Symlink(&'static str),
+}
+
+const ENTRIES: &[(&str, DevNode)] = &[
+	("console", DevNode::Device(Device::Console)),
+	("fd", DevNode::Symlink("/proc/self/fd")),
+	("null", DevNode::Device(Device::Null)),
+	("random", DevNode::Device(Device::Random)),
+	("stderr", DevNode::Symlink("/proc/self/fd/2")),
+	("stdin", DevNode::Symlink("/proc/self/fd/0")),
+	("stdout", DevNode::Symlink("/proc/self/fd/1")),
+	("tty", DevNode::Device(Device::Tty)),
+	("urandom", DevNode::Device(Device::Urandom)),
+	("zero", DevNode::Device(Device::Zero)),
+];
+
+fn lookup(name: &str) -> io::Result<DevNode> {
+	ENTRIES
+		.iter()
+		.find(|(entry, _)| *entry == name)
+		.map(|(_, node)| *node)
+		.ok_or(io::Error::ENOENT)
+}
+
+#[derive(Debug)]
+struct CharDeviceInterface {
+	device: Device,
+	attr: FileAttr,
+}
+
+impl CharDeviceInterface {
+	pub fn new(device: Device, attr: FileAttr) -> Self {
+		Self { device, attr }
+	}
+}
+
+#[async_trait]
+impl ObjectInterface for CharDeviceInterface {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		match self.device {
+			Device::Tty | Device::Console => {
+				let input = if env::is_uhyve() {
+					UhyveStdin::new().poll(event).await?
+				} else {
+					GenericStdin::new().poll(event).await?
+				};
+				let output = if env::is_uhyve() {
+					UhyveStdout::new().poll(event).await?
+				} else {
+					GenericStdout::new().poll(event).await?
+				};
+
+				Ok(input | output)
+			}
+			_ => Ok(event.intersection(
+				PollEvent::POLLIN
+					| PollEvent::POLLRDNORM
+					| PollEvent::POLLOUT
+					| PollEvent::POLLWRNORM,
+			)),
+		}
+	}
+
+	async fn read(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		match self.device {
+			Device::Null => Ok(0),
+			Device::Zero => {
+				buf.fill(MaybeUninit::new(0));
+				Ok(buf.len())
+			}
+			Device::Random | Device::Urandom => {
+				buf.fill(MaybeUninit::new(0));
+				let buf = unsafe { buf.assume_init_mut() };
+
+				let ret = entropy::read(buf, Flags::empty());
+				if ret < 0 {
+					Err(num::FromPrimitive::from_isize(-ret).unwrap_or(io::Error::EIO))
+				} else {
+					Ok(ret.try_into().unwrap())
+				}
+			}
+			Device::Tty | Device::Console => {
+				if env::is_uhyve() {
+					UhyveStdin::new().read(buf).await
+				} else {
+					GenericStdin::new().read(buf).await
+				}
+			}
+		}
+	}
+
+	async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		match self.device {
+			// data, which is written to the random devices, isn't used as entropy
+			Device::Null | Device::Zero | Device::Random | Device::Urandom => Ok(buf.len()),
+			Device::Tty | Device::Console => {
+				if env::is_uhyve() {
+					UhyveStdout::new().write(buf).await
+				} else {
+					GenericStdout::new().write(buf).await
+				}
+			}
+		}
+	}
+
+	async fn pread(&self, buf: &mut [MaybeUninit<u8>], _offset: usize) -> io::Result<usize> {
+		if self.device.is_terminal() {
+			Err(io::Error::ESPIPE)
+		} else {
+			self.read(buf).await
+		}
+	}
+
+	async fn pwrite(&self, buf: &[u8], _offset: usize) -> io::Result<usize> {
+		if self.device.is_terminal() {
+			Err(io::Error::ESPIPE)
+		} else {
+			self.write(buf).await
+		}
+	}
+
+	async fn lseek(&self, _offset: isize, _whence: SeekWhence) -> io::Result<isize> {
+		if self.device.is_terminal() {
+			Err(io::Error::ESPIPE)
+		} else {
+			Ok(0)
+		}
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(self.attr)
+	}
+
+	async fn isatty(&self) -> io::Result<bool> {
+		Ok(self.device.is_terminal())
+	}
+}
+
+/// Root directory of the device file system
+#[derive(Debug)]
+pub(crate) struct DevDirectory {
+	attr: FileAttr,
+}
+
+impl DevDirectory {
+	pub fn new() -> Self {
+		let microseconds = arch::kernel::systemtime::now_micros();
+		let t = timespec::from_usec(microseconds as i64);
+
+		Self {
+			attr: FileAttr {
+				st_dev: DEVFS_DEV,
+				st_nlink: 2,
+				st_mode: AccessPermission::S_IFDIR | AccessPermission::from_bits(0o755).unwrap(),
+				st_atim: t,
+				st_mtim: t,
+				st_ctim: t,
+				..Default::default()
+			},
+		}
+	}
+
+	fn node_attr(&self, node: DevNode) -> FileAttr {
+		match node {
+			DevNode::Device(device) => {
+				let (major, minor) = device.number();
+				FileAttr {
+					st_nlink: 1,
+					st_mode: device.mode(),
+					st_rdev: makedev(major, minor),
+					..self.attr
+				}
+			}
+			DevNode::Symlink(target) => FileAttr {
+				st_nlink: 1,
+				st_mode: AccessPermission::S_IFLNK | AccessPermission::from_bits(0o777).unwrap(),
+				st_size: target.len().try_into().unwrap(),
+				..self.attr
+			},
+		}
+	}
+
+	fn entries(&self) -> Vec<DirectoryEntry> {
+		ENTRIES
+			.iter()
+			.map(|(name, node)| DirectoryEntry::with_attr(name.to_string(), &self.node_attr(*node)))
+			.collect()
+	}
+}
+
+impl VfsNode for DevDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		Ok(self.attr)
+	}
+
+	fn get_object(&self) -> io::Result<Arc<dyn ObjectInterface>> {
+		Ok(Arc::new(DirectoryReader::new(self.entries())))
+	}
+
+	fn traverse_readdir(&self, components: &mut Vec<&str>) -> io::Result<Vec<DirectoryEntry>> {
+		if let Some(component) = components.pop() {
+			match lookup(component)? {
+				DevNode::Device(_) => Err(io::Error::ENOTDIR),
+				// symbolic links are resolved before
+				DevNode::Symlink(_) => Err(io::Error::ENOENT),
+			}
+		} else {
+			Ok(self.entries())
+		}
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		let component = components.pop().ok_or(io::Error::EINVAL)?;
+		if !components.is_empty() {
+			return Err(io::Error::ENOTDIR);
+		}
+
+		match lookup(component)? {
+			DevNode::Symlink(target) => Ok(target.to_string()),
+			DevNode::Device(_) => Err(io::Error::EINVAL),
+		}
+	}
+
+	fn traverse_lookup_symlink(
+		&self,
+		components: &mut Vec<&str>,
+		follow: bool,
+	) -> io::Result<Option<String>> {
+		if let Some(component) = components.pop() {
+			if let Ok(DevNode::Symlink(target)) = lookup(component) {
+				if follow || !components.is_empty() {
+					return Ok(Some(target.to_string()));
+				}
+			}
+		}
+
+		Ok(None)
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		if let Some(component) = components.pop() {
+			if !components.is_empty() {
+				return Err(io::Error::ENOTDIR);
+			}
+
+			Ok(self.node_attr(lookup(component)?))
+		} else {
+			Ok(self.attr)
+		}
+	}
+
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		self.traverse_lstat(components)
+	}
+
+	fn traverse_statfs(&self, _components: &mut Vec<&str>) -> io::Result<StatFs> {
+		Ok(StatFs {
+			f_type: DEVFS_SUPER_MAGIC,
+			f_bsize: 4096,
+			f_namelen: 255,
+			f_frsize: 4096,
+			..Default::default()
+		})
+	}
+
+	fn traverse_open(
+		&self,
+		components: &mut Vec<&str>,
+		opt: OpenOption,
+		_mode: AccessPermission,
+	) -> io::Result<Arc<dyn ObjectInterface>> {
+		let Some(component) = components.pop() else {
+			return self.get_object();
+		};
+		if !components.is_empty() {
+			return Err(io::Error::ENOTDIR);
+		}
+
+		let node = match lookup(component) {
+			Ok(node) => node,
+			// new files cannot be created in /dev
+			Err(_) if opt.contains(OpenOption::O_CREAT) => return Err(io::Error::EACCES),
+			Err(err) => return Err(err),
+		};
+
+		if opt.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) {
+			return Err(io::Error::EEXIST);
+		}
+
+		match node {
+			DevNode::Device(device) => {
+				if opt.contains(OpenOption::O_DIRECTORY) {
+					return Err(io::Error::ENOTDIR);
+				}
+
+				Ok(Arc::new(CharDeviceInterface::new(
+					device,
+					self.node_attr(node),
+				)))
+			}
+			// symbolic links are resolved before
+			DevNode::Symlink(_) => Err(io::Error::ELOOP),
+		}
+	}
+}
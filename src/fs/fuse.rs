@@ -1,45 +1,87 @@
 use alloc::borrow::ToOwned;
 use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 use alloc::ffi::CString;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::mem::MaybeUninit;
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use core::task::Poll;
 use core::{future, mem};
 
 use async_lock::Mutex;
 use async_trait::async_trait;
 use fuse_abi::linux::*;
+use hermit_sync::{InterruptTicketMutex, OnceCell, TicketMutex};
+use memory_addresses::VirtAddr;
 use num_traits::FromPrimitive;
 use zerocopy::FromBytes;
 
 use crate::alloc::string::ToString;
-#[cfg(not(feature = "pci"))]
-use crate::arch::kernel::mmio::get_filesystem_driver;
-#[cfg(feature = "pci")]
-use crate::drivers::pci::get_filesystem_driver;
+use crate::arch::kernel::core_local::core_scheduler;
+use crate::drivers::fs::virtio_fs::VirtioFsDriver;
+use crate::drivers::pci::get_filesystem_drivers;
 use crate::drivers::virtio::virtqueue::error::VirtqError;
 use crate::executor::block_on;
-use crate::fd::PollEvent;
+#[cfg(feature = "mmap")]
+use crate::fd::MmapSource;
+use crate::fd::{FallocateFlags, PollEvent};
+use crate::fs::lock::{FileLock, LockOwner, LockType};
 use crate::fs::{
-	self, AccessPermission, DirectoryEntry, FileAttr, NodeKind, ObjectInterface, OpenOption,
-	SeekWhence, VfsNode,
+	self, AccessPermission, DirectoryEntry, DirectoryPosition, FileAttr, FileType, MountFlags,
+	NodeKind, ObjectInterface, OpenOption, SeekWhence, SetAttr, StatFs, VfsNode, XattrFlags,
 };
 use crate::mm::device_alloc::DeviceAlloc;
 use crate::time::{time_t, timespec};
-use crate::{arch, io};
+use crate::{arch, env, io};
 
 // response out layout eg @ https://github.com/zargony/fuse-rs/blob/bf6d1cf03f3277e35b580f3c7b9999255d72ecf3/src/ll/request.rs#L44
 // op in/out sizes/layout: https://github.com/hanwen/go-fuse/blob/204b45dba899dfa147235c255908236d5fde2d32/fuse/opcode.go#L439
 // possible responses for command: qemu/tools/virtiofsd/fuse_lowlevel.h
 
+/// Magic number of FUSE file systems, as reported by `statfs`
+const FUSE_SUPER_MAGIC: i64 = 0x6573_5546;
+
 const MAX_READ_LEN: usize = 1024 * 64;
 const MAX_WRITE_LEN: usize = 1024 * 64;
 
 const U64_SIZE: usize = mem::size_of::<u64>();
 
+/// Size of the pages in the page cache of regular files
+const CACHE_PAGE_SIZE: usize = 4096;
+/// Number of pages, which are read ahead during sequential reads
+const READ_AHEAD_PAGES: u64 = (MAX_READ_LEN / CACHE_PAGE_SIZE) as u64;
+/// Maximum number of dirty pages of an inode, before they are written back
+const MAX_DIRTY_PAGES: usize = 256;
+/// Maximum number of cached pages of an inode
+const MAX_CACHED_PAGES: usize = 4096;
+/// Maximum number of inodes, which keep their page cache after they are closed
+const MAX_CACHED_INODES: usize = 64;
+/// Size of the ranges, in which files are mapped into the DAX window
+const DAX_RANGE_SIZE: usize = 2 * 1024 * 1024;
+/// Reads of at least this length are served from the DAX window
+const DAX_MIN_READ_LEN: usize = MAX_READ_LEN;
+/// Maximum number of bytes, which are copied from the DAX window per read
+const DAX_MAX_READ_LEN: usize = 16 * DAX_RANGE_SIZE;
+
+/// All virtio-fs devices, in the order of their discovery
+static DEVICES: OnceCell<Vec<FuseDevice>> = OnceCell::new();
+
+/// Negotiated properties of the devices, in the same order as `DEVICES`
+static CONNECTIONS: OnceCell<Vec<Connection>> = OnceCell::new();
+
+/// Page caches of regular files, indexed by the device and the node id
+static PAGE_CACHES: TicketMutex<BTreeMap<(usize, u64), SharedPageCache>> =
+	TicketMutex::new(BTreeMap::new());
+
+/// End of a lock, which extends to the end of the file
+const OFFSET_MAX: u64 = i64::MAX as u64;
+/// Initial delay in microseconds, after which a conflicting lock is requested again
+const SETLK_MIN_DELAY: u64 = 1000;
+/// Maximum delay in microseconds between two requests of a conflicting lock
+const SETLK_MAX_DELAY: u64 = 100_000;
+
 const S_IFLNK: u32 = 0o120_000;
 const S_IFMT: u32 = 0o170_000;
 
@@ -60,8 +102,10 @@ pub(crate) mod ops {
 	#![allow(clippy::type_complexity)]
 	use alloc::boxed::Box;
 	use alloc::ffi::CString;
+	use alloc::vec::Vec;
 
 	use fuse_abi::linux::*;
+	use zerocopy::IntoBytes;
 
 	use super::Cmd;
 	use crate::fd::PollEvent;
@@ -101,6 +145,9 @@ pub(crate) mod ops {
 				fuse_init_in {
 					major: 7,
 					minor: 31,
+					flags: FUSE_MAP_ALIGNMENT
+						| FUSE_POSIX_LOCKS | FUSE_FLOCK_LOCKS
+						| FUSE_SETXATTR_EXT,
 					..Default::default()
 				},
 			);
@@ -243,6 +290,57 @@ pub(crate) mod ops {
 		}
 	}
 
+	#[derive(Debug)]
+	pub(crate) struct Setattr;
+
+	impl Op for Setattr {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_SETATTR;
+		type InStruct = fuse_setattr_in;
+		type InPayload = ();
+		type OutStruct = fuse_attr_out;
+		type OutPayload = ();
+	}
+
+	impl Setattr {
+		pub(crate) fn create(nid: u64, setattr_in: fuse_setattr_in) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(nid, setattr_in);
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Fallocate;
+
+	impl Op for Fallocate {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_FALLOCATE;
+		type InStruct = fuse_fallocate_in;
+		type InPayload = ();
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Fallocate {
+		pub(crate) fn create(
+			nid: u64,
+			fh: u64,
+			offset: u64,
+			length: u64,
+			mode: u32,
+		) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(
+				nid,
+				fuse_fallocate_in {
+					fh,
+					offset,
+					length,
+					mode,
+					..Default::default()
+				},
+			);
+			(cmd, 0)
+		}
+	}
+
 	#[derive(Debug)]
 	pub(crate) struct Getattr;
 
@@ -286,6 +384,126 @@ pub(crate) mod ops {
 		}
 	}
 
+	#[derive(Debug)]
+	pub(crate) struct Getxattr;
+
+	impl Op for Getxattr {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_GETXATTR;
+		type InStruct = fuse_getxattr_in;
+		type InPayload = CString;
+		type OutStruct = ();
+		type OutPayload = [u8];
+	}
+
+	impl Getxattr {
+		/// With `size` 0, the server only answers with the size of the value
+		pub(crate) fn create(nid: u64, name: CString, size: u32) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::with_cstring(
+				nid,
+				fuse_getxattr_in {
+					size,
+					..Default::default()
+				},
+				name,
+			);
+			let rsp_payload_len = if size == 0 {
+				core::mem::size_of::<fuse_getxattr_out>() as u32
+			} else {
+				size
+			};
+			(cmd, rsp_payload_len)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Listxattr;
+
+	impl Op for Listxattr {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_LISTXATTR;
+		type InStruct = fuse_getxattr_in;
+		type InPayload = ();
+		type OutStruct = ();
+		type OutPayload = [u8];
+	}
+
+	impl Listxattr {
+		/// With `size` 0, the server only answers with the size of the list
+		pub(crate) fn create(nid: u64, size: u32) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(
+				nid,
+				fuse_getxattr_in {
+					size,
+					..Default::default()
+				},
+			);
+			let rsp_payload_len = if size == 0 {
+				core::mem::size_of::<fuse_getxattr_out>() as u32
+			} else {
+				size
+			};
+			(cmd, rsp_payload_len)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Setxattr;
+
+	impl Op for Setxattr {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_SETXATTR;
+		// The size of `fuse_setxattr_in` depends on `FUSE_SETXATTR_EXT`,
+		// so the header is part of the payload.
+		type InStruct = ();
+		type InPayload = [u8];
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Setxattr {
+		pub(crate) fn create(
+			nid: u64,
+			name: CString,
+			value: &[u8],
+			flags: u32,
+			ext: bool,
+		) -> (Cmd<Self>, u32) {
+			let setxattr_in = fuse_setxattr_in {
+				size: value.len().try_into().unwrap(),
+				flags,
+				..Default::default()
+			};
+			let header = if ext {
+				setxattr_in.as_bytes()
+			} else {
+				&setxattr_in.as_bytes()[..FUSE_COMPAT_SETXATTR_IN_SIZE as usize]
+			};
+			let mut payload =
+				Vec::with_capacity(header.len() + name.count_bytes() + 1 + value.len());
+			payload.extend_from_slice(header);
+			payload.extend_from_slice(name.as_bytes_with_nul());
+			payload.extend_from_slice(value);
+			let cmd = Cmd::with_boxed_slice(nid, (), payload.into_boxed_slice());
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Removexattr;
+
+	impl Op for Removexattr {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_REMOVEXATTR;
+		type InStruct = ();
+		type InPayload = CString;
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Removexattr {
+		pub(crate) fn create(nid: u64, name: CString) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::with_cstring(nid, (), name);
+			(cmd, 0)
+		}
+	}
+
 	#[derive(Debug)]
 	pub(crate) struct Release;
 
@@ -310,6 +528,92 @@ pub(crate) mod ops {
 		}
 	}
 
+	#[derive(Debug)]
+	pub(crate) struct Flush;
+
+	impl Op for Flush {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_FLUSH;
+		type InStruct = fuse_flush_in;
+		type InPayload = ();
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Flush {
+		pub(crate) fn create(nid: u64, fh: u64, lock_owner: u64) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(
+				nid,
+				fuse_flush_in {
+					fh,
+					lock_owner,
+					..Default::default()
+				},
+			);
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Fsync;
+
+	impl Op for Fsync {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_FSYNC;
+		type InStruct = fuse_fsync_in;
+		type InPayload = ();
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Fsync {
+		pub(crate) fn create(nid: u64, fh: u64, datasync: bool) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(
+				nid,
+				fuse_fsync_in {
+					fh,
+					fsync_flags: if datasync { FUSE_FSYNC_FDATASYNC } else { 0 },
+					..Default::default()
+				},
+			);
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Statfs;
+
+	impl Op for Statfs {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_STATFS;
+		type InStruct = ();
+		type InPayload = ();
+		type OutStruct = fuse_statfs_out;
+		type OutPayload = ();
+	}
+
+	impl Statfs {
+		pub(crate) fn create(nid: u64) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(nid, ());
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Syncfs;
+
+	impl Op for Syncfs {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_SYNCFS;
+		type InStruct = fuse_syncfs_in;
+		type InPayload = ();
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Syncfs {
+		pub(crate) fn create() -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(FUSE_ROOT_ID, fuse_syncfs_in::default());
+			(cmd, 0)
+		}
+	}
+
 	#[derive(Debug)]
 	pub(crate) struct Poll;
 
@@ -336,6 +640,126 @@ pub(crate) mod ops {
 		}
 	}
 
+	#[derive(Debug)]
+	pub(crate) struct Getlk;
+
+	impl Op for Getlk {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_GETLK;
+		type InStruct = fuse_lk_in;
+		type InPayload = ();
+		type OutStruct = fuse_lk_out;
+		type OutPayload = ();
+	}
+
+	impl Getlk {
+		pub(crate) fn create(
+			nid: u64,
+			fh: u64,
+			owner: u64,
+			lk: fuse_file_lock,
+		) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(
+				nid,
+				fuse_lk_in {
+					fh,
+					owner,
+					lk,
+					..Default::default()
+				},
+			);
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Setlk;
+
+	impl Op for Setlk {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_SETLK;
+		type InStruct = fuse_lk_in;
+		type InPayload = ();
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Setlk {
+		pub(crate) fn create(
+			nid: u64,
+			fh: u64,
+			owner: u64,
+			lk: fuse_file_lock,
+			flock: bool,
+		) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(
+				nid,
+				fuse_lk_in {
+					fh,
+					owner,
+					lk,
+					lk_flags: if flock { FUSE_LK_FLOCK } else { 0 },
+					..Default::default()
+				},
+			);
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct SetupMapping;
+
+	impl Op for SetupMapping {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_SETUPMAPPING;
+		type InStruct = fuse_setupmapping_in;
+		type InPayload = ();
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl SetupMapping {
+		pub(crate) fn create(
+			nid: u64,
+			fh: u64,
+			foffset: u64,
+			len: u64,
+			moffset: u64,
+		) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(
+				nid,
+				fuse_setupmapping_in {
+					fh,
+					foffset,
+					len,
+					flags: FUSE_SETUPMAPPING_FLAG_READ.into(),
+					moffset,
+				},
+			);
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct RemoveMapping;
+
+	impl Op for RemoveMapping {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_REMOVEMAPPING;
+		// `fuse_removemapping_in` is part of the payload, because the padding
+		// of the header would separate it from the removed ranges.
+		type InStruct = ();
+		type InPayload = [u8];
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl RemoveMapping {
+		pub(crate) fn create(nid: u64, moffset: u64, len: u64) -> (Cmd<Self>, u32) {
+			let mut payload = Vec::new();
+			payload.extend_from_slice(fuse_removemapping_in { count: 1 }.as_bytes());
+			payload.extend_from_slice(fuse_removemapping_one { moffset, len }.as_bytes());
+			let cmd = Cmd::with_boxed_slice(nid, (), payload.into_boxed_slice());
+			(cmd, 0)
+		}
+	}
+
 	#[derive(Debug)]
 	pub(crate) struct Mkdir;
 
@@ -398,31 +822,119 @@ pub(crate) mod ops {
 	}
 
 	#[derive(Debug)]
-	pub(crate) struct Lookup;
+	pub(crate) struct Symlink;
 
-	impl Op for Lookup {
-		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_LOOKUP;
+	impl Op for Symlink {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_SYMLINK;
 		type InStruct = ();
-		type InPayload = CString;
-		type OutStruct = ();
-		// Lookups return [fuse_entry_out] only when there actually is a result. For this reason,
-		// it is not part of the header (since all other headers are always there).
-		type OutPayload = [u8];
+		type InPayload = (CString, CString);
+		type OutStruct = fuse_entry_out;
+		type OutPayload = ();
 	}
 
-	impl Lookup {
-		pub(crate) fn create(name: CString) -> (Cmd<Self>, u32) {
-			let cmd = Cmd::with_cstring(FUSE_ROOT_ID, (), name);
-			(cmd, size_of::<fuse_entry_out>().try_into().unwrap())
+	impl Symlink {
+		pub(crate) fn create(name: CString, target: CString) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::with_cstrings(FUSE_ROOT_ID, (), name, target);
+			(cmd, 0)
 		}
 	}
-}
 
-impl From<fuse_attr> for FileAttr {
-	fn from(attr: fuse_attr) -> FileAttr {
-		FileAttr {
-			st_ino: attr.ino,
-			st_nlink: attr.nlink.into(),
+	#[derive(Debug)]
+	pub(crate) struct Link;
+
+	impl Op for Link {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_LINK;
+		type InStruct = fuse_link_in;
+		type InPayload = CString;
+		type OutStruct = fuse_entry_out;
+		type OutPayload = ();
+	}
+
+	impl Link {
+		pub(crate) fn create(oldnodeid: u64, name: CString) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::with_cstring(FUSE_ROOT_ID, fuse_link_in { oldnodeid }, name);
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Rename;
+
+	impl Op for Rename {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_RENAME;
+		type InStruct = fuse_rename_in;
+		type InPayload = (CString, CString);
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Rename {
+		pub(crate) fn create(old_name: CString, new_name: CString) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::with_cstrings(
+				FUSE_ROOT_ID,
+				fuse_rename_in {
+					newdir: FUSE_ROOT_ID,
+				},
+				old_name,
+				new_name,
+			);
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Rename2;
+
+	impl Op for Rename2 {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_RENAME2;
+		type InStruct = fuse_rename2_in;
+		type InPayload = (CString, CString);
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Rename2 {
+		pub(crate) fn create(old_name: CString, new_name: CString, flags: u32) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::with_cstrings(
+				FUSE_ROOT_ID,
+				fuse_rename2_in {
+					newdir: FUSE_ROOT_ID,
+					flags,
+					..Default::default()
+				},
+				old_name,
+				new_name,
+			);
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Lookup;
+
+	impl Op for Lookup {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_LOOKUP;
+		type InStruct = ();
+		type InPayload = CString;
+		type OutStruct = ();
+		// Lookups return [fuse_entry_out] only when there actually is a result. For this reason,
+		// it is not part of the header (since all other headers are always there).
+		type OutPayload = [u8];
+	}
+
+	impl Lookup {
+		pub(crate) fn create(name: CString) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::with_cstring(FUSE_ROOT_ID, (), name);
+			(cmd, size_of::<fuse_entry_out>().try_into().unwrap())
+		}
+	}
+}
+
+impl From<fuse_attr> for FileAttr {
+	fn from(attr: fuse_attr) -> FileAttr {
+		FileAttr {
+			st_ino: attr.ino,
+			st_nlink: attr.nlink.into(),
 			st_mode: AccessPermission::from_bits_retain(attr.mode),
 			st_uid: attr.uid,
 			st_gid: attr.gid,
@@ -516,6 +1028,23 @@ where
 	}
 }
 
+impl<O: ops::Op> Cmd<O>
+where
+	O: ops::Op<InPayload = (CString, CString)>,
+{
+	fn with_cstrings(nodeid: u64, op_header: O::InStruct, first: CString, second: CString) -> Self {
+		let mut cstring_bytes = first.into_bytes_with_nul().to_vec_in(DeviceAlloc);
+		cstring_bytes.extend_from_slice(second.as_bytes_with_nul());
+		Self {
+			headers: Box::new_in(
+				CmdHeader::with_payload_size(nodeid, op_header, cstring_bytes.len()),
+				DeviceAlloc,
+			),
+			payload: Some(cstring_bytes),
+		}
+	}
+}
+
 impl<O: ops::Op> Cmd<O>
 where
 	O: ops::Op<InPayload = [u8]>,
@@ -546,13 +1075,79 @@ pub(crate) struct Rsp<O: ops::Op> {
 	pub payload: Option<Vec<u8, DeviceAlloc>>,
 }
 
-fn lookup(name: CString) -> Option<u64> {
+/// virtio-fs device, which provides a FUSE file system
+#[derive(Clone, Copy)]
+pub(crate) struct FuseDevice {
+	/// Index of the device in the order of discovery
+	index: usize,
+	driver: &'static InterruptTicketMutex<VirtioFsDriver>,
+}
+
+impl FuseDevice {
+	fn send_command<O: ops::Op + 'static>(
+		&self,
+		cmd: Cmd<O>,
+		rsp_payload_len: u32,
+	) -> Result<Rsp<O>, VirtqError>
+	where
+		<O as ops::Op>::InStruct: Send,
+		<O as ops::Op>::OutStruct: Send,
+	{
+		self.driver.lock().send_command(cmd, rsp_payload_len)
+	}
+
+	fn tag(&self) -> String {
+		self.driver.lock().get_mount_point()
+	}
+
+	/// Converts the attributes `attr` of the server, which are reported with
+	/// the device number of this device
+	fn file_attr(&self, attr: fuse_attr) -> FileAttr {
+		FileAttr {
+			st_dev: fs::FUSE_DEV + self.index as u64,
+			..FileAttr::from(attr)
+		}
+	}
+
+	fn connection(&self) -> Option<&'static Connection> {
+		CONNECTIONS.get()?.get(self.index)
+	}
+
+	fn dax_window(&self) -> Option<&'static DaxWindow> {
+		self.connection()?.dax_window.as_ref()
+	}
+
+	/// Returns `true`, if the server supports all `flags` of `FUSE_INIT`
+	fn supports(&self, flags: u32) -> bool {
+		self.connection()
+			.is_some_and(|connection| connection.flags & flags == flags)
+	}
+}
+
+/// Properties of a device, which are negotiated during the initialization
+#[derive(Debug)]
+struct Connection {
+	/// Flags of the answer to `FUSE_INIT`
+	flags: u32,
+	dax_window: Option<DaxWindow>,
+}
+
+impl core::fmt::Debug for FuseDevice {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("FuseDevice")
+			.field("index", &self.index)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Returns the virtio-fs device with the tag `tag`
+fn device(tag: &str) -> Option<FuseDevice> {
+	DEVICES.get()?.iter().copied().find(|dev| dev.tag() == tag)
+}
+
+fn lookup(dev: FuseDevice, name: CString) -> Option<u64> {
 	let (cmd, rsp_payload_len) = ops::Lookup::create(name);
-	let rsp = get_filesystem_driver()
-		.unwrap()
-		.lock()
-		.send_command(cmd, rsp_payload_len)
-		.ok()?;
+	let rsp = dev.send_command(cmd, rsp_payload_len).ok()?;
 	if rsp.headers.out_header.error == 0 {
 		let entry_out = fuse_entry_out::ref_from_bytes(rsp.payload.as_ref().unwrap()).unwrap();
 		Some(entry_out.nodeid)
@@ -561,13 +1156,10 @@ fn lookup(name: CString) -> Option<u64> {
 	}
 }
 
-fn readlink(nid: u64) -> io::Result<String> {
+fn readlink(dev: FuseDevice, nid: u64) -> io::Result<String> {
 	let len = MAX_READ_LEN as u32;
 	let (cmd, rsp_payload_len) = ops::Readlink::create(nid, len);
-	let rsp = get_filesystem_driver()
-		.unwrap()
-		.lock()
-		.send_command(cmd, rsp_payload_len)?;
+	let rsp = dev.send_command(cmd, rsp_payload_len)?;
 	let len: usize = if rsp.headers.out_header.len as usize - mem::size_of::<fuse_out_header>()
 		>= len.try_into().unwrap()
 	{
@@ -579,19 +1171,967 @@ fn readlink(nid: u64) -> io::Result<String> {
 	Ok(String::from_utf8(rsp.payload.unwrap()[..len].to_vec()).unwrap())
 }
 
+/// Converts the error of an extended attribute request.
+/// Servers without support for extended attributes answer with `ENOSYS`.
+fn xattr_error(error: i32) -> io::Error {
+	if error == -crate::errno::ENOSYS {
+		io::Error::EOPNOTSUPP
+	} else {
+		num::FromPrimitive::from_i32(-error).unwrap_or(io::Error::EIO)
+	}
+}
+
+/// Returns the size of an extended attribute or of the list of names,
+/// which the server reported for a request with size 0
+fn xattr_size<O>(rsp: Rsp<O>) -> io::Result<u32>
+where
+	O: ops::Op<OutStruct = (), OutPayload = [u8]>,
+{
+	if rsp.headers.out_header.error < 0 {
+		return Err(xattr_error(rsp.headers.out_header.error));
+	}
+
+	let payload = rsp.payload.unwrap();
+	let xattr_out =
+		fuse_getxattr_out::ref_from_bytes(&payload[..mem::size_of::<fuse_getxattr_out>()]).unwrap();
+	Ok(xattr_out.size)
+}
+
+/// Returns at most `size` bytes of the payload of an extended attribute request
+fn xattr_value<O>(rsp: Rsp<O>, size: u32) -> io::Result<Vec<u8>>
+where
+	O: ops::Op<OutStruct = (), OutPayload = [u8]>,
+{
+	if rsp.headers.out_header.error < 0 {
+		return Err(xattr_error(rsp.headers.out_header.error));
+	}
+
+	let len = (size as usize)
+		.min((rsp.headers.out_header.len as usize) - mem::size_of::<fuse_out_header>());
+	Ok(rsp.payload.unwrap()[..len].to_vec())
+}
+
+/// Returns the value of the extended attribute `name` of the node `nid`
+fn getxattr(dev: FuseDevice, nid: u64, name: &str) -> io::Result<Vec<u8>> {
+	let name = CString::new(name).map_err(|_| io::Error::EINVAL)?;
+	let (cmd, rsp_payload_len) = ops::Getxattr::create(nid, name.clone(), 0);
+	let size = xattr_size(dev.send_command(cmd, rsp_payload_len)?)?;
+	if size == 0 {
+		return Ok(Vec::new());
+	}
+
+	let (cmd, rsp_payload_len) = ops::Getxattr::create(nid, name, size);
+	xattr_value(dev.send_command(cmd, rsp_payload_len)?, size)
+}
+
+/// Returns the names of the extended attributes of the node `nid`
+fn listxattr(dev: FuseDevice, nid: u64) -> io::Result<Vec<String>> {
+	let (cmd, rsp_payload_len) = ops::Listxattr::create(nid, 0);
+	let size = xattr_size(dev.send_command(cmd, rsp_payload_len)?)?;
+	if size == 0 {
+		return Ok(Vec::new());
+	}
+
+	let (cmd, rsp_payload_len) = ops::Listxattr::create(nid, size);
+	let list = xattr_value(dev.send_command(cmd, rsp_payload_len)?, size)?;
+	Ok(list
+		.split(|&byte| byte == 0)
+		.filter(|name| !name.is_empty())
+		.map(|name| String::from_utf8_lossy(name).into_owned())
+		.collect())
+}
+
+/// Sets the extended attribute `name` of the node `nid` to `value`
+fn setxattr(
+	dev: FuseDevice,
+	nid: u64,
+	name: &str,
+	value: &[u8],
+	flags: XattrFlags,
+) -> io::Result<()> {
+	let name = CString::new(name).map_err(|_| io::Error::EINVAL)?;
+	let ext = dev.supports(FUSE_SETXATTR_EXT);
+	let (cmd, rsp_payload_len) = ops::Setxattr::create(nid, name, value, flags.bits() as u32, ext);
+	let rsp = dev.send_command(cmd, rsp_payload_len)?;
+	if rsp.headers.out_header.error < 0 {
+		return Err(xattr_error(rsp.headers.out_header.error));
+	}
+
+	Ok(())
+}
+
+/// Removes the extended attribute `name` of the node `nid`
+fn removexattr(dev: FuseDevice, nid: u64, name: &str) -> io::Result<()> {
+	let name = CString::new(name).map_err(|_| io::Error::EINVAL)?;
+	let (cmd, rsp_payload_len) = ops::Removexattr::create(nid, name);
+	let rsp = dev.send_command(cmd, rsp_payload_len)?;
+	if rsp.headers.out_header.error < 0 {
+		return Err(xattr_error(rsp.headers.out_header.error));
+	}
+
+	Ok(())
+}
+
+/// Looks up `name` and returns the node id and the attributes of the node
+fn lookup_attr(dev: FuseDevice, name: CString) -> Option<(u64, fuse_attr)> {
+	let (cmd, rsp_payload_len) = ops::Lookup::create(name);
+	let rsp = dev.send_command(cmd, rsp_payload_len).ok()?;
+	if rsp.headers.out_header.error == 0 {
+		let entry_out = fuse_entry_out::ref_from_bytes(rsp.payload.as_ref().unwrap()).unwrap();
+		Some((entry_out.nodeid, entry_out.attr))
+	} else {
+		None
+	}
+}
+
+/// Returns the owner of a lock as number, which identifies it on the server
+fn lock_owner_id(owner: LockOwner) -> u64 {
+	match owner {
+		LockOwner::Process => 1,
+		LockOwner::File(addr) => addr.try_into().unwrap(),
+	}
+}
+
+/// Converts `lock` into the representation of FUSE, which uses an inclusive end
+fn fuse_file_lock(lock: &FileLock) -> fuse_file_lock {
+	fuse_file_lock {
+		start: lock.start,
+		end: if lock.end == u64::MAX {
+			OFFSET_MAX
+		} else {
+			lock.end - 1
+		},
+		type_: num::ToPrimitive::to_u32(&lock.kind).unwrap(),
+		pid: 0,
+	}
+}
+
+/// Reads at most `len` bytes at `offset` of the opened file `fh`
+fn read_from_server(
+	dev: FuseDevice,
+	nid: u64,
+	fh: u64,
+	offset: u64,
+	len: usize,
+) -> io::Result<Vec<u8>> {
+	let len = len.min(MAX_READ_LEN);
+	let (cmd, rsp_payload_len) = ops::Read::create(nid, fh, len.try_into().unwrap(), offset);
+	let rsp = dev.send_command(cmd, rsp_payload_len)?;
+
+	if rsp.headers.out_header.error < 0 {
+		return Err(
+			num::FromPrimitive::from_i32(-rsp.headers.out_header.error).unwrap_or(io::Error::EIO)
+		);
+	}
+
+	let len = len.min((rsp.headers.out_header.len as usize) - mem::size_of::<fuse_out_header>());
+	Ok(rsp
+		.payload
+		.map_or_else(Vec::new, |payload| payload[..len].to_vec()))
+}
+
+/// Writes `buf` at `offset` of the opened file `fh` and returns the number of written bytes
+fn write_to_server(
+	dev: FuseDevice,
+	nid: u64,
+	fh: u64,
+	buf: &[u8],
+	offset: u64,
+) -> io::Result<usize> {
+	let mut truncated_len = buf.len();
+	if truncated_len > MAX_WRITE_LEN {
+		debug!(
+			"Writing longer than max_write_len: {} > {}",
+			buf.len(),
+			MAX_WRITE_LEN
+		);
+		truncated_len = MAX_WRITE_LEN;
+	}
+
+	let truncated_buf = Box::<[u8]>::from(&buf[..truncated_len]);
+	let (cmd, rsp_payload_len) = ops::Write::create(nid, fh, truncated_buf, offset);
+	let rsp = dev.send_command(cmd, rsp_payload_len)?;
+
+	if rsp.headers.out_header.error < 0 {
+		return Err(
+			num::FromPrimitive::from_i32(-rsp.headers.out_header.error).unwrap_or(io::Error::EIO)
+		);
+	}
+
+	let rsp_size = rsp.headers.op_header.size;
+	let rsp_len: usize = if rsp_size > truncated_len.try_into().unwrap() {
+		truncated_len
+	} else {
+		rsp_size.try_into().unwrap()
+	};
+	Ok(rsp_len)
+}
+
+/// Opens the directory with the node id `nid` and returns the file handle
+fn opendir(dev: FuseDevice, nid: u64) -> io::Result<u64> {
+	// Flag 0x10000 for O_DIRECTORY might not be necessary
+	let (mut cmd, rsp_payload_len) = ops::Open::create(nid, 0x10000);
+	cmd.headers.in_header.opcode = fuse_opcode::FUSE_OPENDIR as u32;
+	let rsp = dev.send_command(cmd, rsp_payload_len)?;
+	if rsp.headers.out_header.error < 0 {
+		return Err(
+			num::FromPrimitive::from_i32(-rsp.headers.out_header.error).unwrap_or(io::Error::EIO)
+		);
+	}
+
+	Ok(rsp.headers.op_header.fh)
+}
+
+/// Closes the directory handle `fh`, which was returned by [`opendir`]
+fn releasedir(dev: FuseDevice, nid: u64, fh: u64) -> io::Result<()> {
+	let (mut cmd, rsp_payload_len) = ops::Release::create(nid, fh);
+	cmd.headers.in_header.opcode = fuse_opcode::FUSE_RELEASEDIR as u32;
+	dev.send_command(cmd, rsp_payload_len)?;
+
+	Ok(())
+}
+
+/// Reads the entries of the opened directory `fh`, which follow the position `offset`.
+/// An empty vector marks the end of the directory.
+///
+/// `FUSE_READDIRPLUS` isn't used, because it increases the lookup count of
+/// each entry on the server, which would have to be released by `FUSE_FORGET`.
+fn readdir(dev: FuseDevice, nid: u64, fh: u64, offset: u64) -> io::Result<Vec<DirectoryEntry>> {
+	let len = MAX_READ_LEN as u32;
+	let (mut cmd, rsp_payload_len) = ops::Read::create(nid, fh, len, offset);
+	cmd.headers.in_header.opcode = fuse_opcode::FUSE_READDIR as u32;
+	let rsp = dev.send_command(cmd, rsp_payload_len)?;
+
+	if rsp.headers.out_header.error != 0 {
+		return Err(
+			num::FromPrimitive::from_i32(-rsp.headers.out_header.error).unwrap_or(io::Error::EIO)
+		);
+	}
+
+	let len = (rsp.headers.out_header.len as usize - mem::size_of::<fuse_out_header>())
+		.min(len.try_into().unwrap());
+	let mut data = rsp
+		.payload
+		.as_ref()
+		.map_or(&[][..], |payload| &payload[..len]);
+
+	let mut entries: Vec<DirectoryEntry> = Vec::new();
+	while let Ok((dirent, rest)) = fuse_dirent::read_from_prefix(data) {
+		let namelen: usize = dirent.namelen.try_into().unwrap();
+		let Some(name) = rest.get(..namelen) else {
+			break;
+		};
+
+		entries.push(DirectoryEntry {
+			name: String::from_utf8_lossy(name).into_owned(),
+			ino: dirent.ino,
+			file_type: FileType::from_u32(dirent.type_).unwrap_or(FileType::Unknown),
+			offset: dirent.off,
+		});
+
+		// Align to dirent struct
+		let record_len = (data.len() - rest.len() + namelen).next_multiple_of(U64_SIZE);
+		data = data.get(record_len..).unwrap_or_default();
+	}
+
+	Ok(entries)
+}
+
+/// Reads all entries of the directory with the node id `nid`
+fn readdir_all(dev: FuseDevice, nid: u64) -> io::Result<Vec<DirectoryEntry>> {
+	let fh = opendir(dev, nid)?;
+
+	let mut entries: Vec<DirectoryEntry> = Vec::new();
+	let mut offset = 0;
+	let ret = loop {
+		match readdir(dev, nid, fh, offset) {
+			Ok(page) => {
+				let Some(last) = page.last() else {
+					break Ok(entries);
+				};
+				offset = last.offset;
+				entries.extend(page);
+			}
+			Err(err) => break Err(err),
+		}
+	};
+
+	releasedir(dev, nid, fh)?;
+	ret
+}
+
+/// Converts the attribute changes to the input of `FUSE_SETATTR`
+fn setattr_in(attr: &SetAttr) -> fuse_setattr_in {
+	let mut setattr_in = fuse_setattr_in::default();
+
+	if let Some(mode) = attr.mode {
+		setattr_in.valid |= FATTR_MODE;
+		setattr_in.mode = mode.bits();
+	}
+	if let Some(uid) = attr.uid {
+		setattr_in.valid |= FATTR_UID;
+		setattr_in.uid = uid;
+	}
+	if let Some(gid) = attr.gid {
+		setattr_in.valid |= FATTR_GID;
+		setattr_in.gid = gid;
+	}
+	if let Some(atime) = attr.atime {
+		setattr_in.valid |= FATTR_ATIME;
+		setattr_in.atime = atime.tv_sec.try_into().unwrap_or_default();
+		setattr_in.atimensec = atime.tv_nsec.try_into().unwrap_or_default();
+	}
+	if let Some(mtime) = attr.mtime {
+		setattr_in.valid |= FATTR_MTIME;
+		setattr_in.mtime = mtime.tv_sec.try_into().unwrap_or_default();
+		setattr_in.mtimensec = mtime.tv_nsec.try_into().unwrap_or_default();
+	}
+
+	setattr_in
+}
+
+#[derive(Debug)]
+struct CachePage {
+	/// Content of the page, which can be shorter than a page. The missing
+	/// bytes are zero, as long as they are part of the file.
+	data: Vec<u8>,
+	dirty: bool,
+}
+
+type SharedPageCache = Arc<TicketMutex<PageCache>>;
+
+/// Consecutive dirty pages, which are written back with a single request:
+/// the file offset, the indices of the pages and their content
+type DirtyRun = (u64, Vec<u64>, Vec<u8>);
+
+/// Cached content of a regular file, which is shared by all handles of the inode
+#[derive(Debug)]
+struct PageCache {
+	dev: FuseDevice,
+	nid: u64,
+	/// Cached pages, indexed by the page number
+	pages: BTreeMap<u64, CachePage>,
+	/// Size of the file, including data which isn't written back
+	size: u64,
+	/// Modification time of the file, when its pages were cached
+	mtime: (u64, u32),
+	/// File handle, which is used to write back dirty pages
+	writer: Option<u64>,
+	/// Is incremented, when clean pages are dropped. Pages, which were
+	/// requested from the server before, aren't inserted afterwards.
+	generation: u64,
+	/// Number of write backs, which are in progress
+	writebacks: usize,
+}
+
+impl PageCache {
+	fn new(dev: FuseDevice, nid: u64) -> Self {
+		Self {
+			dev,
+			nid,
+			pages: BTreeMap::new(),
+			size: 0,
+			mtime: (0, 0),
+			writer: None,
+			generation: 0,
+			writebacks: 0,
+		}
+	}
+
+	/// Returns true, if the cache contains data, which the server doesn't know yet
+	fn is_dirty(&self) -> bool {
+		self.writebacks > 0 || self.pages.values().any(|page| page.dirty)
+	}
+
+	/// Returns the number of bytes of the page `index`, which are part of the file
+	fn page_len(&self, index: u64) -> usize {
+		let start = index * CACHE_PAGE_SIZE as u64;
+		self.size
+			.saturating_sub(start)
+			.min(CACHE_PAGE_SIZE as u64)
+			.try_into()
+			.unwrap()
+	}
+
+	/// Drops all clean pages. Pages, which are written back at the moment,
+	/// are kept, because the server might still return the old content.
+	fn invalidate(&mut self) {
+		let writeback = self.writebacks > 0;
+		self.pages.retain(|_, page| writeback || page.dirty);
+		self.generation += 1;
+	}
+
+	/// Updates the cache with the attributes of the server. The cached pages
+	/// are dropped, if the file was modified by someone else.
+	fn update(&mut self, attr: &fuse_attr) {
+		// dirty pages are newer than the attributes of the server
+		if self.is_dirty() {
+			return;
+		}
+
+		let mtime = (attr.mtime, attr.mtimensec);
+		if self.size != attr.size || self.mtime != mtime {
+			self.invalidate();
+			self.size = attr.size;
+			self.mtime = mtime;
+		}
+	}
+
+	/// Returns the first range of missing pages between `first` and `last`,
+	/// which can be read with a single request
+	fn missing(&self, first: u64, last: u64) -> Option<(u64, u64)> {
+		let pages_per_read = (MAX_READ_LEN / CACHE_PAGE_SIZE) as u64;
+		let index = (first..=last).find(|index| !self.pages.contains_key(index))?;
+
+		let mut count = 1;
+		while count < pages_per_read
+			&& index + count <= last
+			&& !self.pages.contains_key(&(index + count))
+		{
+			count += 1;
+		}
+
+		Some((index, count))
+	}
+
+	/// Inserts the pages `index..index + count`, which were read from the server.
+	/// Pages, which were cached in the meantime, are newer and kept.
+	fn insert(&mut self, index: u64, count: u64, data: &[u8]) {
+		for (i, chunk) in (index..index + count).zip(data.chunks(CACHE_PAGE_SIZE)) {
+			self.pages.entry(i).or_insert_with(|| CachePage {
+				data: chunk.to_vec(),
+				dirty: false,
+			});
+		}
+		// the remaining pages are beyond the end of the file on the server
+		for i in index + data.len().div_ceil(CACHE_PAGE_SIZE) as u64..index + count {
+			self.pages.entry(i).or_insert(CachePage {
+				data: Vec::new(),
+				dirty: false,
+			});
+		}
+	}
+
+	/// Drops clean pages, if too many pages are cached. The pages between
+	/// `first` and `last` were just requested and are kept.
+	fn shrink(&mut self, first: u64, last: u64) {
+		if self.pages.len() > MAX_CACHED_PAGES {
+			self.pages
+				.retain(|index, page| page.dirty || (first..=last).contains(index));
+		}
+	}
+
+	/// Copies `len` bytes at `offset` into `buf`. Returns the index of the
+	/// first missing page, if not all pages are cached.
+	fn copy_to(&self, buf: &mut [MaybeUninit<u8>], offset: u64, len: usize) -> Result<(), u64> {
+		let first = offset / CACHE_PAGE_SIZE as u64;
+		let last = (offset + len as u64 - 1) / CACHE_PAGE_SIZE as u64;
+		if let Some(index) = (first..=last).find(|index| !self.pages.contains_key(index)) {
+			return Err(index);
+		}
+
+		let mut pos = 0;
+		while pos < len {
+			let index = (offset + pos as u64) / CACHE_PAGE_SIZE as u64;
+			let start = usize::try_from((offset + pos as u64) % CACHE_PAGE_SIZE as u64).unwrap();
+			let end = (start + len - pos).min(self.page_len(index));
+			let data = &self.pages[&index].data;
+
+			let buf = &mut buf[pos..pos + end - start];
+			let copied = data.len().clamp(start, end) - start;
+			buf[..copied].write_copy_of_slice(&data[start..start + copied]);
+			buf[copied..].fill(MaybeUninit::new(0));
+			pos += end - start;
+		}
+
+		Ok(())
+	}
+
+	/// Writes `buf` at `offset` into the cache. Returns the index of a
+	/// partially written page, which has to be read before.
+	fn copy_from(&mut self, fh: u64, buf: &[u8], offset: u64) -> Result<(), u64> {
+		let first = offset / CACHE_PAGE_SIZE as u64;
+		let last = (offset + buf.len() as u64).div_ceil(CACHE_PAGE_SIZE as u64);
+		let partial = [
+			(first, offset),
+			(last.saturating_sub(1), offset + buf.len() as u64),
+		];
+		for (index, pos) in partial {
+			if pos % CACHE_PAGE_SIZE as u64 != 0
+				&& self.page_len(index) > 0
+				&& !self.pages.contains_key(&index)
+			{
+				return Err(index);
+			}
+		}
+
+		let mut pos = 0;
+		while pos < buf.len() {
+			let index = (offset + pos as u64) / CACHE_PAGE_SIZE as u64;
+			let start = usize::try_from((offset + pos as u64) % CACHE_PAGE_SIZE as u64).unwrap();
+			let end = (start + buf.len() - pos).min(CACHE_PAGE_SIZE);
+
+			let page = self.pages.entry(index).or_insert(CachePage {
+				data: Vec::new(),
+				dirty: false,
+			});
+			if page.data.len() < end {
+				page.data.resize(end, 0);
+			}
+			page.data[start..end].copy_from_slice(&buf[pos..pos + end - start]);
+			page.dirty = true;
+			pos += end - start;
+		}
+
+		self.size = self.size.max(offset + buf.len() as u64);
+		self.writer = Some(fh);
+
+		Ok(())
+	}
+
+	/// Takes the dirty pages for a write back and marks them as clean.
+	/// Consecutive pages are combined to a single buffer, which is returned
+	/// together with the offset and the indices of the pages.
+	fn take_dirty(&mut self) -> Option<(u64, Vec<DirtyRun>)> {
+		let fh = self.writer.take()?;
+
+		let dirty: Vec<u64> = self
+			.pages
+			.iter()
+			.filter_map(|(index, page)| page.dirty.then_some(*index))
+			.collect();
+		let mut runs = Vec::new();
+		let mut i = 0;
+		while i < dirty.len() {
+			let first = dirty[i];
+			let mut indices = Vec::new();
+			let mut buf: Vec<u8> = Vec::new();
+			while i < dirty.len()
+				&& dirty[i] == first + (buf.len() / CACHE_PAGE_SIZE) as u64
+				&& buf.len() + CACHE_PAGE_SIZE <= MAX_WRITE_LEN
+			{
+				let len = self.page_len(dirty[i]);
+				let page = self.pages.get_mut(&dirty[i]).unwrap();
+				page.data.resize(len, 0);
+				page.dirty = false;
+				buf.extend_from_slice(&page.data);
+				indices.push(dirty[i]);
+				i += 1;
+
+				if len < CACHE_PAGE_SIZE {
+					break;
+				}
+			}
+
+			runs.push((first * CACHE_PAGE_SIZE as u64, indices, buf));
+		}
+
+		self.writebacks += 1;
+		Some((fh, runs))
+	}
+
+	/// Marks the pages of a failed write back as dirty again
+	fn restore(&mut self, fh: u64, indices: &[u64]) {
+		for index in indices {
+			if let Some(page) = self.pages.get_mut(index) {
+				page.dirty = true;
+			}
+		}
+		self.writer.get_or_insert(fh);
+	}
+}
+
+/// Reads the missing pages between `first` and `last`. The cache isn't
+/// locked during the requests to the server.
+fn fetch_pages(cache: &SharedPageCache, fh: u64, first: u64, last: u64) -> io::Result<()> {
+	loop {
+		let (dev, nid, generation, (index, count)) = {
+			let guard = cache.lock();
+			let Some(missing) = guard.missing(first, last) else {
+				break;
+			};
+			(guard.dev, guard.nid, guard.generation, missing)
+		};
+
+		let offset = index * CACHE_PAGE_SIZE as u64;
+		let len = usize::try_from(count).unwrap() * CACHE_PAGE_SIZE;
+		let data = read_from_server(dev, nid, fh, offset, len)?;
+
+		// the pages are outdated, if the cache was invalidated in the meantime
+		let mut guard = cache.lock();
+		if guard.generation == generation {
+			guard.insert(index, count, &data);
+		}
+	}
+
+	cache.lock().shrink(first, last);
+	Ok(())
+}
+
+/// Reads from the page cache and fetches missing pages from the server.
+/// Additionally, `read_ahead` pages are fetched after the requested range.
+fn read_cached(
+	cache: &SharedPageCache,
+	fh: u64,
+	buf: &mut [MaybeUninit<u8>],
+	offset: u64,
+	read_ahead: u64,
+) -> io::Result<usize> {
+	let mut fetch = None;
+	loop {
+		if let Some((first, last)) = fetch {
+			fetch_pages(cache, fh, first, last)?;
+		}
+
+		let guard = cache.lock();
+		if offset >= guard.size || buf.is_empty() {
+			return Ok(0);
+		}
+
+		let len: usize = (guard.size - offset)
+			.min(buf.len().try_into().unwrap())
+			.try_into()
+			.unwrap();
+		if fetch.is_some() && guard.copy_to(buf, offset, len).is_ok() {
+			return Ok(len);
+		}
+
+		let first = offset / CACHE_PAGE_SIZE as u64;
+		let last = (offset + len as u64 - 1) / CACHE_PAGE_SIZE as u64;
+		let last_page = (guard.size - 1) / CACHE_PAGE_SIZE as u64;
+		fetch = Some((first, (last + read_ahead).min(last_page)));
+	}
+}
+
+/// Writes into the page cache. The pages are written back, if too many pages are dirty.
+fn write_cached(cache: &SharedPageCache, fh: u64, buf: &[u8], offset: u64) -> io::Result<usize> {
+	// a partially written page has to be read before
+	while let Err(index) = cache.lock().copy_from(fh, buf, offset) {
+		fetch_pages(cache, fh, index, index)?;
+	}
+
+	let dirty = cache
+		.lock()
+		.pages
+		.values()
+		.filter(|page| page.dirty)
+		.count();
+	if dirty > MAX_DIRTY_PAGES {
+		writeback_pages(cache)?;
+	}
+
+	Ok(buf.len())
+}
+
+/// Writes all dirty pages back to the server. The cache isn't locked during
+/// the requests to the server.
+fn writeback_pages(cache: &SharedPageCache) -> io::Result<()> {
+	let (dev, nid, (fh, runs)) = {
+		let mut guard = cache.lock();
+		let Some(dirty) = guard.take_dirty() else {
+			return Ok(());
+		};
+		(guard.dev, guard.nid, dirty)
+	};
+
+	let mut result = Ok(());
+	let mut failed = Vec::new();
+	for (offset, indices, buf) in runs {
+		if result.is_ok() {
+			let mut written = 0;
+			while written < buf.len() {
+				match write_to_server(dev, nid, fh, &buf[written..], offset + written as u64) {
+					Ok(0) => result = Err(io::Error::EIO),
+					Ok(len) => {
+						written += len;
+						continue;
+					}
+					Err(err) => result = Err(err),
+				}
+				break;
+			}
+		}
+		if result.is_err() {
+			failed.extend(indices);
+		}
+	}
+
+	let mut guard = cache.lock();
+	guard.writebacks -= 1;
+	if result.is_err() {
+		guard.restore(fh, &failed);
+	}
+
+	result
+}
+
+/// Returns the page cache of the inode `nid`
+fn page_cache(dev: FuseDevice, nid: u64) -> SharedPageCache {
+	let mut caches = PAGE_CACHES.lock();
+	if let Some(cache) = caches.get(&(dev.index, nid)) {
+		return cache.clone();
+	}
+
+	if caches.len() >= MAX_CACHED_INODES {
+		// drop the caches of inodes, which aren't opened anymore
+		caches.retain(|_, cache| Arc::strong_count(cache) > 1 || cache.lock().is_dirty());
+	}
+
+	let cache = Arc::new(TicketMutex::new(PageCache::new(dev, nid)));
+	caches.insert((dev.index, nid), cache.clone());
+	cache
+}
+
+/// Returns the page cache of the inode `nid`, if it exists
+fn existing_page_cache(dev: FuseDevice, nid: u64) -> Option<SharedPageCache> {
+	PAGE_CACHES.lock().get(&(dev.index, nid)).cloned()
+}
+
+/// Writes back the dirty pages of the inode `nid`. If `invalidate` is set,
+/// the cached pages are dropped as well.
+fn writeback_page_cache(dev: FuseDevice, nid: u64, invalidate: bool) -> io::Result<()> {
+	if let Some(cache) = existing_page_cache(dev, nid) {
+		writeback_pages(&cache)?;
+		if invalidate {
+			cache.lock().invalidate();
+		}
+	}
+
+	Ok(())
+}
+
+/// Drops the cached pages of the inode `nid`, after the server changed the file
+fn update_page_cache(dev: FuseDevice, nid: u64, attr: &fuse_attr) {
+	if let Some(cache) = existing_page_cache(dev, nid) {
+		let mut cache = cache.lock();
+		cache.invalidate();
+		cache.update(attr);
+	}
+}
+
+/// Range of a file, which is mapped into a slot of the DAX window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct DaxRange {
+	nid: u64,
+	/// Offset in the file, aligned to `DAX_RANGE_SIZE`
+	foffset: u64,
+}
+
+#[derive(Debug)]
+struct DaxSlots {
+	/// Mapped range of each slot
+	ranges: Vec<Option<DaxRange>>,
+	/// Slot of each mapped range
+	index: BTreeMap<DaxRange, usize>,
+	/// Number of pages of memory mappings in each slot. Slots, which are
+	/// used by memory mappings, aren't reused.
+	pins: Vec<usize>,
+	/// Next slot, which is reused if all slots are mapped
+	next_victim: usize,
+}
+
+/// Shared memory window of a virtio-fs device, in which the server maps
+/// ranges of files (DAX). Reads are copied from the host's page cache
+/// instead of being transferred through the virtqueue.
+#[derive(Debug)]
+struct DaxWindow {
+	addr: VirtAddr,
+	/// Is cleared, if the server doesn't support mappings
+	enabled: AtomicBool,
+	slots: TicketMutex<DaxSlots>,
+}
+
+impl DaxWindow {
+	fn new(addr: VirtAddr, len: usize) -> Option<Self> {
+		let count = len / DAX_RANGE_SIZE;
+		if count == 0 {
+			return None;
+		}
+
+		let mut ranges = Vec::new();
+		ranges.resize(count, None);
+		Some(Self {
+			addr,
+			enabled: AtomicBool::new(true),
+			slots: TicketMutex::new(DaxSlots {
+				ranges,
+				index: BTreeMap::new(),
+				pins: vec![0; count],
+				next_victim: 0,
+			}),
+		})
+	}
+
+	/// Returns the slot, in which `range` is mapped. If the range isn't
+	/// mapped yet, a free slot or the least recently mapped slot, which isn't
+	/// used by memory mappings, is used.
+	fn map(
+		&self,
+		dev: FuseDevice,
+		slots: &mut DaxSlots,
+		fh: u64,
+		range: DaxRange,
+	) -> io::Result<usize> {
+		if let Some(slot) = slots.index.get(&range) {
+			return Ok(*slot);
+		}
+
+		let slot = if let Some(slot) = slots.ranges.iter().position(Option::is_none) {
+			slot
+		} else {
+			let count = slots.ranges.len();
+			let slot = (0..count)
+				.map(|i| (slots.next_victim + i) % count)
+				.find(|slot| slots.pins[*slot] == 0)
+				.ok_or(io::Error::EAGAIN)?;
+			slots.next_victim = (slot + 1) % count;
+
+			let victim = slots.ranges[slot].take().unwrap();
+			slots.index.remove(&victim);
+			let (cmd, rsp_payload_len) = ops::RemoveMapping::create(
+				victim.nid,
+				(slot * DAX_RANGE_SIZE).try_into().unwrap(),
+				DAX_RANGE_SIZE.try_into().unwrap(),
+			);
+			let rsp = dev.send_command(cmd, rsp_payload_len)?;
+			if rsp.headers.out_header.error < 0 {
+				warn!(
+					"Unable to remove the mapping of slot {slot}: {}",
+					rsp.headers.out_header.error
+				);
+			}
+			slot
+		};
+
+		let (cmd, rsp_payload_len) = ops::SetupMapping::create(
+			range.nid,
+			fh,
+			range.foffset,
+			DAX_RANGE_SIZE.try_into().unwrap(),
+			(slot * DAX_RANGE_SIZE).try_into().unwrap(),
+		);
+		let rsp = dev.send_command(cmd, rsp_payload_len)?;
+		if rsp.headers.out_header.error < 0 {
+			let err = num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+				.unwrap_or(io::Error::EIO);
+			if matches!(err, io::Error::ENOSYS | io::Error::EOPNOTSUPP) {
+				info!("virtio-fs server doesn't support DAX mappings");
+				self.enabled.store(false, Ordering::Relaxed);
+			}
+			return Err(err);
+		}
+
+		slots.ranges[slot] = Some(range);
+		slots.index.insert(range, slot);
+		Ok(slot)
+	}
+
+	/// Copies the content of the file at `offset` into `buf`. The caller has to
+	/// ensure that the file isn't shorter, because the access of unbacked pages
+	/// of the window fails. Returns the number of copied bytes, which is only
+	/// smaller than `buf.len()`, if the mapping of a range failed.
+	fn read(
+		&self,
+		dev: FuseDevice,
+		nid: u64,
+		fh: u64,
+		buf: &mut [MaybeUninit<u8>],
+		offset: u64,
+	) -> io::Result<usize> {
+		let mut pos = 0;
+		while pos < buf.len() {
+			let file_offset = offset + u64::try_from(pos).unwrap();
+			let range = DaxRange {
+				nid,
+				foffset: file_offset - file_offset % DAX_RANGE_SIZE as u64,
+			};
+			let start = usize::try_from(file_offset - range.foffset).unwrap();
+			let len = (DAX_RANGE_SIZE - start).min(buf.len() - pos);
+
+			let mut slots = self.slots.lock();
+			let slot = match self.map(dev, &mut slots, fh, range) {
+				Ok(slot) => slot,
+				Err(err) if pos == 0 => return Err(err),
+				Err(_) => break,
+			};
+			let src = unsafe {
+				core::slice::from_raw_parts(
+					(self.addr + slot * DAX_RANGE_SIZE + start).as_ptr::<u8>(),
+					len,
+				)
+			};
+			buf[pos..pos + len].write_copy_of_slice(src);
+			drop(slots);
+
+			pos += len;
+		}
+
+		Ok(pos)
+	}
+}
+
 #[derive(Debug)]
 struct FuseFileHandleInner {
+	dev: FuseDevice,
 	fuse_nid: Option<u64>,
 	fuse_fh: Option<u64>,
 	offset: usize,
+	/// Page cache of the inode or `None`, if the cache is bypassed
+	cache: Option<SharedPageCache>,
+	/// Offset after the last read, to detect sequential reads
+	next_read: usize,
+	/// Owner of the locks of the open file, which are managed by the server
+	lock_owner: Option<u64>,
 }
 
 impl FuseFileHandleInner {
-	pub fn new() -> Self {
+	pub fn new(dev: FuseDevice) -> Self {
 		Self {
+			dev,
 			fuse_nid: None,
 			fuse_fh: None,
 			offset: 0,
+			cache: None,
+			next_read: 0,
+			lock_owner: None,
+		}
+	}
+
+	/// Sets up the page cache after the file was opened. `open_flags` is the
+	/// answer of the server and `attr` contains the attributes during the lookup.
+	fn enable_cache(
+		&mut self,
+		opt: OpenOption,
+		open_flags: u32,
+		attr: &fuse_attr,
+	) -> io::Result<()> {
+		let nid = self.fuse_nid.unwrap();
+		if opt.contains(OpenOption::O_DIRECT) || open_flags & FOPEN_DIRECT_IO != 0 {
+			// other handles may have cached the file
+			return writeback_page_cache(self.dev, nid, true);
+		}
+
+		let cache = page_cache(self.dev, nid);
+		{
+			let mut guard = cache.lock();
+			if open_flags & FOPEN_KEEP_CACHE == 0 {
+				guard.invalidate();
+			}
+			guard.update(attr);
+		}
+		self.cache = Some(cache);
+
+		Ok(())
+	}
+
+	/// Writes back the dirty pages of the inode
+	fn writeback(&self) -> io::Result<()> {
+		if let Some(cache) = &self.cache {
+			writeback_pages(cache)
+		} else if let Some(nid) = self.fuse_nid {
+			writeback_page_cache(self.dev, nid, false)
+		} else {
+			Ok(())
 		}
 	}
 
@@ -602,10 +2142,7 @@ impl FuseFileHandleInner {
 		future::poll_fn(|cx| {
 			if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
 				let (cmd, rsp_payload_len) = ops::Poll::create(nid, fh, kh, events);
-				let rsp = get_filesystem_driver()
-					.ok_or(io::Error::ENOSYS)?
-					.lock()
-					.send_command(cmd, rsp_payload_len)?;
+				let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
 
 				if rsp.headers.out_header.error < 0 {
 					Poll::Ready(Err(io::Error::EIO))
@@ -631,30 +2168,149 @@ impl FuseFileHandleInner {
 	}
 
 	fn read(&mut self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		let len = self.pread(buf, self.offset)?;
+		self.offset += len;
+		Ok(len)
+	}
+
+	/// Reads large blocks of cached files from the DAX window. Returns `None`,
+	/// if the window cannot be used.
+	fn dax_read(
+		&mut self,
+		buf: &mut [MaybeUninit<u8>],
+		offset: usize,
+	) -> Option<io::Result<usize>> {
+		let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) else {
+			return None;
+		};
+		let window = self.dev.dax_window()?;
+		if self.cache.is_none()
+			|| buf.len() < DAX_MIN_READ_LEN
+			|| !window.enabled.load(Ordering::Relaxed)
+		{
+			return None;
+		}
+
+		// writes back the dirty pages and returns the current size of the file
+		let size = match self.fstat() {
+			Ok(attr) => usize::try_from(attr.st_size).unwrap(),
+			Err(err) => return Some(Err(err)),
+		};
+		let len = buf
+			.len()
+			.min(size.saturating_sub(offset))
+			.min(DAX_MAX_READ_LEN);
+		if len == 0 {
+			return Some(Ok(0));
+		}
+
+		match window.read(
+			self.dev,
+			nid,
+			fh,
+			&mut buf[..len],
+			offset.try_into().unwrap(),
+		) {
+			Ok(len) => {
+				self.next_read = offset + len;
+				Some(Ok(len))
+			}
+			Err(err) => {
+				debug!("Unable to read {nid} through the DAX window: {err:?}");
+				None
+			}
+		}
+	}
+
+	/// Maps the page at `offset` into the DAX window for a memory mapping and
+	/// pins its slot
+	#[cfg(feature = "mmap")]
+	fn map_window(&mut self, offset: usize) -> io::Result<VirtAddr> {
+		let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) else {
+			return Err(io::Error::EBADF);
+		};
+		let window = self.dev.dax_window().ok_or(io::Error::ENODEV)?;
+		if !window.enabled.load(Ordering::Relaxed) {
+			return Err(io::Error::ENODEV);
+		}
+
+		// writes back the dirty pages, the access of pages beyond the end
+		// of the file fails
+		let size = usize::try_from(self.fstat()?.st_size).unwrap();
+		if offset >= size {
+			return Err(io::Error::ENXIO);
+		}
+
+		let foffset = offset - offset % DAX_RANGE_SIZE;
+		let range = DaxRange {
+			nid,
+			foffset: foffset.try_into().unwrap(),
+		};
+		let mut slots = window.slots.lock();
+		let slot = window.map(self.dev, &mut slots, fh, range)?;
+		slots.pins[slot] += 1;
+
+		Ok(window.addr + slot * DAX_RANGE_SIZE + (offset - foffset))
+	}
+
+	/// Releases a page, which was mapped by `map_window`. Writes through the
+	/// window bypass the page cache, so that the cache is invalidated.
+	#[cfg(feature = "mmap")]
+	fn unmap_window(&mut self, offset: usize) {
+		let (Some(nid), Some(window)) = (self.fuse_nid, self.dev.dax_window()) else {
+			return;
+		};
+
+		let range = DaxRange {
+			nid,
+			foffset: (offset - offset % DAX_RANGE_SIZE).try_into().unwrap(),
+		};
+		let mut slots = window.slots.lock();
+		if let Some(slot) = slots.index.get(&range).copied() {
+			slots.pins[slot] -= 1;
+		}
+		drop(slots);
+
+		if let Some(cache) = &self.cache {
+			cache.lock().invalidate();
+		}
+	}
+
+	fn pread(&mut self, buf: &mut [MaybeUninit<u8>], offset: usize) -> io::Result<usize> {
+		if let Some(result) = self.dax_read(buf, offset) {
+			return result;
+		}
+
 		let mut len = buf.len();
 		if len > MAX_READ_LEN {
 			debug!("Reading longer than max_read_len: {}", len);
 			len = MAX_READ_LEN;
 		}
 		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
-			let (cmd, rsp_payload_len) =
-				ops::Read::create(nid, fh, len.try_into().unwrap(), self.offset as u64);
-			let rsp = get_filesystem_driver()
-				.ok_or(io::Error::ENOSYS)?
-				.lock()
-				.send_command(cmd, rsp_payload_len)?;
-			let len: usize =
-				if (rsp.headers.out_header.len as usize) - mem::size_of::<fuse_out_header>() >= len
-				{
-					len
+			if let Some(cache) = &self.cache {
+				let read_ahead = if offset == self.next_read {
+					READ_AHEAD_PAGES
 				} else {
-					(rsp.headers.out_header.len as usize) - mem::size_of::<fuse_out_header>()
+					0
 				};
-			self.offset += len;
+				let len = read_cached(
+					cache,
+					fh,
+					&mut buf[..len],
+					offset.try_into().unwrap(),
+					read_ahead,
+				)?;
+				self.next_read = offset + len;
 
-			buf[..len].write_copy_of_slice(&rsp.payload.unwrap()[..len]);
+				return Ok(len);
+			}
 
-			Ok(len)
+			// dirty pages of other handles have to be written back before
+			writeback_page_cache(self.dev, nid, false)?;
+			let data = read_from_server(self.dev, nid, fh, offset.try_into().unwrap(), len)?;
+			buf[..data.len()].write_copy_of_slice(&data);
+
+			Ok(data.len())
 		} else {
 			debug!("File not open, cannot read!");
 			Err(io::Error::ENOENT)
@@ -662,77 +2318,265 @@ impl FuseFileHandleInner {
 	}
 
 	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let len = self.pwrite(buf, self.offset)?;
+		self.offset += len;
+		Ok(len)
+	}
+
+	/// Writes `buf` at the end of the file, whose size is requested from
+	/// the server, and moves the offset behind it
+	fn append(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let size = self.fstat()?.st_size;
+		self.offset = size.try_into().map_err(|_| io::Error::EIO)?;
+		self.write(buf)
+	}
+
+	fn pwrite(&self, buf: &[u8], offset: usize) -> io::Result<usize> {
 		debug!("FUSE write!");
-		let mut truncated_len = buf.len();
-		if truncated_len > MAX_WRITE_LEN {
-			debug!(
-				"Writing longer than max_write_len: {} > {}",
-				buf.len(),
-				MAX_WRITE_LEN
-			);
-			truncated_len = MAX_WRITE_LEN;
-		}
 		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
-			let truncated_buf = Box::<[u8]>::from(&buf[..truncated_len]);
-			let (cmd, rsp_payload_len) =
-				ops::Write::create(nid, fh, truncated_buf, self.offset as u64);
-			let rsp = get_filesystem_driver()
-				.ok_or(io::Error::ENOSYS)?
-				.lock()
-				.send_command(cmd, rsp_payload_len)?;
+			if let Some(cache) = &self.cache {
+				let len = buf.len().min(MAX_WRITE_LEN);
+				return write_cached(cache, fh, &buf[..len], offset.try_into().unwrap());
+			}
 
-			if rsp.headers.out_header.error < 0 {
-				return Err(io::Error::EIO);
+			// dirty pages of other handles have to be written back before
+			writeback_page_cache(self.dev, nid, true)?;
+			let len = write_to_server(self.dev, nid, fh, buf, offset.try_into().unwrap())?;
+			if let Some(cache) = existing_page_cache(self.dev, nid) {
+				let mut cache = cache.lock();
+				cache.size = cache.size.max((offset + len).try_into().unwrap());
 			}
 
-			let rsp_size = rsp.headers.op_header.size;
-			let rsp_len: usize = if rsp_size > truncated_len.try_into().unwrap() {
-				truncated_len
-			} else {
-				rsp_size.try_into().unwrap()
-			};
-			self.offset += rsp_len;
-			Ok(rsp_len)
+			Ok(len)
 		} else {
 			warn!("File not open, cannot read!");
 			Err(io::Error::ENOENT)
 		}
 	}
 
+	fn truncate(&self, size: usize) -> io::Result<()> {
+		debug!("FUSE setattr: size {size}");
+		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
+			self.writeback()?;
+			let setattr_in = fuse_setattr_in {
+				valid: FATTR_SIZE | FATTR_FH,
+				fh,
+				size: size.try_into().unwrap(),
+				..Default::default()
+			};
+			let (cmd, rsp_payload_len) = ops::Setattr::create(nid, setattr_in);
+			let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+
+			if rsp.headers.out_header.error < 0 {
+				return Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+					.unwrap_or(io::Error::EIO));
+			}
+
+			update_page_cache(self.dev, nid, &rsp.headers.op_header.attr);
+			Ok(())
+		} else {
+			Err(io::Error::EIO)
+		}
+	}
+
+	fn setattr(&self, attr: SetAttr) -> io::Result<()> {
+		debug!("FUSE setattr: {attr:?}");
+		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
+			self.writeback()?;
+			let mut setattr_in = setattr_in(&attr);
+			setattr_in.valid |= FATTR_FH;
+			setattr_in.fh = fh;
+
+			let (cmd, rsp_payload_len) = ops::Setattr::create(nid, setattr_in);
+			let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+
+			if rsp.headers.out_header.error < 0 {
+				return Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+					.unwrap_or(io::Error::EIO));
+			}
+
+			update_page_cache(self.dev, nid, &rsp.headers.op_header.attr);
+			Ok(())
+		} else {
+			Err(io::Error::EIO)
+		}
+	}
+
+	fn fallocate(&mut self, mode: FallocateFlags, offset: usize, len: usize) -> io::Result<()> {
+		debug!("FUSE fallocate: {mode:?} {offset} {len}");
+		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
+			self.writeback()?;
+			let (cmd, rsp_payload_len) = ops::Fallocate::create(
+				nid,
+				fh,
+				offset.try_into().unwrap(),
+				len.try_into().unwrap(),
+				mode.bits().try_into().unwrap(),
+			);
+			let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+
+			if rsp.headers.out_header.error < 0 {
+				return Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+					.unwrap_or(io::Error::EIO));
+			}
+
+			// the size of the file may have changed
+			self.fstat().map(|_| ())
+		} else {
+			Err(io::Error::EIO)
+		}
+	}
+
 	fn lseek(&mut self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
 		debug!("FUSE lseek");
 
 		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
+			// the server has to know the size of the file
+			self.writeback()?;
 			let (cmd, rsp_payload_len) = ops::Lseek::create(nid, fh, offset, whence);
-			let rsp = get_filesystem_driver()
-				.ok_or(io::Error::ENOSYS)?
-				.lock()
-				.send_command(cmd, rsp_payload_len)?;
+			let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
 
 			if rsp.headers.out_header.error < 0 {
 				return Err(io::Error::EIO);
 			}
 
-			let rsp_offset = rsp.headers.op_header.offset;
+			let rsp_offset = rsp.headers.op_header.offset;
+
+			Ok(rsp_offset.try_into().unwrap())
+		} else {
+			Err(io::Error::EIO)
+		}
+	}
+
+	fn flush(&self) -> io::Result<()> {
+		debug!("FUSE flush");
+		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
+			self.writeback()?;
+			let (cmd, rsp_payload_len) =
+				ops::Flush::create(nid, fh, lock_owner_id(LockOwner::Process));
+			let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+
+			// a server without flush support doesn't need to be notified
+			match rsp.headers.out_header.error {
+				0 => Ok(()),
+				error if error == -crate::errno::ENOSYS => Ok(()),
+				error => Err(num::FromPrimitive::from_i32(-error).unwrap_or(io::Error::EIO)),
+			}
+		} else {
+			Err(io::Error::EBADF)
+		}
+	}
+
+	/// Returns `true`, if the server manages locks like `lock`
+	fn forwards_lock(&self, lock: &FileLock) -> bool {
+		self.dev.supports(if lock.flock {
+			FUSE_FLOCK_LOCKS
+		} else {
+			FUSE_POSIX_LOCKS
+		})
+	}
+
+	fn getlk(&self, lock: &FileLock) -> io::Result<Option<FileLock>> {
+		let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) else {
+			return Err(io::Error::EBADF);
+		};
+
+		let (cmd, rsp_payload_len) =
+			ops::Getlk::create(nid, fh, lock_owner_id(lock.owner), fuse_file_lock(lock));
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+		if rsp.headers.out_header.error < 0 {
+			return Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+				.unwrap_or(io::Error::EIO));
+		}
+
+		let lk = rsp.headers.op_header.lk;
+		let kind = num::FromPrimitive::from_u32(lk.type_).ok_or(io::Error::EIO)?;
+		if kind == LockType::Unlock {
+			return Ok(None);
+		}
+
+		Ok(Some(FileLock {
+			kind,
+			// the lock is held by another process on the host
+			owner: LockOwner::File(0),
+			flock: false,
+			start: lk.start,
+			end: if lk.end >= OFFSET_MAX {
+				u64::MAX
+			} else {
+				lk.end + 1
+			},
+			pid: lk.pid.try_into().unwrap_or(-1),
+		}))
+	}
+
+	fn setlk(&mut self, lock: &FileLock) -> io::Result<()> {
+		let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) else {
+			return Err(io::Error::EBADF);
+		};
+
+		let owner = lock_owner_id(lock.owner);
+		let (cmd, rsp_payload_len) =
+			ops::Setlk::create(nid, fh, owner, fuse_file_lock(lock), lock.flock);
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+		if rsp.headers.out_header.error < 0 {
+			return Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+				.unwrap_or(io::Error::EIO));
+		}
+
+		if matches!(lock.owner, LockOwner::File(_)) {
+			self.lock_owner = Some(owner);
+		}
+		Ok(())
+	}
+
+	fn fsync(&self, datasync: bool) -> io::Result<()> {
+		debug!("FUSE fsync");
+		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
+			self.writeback()?;
+			let (cmd, rsp_payload_len) = ops::Fsync::create(nid, fh, datasync);
+			let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+
+			if rsp.headers.out_header.error == 0 {
+				Ok(())
+			} else {
+				Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+					.unwrap_or(io::Error::EIO))
+			}
+		} else {
+			Err(io::Error::EBADF)
+		}
+	}
+
+	fn syncfs(&self) -> io::Result<()> {
+		debug!("FUSE syncfs");
+		let (cmd, rsp_payload_len) = ops::Syncfs::create();
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
 
-			Ok(rsp_offset.try_into().unwrap())
-		} else {
-			Err(io::Error::EIO)
+		match rsp.headers.out_header.error {
+			0 => Ok(()),
+			// older servers don't support syncfs, at least sync the current file
+			error if error == -crate::errno::ENOSYS => self.fsync(false),
+			error => Err(num::FromPrimitive::from_i32(-error).unwrap_or(io::Error::EIO)),
 		}
 	}
 
 	fn fstat(&mut self) -> io::Result<FileAttr> {
 		debug!("FUSE getattr");
 		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
+			// the server has to know the size of the file
+			self.writeback()?;
 			let (cmd, rsp_payload_len) = ops::Getattr::create(nid, fh, FUSE_GETATTR_FH);
-			let rsp = get_filesystem_driver()
-				.ok_or(io::Error::ENOSYS)?
-				.lock()
-				.send_command(cmd, rsp_payload_len)?;
+			let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
 			if rsp.headers.out_header.error < 0 {
 				return Err(io::Error::EIO);
 			}
-			Ok(rsp.headers.op_header.attr.into())
+
+			let attr = rsp.headers.op_header.attr;
+			if let Some(cache) = &self.cache {
+				cache.lock().update(&attr);
+			}
+			Ok(self.dev.file_attr(attr))
 		} else {
 			Err(io::Error::EIO)
 		}
@@ -741,14 +2585,33 @@ impl FuseFileHandleInner {
 
 impl Drop for FuseFileHandleInner {
 	fn drop(&mut self) {
-		if self.fuse_nid.is_some() && self.fuse_fh.is_some() {
-			let (cmd, rsp_payload_len) =
-				ops::Release::create(self.fuse_nid.unwrap(), self.fuse_fh.unwrap());
-			get_filesystem_driver()
-				.unwrap()
-				.lock()
-				.send_command(cmd, rsp_payload_len)
-				.unwrap();
+		// the file handle cannot be used to write back the pages after the release
+		if let (Some(nid), Some(cache)) = (self.fuse_nid, &self.cache) {
+			if let Err(err) = writeback_pages(cache) {
+				error!("Unable to write back the cached pages of {nid}: {err:?}");
+			}
+		}
+
+		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
+			let (mut cmd, rsp_payload_len) = ops::Release::create(nid, fh);
+
+			// the locks of the open file are released with it
+			if let Some(owner) = self.lock_owner {
+				if self.dev.supports(FUSE_POSIX_LOCKS) {
+					let unlock = fuse_file_lock {
+						start: 0,
+						end: OFFSET_MAX,
+						type_: num::ToPrimitive::to_u32(&LockType::Unlock).unwrap(),
+						pid: 0,
+					};
+					let (cmd, rsp_payload_len) = ops::Setlk::create(nid, fh, owner, unlock, false);
+					let _ = self.dev.send_command(cmd, rsp_payload_len);
+				}
+				cmd.headers.op_header.release_flags = FUSE_RELEASE_FLOCK_UNLOCK;
+				cmd.headers.op_header.lock_owner = owner;
+			}
+
+			self.dev.send_command(cmd, rsp_payload_len).unwrap();
 		}
 	}
 }
@@ -757,8 +2620,8 @@ impl Drop for FuseFileHandleInner {
 struct FuseFileHandle(pub Arc<Mutex<FuseFileHandleInner>>);
 
 impl FuseFileHandle {
-	pub fn new() -> Self {
-		Self(Arc::new(Mutex::new(FuseFileHandleInner::new())))
+	pub fn new(dev: FuseDevice) -> Self {
+		Self(Arc::new(Mutex::new(FuseFileHandleInner::new(dev))))
 	}
 }
 
@@ -776,13 +2639,139 @@ impl ObjectInterface for FuseFileHandle {
 		self.0.lock().await.write(buf)
 	}
 
+	async fn append(&self, buf: &[u8]) -> io::Result<usize> {
+		self.0.lock().await.append(buf)
+	}
+
+	async fn pread(&self, buf: &mut [MaybeUninit<u8>], offset: usize) -> io::Result<usize> {
+		self.0.lock().await.pread(buf, offset)
+	}
+
+	async fn pwrite(&self, buf: &[u8], offset: usize) -> io::Result<usize> {
+		self.0.lock().await.pwrite(buf, offset)
+	}
+
 	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
 		self.0.lock().await.lseek(offset, whence)
 	}
 
+	async fn truncate(&self, size: usize) -> io::Result<()> {
+		self.0.lock().await.truncate(size)
+	}
+
+	async fn fallocate(&self, mode: FallocateFlags, offset: usize, len: usize) -> io::Result<()> {
+		self.0.lock().await.fallocate(mode, offset, len)
+	}
+
+	async fn flush(&self) -> io::Result<()> {
+		self.0.lock().await.flush()
+	}
+
+	async fn fsync(&self, datasync: bool) -> io::Result<()> {
+		self.0.lock().await.fsync(datasync)
+	}
+
+	async fn syncfs(&self) -> io::Result<()> {
+		self.0.lock().await.syncfs()
+	}
+
+	#[cfg(feature = "mmap")]
+	async fn mmap(&self) -> io::Result<MmapSource> {
+		let guard = self.0.lock().await;
+		let window = guard.dev.dax_window();
+		if window.is_some_and(|window| window.enabled.load(Ordering::Relaxed)) {
+			Ok(MmapSource::Window)
+		} else {
+			Ok(MmapSource::Copy)
+		}
+	}
+
+	#[cfg(feature = "mmap")]
+	async fn map_window(&self, offset: usize) -> io::Result<VirtAddr> {
+		self.0.lock().await.map_window(offset)
+	}
+
+	#[cfg(feature = "mmap")]
+	async fn unmap_window(&self, offset: usize) {
+		self.0.lock().await.unmap_window(offset);
+	}
+
 	async fn fstat(&self) -> io::Result<FileAttr> {
 		self.0.lock().await.fstat()
 	}
+
+	async fn setattr(&self, attr: SetAttr) -> io::Result<()> {
+		self.0.lock().await.setattr(attr)
+	}
+
+	async fn getxattr(&self, name: &str) -> io::Result<Vec<u8>> {
+		let guard = self.0.lock().await;
+		getxattr(guard.dev, guard.fuse_nid.ok_or(io::Error::EBADF)?, name)
+	}
+
+	async fn setxattr(&self, name: &str, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+		let guard = self.0.lock().await;
+		setxattr(
+			guard.dev,
+			guard.fuse_nid.ok_or(io::Error::EBADF)?,
+			name,
+			value,
+			flags,
+		)
+	}
+
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		let guard = self.0.lock().await;
+		listxattr(guard.dev, guard.fuse_nid.ok_or(io::Error::EBADF)?)
+	}
+
+	async fn removexattr(&self, name: &str) -> io::Result<()> {
+		let guard = self.0.lock().await;
+		removexattr(guard.dev, guard.fuse_nid.ok_or(io::Error::EBADF)?, name)
+	}
+
+	async fn getlk(&self, lock: FileLock) -> io::Result<Option<FileLock>> {
+		let mut guard = self.0.lock().await;
+		if guard.forwards_lock(&lock) {
+			guard.getlk(&lock)
+		} else {
+			let attr = guard.fstat()?;
+			Ok(fs::lock::getlk(&attr, &lock))
+		}
+	}
+
+	async fn setlk(&self, lock: FileLock, wait: bool) -> io::Result<()> {
+		let mut guard = self.0.lock().await;
+		if !guard.forwards_lock(&lock) {
+			let attr = guard.fstat()?;
+			drop(guard);
+			return fs::lock::setlk(&attr, lock, wait).await;
+		}
+		drop(guard);
+
+		// FUSE_SETLKW would block the request queue of the device. Consequently,
+		// FUSE_SETLK is repeated with an increasing delay until the conflicting
+		// lock is released.
+		let mut delay = SETLK_MIN_DELAY;
+		loop {
+			match self.0.lock().await.setlk(&lock) {
+				Err(io::Error::EAGAIN) if wait => {}
+				result => return result,
+			}
+
+			let wakeup = arch::processor::get_timer_ticks() + delay;
+			future::poll_fn(|cx| {
+				if arch::processor::get_timer_ticks() >= wakeup {
+					Poll::Ready(())
+				} else {
+					core_scheduler().add_timer(wakeup, cx.waker().clone());
+					Poll::Pending
+				}
+			})
+			.await;
+			delay = (delay * 2).min(SETLK_MAX_DELAY);
+		}
+	}
 }
 
 impl Clone for FuseFileHandle {
@@ -792,118 +2781,88 @@ impl Clone for FuseFileHandle {
 	}
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct FuseDirectoryHandle {
+	dev: FuseDevice,
 	name: Option<String>,
+	/// Node id and file handle, after the directory was opened on the server
+	handle: Mutex<Option<(u64, u64)>>,
+	/// Position of the directory stream
+	pos: DirectoryPosition,
 }
 
 impl FuseDirectoryHandle {
-	pub fn new(name: Option<String>) -> Self {
-		Self { name }
+	pub fn new(dev: FuseDevice, name: Option<String>) -> Self {
+		Self {
+			dev,
+			name,
+			handle: Mutex::new(None),
+			pos: DirectoryPosition::default(),
+		}
 	}
 }
 
 #[async_trait]
 impl ObjectInterface for FuseDirectoryHandle {
-	async fn readdir(&self) -> io::Result<Vec<DirectoryEntry>> {
-		let path: CString = if let Some(name) = &self.name {
-			CString::new("/".to_string() + name).unwrap()
-		} else {
-			CString::new("/".to_string()).unwrap()
-		};
-
-		debug!("FUSE opendir: {path:#?}");
-
-		let fuse_nid = lookup(path.clone()).ok_or(io::Error::ENOENT)?;
-
-		// Opendir
-		// Flag 0x10000 for O_DIRECTORY might not be necessary
-		let (mut cmd, rsp_payload_len) = ops::Open::create(fuse_nid, 0x10000);
-		cmd.headers.in_header.opcode = fuse_opcode::FUSE_OPENDIR as u32;
-		let rsp = get_filesystem_driver()
-			.ok_or(io::Error::ENOSYS)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
-		let fuse_fh = rsp.headers.op_header.fh;
-
-		debug!("FUSE readdir: {path:#?}");
-
-		// Linux seems to allocate a single page to store the dirfile
-		let len = MAX_READ_LEN as u32;
-		let mut offset: usize = 0;
+	async fn fsync(&self, _datasync: bool) -> io::Result<()> {
+		// directory entries are directly modified on the server
+		Ok(())
+	}
 
-		// read content of the directory
-		let (mut cmd, rsp_payload_len) = ops::Read::create(fuse_nid, fuse_fh, len, 0);
-		cmd.headers.in_header.opcode = fuse_opcode::FUSE_READDIR as u32;
-		let rsp = get_filesystem_driver()
-			.ok_or(io::Error::ENOSYS)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+		self.pos.seek(offset, whence)
+	}
 
-		let len: usize = if rsp.headers.out_header.len as usize - mem::size_of::<fuse_out_header>()
-			>= len.try_into().unwrap()
-		{
-			len.try_into().unwrap()
+	async fn readdir(&self, offset: u64) -> io::Result<Vec<DirectoryEntry>> {
+		let mut handle = self.handle.lock().await;
+		let (fuse_nid, fuse_fh) = if let Some(handle) = *handle {
+			handle
 		} else {
-			(rsp.headers.out_header.len as usize) - mem::size_of::<fuse_out_header>()
-		};
-
-		if len <= core::mem::size_of::<fuse_dirent>() {
-			debug!("FUSE no new dirs");
-			return Err(io::Error::ENOENT);
-		}
-
-		let mut entries: Vec<DirectoryEntry> = Vec::new();
-		while (rsp.headers.out_header.len as usize) - offset > core::mem::size_of::<fuse_dirent>() {
-			let dirent = unsafe {
-				&*rsp
-					.payload
-					.as_ref()
-					.unwrap()
-					.as_ptr()
-					.byte_add(offset)
-					.cast::<fuse_dirent>()
+			let path: CString = if let Some(name) = &self.name {
+				CString::new("/".to_string() + name).unwrap()
+			} else {
+				CString::new("/".to_string()).unwrap()
 			};
 
-			offset += core::mem::size_of::<fuse_dirent>() + dirent.namelen as usize;
-			// Align to dirent struct
-			offset = ((offset) + U64_SIZE - 1) & (!(U64_SIZE - 1));
+			debug!("FUSE opendir: {path:#?}");
 
-			let name: &'static [u8] = unsafe {
-				core::slice::from_raw_parts(
-					dirent.name.as_ptr().cast(),
-					dirent.namelen.try_into().unwrap(),
-				)
-			};
-			entries.push(DirectoryEntry::new(unsafe {
-				core::str::from_utf8_unchecked(name).to_string()
-			}));
-		}
+			let fuse_nid = lookup(self.dev, path).ok_or(io::Error::ENOENT)?;
+			let fuse_fh = opendir(self.dev, fuse_nid)?;
+			*handle.insert((fuse_nid, fuse_fh))
+		};
 
-		let (cmd, rsp_payload_len) = ops::Release::create(fuse_nid, fuse_fh);
-		get_filesystem_driver()
-			.unwrap()
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		debug!("FUSE readdir: {fuse_nid} at {offset}");
+		readdir(self.dev, fuse_nid, fuse_fh, offset)
+	}
+}
 
-		Ok(entries)
+impl Drop for FuseDirectoryHandle {
+	fn drop(&mut self) {
+		if let Some((fuse_nid, fuse_fh)) = *self.handle.get_mut() {
+			if let Err(err) = releasedir(self.dev, fuse_nid, fuse_fh) {
+				error!("Unable to release FUSE directory {fuse_nid}: {err:?}");
+			}
+		}
 	}
 }
 
 #[derive(Debug)]
 pub(crate) struct FuseDirectory {
+	dev: FuseDevice,
 	prefix: Option<String>,
 	attr: FileAttr,
 }
 
 impl FuseDirectory {
-	pub fn new(prefix: Option<String>) -> Self {
+	pub fn new(dev: FuseDevice, prefix: Option<String>) -> Self {
 		let microseconds = arch::kernel::systemtime::now_micros();
 		let t = timespec::from_usec(microseconds as i64);
 
 		FuseDirectory {
+			dev,
 			prefix,
 			attr: FileAttr {
+				st_dev: fs::FUSE_DEV + dev.index as u64,
 				st_mode: AccessPermission::from_bits(0o777).unwrap() | AccessPermission::S_IFDIR,
 				st_atim: t,
 				st_mtim: t,
@@ -938,87 +2897,19 @@ impl VfsNode for FuseDirectory {
 	}
 
 	fn get_object(&self) -> io::Result<Arc<dyn ObjectInterface>> {
-		Ok(Arc::new(FuseDirectoryHandle::new(self.prefix.clone())))
+		Ok(Arc::new(FuseDirectoryHandle::new(
+			self.dev,
+			self.prefix.clone(),
+		)))
 	}
 
 	fn traverse_readdir(&self, components: &mut Vec<&str>) -> io::Result<Vec<DirectoryEntry>> {
 		let path = self.traversal_path(components);
 
-		debug!("FUSE opendir: {path:#?}");
-
-		let fuse_nid = lookup(path.clone()).ok_or(io::Error::ENOENT)?;
-
-		// Opendir
-		// Flag 0x10000 for O_DIRECTORY might not be necessary
-		let (mut cmd, rsp_payload_len) = ops::Open::create(fuse_nid, 0x10000);
-		cmd.headers.in_header.opcode = fuse_opcode::FUSE_OPENDIR as u32;
-		let rsp = get_filesystem_driver()
-			.ok_or(io::Error::ENOSYS)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
-		let fuse_fh = rsp.headers.op_header.fh;
-
 		debug!("FUSE readdir: {path:#?}");
 
-		// Linux seems to allocate a single page to store the dirfile
-		let len = MAX_READ_LEN as u32;
-		let mut offset: usize = 0;
-
-		// read content of the directory
-		let (mut cmd, rsp_payload_len) = ops::Read::create(fuse_nid, fuse_fh, len, 0);
-		cmd.headers.in_header.opcode = fuse_opcode::FUSE_READDIR as u32;
-		let rsp = get_filesystem_driver()
-			.ok_or(io::Error::ENOSYS)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
-
-		let len: usize = if rsp.headers.out_header.len as usize - mem::size_of::<fuse_out_header>()
-			>= len.try_into().unwrap()
-		{
-			len.try_into().unwrap()
-		} else {
-			(rsp.headers.out_header.len as usize) - mem::size_of::<fuse_out_header>()
-		};
-
-		if len <= core::mem::size_of::<fuse_dirent>() {
-			debug!("FUSE no new dirs");
-			return Err(io::Error::ENOENT);
-		}
-
-		let mut entries: Vec<DirectoryEntry> = Vec::new();
-		while (rsp.headers.out_header.len as usize) - offset > core::mem::size_of::<fuse_dirent>() {
-			let dirent = unsafe {
-				&*rsp
-					.payload
-					.as_ref()
-					.unwrap()
-					.as_ptr()
-					.byte_add(offset)
-					.cast::<fuse_dirent>()
-			};
-
-			offset += core::mem::size_of::<fuse_dirent>() + dirent.namelen as usize;
-			// Align to dirent struct
-			offset = ((offset) + U64_SIZE - 1) & (!(U64_SIZE - 1));
-
-			let name: &'static [u8] = unsafe {
-				core::slice::from_raw_parts(
-					dirent.name.as_ptr().cast(),
-					dirent.namelen.try_into().unwrap(),
-				)
-			};
-			entries.push(DirectoryEntry::new(unsafe {
-				core::str::from_utf8_unchecked(name).to_string()
-			}));
-		}
-
-		let (cmd, rsp_payload_len) = ops::Release::create(fuse_nid, fuse_fh);
-		get_filesystem_driver()
-			.unwrap()
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
-
-		Ok(entries)
+		let fuse_nid = lookup(self.dev, path).ok_or(io::Error::ENOENT)?;
+		readdir_all(self.dev, fuse_nid)
 	}
 
 	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
@@ -1028,10 +2919,7 @@ impl VfsNode for FuseDirectory {
 
 		// Is there a better way to implement this?
 		let (cmd, rsp_payload_len) = ops::Lookup::create(path);
-		let rsp = get_filesystem_driver()
-			.unwrap()
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
 
 		if rsp.headers.out_header.error != 0 {
 			return Err(io::Error::from_i32(-rsp.headers.out_header.error).unwrap());
@@ -1041,10 +2929,10 @@ impl VfsNode for FuseDirectory {
 		let attr = entry_out.attr;
 
 		if attr.mode & S_IFMT != S_IFLNK {
-			return Ok(FileAttr::from(attr));
+			return Ok(self.dev.file_attr(attr));
 		}
 
-		let path = readlink(entry_out.nodeid)?;
+		let path = readlink(self.dev, entry_out.nodeid)?;
 		let mut components: Vec<&str> = path.split('/').collect();
 		self.traverse_stat(&mut components)
 	}
@@ -1055,17 +2943,104 @@ impl VfsNode for FuseDirectory {
 		debug!("FUSE lstat: {path:#?}");
 
 		let (cmd, rsp_payload_len) = ops::Lookup::create(path);
-		let rsp = get_filesystem_driver()
-			.unwrap()
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
 
 		if rsp.headers.out_header.error != 0 {
 			Err(io::Error::from_i32(-rsp.headers.out_header.error).unwrap())
 		} else {
 			let entry_out = fuse_entry_out::ref_from_bytes(rsp.payload.as_ref().unwrap()).unwrap();
-			Ok(FileAttr::from(entry_out.attr))
+			Ok(self.dev.file_attr(entry_out.attr))
+		}
+	}
+
+	fn traverse_setattr(&self, components: &mut Vec<&str>, attr: SetAttr) -> io::Result<()> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE setattr: {path:#?} {attr:?}");
+
+		let fuse_nid = lookup(self.dev, path).ok_or(io::Error::ENOENT)?;
+		writeback_page_cache(self.dev, fuse_nid, false)?;
+		let (cmd, rsp_payload_len) = ops::Setattr::create(fuse_nid, setattr_in(&attr));
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+
+		if rsp.headers.out_header.error != 0 {
+			return Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+				.unwrap_or(io::Error::EIO));
+		}
+
+		update_page_cache(self.dev, fuse_nid, &rsp.headers.op_header.attr);
+		Ok(())
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE getxattr: {path:#?} {name}");
+
+		let fuse_nid = lookup(self.dev, path).ok_or(io::Error::ENOENT)?;
+		getxattr(self.dev, fuse_nid, name)
+	}
+
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE setxattr: {path:#?} {name}");
+
+		let fuse_nid = lookup(self.dev, path).ok_or(io::Error::ENOENT)?;
+		setxattr(self.dev, fuse_nid, name, value, flags)
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE listxattr: {path:#?}");
+
+		let fuse_nid = lookup(self.dev, path).ok_or(io::Error::ENOENT)?;
+		listxattr(self.dev, fuse_nid)
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<()> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE removexattr: {path:#?} {name}");
+
+		let fuse_nid = lookup(self.dev, path).ok_or(io::Error::ENOENT)?;
+		removexattr(self.dev, fuse_nid, name)
+	}
+
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE statfs: {path:#?}");
+
+		let fuse_nid = lookup(self.dev, path).ok_or(io::Error::ENOENT)?;
+		let (cmd, rsp_payload_len) = ops::Statfs::create(fuse_nid);
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+
+		if rsp.headers.out_header.error != 0 {
+			return Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+				.unwrap_or(io::Error::EIO));
 		}
+
+		let st = rsp.headers.op_header.st;
+		Ok(StatFs {
+			f_type: FUSE_SUPER_MAGIC,
+			f_bsize: st.bsize.into(),
+			f_blocks: st.blocks,
+			f_bfree: st.bfree,
+			f_bavail: st.bavail,
+			f_files: st.files,
+			f_ffree: st.ffree,
+			f_namelen: st.namelen.into(),
+			f_frsize: st.frsize.into(),
+			..Default::default()
+		})
 	}
 
 	fn traverse_open(
@@ -1086,10 +3061,7 @@ impl VfsNode for FuseDirectory {
 			}
 
 			let (cmd, rsp_payload_len) = ops::Lookup::create(path.clone());
-			let rsp = get_filesystem_driver()
-				.unwrap()
-				.lock()
-				.send_command(cmd, rsp_payload_len)?;
+			let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
 
 			if rsp.headers.out_header.error == 0 {
 				let entry_out =
@@ -1098,7 +3070,7 @@ impl VfsNode for FuseDirectory {
 				if attr.st_mode.contains(AccessPermission::S_IFDIR) {
 					let mut path = path.into_string().unwrap();
 					path.remove(0);
-					Ok(Arc::new(FuseDirectoryHandle::new(Some(path))))
+					Ok(Arc::new(FuseDirectoryHandle::new(self.dev, Some(path))))
 				} else {
 					Err(io::Error::ENOTDIR)
 				}
@@ -1106,42 +3078,64 @@ impl VfsNode for FuseDirectory {
 				Err(io::Error::from_i32(-rsp.headers.out_header.error).unwrap())
 			}
 		} else {
-			let file = FuseFileHandle::new();
+			let file = FuseFileHandle::new(self.dev);
 
 			// 1.FUSE_INIT to create session
 			// Already done
 			let mut file_guard = block_on(async { Ok(file.0.lock().await) }, None)?;
 
+			// The page cache has to read partially written pages. Therefore,
+			// write-only files are opened for reading as well. Dirty pages are
+			// written back at their offset, so that the server mustn't append them.
+			// Appending is handled by the handle.
+			let cached_opt = if opt.contains(OpenOption::O_DIRECT) {
+				opt
+			} else if opt.contains(OpenOption::O_WRONLY) {
+				opt.difference(OpenOption::O_WRONLY | OpenOption::O_APPEND) | OpenOption::O_RDWR
+			} else {
+				opt.difference(OpenOption::O_APPEND)
+			};
+
 			// Differentiate between opening and creating new file, since fuse does not support O_CREAT on open.
 			if opt.contains(OpenOption::O_CREAT) {
 				// Create file (opens implicitly, returns results from both lookup and open calls)
 				let (cmd, rsp_payload_len) =
-					ops::Create::create(path, opt.bits().try_into().unwrap(), mode.bits());
-				let rsp = get_filesystem_driver()
-					.ok_or(io::Error::ENOSYS)?
-					.lock()
-					.send_command(cmd, rsp_payload_len)?;
+					ops::Create::create(path, cached_opt.bits().try_into().unwrap(), mode.bits());
+				let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
 
 				let inner = rsp.headers.op_header;
 				file_guard.fuse_nid = Some(inner.entry.nodeid);
 				file_guard.fuse_fh = Some(inner.open.fh);
+				file_guard.enable_cache(cached_opt, inner.open.open_flags, &inner.entry.attr)?;
 			} else {
 				// 2.FUSE_LOOKUP(FUSE_ROOT_ID, “foo”) -> nodeid
-				file_guard.fuse_nid = lookup(path);
-
-				if file_guard.fuse_nid.is_none() {
+				let Some((nid, attr)) = lookup_attr(self.dev, path) else {
 					warn!("Fuse lookup seems to have failed!");
 					return Err(io::Error::ENOENT);
-				}
+				};
+				file_guard.fuse_nid = Some(nid);
 
 				// 3.FUSE_OPEN(nodeid, O_RDONLY) -> fh
-				let (cmd, rsp_payload_len) =
-					ops::Open::create(file_guard.fuse_nid.unwrap(), opt.bits().try_into().unwrap());
-				let rsp = get_filesystem_driver()
-					.ok_or(io::Error::ENOSYS)?
-					.lock()
-					.send_command(cmd, rsp_payload_len)?;
-				file_guard.fuse_fh = Some(rsp.headers.op_header.fh);
+				let open = |opt: OpenOption| -> io::Result<fuse_open_out> {
+					let (cmd, rsp_payload_len) =
+						ops::Open::create(nid, opt.bits().try_into().unwrap());
+					let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+					if rsp.headers.out_header.error < 0 {
+						return Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+							.unwrap_or(io::Error::EIO));
+					}
+					Ok(rsp.headers.op_header)
+				};
+
+				// files, which aren't readable, bypass the page cache
+				let (opt, open_out) = match open(cached_opt) {
+					Err(io::Error::EACCES) if opt.contains(OpenOption::O_WRONLY) => {
+						(opt | OpenOption::O_DIRECT, open(opt)?)
+					}
+					ret => (cached_opt, ret?),
+				};
+				file_guard.fuse_fh = Some(open_out.fh);
+				file_guard.enable_cache(opt, open_out.open_flags, &attr)?;
 			}
 
 			drop(file_guard);
@@ -1154,23 +3148,106 @@ impl VfsNode for FuseDirectory {
 		let path = self.traversal_path(components);
 
 		let (cmd, rsp_payload_len) = ops::Unlink::create(path);
-		let rsp = get_filesystem_driver()
-			.ok_or(io::Error::ENOSYS)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
 		trace!("unlink answer {:?}", rsp);
 
 		Ok(())
 	}
 
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE readlink: {path:#?}");
+
+		let (cmd, rsp_payload_len) = ops::Lookup::create(path);
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+
+		if rsp.headers.out_header.error != 0 {
+			return Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+				.unwrap_or(io::Error::EIO));
+		}
+
+		let entry_out = fuse_entry_out::ref_from_bytes(rsp.payload.as_ref().unwrap()).unwrap();
+		if entry_out.attr.mode & S_IFMT != S_IFLNK {
+			return Err(io::Error::EINVAL);
+		}
+
+		readlink(self.dev, entry_out.nodeid)
+	}
+
+	fn traverse_symlink(&self, components: &mut Vec<&str>, target: &str) -> io::Result<()> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE symlink: {path:#?} -> {target}");
+
+		let target = CString::new(target).map_err(|_| io::Error::EINVAL)?;
+		let (cmd, rsp_payload_len) = ops::Symlink::create(path, target);
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+
+		if rsp.headers.out_header.error == 0 {
+			Ok(())
+		} else {
+			Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+				.unwrap_or(io::Error::EIO))
+		}
+	}
+
+	fn traverse_link(
+		&self,
+		old_components: &mut Vec<&str>,
+		new_components: &mut Vec<&str>,
+	) -> io::Result<()> {
+		let old_path = self.traversal_path(old_components);
+		let new_path = self.traversal_path(new_components);
+
+		debug!("FUSE link: {new_path:#?} -> {old_path:#?}");
+
+		let fuse_nid = lookup(self.dev, old_path).ok_or(io::Error::ENOENT)?;
+		let (cmd, rsp_payload_len) = ops::Link::create(fuse_nid, new_path);
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+
+		if rsp.headers.out_header.error == 0 {
+			Ok(())
+		} else {
+			Err(num::FromPrimitive::from_i32(-rsp.headers.out_header.error)
+				.unwrap_or(io::Error::EIO))
+		}
+	}
+
+	fn traverse_rename(
+		&self,
+		old_components: &mut Vec<&str>,
+		new_components: &mut Vec<&str>,
+	) -> io::Result<()> {
+		let old_path = self.traversal_path(old_components);
+		let new_path = self.traversal_path(new_components);
+
+		debug!("FUSE rename: {old_path:#?} -> {new_path:#?}");
+
+		let (cmd, rsp_payload_len) = ops::Rename2::create(old_path.clone(), new_path.clone(), 0);
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+
+		// older FUSE servers don't support FUSE_RENAME2 => fall back to FUSE_RENAME
+		let error = if -rsp.headers.out_header.error == crate::errno::ENOSYS {
+			let (cmd, rsp_payload_len) = ops::Rename::create(old_path, new_path);
+			let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
+			rsp.headers.out_header.error
+		} else {
+			rsp.headers.out_header.error
+		};
+
+		if error == 0 {
+			Ok(())
+		} else {
+			Err(num::FromPrimitive::from_i32(-error).unwrap_or(io::Error::EIO))
+		}
+	}
+
 	fn traverse_rmdir(&self, components: &mut Vec<&str>) -> io::Result<()> {
 		let path = self.traversal_path(components);
 
 		let (cmd, rsp_payload_len) = ops::Rmdir::create(path);
-		let rsp = get_filesystem_driver()
-			.ok_or(io::Error::ENOSYS)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
 		trace!("rmdir answer {:?}", rsp);
 
 		Ok(())
@@ -1180,10 +3257,7 @@ impl VfsNode for FuseDirectory {
 		let path = self.traversal_path(components);
 		let (cmd, rsp_payload_len) = ops::Mkdir::create(path, mode.bits());
 
-		let rsp = get_filesystem_driver()
-			.ok_or(io::Error::ENOSYS)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.dev.send_command(cmd, rsp_payload_len)?;
 		if rsp.headers.out_header.error == 0 {
 			Ok(())
 		} else {
@@ -1192,140 +3266,169 @@ impl VfsNode for FuseDirectory {
 	}
 }
 
-pub(crate) fn init() {
-	debug!("Try to initialize fuse filesystem");
+/// Creates the root directory of the virtio-fs device with the tag `tag`,
+/// which can be mounted at runtime
+pub(crate) fn new_mount(
+	tag: &str,
+) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+	let dev = device(tag).ok_or(io::Error::ENODEV)?;
+	Ok(Box::new(FuseDirectory::new(dev, None)))
+}
 
-	if let Some(driver) = get_filesystem_driver() {
-		let (cmd, rsp_payload_len) = ops::Init::create();
-		let rsp = driver.lock().send_command(cmd, rsp_payload_len).unwrap();
-		trace!("fuse init answer: {:?}", rsp);
+/// Returns the mount point and the mount flags of the device with the tag
+/// `tag`, if it is given by the boot argument `-mount tag:/path[:ro]`
+fn mount_point(tag: &str) -> Option<(String, MountFlags)> {
+	env::mounts().iter().find_map(|mount| {
+		let mut parts = mount.split(':');
+		if parts.next()? != tag {
+			return None;
+		}
 
-		let mount_point = driver.lock().get_mount_point();
-		if mount_point == "/" {
-			let fuse_nid = lookup(c"/".to_owned()).unwrap();
-			// Opendir
-			// Flag 0x10000 for O_DIRECTORY might not be necessary
-			let (mut cmd, rsp_payload_len) = ops::Open::create(fuse_nid, 0x10000);
-			cmd.headers.in_header.opcode = fuse_opcode::FUSE_OPENDIR as u32;
-			let rsp = get_filesystem_driver()
-				.unwrap()
-				.lock()
-				.send_command(cmd, rsp_payload_len)
-				.unwrap();
-			let fuse_fh = rsp.headers.op_header.fh;
-
-			// Linux seems to allocate a single page to store the dirfile
-			let len = MAX_READ_LEN as u32;
-			let mut offset: usize = 0;
-
-			// read content of the directory
-			let (mut cmd, rsp_payload_len) = ops::Read::create(fuse_nid, fuse_fh, len, 0);
-			cmd.headers.in_header.opcode = fuse_opcode::FUSE_READDIR as u32;
-			let rsp = get_filesystem_driver()
-				.unwrap()
-				.lock()
-				.send_command(cmd, rsp_payload_len)
-				.unwrap();
-
-			let len: usize = if rsp.headers.out_header.len as usize
-				- mem::size_of::<fuse_out_header>()
-				>= len.try_into().unwrap()
-			{
-				len.try_into().unwrap()
-			} else {
-				(rsp.headers.out_header.len as usize) - mem::size_of::<fuse_out_header>()
-			};
+		let path = parts.next().filter(|path| path.starts_with('/'));
+		let Some(path) = path else {
+			error!("Invalid mount point in {mount}");
+			return None;
+		};
 
-			assert!(
-				len > core::mem::size_of::<fuse_dirent>(),
-				"FUSE no new dirs"
-			);
+		let mut flags = MountFlags::empty();
+		for option in parts {
+			match option {
+				"ro" => flags.insert(MountFlags::MS_RDONLY),
+				"rw" => flags.remove(MountFlags::MS_RDONLY),
+				option => warn!("Ignore unknown mount option {option} of {tag}"),
+			}
+		}
 
-			let mut entries: Vec<String> = Vec::new();
-			while (rsp.headers.out_header.len as usize) - offset
-				> core::mem::size_of::<fuse_dirent>()
-			{
-				let dirent = unsafe {
-					&*rsp
-						.payload
-						.as_ref()
-						.unwrap()
-						.as_ptr()
-						.byte_add(offset)
-						.cast::<fuse_dirent>()
-				};
+		Some((path.to_string(), flags))
+	})
+}
 
-				offset += core::mem::size_of::<fuse_dirent>() + dirent.namelen as usize;
-				// Align to dirent struct
-				offset = ((offset) + U64_SIZE - 1) & (!(U64_SIZE - 1));
+/// Mounts the directories in the root of the device individually, except
+/// the directories which are provided by the kernel
+fn mount_root_entries(dev: FuseDevice, tag: &str) {
+	let entries = lookup(dev, c"/".to_owned())
+		.ok_or(io::Error::ENOENT)
+		.and_then(|fuse_nid| readdir_all(dev, fuse_nid));
+	let mut entries: Vec<String> = match entries {
+		Ok(entries) => entries.into_iter().map(|entry| entry.name).collect(),
+		Err(err) => {
+			error!("Unable to read the root directory of virtio-fs {tag}: {err:?}");
+			return;
+		}
+	};
 
-				let name: &'static [u8] = unsafe {
-					core::slice::from_raw_parts(
-						dirent.name.as_ptr().cast(),
-						dirent.namelen.try_into().unwrap(),
-					)
-				};
-				entries.push(unsafe { core::str::from_utf8_unchecked(name).to_string() });
+	// remove predefined directories
+	entries.retain(|x| x != ".");
+	entries.retain(|x| x != "..");
+	entries.retain(|x| x != "tmp");
+	entries.retain(|x| x != "proc");
+	entries.retain(|x| x != "dev");
+	warn!(
+		"Fuse don't mount the host directories 'tmp', 'proc' and 'dev' into the guest file system!"
+	);
+
+	for i in entries {
+		let Some((_, attr)) = CString::new(i.clone())
+			.ok()
+			.and_then(|i_cstr| lookup_attr(dev, i_cstr))
+		else {
+			warn!("Fuse don't mount {}. Its attributes are unknown!", i);
+			continue;
+		};
+		let attr = FileAttr::from(attr);
+
+		if attr.st_mode.contains(AccessPermission::S_IFDIR) {
+			info!("Fuse mount {} to /{}", i, i);
+			if let Err(err) = fs::FILESYSTEM.get().unwrap().mount(
+				tag,
+				&("/".to_owned() + i.as_str()),
+				"virtiofs",
+				MountFlags::empty(),
+				"",
+				Box::new(FuseDirectory::new(dev, Some(i.clone()))),
+			) {
+				error!("Unable to mount {i} of virtio-fs {tag}: {err:?}");
 			}
+		} else {
+			warn!("Fuse don't mount {}. It isn't a directory!", i);
+		}
+	}
+}
 
-			let (cmd, rsp_payload_len) = ops::Release::create(fuse_nid, fuse_fh);
-			get_filesystem_driver()
-				.unwrap()
-				.lock()
-				.send_command(cmd, rsp_payload_len)
-				.unwrap();
-
-			// remove predefined directories
-			entries.retain(|x| x != ".");
-			entries.retain(|x| x != "..");
-			entries.retain(|x| x != "tmp");
-			entries.retain(|x| x != "proc");
-			warn!(
-				"Fuse don't mount the host directories 'tmp' and 'proc' into the guest file system!"
-			);
-
-			for i in entries {
-				let i_cstr = CString::new(i.clone()).unwrap();
-				let (cmd, rsp_payload_len) = ops::Lookup::create(i_cstr);
-				let rsp = get_filesystem_driver()
-					.unwrap()
-					.lock()
-					.send_command(cmd, rsp_payload_len)
-					.unwrap();
+pub(crate) fn init() {
+	debug!("Try to initialize fuse filesystem");
 
-				assert_eq!(rsp.headers.out_header.error, 0);
-				let entry_out =
-					fuse_entry_out::ref_from_bytes(rsp.payload.as_ref().unwrap()).unwrap();
-				let attr = entry_out.attr;
-				let attr = FileAttr::from(attr);
+	let mut devices = Vec::new();
+	let mut connections = Vec::new();
+	for driver in get_filesystem_drivers() {
+		let dev = FuseDevice {
+			index: devices.len(),
+			driver,
+		};
+		let (cmd, rsp_payload_len) = ops::Init::create();
+		let rsp = match dev.send_command(cmd, rsp_payload_len) {
+			Ok(rsp) if rsp.headers.out_header.error == 0 => rsp,
+			Ok(rsp) => {
+				error!(
+					"Skip virtio-fs device {}, FUSE_INIT failed with error {}",
+					dev.tag(),
+					-rsp.headers.out_header.error
+				);
+				continue;
+			}
+			Err(err) => {
+				error!("Skip virtio-fs device {}: {err:?}", dev.tag());
+				continue;
+			}
+		};
+		trace!("fuse init answer: {:?}", rsp);
 
-				if attr.st_mode.contains(AccessPermission::S_IFDIR) {
-					info!("Fuse mount {} to /{}", i, i);
-					fs::FILESYSTEM
-						.get()
-						.unwrap()
-						.mount(
-							&("/".to_owned() + i.as_str()),
-							Box::new(FuseDirectory::new(Some(i))),
-						)
-						.expect("Mount failed. Invalid mount_point?");
-				} else {
-					warn!("Fuse don't mount {}. It isn't a directory!", i);
-				}
+		let init_out = &rsp.headers.op_header;
+		let map_alignment = if init_out.flags & FUSE_MAP_ALIGNMENT != 0 {
+			init_out.map_alignment
+		} else {
+			0
+		};
+		let window = dev.driver.lock().cache_window().and_then(|(addr, len)| {
+			if 1usize << map_alignment > DAX_RANGE_SIZE {
+				warn!("DAX window isn't used, because of the map alignment {map_alignment}");
+				return None;
 			}
+			info!("Use DAX window of {len:#x} bytes at {addr:p}");
+			DaxWindow::new(addr, len)
+		});
+		devices.push(dev);
+		connections.push(Connection {
+			flags: init_out.flags,
+			dax_window: window,
+		});
+	}
+	DEVICES.set(devices).unwrap();
+	CONNECTIONS.set(connections).unwrap();
+
+	for dev in DEVICES.get().unwrap().iter().copied() {
+		let tag = dev.tag();
+		let (mount_point, flags) = if let Some(mount) = mount_point(&tag) {
+			mount
+		} else if tag == "/" {
+			mount_root_entries(dev, &tag);
+			continue;
+		} else if tag.starts_with('/') {
+			(tag.clone(), MountFlags::empty())
 		} else {
-			let mount_point = if mount_point.starts_with('/') {
-				mount_point
-			} else {
-				"/".to_owned() + &mount_point
-			};
+			("/".to_owned() + &tag, MountFlags::empty())
+		};
 
-			info!("Mounting virtio-fs at {}", mount_point);
-			fs::FILESYSTEM
-				.get()
-				.unwrap()
-				.mount(mount_point.as_str(), Box::new(FuseDirectory::new(None)))
-				.expect("Mount failed. Invalid mount_point?");
+		info!("Mounting virtio-fs {} at {}", tag, mount_point);
+		if let Err(err) = fs::FILESYSTEM.get().unwrap().mount(
+			&tag,
+			mount_point.as_str(),
+			"virtiofs",
+			flags,
+			"",
+			Box::new(FuseDirectory::new(dev, None)),
+		) {
+			error!("Unable to mount virtio-fs {tag} at {mount_point}: {err:?}");
 		}
 	}
 }
@@ -0,0 +1,484 @@
+//! Unpacks an initial RAM disk into the in-memory file system.
+//!
+//! The archive can be passed by the loader as boot module or be linked into
+//! the kernel image by enabling the feature `initramfs` and setting the
+//! environment variable `HERMIT_INITRAMFS` to the path of the archive.
+//! Supported formats are cpio (newc) and ustar. The content of regular files
+//! isn't copied, the files reference the archive as [`RomFile`](super::mem::RomFile).
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str;
+
+use memory_addresses::PhysAddr;
+
+use crate::arch::mm::paging::{BasePageSize, PageSize};
+use crate::fd::AccessPermission;
+use crate::fs::Filesystem;
+use crate::fs::mem::{MemDirectory, MemSymlink, RomFile};
+use crate::time::timespec;
+use crate::{env, io, mm};
+
+/// Archive, which is linked into the kernel image
+#[cfg(feature = "initramfs")]
+static BUILTIN: &[u8] = include_bytes!(concat!(core::env!("OUT_DIR"), "/initramfs"));
+
+const CPIO_HEADER_LEN: usize = 110;
+const CPIO_TRAILER: &str = "TRAILER!!!";
+const TAR_BLOCK_LEN: usize = 512;
+
+#[derive(Debug)]
+enum EntryKind {
+	File(&'static [u8]),
+	Directory,
+	Symlink(String),
+	HardLink(String),
+}
+
+#[derive(Debug)]
+struct Entry {
+	path: String,
+	kind: EntryKind,
+	mode: AccessPermission,
+	mtime: timespec,
+}
+
+/// Converts a path of the archive to an absolute path. Returns `None`
+/// for the root directory.
+fn absolute_path(path: &str) -> Option<String> {
+	let path = path.trim_start_matches("./").trim_matches('/');
+	if path.is_empty() || path == "." {
+		None
+	} else {
+		Some("/".to_string() + path)
+	}
+}
+
+fn parse_hex(field: &[u8]) -> io::Result<u32> {
+	let field = str::from_utf8(field).map_err(|_| io::Error::EINVAL)?;
+	u32::from_str_radix(field, 16).map_err(|_| io::Error::EINVAL)
+}
+
+fn parse_octal(field: &[u8]) -> io::Result<u64> {
+	let field = str::from_utf8(field).map_err(|_| io::Error::EINVAL)?;
+	let field = field.trim_matches(|c: char| c == '\0' || c == ' ');
+	if field.is_empty() {
+		Ok(0)
+	} else {
+		u64::from_str_radix(field, 8).map_err(|_| io::Error::EINVAL)
+	}
+}
+
+/// Returns the null-terminated string at the beginning of `field`
+fn parse_str(field: &[u8]) -> io::Result<&str> {
+	let len = field.iter().position(|c| *c == 0).unwrap_or(field.len());
+	str::from_utf8(&field[..len]).map_err(|_| io::Error::EINVAL)
+}
+
+fn mtime(seconds: u64) -> timespec {
+	timespec {
+		tv_sec: seconds.try_into().unwrap_or_default(),
+		tv_nsec: 0,
+	}
+}
+
+/// Regular file of a cpio archive with several hard links
+struct LinkedFile {
+	paths: Vec<String>,
+	data: &'static [u8],
+	mode: AccessPermission,
+	mtime: timespec,
+}
+
+/// Parses an archive in the "new ASCII" cpio format
+fn parse_cpio(archive: &'static [u8]) -> io::Result<Vec<Entry>> {
+	let mut entries = Vec::new();
+	// hard links share the inode number and only one of them carries the data
+	let mut linked_files: BTreeMap<(u32, u32, u32), LinkedFile> = BTreeMap::new();
+	let mut offset = 0;
+
+	loop {
+		let header = archive
+			.get(offset..offset + CPIO_HEADER_LEN)
+			.ok_or(io::Error::EINVAL)?;
+		if &header[..6] != b"070701" && &header[..6] != b"070702" {
+			return Err(io::Error::EINVAL);
+		}
+
+		let field = |i: usize| parse_hex(&header[6 + i * 8..14 + i * 8]);
+		let ino = field(0)?;
+		let mode = field(1)?;
+		let nlink = field(4)?;
+		let mtime = field(5)?;
+		let file_size: usize = field(6)?.try_into().unwrap();
+		let name_size: usize = field(11)?.try_into().unwrap();
+
+		let name_start = offset + CPIO_HEADER_LEN;
+		let name = archive
+			.get(name_start..name_start + name_size)
+			.ok_or(io::Error::EINVAL)?;
+		let name = parse_str(name)?;
+		if name == CPIO_TRAILER {
+			break;
+		}
+
+		let data_start = (name_start + name_size).next_multiple_of(4);
+		let data = archive
+			.get(data_start..data_start + file_size)
+			.ok_or(io::Error::EINVAL)?;
+		offset = (data_start + file_size).next_multiple_of(4);
+
+		let kind = match mode & AccessPermission::S_IFMT.bits() {
+			m if m == AccessPermission::S_IFREG.bits() => EntryKind::File(data),
+			m if m == AccessPermission::S_IFDIR.bits() => EntryKind::Directory,
+			m if m == AccessPermission::S_IFLNK.bits() => EntryKind::Symlink(
+				str::from_utf8(data)
+					.map_err(|_| io::Error::EINVAL)?
+					.to_string(),
+			),
+			_ => {
+				warn!("Ignore special file {name} in the initramfs");
+				continue;
+			}
+		};
+
+		let Some(path) = absolute_path(name) else {
+			continue;
+		};
+		let mode = AccessPermission::from_bits_truncate(mode & 0o7777);
+		let mtime = self::mtime(mtime.into());
+
+		match kind {
+			EntryKind::File(data) if nlink > 1 => {
+				let key = (field(7)?, field(8)?, ino);
+				let file = linked_files.entry(key).or_insert_with(|| LinkedFile {
+					paths: Vec::new(),
+					data,
+					mode,
+					mtime,
+				});
+				file.paths.push(path);
+				if !data.is_empty() {
+					file.data = data;
+				}
+			}
+			kind => entries.push(Entry {
+				path,
+				kind,
+				mode,
+				mtime,
+			}),
+		}
+	}
+
+	for file in linked_files.into_values() {
+		let mut paths = file.paths.into_iter();
+		let target = paths.next().unwrap();
+		let links = paths.map(|path| Entry {
+			path,
+			kind: EntryKind::HardLink(target.clone()),
+			mode: file.mode,
+			mtime: file.mtime,
+		});
+		let links: Vec<Entry> = links.collect();
+
+		entries.push(Entry {
+			path: target,
+			kind: EntryKind::File(file.data),
+			mode: file.mode,
+			mtime: file.mtime,
+		});
+		entries.extend(links);
+	}
+
+	Ok(entries)
+}
+
+/// Parses an archive in the ustar format. GNU long names are supported as well.
+fn parse_ustar(archive: &'static [u8]) -> io::Result<Vec<Entry>> {
+	let mut entries = Vec::new();
+	let mut offset = 0;
+	let mut long_name: Option<String> = None;
+	let mut long_link: Option<String> = None;
+
+	while let Some(header) = archive.get(offset..offset + TAR_BLOCK_LEN) {
+		// the archive ends with two empty blocks
+		if header.iter().all(|c| *c == 0) {
+			break;
+		}
+
+		let size: usize = parse_octal(&header[124..136])?.try_into().unwrap();
+		let data_start = offset + TAR_BLOCK_LEN;
+		let data = archive
+			.get(data_start..data_start + size)
+			.ok_or(io::Error::EINVAL)?;
+		offset = data_start + size.next_multiple_of(TAR_BLOCK_LEN);
+
+		let name = match long_name.take() {
+			Some(name) => name,
+			None => {
+				let prefix = parse_str(&header[345..500])?;
+				let name = parse_str(&header[..100])?;
+				if prefix.is_empty() {
+					name.to_string()
+				} else {
+					prefix.to_string() + "/" + name
+				}
+			}
+		};
+		let link = match long_link.take() {
+			Some(link) => link,
+			None => parse_str(&header[157..257])?.to_string(),
+		};
+
+		let kind = match header[156] {
+			b'0' | b'\0' | b'7' => EntryKind::File(data),
+			b'1' => EntryKind::HardLink(link),
+			b'2' => EntryKind::Symlink(link),
+			b'5' => EntryKind::Directory,
+			b'L' => {
+				long_name = Some(parse_str(data)?.to_string());
+				continue;
+			}
+			b'K' => {
+				long_link = Some(parse_str(data)?.to_string());
+				continue;
+			}
+			typeflag => {
+				warn!(
+					"Ignore entry {name} of type {} in the initramfs",
+					char::from(typeflag)
+				);
+				continue;
+			}
+		};
+
+		let mode: u32 = parse_octal(&header[100..108])?.try_into().unwrap();
+		let mtime = parse_octal(&header[136..148])?;
+		if let Some(path) = absolute_path(&name) {
+			entries.push(Entry {
+				path,
+				kind,
+				mode: AccessPermission::from_bits_truncate(mode & 0o7777),
+				mtime: self::mtime(mtime),
+			});
+		}
+	}
+
+	Ok(entries)
+}
+
+/// Creates all missing parent directories of `path`
+fn create_parents(fs: &Filesystem, path: &str) -> io::Result<()> {
+	let mut parent = String::new();
+	let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+
+	for component in components.iter().take(components.len().saturating_sub(1)) {
+		parent = parent + "/" + component;
+		if fs.lstat(&parent).is_err() {
+			fs.mkdir(&parent, AccessPermission::from_bits(0o755).unwrap())?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Unpacks the cpio or ustar archive `archive` into the file system
+pub(crate) fn unpack(fs: &Filesystem, archive: &'static [u8]) -> io::Result<()> {
+	let entries = if archive.starts_with(b"07070") {
+		parse_cpio(archive)?
+	} else if archive.get(257..262) == Some(b"ustar") {
+		parse_ustar(archive)?
+	} else {
+		return Err(io::Error::EINVAL);
+	};
+
+	for entry in entries {
+		create_parents(fs, &entry.path)?;
+
+		let result = match entry.kind {
+			EntryKind::File(data) => fs.insert(
+				&entry.path,
+				Box::new(RomFile::with_mtime(data, entry.mode, entry.mtime)),
+			),
+			EntryKind::Directory => {
+				// directories can already exist, e.g. /tmp
+				if fs.lstat(&entry.path).is_err() {
+					fs.insert(
+						&entry.path,
+						Box::new(MemDirectory::with_mtime(entry.mode, entry.mtime)),
+					)
+				} else {
+					Ok(())
+				}
+			}
+			EntryKind::Symlink(target) => fs.insert(
+				&entry.path,
+				Box::new(MemSymlink::with_mtime(&target, entry.mtime)),
+			),
+			EntryKind::HardLink(target) => {
+				let target = absolute_path(&target).ok_or(io::Error::EINVAL)?;
+				fs.link(&target, &entry.path)
+			}
+		};
+
+		match result {
+			// an existing file isn't overwritten, e.g. by a second archive
+			Err(io::Error::EEXIST) => warn!("Skip existing file {} of the initramfs", entry.path),
+			result => result?,
+		}
+	}
+
+	Ok(())
+}
+
+/// Returns the archive, which was passed by the loader as boot module
+fn boot_module() -> Option<&'static [u8]> {
+	let initrd = env::initrd()?;
+	let offset = initrd.start % BasePageSize::SIZE;
+	let len: usize = (initrd.end - initrd.start).try_into().unwrap();
+
+	let virtual_address = mm::map(
+		PhysAddr::new(initrd.start - offset),
+		len + usize::try_from(offset).unwrap(),
+		false,
+		true,
+		false,
+	);
+
+	Some(unsafe { core::slice::from_raw_parts((virtual_address + offset).as_ptr(), len) })
+}
+
+pub(crate) fn init(fs: &Filesystem) {
+	#[cfg(feature = "initramfs")]
+	if !BUILTIN.is_empty() {
+		if let Err(err) = unpack(fs, BUILTIN) {
+			error!("Unable to unpack the builtin initramfs: {err:?}");
+		}
+	}
+
+	if let Some(archive) = boot_module() {
+		info!("Unpack initramfs with {} bytes", archive.len());
+		if let Err(err) = unpack(fs, archive) {
+			error!("Unable to unpack the initramfs: {err:?}");
+		}
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use alloc::format;
+
+	use super::*;
+
+	fn cpio_entry(archive: &mut Vec<u8>, ino: u32, mode: u32, nlink: u32, name: &str, data: &[u8]) {
+		let header = format!(
+			"070701{ino:08X}{mode:08X}{:08X}{:08X}{nlink:08X}{:08X}{:08X}{:08X}{:08X}{:08X}{:08X}{:08X}{:08X}",
+			0,
+			0,
+			60,
+			data.len(),
+			0,
+			1,
+			0,
+			0,
+			name.len() + 1,
+			0
+		);
+		archive.extend_from_slice(header.as_bytes());
+		archive.extend_from_slice(name.as_bytes());
+		archive.push(0);
+		archive.resize(archive.len().next_multiple_of(4), 0);
+		archive.extend_from_slice(data);
+		archive.resize(archive.len().next_multiple_of(4), 0);
+	}
+
+	fn ustar_entry(archive: &mut Vec<u8>, typeflag: u8, name: &str, link: &str, data: &[u8]) {
+		let mut header = [0u8; TAR_BLOCK_LEN];
+		header[..name.len()].copy_from_slice(name.as_bytes());
+		header[100..107].copy_from_slice(b"0000644");
+		header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+		header[136..147].copy_from_slice(b"00000000074");
+		header[156] = typeflag;
+		header[157..157 + link.len()].copy_from_slice(link.as_bytes());
+		header[257..263].copy_from_slice(b"ustar\0");
+		archive.extend_from_slice(&header);
+		archive.extend_from_slice(data);
+		archive.resize(archive.len().next_multiple_of(TAR_BLOCK_LEN), 0);
+	}
+
+	#[test]
+	fn cpio() {
+		let mut archive = Vec::new();
+		cpio_entry(&mut archive, 1, 0o40755, 2, ".", &[]);
+		cpio_entry(&mut archive, 2, 0o40700, 2, "dir", &[]);
+		cpio_entry(&mut archive, 3, 0o100644, 1, "dir/file", b"hello");
+		cpio_entry(&mut archive, 4, 0o120777, 1, "link", b"dir/file");
+		// only the last hard link carries the data
+		cpio_entry(&mut archive, 5, 0o100600, 2, "a", &[]);
+		cpio_entry(&mut archive, 5, 0o100600, 2, "b", b"shared");
+		cpio_entry(&mut archive, 0, 0, 1, CPIO_TRAILER, &[]);
+
+		let entries = parse_cpio(archive.leak()).unwrap();
+		let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+		assert_eq!(paths, ["/dir", "/dir/file", "/link", "/a", "/b"]);
+
+		assert!(matches!(entries[0].kind, EntryKind::Directory));
+		assert_eq!(entries[0].mode.bits(), 0o700);
+		assert!(matches!(entries[1].kind, EntryKind::File(b"hello")));
+		assert_eq!(entries[1].mtime.tv_sec, 60);
+		assert!(matches!(&entries[2].kind, EntryKind::Symlink(target) if target == "dir/file"));
+		assert!(matches!(entries[3].kind, EntryKind::File(b"shared")));
+		assert!(matches!(&entries[4].kind, EntryKind::HardLink(target) if target == "/a"));
+	}
+
+	#[test]
+	fn cpio_truncated() {
+		let mut archive = Vec::new();
+		cpio_entry(&mut archive, 1, 0o100644, 1, "file", b"hello");
+		archive.truncate(archive.len() - 4);
+
+		assert_eq!(parse_cpio(archive.leak()).unwrap_err(), io::Error::EINVAL);
+	}
+
+	#[test]
+	fn ustar() {
+		let long_name = "d/".repeat(60) + "file";
+		let mut archive = Vec::new();
+		ustar_entry(&mut archive, b'5', "./dir/", "", &[]);
+		ustar_entry(&mut archive, b'0', "dir/file", "", b"hello");
+		ustar_entry(&mut archive, b'1', "dir/hard", "dir/file", &[]);
+		ustar_entry(&mut archive, b'2', "dir/soft", "file", &[]);
+		ustar_entry(
+			&mut archive,
+			b'L',
+			"././@LongLink",
+			"",
+			long_name.as_bytes(),
+		);
+		ustar_entry(&mut archive, b'0', "truncated", "", &[]);
+		archive.resize(archive.len() + 2 * TAR_BLOCK_LEN, 0);
+
+		let entries = parse_ustar(archive.leak()).unwrap();
+		let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+		assert_eq!(
+			paths,
+			[
+				"/dir",
+				"/dir/file",
+				"/dir/hard",
+				"/dir/soft",
+				&*("/".to_string() + &long_name)
+			]
+		);
+
+		assert!(matches!(entries[0].kind, EntryKind::Directory));
+		assert!(matches!(entries[1].kind, EntryKind::File(b"hello")));
+		assert_eq!(entries[1].mode.bits(), 0o644);
+		assert_eq!(entries[1].mtime.tv_sec, 0o74);
+		assert!(matches!(&entries[2].kind, EntryKind::HardLink(target) if target == "dir/file"));
+		assert!(matches!(&entries[3].kind, EntryKind::Symlink(target) if target == "file"));
+	}
+}
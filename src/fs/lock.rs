@@ -0,0 +1,278 @@
+//! Advisory file locks of `fcntl` and `flock`.
+//!
+//! The locks are stored in a table, which is indexed by the device and the inode
+//! of the file. Record locks of `fcntl` belong to the process and open file
+//! description locks belong to the open file, but both kinds conflict with
+//! each other. Locks of `flock` cover the whole file and are independent of
+//! the record locks.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+use core::future;
+use core::task::{Poll, Waker};
+
+use hermit_sync::TicketMutex;
+
+use crate::fs::FileAttr;
+use crate::io;
+
+/// Type of a lock, values are identical to `F_RDLCK`, `F_WRLCK` and `F_UNLCK`
+#[derive(Debug, Copy, Clone, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub(crate) enum LockType {
+	Read = 0,
+	Write = 1,
+	Unlock = 2,
+}
+
+/// Owner of a lock
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum LockOwner {
+	/// Record locks of `fcntl` belong to the process
+	Process,
+	/// Locks of `flock` and open file description locks belong to the open
+	/// file, which is identified by the address of the object
+	File(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct FileLock {
+	pub kind: LockType,
+	pub owner: LockOwner,
+	/// Is set for locks of `flock`
+	pub flock: bool,
+	/// First byte of the range
+	pub start: u64,
+	/// First byte after the range. `u64::MAX` extends the lock to the end
+	/// of the file, even if the file grows.
+	pub end: u64,
+	/// Process id of the owner, as reported by `F_GETLK`
+	pub pid: i32,
+}
+
+impl FileLock {
+	/// Creates a lock of `flock`, which covers the whole file
+	pub fn flock(kind: LockType, owner: LockOwner) -> Self {
+		Self {
+			kind,
+			owner,
+			flock: true,
+			start: 0,
+			end: u64::MAX,
+			pid: -1,
+		}
+	}
+
+	fn overlaps(&self, other: &FileLock) -> bool {
+		self.flock == other.flock && self.start < other.end && other.start < self.end
+	}
+
+	fn conflicts(&self, other: &FileLock) -> bool {
+		self.owner != other.owner
+			&& (self.kind == LockType::Write || other.kind == LockType::Write)
+			&& self.overlaps(other)
+	}
+}
+
+/// Device and inode of a file
+type LockKey = (u64, u64);
+
+#[derive(Debug)]
+struct LockTable {
+	locks: BTreeMap<LockKey, Vec<FileLock>>,
+	/// Tasks, which wait for the release of a lock
+	waiters: VecDeque<Waker>,
+}
+
+static LOCKS: TicketMutex<LockTable> = TicketMutex::new(LockTable {
+	locks: BTreeMap::new(),
+	waiters: VecDeque::new(),
+});
+
+fn key(attr: &FileAttr) -> LockKey {
+	(attr.st_dev, attr.st_ino)
+}
+
+/// Releases the locks of `owner` on all files
+pub(crate) fn release(owner: LockOwner) {
+	let mut table = LOCKS.lock();
+	let mut released = false;
+	table.locks.retain(|_, locks| {
+		let len = locks.len();
+		locks.retain(|lock| lock.owner != owner);
+		released |= locks.len() != len;
+		!locks.is_empty()
+	});
+	if released {
+		for waker in table.waiters.drain(..) {
+			waker.wake();
+		}
+	}
+}
+
+/// Returns a lock of the file `attr`, which conflicts with `lock`
+pub(crate) fn getlk(attr: &FileAttr, lock: &FileLock) -> Option<FileLock> {
+	LOCKS
+		.lock()
+		.locks
+		.get(&key(attr))?
+		.iter()
+		.find(|other| other.conflicts(lock))
+		.copied()
+}
+
+/// Replaces the existing locks of the owner of `lock` in its range by `lock`.
+/// Returns `true`, if an existing lock was released or shrunk.
+fn replace(locks: &mut Vec<FileLock>, lock: FileLock) -> bool {
+	let mut released = false;
+	let mut remaining = Vec::with_capacity(locks.len() + 2);
+	for other in locks.drain(..) {
+		if other.owner != lock.owner || !other.overlaps(&lock) {
+			remaining.push(other);
+			continue;
+		}
+
+		released = true;
+		if other.start < lock.start {
+			remaining.push(FileLock {
+				end: lock.start,
+				..other
+			});
+		}
+		if other.end > lock.end {
+			remaining.push(FileLock {
+				start: lock.end,
+				..other
+			});
+		}
+	}
+	if lock.kind != LockType::Unlock {
+		remaining.push(lock);
+	}
+
+	*locks = remaining;
+	released
+}
+
+/// Acquires, converts or releases `lock` on the file `attr`. If a conflicting
+/// lock exists, `EAGAIN` is returned or the call waits until it is released.
+pub(crate) async fn setlk(attr: &FileAttr, lock: FileLock, wait: bool) -> io::Result<()> {
+	let key = key(attr);
+
+	future::poll_fn(|cx| {
+		let mut guard = LOCKS.lock();
+		let table = &mut *guard;
+		let locks = table.locks.entry(key).or_default();
+
+		if lock.kind != LockType::Unlock && locks.iter().any(|other| other.conflicts(&lock)) {
+			if wait {
+				table.waiters.push_back(cx.waker().clone());
+				return Poll::Pending;
+			} else {
+				return Poll::Ready(Err(io::Error::EAGAIN));
+			}
+		}
+
+		let released = replace(locks, lock);
+		if locks.is_empty() {
+			table.locks.remove(&key);
+		}
+		if released {
+			for waker in table.waiters.drain(..) {
+				waker.wake();
+			}
+		}
+
+		Poll::Ready(Ok(()))
+	})
+	.await
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+
+	fn record(kind: LockType, owner: usize, start: u64, end: u64) -> FileLock {
+		FileLock {
+			kind,
+			owner: LockOwner::File(owner),
+			flock: false,
+			start,
+			end,
+			pid: -1,
+		}
+	}
+
+	fn ranges(locks: &[FileLock]) -> Vec<(LockType, usize, u64, u64)> {
+		let mut ranges: Vec<_> = locks
+			.iter()
+			.map(|lock| {
+				let LockOwner::File(owner) = lock.owner else {
+					unreachable!()
+				};
+				(lock.kind, owner, lock.start, lock.end)
+			})
+			.collect();
+		ranges.sort_by_key(|(_, owner, start, _)| (*owner, *start));
+		ranges
+	}
+
+	#[test]
+	fn split() {
+		let mut locks = vec![record(LockType::Write, 1, 0, 100)];
+
+		assert!(replace(&mut locks, record(LockType::Read, 1, 20, 30)));
+		assert_eq!(
+			ranges(&locks),
+			[
+				(LockType::Write, 1, 0, 20),
+				(LockType::Read, 1, 20, 30),
+				(LockType::Write, 1, 30, 100),
+			]
+		);
+
+		assert!(replace(&mut locks, record(LockType::Unlock, 1, 10, 50)));
+		assert_eq!(
+			ranges(&locks),
+			[(LockType::Write, 1, 0, 10), (LockType::Write, 1, 50, 100)]
+		);
+	}
+
+	#[test]
+	fn owners() {
+		let mut locks = vec![record(LockType::Read, 1, 0, u64::MAX)];
+
+		assert!(!replace(&mut locks, record(LockType::Read, 2, 0, 10)));
+		assert_eq!(
+			ranges(&locks),
+			[(LockType::Read, 1, 0, u64::MAX), (LockType::Read, 2, 0, 10)]
+		);
+
+		assert!(replace(
+			&mut locks,
+			record(LockType::Unlock, 2, 0, u64::MAX)
+		));
+		assert!(!replace(
+			&mut locks,
+			record(LockType::Unlock, 2, 0, u64::MAX)
+		));
+		assert_eq!(ranges(&locks), [(LockType::Read, 1, 0, u64::MAX)]);
+
+		assert!(replace(
+			&mut locks,
+			record(LockType::Unlock, 1, 0, u64::MAX)
+		));
+		assert!(locks.is_empty());
+	}
+
+	#[test]
+	fn conflicts() {
+		let write = record(LockType::Write, 1, 10, 20);
+
+		assert!(write.conflicts(&record(LockType::Read, 2, 19, 30)));
+		assert!(!write.conflicts(&record(LockType::Read, 2, 20, 30)));
+		assert!(!write.conflicts(&record(LockType::Write, 1, 0, 100)));
+		assert!(!record(LockType::Read, 1, 0, 10).conflicts(&record(LockType::Read, 2, 0, 10)));
+		// locks of `flock` are independent of record locks
+		assert!(!write.conflicts(&FileLock::flock(LockType::Write, LockOwner::File(2))));
+	}
+}
@@ -15,25 +15,149 @@ use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU64, Ordering};
 
 use async_lock::{Mutex, RwLock};
 use async_trait::async_trait;
 
+use crate::arch::mm::paging::{BasePageSize, PageSize};
 use crate::executor::block_on;
-use crate::fd::{AccessPermission, ObjectInterface, OpenOption, PollEvent};
-use crate::fs::{DirectoryEntry, FileAttr, NodeKind, SeekWhence, VfsNode};
+#[cfg(feature = "mmap")]
+use crate::fd::MmapSource;
+use crate::fd::{AccessPermission, FallocateFlags, ObjectInterface, OpenOption, PollEvent};
+use crate::fs::{
+	DirectoryEntry, DirectoryPosition, FileAttr, FileType, MEM_DEV, NodeKind, READDIR_PAGE_LEN,
+	SeekWhence, SetAttr, StatFs, VfsNode, XattrFlags,
+};
 use crate::time::timespec;
-use crate::{arch, io};
+use crate::{arch, io, mm};
+
+/// Magic number of the RAM disk, as reported by `statfs`
+const TMPFS_MAGIC: i64 = 0x0102_1994;
+
+/// Entries of a directory, which is part of the RAM disk
+pub(crate) type MemDirectoryEntries =
+	Arc<RwLock<BTreeMap<String, Box<dyn VfsNode + core::marker::Send + core::marker::Sync>>>>;
+
+/// Inode number of the next node, which is created on the RAM disk
+static NEXT_INODE: AtomicU64 = AtomicU64::new(1);
+
+fn next_inode() -> u64 {
+	NEXT_INODE.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Returns at most `len` entries of the directory, starting at position `offset`
+fn directory_entries(
+	entries: &BTreeMap<String, Box<dyn VfsNode + core::marker::Send + core::marker::Sync>>,
+	offset: usize,
+	len: usize,
+) -> Vec<DirectoryEntry> {
+	entries
+		.iter()
+		.enumerate()
+		.skip(offset)
+		.take(len)
+		.map(|(i, (name, node))| {
+			let mut entry = if let Ok(attr) = node.get_file_attributes() {
+				DirectoryEntry::with_attr(name.clone(), &attr)
+			} else {
+				let mut entry = DirectoryEntry::new(name.clone());
+				entry.file_type = match node.get_kind() {
+					NodeKind::File => FileType::RegularFile,
+					NodeKind::Directory => FileType::Directory,
+					NodeKind::Symlink => FileType::SymbolicLink,
+				};
+				entry
+			};
+			entry.offset = (i + 1).try_into().unwrap();
+			entry
+		})
+		.collect()
+}
+
+/// Checks if `node` is allowed to replace `target` during a rename.
+fn check_rename_target(
+	node: &(dyn VfsNode + core::marker::Send + core::marker::Sync),
+	target: &(dyn VfsNode + core::marker::Send + core::marker::Sync),
+) -> io::Result<()> {
+	match (node.get_kind(), target.get_kind()) {
+		(NodeKind::Directory, NodeKind::Directory) => {
+			if target.traverse_readdir(&mut Vec::new())?.is_empty() {
+				Ok(())
+			} else {
+				Err(io::Error::ENOTEMPTY)
+			}
+		}
+		(NodeKind::Directory, _) => Err(io::Error::ENOTDIR),
+		(_, NodeKind::Directory) => Err(io::Error::EISDIR),
+		_ => Ok(()),
+	}
+}
+
+/// Extended attributes of a node on the RAM disk
+#[derive(Debug, Default)]
+pub(crate) struct Xattrs(BTreeMap<String, Vec<u8>>);
+
+impl Xattrs {
+	pub fn get(&self, name: &str) -> io::Result<Vec<u8>> {
+		self.0.get(name).cloned().ok_or(io::Error::ENODATA)
+	}
+
+	/// Creates or replaces the attribute `name` and updates the time of
+	/// the last status change in `attr`
+	pub fn set(
+		&mut self,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+		attr: &mut FileAttr,
+	) -> io::Result<()> {
+		let exists = self.0.contains_key(name);
+		if exists && flags.contains(XattrFlags::XATTR_CREATE) {
+			return Err(io::Error::EEXIST);
+		}
+		if !exists && flags.contains(XattrFlags::XATTR_REPLACE) {
+			return Err(io::Error::ENODATA);
+		}
+
+		self.0.insert(name.to_string(), value.to_vec());
+		touch_ctime(attr);
+		Ok(())
+	}
+
+	pub fn list(&self) -> Vec<String> {
+		self.0.keys().cloned().collect()
+	}
+
+	/// Removes the attribute `name` and updates the time of the last
+	/// status change in `attr`
+	pub fn remove(&mut self, name: &str, attr: &mut FileAttr) -> io::Result<()> {
+		self.0.remove(name).ok_or(io::Error::ENODATA)?;
+		touch_ctime(attr);
+		Ok(())
+	}
+}
+
+/// Sets the time of the last status change to the current time
+fn touch_ctime(attr: &mut FileAttr) {
+	let microseconds = arch::kernel::systemtime::now_micros();
+	attr.st_ctim = timespec::from_usec(microseconds as i64);
+}
 
 #[derive(Debug)]
 pub(crate) struct RomFileInner {
 	pub data: &'static [u8],
 	pub attr: FileAttr,
+	pub xattrs: Xattrs,
 }
 
 impl RomFileInner {
 	pub fn new(data: &'static [u8], attr: FileAttr) -> Self {
-		Self { data, attr }
+		Self {
+			data,
+			attr,
+			xattrs: Xattrs::default(),
+		}
 	}
 }
 
@@ -88,6 +212,23 @@ impl ObjectInterface for RomFileInterface {
 		Ok(len)
 	}
 
+	async fn pread(&self, buf: &mut [MaybeUninit<u8>], offset: usize) -> io::Result<usize> {
+		let vec = self.inner.read().await.data;
+
+		if offset >= vec.len() {
+			return Ok(0);
+		}
+
+		let len = buf.len().min(vec.len() - offset);
+		buf[..len].write_copy_of_slice(&vec[offset..offset + len]);
+
+		Ok(len)
+	}
+
+	async fn fsync(&self, _datasync: bool) -> io::Result<()> {
+		Ok(())
+	}
+
 	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
 		let guard = self.inner.read().await;
 		let mut pos_guard = self.pos.lock().await;
@@ -103,7 +244,16 @@ impl ObjectInterface for RomFileInterface {
 		} else if whence == SeekWhence::Cur {
 			(*pos_guard as isize) + offset
 		} else {
-			return Err(io::Error::EINVAL);
+			// a read-only file doesn't have holes
+			if offset < 0 || offset >= guard.data.len() as isize {
+				return Err(io::Error::ENXIO);
+			}
+
+			if whence == SeekWhence::Data {
+				offset
+			} else {
+				guard.data.len() as isize
+			}
 		};
 
 		if new_pos <= guard.data.len().try_into().unwrap() {
@@ -118,6 +268,43 @@ impl ObjectInterface for RomFileInterface {
 		let guard = self.inner.read().await;
 		Ok(guard.attr)
 	}
+
+	async fn setattr(&self, attr: SetAttr) -> io::Result<()> {
+		attr.apply(&mut self.inner.write().await.attr);
+		Ok(())
+	}
+
+	async fn getxattr(&self, name: &str) -> io::Result<Vec<u8>> {
+		self.inner.read().await.xattrs.get(name)
+	}
+
+	async fn setxattr(&self, name: &str, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+		let mut guard = self.inner.write().await;
+		let inner = &mut *guard;
+		inner.xattrs.set(name, value, flags, &mut inner.attr)
+	}
+
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		Ok(self.inner.read().await.xattrs.list())
+	}
+
+	async fn removexattr(&self, name: &str) -> io::Result<()> {
+		let mut guard = self.inner.write().await;
+		let inner = &mut *guard;
+		inner.xattrs.remove(name, &mut inner.attr)
+	}
+
+	#[cfg(feature = "mmap")]
+	async fn mmap(&self) -> io::Result<MmapSource> {
+		let data = self.inner.read().await.data;
+
+		// content of the archive is only page-aligned by chance
+		if data.as_ptr().addr() % BasePageSize::SIZE as usize == 0 {
+			Ok(MmapSource::Static(data))
+		} else {
+			Ok(MmapSource::Copy)
+		}
+	}
 }
 
 impl RomFileInterface {
@@ -133,18 +320,215 @@ impl RomFileInterface {
 	}
 }
 
+/// Allocated byte ranges of a file on the RAM disk. The ranges are stored
+/// as a map from the start to the (exclusive) end of each range. Bytes below
+/// the file size, which aren't part of a range, belong to a hole.
+#[derive(Debug, Default)]
+pub(crate) struct Extents(BTreeMap<usize, usize>);
+
+impl Extents {
+	/// Marks the range `start..end` as allocated
+	pub fn insert(&mut self, mut start: usize, mut end: usize) {
+		if start >= end {
+			return;
+		}
+
+		if let Some((&s, &e)) = self.0.range(..=start).next_back() {
+			if e >= start {
+				start = s;
+				end = end.max(e);
+				self.0.remove(&s);
+			}
+		}
+
+		while let Some((&s, &e)) = self.0.range(start..=end).next() {
+			end = end.max(e);
+			self.0.remove(&s);
+		}
+
+		self.0.insert(start, end);
+	}
+
+	/// Marks the range `start..end` as hole
+	pub fn remove(&mut self, start: usize, end: usize) {
+		if start >= end {
+			return;
+		}
+
+		if let Some((&s, &e)) = self.0.range(..start).next_back() {
+			if e > start {
+				self.0.insert(s, start);
+				if e > end {
+					self.0.insert(end, e);
+				}
+			}
+		}
+
+		let overlapping: Vec<(usize, usize)> =
+			self.0.range(start..end).map(|(&s, &e)| (s, e)).collect();
+		for (s, e) in overlapping {
+			self.0.remove(&s);
+			if e > end {
+				self.0.insert(end, e);
+			}
+		}
+	}
+
+	/// Returns the first allocated offset, which is greater or equal to `offset`
+	pub fn next_data(&self, offset: usize) -> Option<usize> {
+		match self.0.range(..=offset).next_back() {
+			Some((_, &e)) if e > offset => Some(offset),
+			_ => self.0.range(offset..).next().map(|(&s, _)| s),
+		}
+	}
+
+	/// Returns the first unallocated offset, which is greater or equal to `offset`
+	pub fn next_hole(&self, offset: usize) -> usize {
+		match self.0.range(..=offset).next_back() {
+			Some((_, &e)) if e > offset => e,
+			_ => offset,
+		}
+	}
+
+	/// Returns the number of allocated bytes
+	pub fn allocated(&self) -> usize {
+		self.0.iter().map(|(s, e)| e - s).sum()
+	}
+}
+
+/// Size of the pages, which store the content of a file on the RAM disk
+const PAGE_SIZE: usize = BasePageSize::SIZE as usize;
+
+/// Maximum size of a file on the RAM disk
+const MAX_FILE_SIZE: usize = isize::MAX as usize;
+
+/// Page of a file on the RAM disk
+#[derive(Debug)]
+#[repr(C, align(4096))]
+pub(crate) struct Page([u8; PAGE_SIZE]);
+
 #[derive(Debug)]
 pub(crate) struct RamFileInner {
-	pub data: Vec<u8>,
+	/// Content of the file as map from the page index to the page. Holes
+	/// aren't backed by pages and read as zeros.
+	pub pages: BTreeMap<usize, Box<Page>>,
+	/// File size in bytes
+	pub size: usize,
+	pub extents: Extents,
 	pub attr: FileAttr,
+	pub xattrs: Xattrs,
 }
 
 impl RamFileInner {
 	pub fn new(attr: FileAttr) -> Self {
 		Self {
-			data: Vec::new(),
+			pages: BTreeMap::new(),
+			size: 0,
+			extents: Extents::default(),
 			attr,
+			xattrs: Xattrs::default(),
+		}
+	}
+
+	/// Sets the file size to `size` bytes. New bytes are part of a hole.
+	fn resize(&mut self, size: usize) -> io::Result<()> {
+		if size > MAX_FILE_SIZE {
+			return Err(io::Error::EFBIG);
+		}
+
+		if size < self.size {
+			self.zero(size, self.size);
+		}
+		self.size = size;
+		self.extents.remove(size, usize::MAX);
+		self.update_attr();
+
+		Ok(())
+	}
+
+	/// Fills the range `start..end` with zeros and releases the pages,
+	/// which are completely part of the range
+	fn zero(&mut self, start: usize, end: usize) {
+		let first = start.div_ceil(PAGE_SIZE);
+		let last = end / PAGE_SIZE;
+		if first < last {
+			let mut tail = self.pages.split_off(&first);
+			let mut tail = tail.split_off(&last);
+			self.pages.append(&mut tail);
+		}
+
+		let mut pos = start;
+		while pos < end {
+			let index = pos / PAGE_SIZE;
+			let offset = pos % PAGE_SIZE;
+			let len = (PAGE_SIZE - offset).min(end - pos);
+			if let Some(page) = self.pages.get_mut(&index) {
+				page.0[offset..offset + len].fill(0);
+			}
+			pos += len;
+		}
+	}
+
+	/// Updates size and number of allocated blocks in the file attributes
+	fn update_attr(&mut self) {
+		self.attr.st_size = self.size.try_into().unwrap();
+		self.attr.st_blocks = self.extents.allocated().div_ceil(512).try_into().unwrap();
+	}
+
+	fn read_at(&self, buf: &mut [MaybeUninit<u8>], pos: usize) -> usize {
+		if pos >= self.size {
+			return 0;
 		}
+
+		let len = buf.len().min(self.size - pos);
+		let mut done = 0;
+		while done < len {
+			let index = (pos + done) / PAGE_SIZE;
+			let offset = (pos + done) % PAGE_SIZE;
+			let n = (PAGE_SIZE - offset).min(len - done);
+			let dst = &mut buf[done..done + n];
+			match self.pages.get(&index) {
+				Some(page) => {
+					dst.write_copy_of_slice(&page.0[offset..offset + n]);
+				}
+				None => {
+					dst.fill(MaybeUninit::new(0));
+				}
+			}
+			done += n;
+		}
+
+		len
+	}
+
+	fn write_at(&mut self, buf: &[u8], pos: usize) -> io::Result<usize> {
+		let end = pos
+			.checked_add(buf.len())
+			.filter(|end| *end <= MAX_FILE_SIZE)
+			.ok_or(io::Error::EFBIG)?;
+		let microseconds = arch::kernel::systemtime::now_micros();
+		let t = timespec::from_usec(microseconds as i64);
+
+		let mut done = 0;
+		while done < buf.len() {
+			let index = (pos + done) / PAGE_SIZE;
+			let offset = (pos + done) % PAGE_SIZE;
+			let n = (PAGE_SIZE - offset).min(buf.len() - done);
+			let page = self
+				.pages
+				.entry(index)
+				.or_insert_with(|| Box::new(Page([0; PAGE_SIZE])));
+			page.0[offset..offset + n].copy_from_slice(&buf[done..done + n]);
+			done += n;
+		}
+		self.size = self.size.max(end);
+		self.extents.insert(pos, end);
+		self.update_attr();
+
+		self.attr.st_mtim = t;
+		self.attr.st_ctim = t;
+
+		Ok(buf.len())
 	}
 }
 
@@ -159,7 +543,7 @@ pub struct RamFileInterface {
 #[async_trait]
 impl ObjectInterface for RamFileInterface {
 	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
-		let len = self.inner.read().await.data.len();
+		let len = self.inner.read().await.size;
 		let pos = *self.pos.lock().await;
 
 		let mut available = PollEvent::POLLOUT | PollEvent::POLLWRNORM | PollEvent::POLLWRBAND;
@@ -181,67 +565,77 @@ impl ObjectInterface for RamFileInterface {
 
 		let guard = self.inner.read().await;
 		let mut pos_guard = self.pos.lock().await;
-		let pos = *pos_guard;
 
-		if pos >= guard.data.len() {
-			return Ok(0);
-		}
+		let len = guard.read_at(buf, *pos_guard);
+		*pos_guard += len;
 
-		let len = if guard.data.len() - pos < buf.len() {
-			guard.data.len() - pos
-		} else {
-			buf.len()
-		};
+		Ok(len)
+	}
 
-		buf[..len].write_copy_of_slice(&guard.data[pos..pos + len]);
-		*pos_guard = pos + len;
+	async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		let mut guard = self.inner.write().await;
+		let mut pos_guard = self.pos.lock().await;
+
+		let len = guard.write_at(buf, *pos_guard)?;
+		*pos_guard += len;
 
 		Ok(len)
 	}
 
-	async fn write(&self, buf: &[u8]) -> io::Result<usize> {
-		let microseconds = arch::kernel::systemtime::now_micros();
-		let t = timespec::from_usec(microseconds as i64);
+	async fn append(&self, buf: &[u8]) -> io::Result<usize> {
 		let mut guard = self.inner.write().await;
 		let mut pos_guard = self.pos.lock().await;
-		let pos = *pos_guard;
 
-		if pos + buf.len() > guard.data.len() {
-			guard.data.resize(pos + buf.len(), 0);
-			guard.attr.st_size = guard.data.len().try_into().unwrap();
-		}
+		let pos = guard.size;
+		let len = guard.write_at(buf, pos)?;
+		*pos_guard = pos + len;
 
-		guard.attr.st_atim = t;
-		guard.attr.st_mtim = t;
-		guard.attr.st_ctim = t;
+		Ok(len)
+	}
+
+	async fn pread(&self, buf: &mut [MaybeUninit<u8>], offset: usize) -> io::Result<usize> {
+		{
+			let microseconds = arch::kernel::systemtime::now_micros();
+			let t = timespec::from_usec(microseconds as i64);
+			let mut guard = self.inner.write().await;
+			guard.attr.st_atim = t;
+		}
 
-		guard.data[pos..pos + buf.len()].copy_from_slice(buf);
-		*pos_guard = pos + buf.len();
+		Ok(self.inner.read().await.read_at(buf, offset))
+	}
 
-		Ok(buf.len())
+	async fn pwrite(&self, buf: &[u8], offset: usize) -> io::Result<usize> {
+		self.inner.write().await.write_at(buf, offset)
 	}
 
 	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
-		let mut guard = self.inner.write().await;
+		let guard = self.inner.read().await;
 		let mut pos_guard = self.pos.lock().await;
+		let len = guard.size;
+
+		let new_pos: isize = match whence {
+			SeekWhence::Set => offset,
+			SeekWhence::End => len as isize + offset,
+			SeekWhence::Cur => (*pos_guard as isize) + offset,
+			SeekWhence::Data | SeekWhence::Hole => {
+				let offset: usize = offset.try_into().map_err(|_| io::Error::EINVAL)?;
+				if offset >= len {
+					return Err(io::Error::ENXIO);
+				}
 
-		let new_pos: isize = if whence == SeekWhence::Set {
-			if offset < 0 {
-				return Err(io::Error::EINVAL);
-			}
+				let new_pos = if whence == SeekWhence::Data {
+					guard.extents.next_data(offset).ok_or(io::Error::ENXIO)?
+				} else {
+					// the end of the file is always an implicit hole
+					guard.extents.next_hole(offset).min(len)
+				};
 
-			offset
-		} else if whence == SeekWhence::End {
-			guard.data.len() as isize + offset
-		} else if whence == SeekWhence::Cur {
-			(*pos_guard as isize) + offset
-		} else {
-			return Err(io::Error::EINVAL);
+				new_pos.try_into().unwrap()
+			}
 		};
 
-		if new_pos > guard.data.len().try_into().unwrap() {
-			guard.data.resize(new_pos.try_into().unwrap(), 0);
-			guard.attr.st_size = guard.data.len().try_into().unwrap();
+		if new_pos < 0 {
+			return Err(io::Error::EINVAL);
 		}
 		*pos_guard = new_pos.try_into().unwrap();
 
@@ -252,6 +646,93 @@ impl ObjectInterface for RamFileInterface {
 		let guard = self.inner.read().await;
 		Ok(guard.attr)
 	}
+
+	async fn setattr(&self, attr: SetAttr) -> io::Result<()> {
+		attr.apply(&mut self.inner.write().await.attr);
+		Ok(())
+	}
+
+	async fn getxattr(&self, name: &str) -> io::Result<Vec<u8>> {
+		self.inner.read().await.xattrs.get(name)
+	}
+
+	async fn setxattr(&self, name: &str, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+		let mut guard = self.inner.write().await;
+		let inner = &mut *guard;
+		inner.xattrs.set(name, value, flags, &mut inner.attr)
+	}
+
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		Ok(self.inner.read().await.xattrs.list())
+	}
+
+	async fn removexattr(&self, name: &str) -> io::Result<()> {
+		let mut guard = self.inner.write().await;
+		let inner = &mut *guard;
+		inner.xattrs.remove(name, &mut inner.attr)
+	}
+
+	#[cfg(feature = "mmap")]
+	async fn mmap(&self) -> io::Result<MmapSource> {
+		// the content can be moved, if the file grows
+		Ok(MmapSource::Copy)
+	}
+
+	async fn truncate(&self, size: usize) -> io::Result<()> {
+		let microseconds = arch::kernel::systemtime::now_micros();
+		let t = timespec::from_usec(microseconds as i64);
+		let mut guard = self.inner.write().await;
+
+		guard.resize(size)?;
+		guard.attr.st_mtim = t;
+		guard.attr.st_ctim = t;
+
+		Ok(())
+	}
+
+	async fn fsync(&self, _datasync: bool) -> io::Result<()> {
+		// the RAM disk doesn't have a backing storage
+		Ok(())
+	}
+
+	async fn fallocate(&self, mode: FallocateFlags, offset: usize, len: usize) -> io::Result<()> {
+		let end = offset.checked_add(len).ok_or(io::Error::EFBIG)?;
+		if len == 0 {
+			return Err(io::Error::EINVAL);
+		}
+
+		let mut guard = self.inner.write().await;
+		let keep_size = mode.contains(FallocateFlags::FALLOC_FL_KEEP_SIZE);
+
+		if mode.contains(FallocateFlags::FALLOC_FL_PUNCH_HOLE) {
+			if !keep_size || mode.contains(FallocateFlags::FALLOC_FL_ZERO_RANGE) {
+				return Err(io::Error::EOPNOTSUPP);
+			}
+
+			let size = guard.size;
+			guard.zero(offset.min(size), end.min(size));
+			guard.extents.remove(offset, end);
+		} else {
+			if !keep_size && end > guard.size {
+				guard.resize(end)?;
+			}
+
+			// allocated space beyond the end of the file isn't tracked
+			let size = guard.size;
+			if mode.contains(FallocateFlags::FALLOC_FL_ZERO_RANGE) {
+				guard.zero(offset.min(size), end.min(size));
+			}
+			guard.extents.insert(offset.min(size), end.min(size));
+		}
+		guard.update_attr();
+
+		let microseconds = arch::kernel::systemtime::now_micros();
+		let t = timespec::from_usec(microseconds as i64);
+		guard.attr.st_mtim = t;
+		guard.attr.st_ctim = t;
+
+		Ok(())
+	}
 }
 
 impl RamFileInterface {
@@ -263,7 +744,7 @@ impl RamFileInterface {
 	}
 
 	pub fn len(&self) -> usize {
-		block_on(async { Ok(self.inner.read().await.data.len()) }, None).unwrap()
+		block_on(async { Ok(self.inner.read().await.size) }, None).unwrap()
 	}
 }
 
@@ -285,6 +766,21 @@ impl VfsNode for RomFile {
 		block_on(async { Ok(self.data.read().await.attr) }, None)
 	}
 
+	fn hard_link(&self) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		block_on(
+			async {
+				self.data.write().await.attr.st_nlink += 1;
+				Ok(Box::new(Self {
+					data: self.data.clone(),
+				})
+					as Box<
+						dyn VfsNode + core::marker::Send + core::marker::Sync,
+					>)
+			},
+			None,
+		)
+	}
+
 	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
 		if components.is_empty() {
 			self.get_file_attributes()
@@ -300,29 +796,118 @@ impl VfsNode for RomFile {
 			Err(io::Error::EBADF)
 		}
 	}
-}
 
-impl RomFile {
-	pub fn new(data: &'static [u8], mode: AccessPermission) -> Self {
-		let microseconds = arch::kernel::systemtime::now_micros();
-		let t = timespec::from_usec(microseconds as i64);
-		let attr = FileAttr {
-			st_size: data.len() as u64,
-			st_mode: mode | AccessPermission::S_IFREG,
-			st_atim: t,
-			st_mtim: t,
-			st_ctim: t,
-			..Default::default()
-		};
+	fn traverse_setattr(&self, components: &mut Vec<&str>, attr: SetAttr) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					attr.apply(&mut self.data.write().await.attr);
+					Ok(())
+				},
+				None,
+			)
+		} else {
+			Err(io::Error::EBADF)
+		}
+	}
 
-		Self {
-			data: Arc::new(RwLock::new(RomFileInner::new(data, attr))),
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		if components.is_empty() {
+			block_on(async { self.data.read().await.xattrs.get(name) }, None)
+		} else {
+			Err(io::Error::EBADF)
 		}
 	}
-}
 
-#[derive(Debug, Clone)]
-pub(crate) struct RamFile {
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					let mut guard = self.data.write().await;
+					let data = &mut *guard;
+					data.xattrs.set(name, value, flags, &mut data.attr)
+				},
+				None,
+			)
+		} else {
+			Err(io::Error::EBADF)
+		}
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		if components.is_empty() {
+			block_on(async { Ok(self.data.read().await.xattrs.list()) }, None)
+		} else {
+			Err(io::Error::EBADF)
+		}
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					let mut guard = self.data.write().await;
+					let data = &mut *guard;
+					data.xattrs.remove(name, &mut data.attr)
+				},
+				None,
+			)
+		} else {
+			Err(io::Error::EBADF)
+		}
+	}
+}
+
+impl Drop for RomFile {
+	fn drop(&mut self) {
+		// the directory entry of this link is removed
+		let _ = block_on(
+			async {
+				let mut guard = self.data.write().await;
+				guard.attr.st_nlink -= 1;
+				Ok(())
+			},
+			None,
+		);
+	}
+}
+
+impl RomFile {
+	pub fn new(data: &'static [u8], mode: AccessPermission) -> Self {
+		let microseconds = arch::kernel::systemtime::now_micros();
+		let t = timespec::from_usec(microseconds as i64);
+
+		Self::with_mtime(data, mode, t)
+	}
+
+	/// Creates a read-only file, which was last modified at `mtime`
+	pub fn with_mtime(data: &'static [u8], mode: AccessPermission, mtime: timespec) -> Self {
+		let attr = FileAttr {
+			st_dev: MEM_DEV,
+			st_ino: next_inode(),
+			st_nlink: 1,
+			st_size: data.len() as u64,
+			st_mode: mode | AccessPermission::S_IFREG,
+			st_atim: mtime,
+			st_mtim: mtime,
+			st_ctim: mtime,
+			..Default::default()
+		};
+
+		Self {
+			data: Arc::new(RwLock::new(RomFileInner::new(data, attr))),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub(crate) struct RamFile {
 	data: Arc<RwLock<RamFileInner>>,
 }
 
@@ -339,6 +924,21 @@ impl VfsNode for RamFile {
 		block_on(async { Ok(self.data.read().await.attr) }, None)
 	}
 
+	fn hard_link(&self) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		block_on(
+			async {
+				self.data.write().await.attr.st_nlink += 1;
+				Ok(Box::new(Self {
+					data: self.data.clone(),
+				})
+					as Box<
+						dyn VfsNode + core::marker::Send + core::marker::Sync,
+					>)
+			},
+			None,
+		)
+	}
+
 	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
 		if components.is_empty() {
 			self.get_file_attributes()
@@ -354,6 +954,86 @@ impl VfsNode for RamFile {
 			Err(io::Error::EBADF)
 		}
 	}
+
+	fn traverse_setattr(&self, components: &mut Vec<&str>, attr: SetAttr) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					attr.apply(&mut self.data.write().await.attr);
+					Ok(())
+				},
+				None,
+			)
+		} else {
+			Err(io::Error::EBADF)
+		}
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		if components.is_empty() {
+			block_on(async { self.data.read().await.xattrs.get(name) }, None)
+		} else {
+			Err(io::Error::EBADF)
+		}
+	}
+
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					let mut guard = self.data.write().await;
+					let data = &mut *guard;
+					data.xattrs.set(name, value, flags, &mut data.attr)
+				},
+				None,
+			)
+		} else {
+			Err(io::Error::EBADF)
+		}
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		if components.is_empty() {
+			block_on(async { Ok(self.data.read().await.xattrs.list()) }, None)
+		} else {
+			Err(io::Error::EBADF)
+		}
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					let mut guard = self.data.write().await;
+					let data = &mut *guard;
+					data.xattrs.remove(name, &mut data.attr)
+				},
+				None,
+			)
+		} else {
+			Err(io::Error::EBADF)
+		}
+	}
+}
+
+impl Drop for RamFile {
+	fn drop(&mut self) {
+		// the directory entry of this link is removed
+		let _ = block_on(
+			async {
+				let mut guard = self.data.write().await;
+				guard.attr.st_nlink -= 1;
+				Ok(())
+			},
+			None,
+		);
+	}
 }
 
 impl RamFile {
@@ -361,6 +1041,9 @@ impl RamFile {
 		let microseconds = arch::kernel::systemtime::now_micros();
 		let t = timespec::from_usec(microseconds as i64);
 		let attr = FileAttr {
+			st_dev: MEM_DEV,
+			st_ino: next_inode(),
+			st_nlink: 1,
 			st_mode: mode | AccessPermission::S_IFREG,
 			st_atim: t,
 			st_mtim: t,
@@ -374,11 +1057,265 @@ impl RamFile {
 	}
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
+pub(crate) struct MemSymlink {
+	/// Path, to which the link points
+	target: String,
+	/// Attributes, which are shared between all hard links
+	attr: Arc<RwLock<FileAttr>>,
+}
+
+impl MemSymlink {
+	pub fn new(target: &str) -> Self {
+		let microseconds = arch::kernel::systemtime::now_micros();
+		let t = timespec::from_usec(microseconds as i64);
+
+		Self::with_mtime(target, t)
+	}
+
+	/// Creates a symbolic link, which was last modified at `mtime`
+	pub fn with_mtime(target: &str, mtime: timespec) -> Self {
+		let attr = FileAttr {
+			st_dev: MEM_DEV,
+			st_ino: next_inode(),
+			st_nlink: 1,
+			st_size: target.len() as u64,
+			st_mode: AccessPermission::from_bits(0o777).unwrap() | AccessPermission::S_IFLNK,
+			st_atim: mtime,
+			st_mtim: mtime,
+			st_ctim: mtime,
+			..Default::default()
+		};
+
+		Self {
+			target: target.to_string(),
+			attr: Arc::new(RwLock::new(attr)),
+		}
+	}
+}
+
+impl Drop for MemSymlink {
+	fn drop(&mut self) {
+		// the directory entry of this link is removed
+		let _ = block_on(
+			async {
+				self.attr.write().await.st_nlink -= 1;
+				Ok(())
+			},
+			None,
+		);
+	}
+}
+
+impl VfsNode for MemSymlink {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Symlink
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		block_on(async { Ok(*self.attr.read().await) }, None)
+	}
+
+	fn hard_link(&self) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		block_on(
+			async {
+				self.attr.write().await.st_nlink += 1;
+				Ok(Box::new(Self {
+					target: self.target.clone(),
+					attr: self.attr.clone(),
+				})
+					as Box<
+						dyn VfsNode + core::marker::Send + core::marker::Sync,
+					>)
+			},
+			None,
+		)
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		if components.is_empty() {
+			Ok(self.target.clone())
+		} else {
+			Err(io::Error::ENOTDIR)
+		}
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		if components.is_empty() {
+			self.get_file_attributes()
+		} else {
+			Err(io::Error::ENOTDIR)
+		}
+	}
+
+	fn traverse_setattr(&self, components: &mut Vec<&str>, attr: SetAttr) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					attr.apply(&mut *self.attr.write().await);
+					Ok(())
+				},
+				None,
+			)
+		} else {
+			Err(io::Error::ENOTDIR)
+		}
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, _name: &str) -> io::Result<Vec<u8>> {
+		if components.is_empty() {
+			Err(io::Error::ENODATA)
+		} else {
+			Err(io::Error::ENOTDIR)
+		}
+	}
+
+	/// Like Linux' tmpfs, symbolic links don't have user attributes
+	/// and the RAM disk doesn't store other attributes for them
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		_name: &str,
+		_value: &[u8],
+		_flags: XattrFlags,
+	) -> io::Result<()> {
+		if components.is_empty() {
+			Err(io::Error::EPERM)
+		} else {
+			Err(io::Error::ENOTDIR)
+		}
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		if components.is_empty() {
+			Ok(Vec::new())
+		} else {
+			Err(io::Error::ENOTDIR)
+		}
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, _name: &str) -> io::Result<()> {
+		if components.is_empty() {
+			Err(io::Error::ENODATA)
+		} else {
+			Err(io::Error::ENOTDIR)
+		}
+	}
+}
+
+/// Special file, which only consists of its attributes. Currently, only
+/// sockets are supported, which are bound to a path.
+#[derive(Debug)]
+pub(crate) struct MemSpecialFile {
+	/// Attributes, which are shared between all hard links
+	attr: Arc<RwLock<FileAttr>>,
+}
+
+impl MemSpecialFile {
+	pub fn new(mode: AccessPermission) -> io::Result<Self> {
+		if FileType::from(mode) != FileType::Socket {
+			return Err(io::Error::EINVAL);
+		}
+
+		let microseconds = arch::kernel::systemtime::now_micros();
+		let t = timespec::from_usec(microseconds as i64);
+		let attr = FileAttr {
+			st_dev: MEM_DEV,
+			st_ino: next_inode(),
+			st_nlink: 1,
+			st_mode: mode,
+			st_atim: t,
+			st_mtim: t,
+			st_ctim: t,
+			..Default::default()
+		};
+
+		Ok(Self {
+			attr: Arc::new(RwLock::new(attr)),
+		})
+	}
+}
+
+impl Drop for MemSpecialFile {
+	fn drop(&mut self) {
+		// the directory entry of this link is removed
+		let _ = block_on(
+			async {
+				self.attr.write().await.st_nlink -= 1;
+				Ok(())
+			},
+			None,
+		);
+	}
+}
+
+impl VfsNode for MemSpecialFile {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::File
+	}
+
+	fn get_object(&self) -> io::Result<Arc<dyn ObjectInterface>> {
+		// a socket cannot be opened
+		Err(io::Error::ENXIO)
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		block_on(async { Ok(*self.attr.read().await) }, None)
+	}
+
+	fn hard_link(&self) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		block_on(
+			async {
+				self.attr.write().await.st_nlink += 1;
+				Ok(Box::new(Self {
+					attr: self.attr.clone(),
+				})
+					as Box<
+						dyn VfsNode + core::marker::Send + core::marker::Sync,
+					>)
+			},
+			None,
+		)
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		if components.is_empty() {
+			self.get_file_attributes()
+		} else {
+			Err(io::Error::ENOTDIR)
+		}
+	}
+
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		if components.is_empty() {
+			self.get_file_attributes()
+		} else {
+			Err(io::Error::ENOTDIR)
+		}
+	}
+
+	fn traverse_setattr(&self, components: &mut Vec<&str>, attr: SetAttr) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					attr.apply(&mut *self.attr.write().await);
+					Ok(())
+				},
+				None,
+			)
+		} else {
+			Err(io::Error::ENOTDIR)
+		}
+	}
+}
+
+#[derive(Debug)]
 pub struct MemDirectoryInterface {
 	/// Directory entries
 	inner:
 		Arc<RwLock<BTreeMap<String, Box<dyn VfsNode + core::marker::Send + core::marker::Sync>>>>,
+	/// Position of the directory stream
+	pos: DirectoryPosition,
 }
 
 impl MemDirectoryInterface {
@@ -387,19 +1324,31 @@ impl MemDirectoryInterface {
 			RwLock<BTreeMap<String, Box<dyn VfsNode + core::marker::Send + core::marker::Sync>>>,
 		>,
 	) -> Self {
-		Self { inner }
+		Self {
+			inner,
+			pos: DirectoryPosition::default(),
+		}
 	}
 }
 
 #[async_trait]
 impl ObjectInterface for MemDirectoryInterface {
-	async fn readdir(&self) -> io::Result<Vec<DirectoryEntry>> {
-		let mut entries: Vec<DirectoryEntry> = Vec::new();
-		for name in self.inner.read().await.keys() {
-			entries.push(DirectoryEntry::new(name.to_string()));
-		}
+	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+		self.pos.seek(offset, whence)
+	}
 
-		Ok(entries)
+	async fn readdir(&self, offset: u64) -> io::Result<Vec<DirectoryEntry>> {
+		let offset = usize::try_from(offset).map_err(|_| io::Error::EINVAL)?;
+
+		Ok(directory_entries(
+			&*self.inner.read().await,
+			offset,
+			READDIR_PAGE_LEN,
+		))
+	}
+
+	async fn fsync(&self, _datasync: bool) -> io::Result<()> {
+		Ok(())
 	}
 }
 
@@ -407,7 +1356,8 @@ impl ObjectInterface for MemDirectoryInterface {
 pub(crate) struct MemDirectory {
 	inner:
 		Arc<RwLock<BTreeMap<String, Box<dyn VfsNode + core::marker::Send + core::marker::Sync>>>>,
-	attr: FileAttr,
+	attr: RwLock<FileAttr>,
+	xattrs: RwLock<Xattrs>,
 }
 
 impl MemDirectory {
@@ -415,18 +1365,47 @@ impl MemDirectory {
 		let microseconds = arch::kernel::systemtime::now_micros();
 		let t = timespec::from_usec(microseconds as i64);
 
+		Self::with_mtime(mode, t)
+	}
+
+	/// Creates an empty directory, which was last modified at `mtime`
+	pub fn with_mtime(mode: AccessPermission, mtime: timespec) -> Self {
 		Self {
 			inner: Arc::new(RwLock::new(BTreeMap::new())),
-			attr: FileAttr {
+			attr: RwLock::new(FileAttr {
+				st_dev: MEM_DEV,
+				st_ino: next_inode(),
 				st_mode: mode | AccessPermission::S_IFDIR,
-				st_atim: t,
-				st_mtim: t,
-				st_ctim: t,
+				st_atim: mtime,
+				st_mtim: mtime,
+				st_ctim: mtime,
 				..Default::default()
-			},
+			}),
+			xattrs: RwLock::new(Xattrs::default()),
 		}
 	}
 
+	/// Updates the time of the last modification, after an entry was
+	/// added or removed
+	async fn touch(&self) {
+		let microseconds = arch::kernel::systemtime::now_micros();
+		let t = timespec::from_usec(microseconds as i64);
+		let mut attr = self.attr.write().await;
+		attr.st_mtim = t;
+		attr.st_ctim = t;
+	}
+
+	/// Updates the time of the last modification of the directory at
+	/// `components`, after an entry was added or removed
+	fn touch_at(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		let microseconds = arch::kernel::systemtime::now_micros();
+		let attr = SetAttr {
+			mtime: Some(timespec::from_usec(microseconds as i64)),
+			..Default::default()
+		};
+		self.traverse_setattr(components, attr)
+	}
+
 	async fn async_traverse_open(
 		&self,
 		components: &mut Vec<&str>,
@@ -442,9 +1421,11 @@ impl MemDirectory {
 					if guard.get(&node_name).is_some() {
 						return Err(io::Error::EEXIST);
 					} else {
-						let file = Box::new(RamFile::new(mode));
-						guard.insert(node_name, file.clone());
-						return Ok(Arc::new(RamFileInterface::new(file.data.clone())));
+						let file = RamFile::new(mode);
+						let data = file.data.clone();
+						guard.insert(node_name, Box::new(file));
+						self.touch().await;
+						return Ok(Arc::new(RamFileInterface::new(data)));
 					}
 				} else if let Some(file) = guard.get(&node_name) {
 					if opt.contains(OpenOption::O_DIRECTORY)
@@ -466,6 +1447,8 @@ impl MemDirectory {
 			if let Some(directory) = self.inner.read().await.get(&node_name) {
 				return directory.traverse_open(components, opt, mode);
 			}
+		} else {
+			return self.get_object();
 		}
 
 		Err(io::Error::ENOENT)
@@ -482,7 +1465,22 @@ impl VfsNode for MemDirectory {
 	}
 
 	fn get_file_attributes(&self) -> io::Result<FileAttr> {
-		Ok(self.attr)
+		block_on(
+			async {
+				// the entry in the parent directory, "." and ".." of all subdirectories
+				let subdirs = self
+					.inner
+					.read()
+					.await
+					.values()
+					.filter(|node| node.get_kind() == NodeKind::Directory)
+					.count();
+				let mut attr = *self.attr.read().await;
+				attr.st_nlink = (2 + subdirs).try_into().unwrap();
+				Ok(attr)
+			},
+			None,
+		)
 	}
 
 	fn traverse_mkdir(&self, components: &mut Vec<&str>, mode: AccessPermission) -> io::Result<()> {
@@ -500,6 +1498,7 @@ impl VfsNode for MemDirectory {
 							.write()
 							.await
 							.insert(node_name, Box::new(MemDirectory::new(mode)));
+						self.touch().await;
 						return Ok(());
 					}
 				}
@@ -521,6 +1520,8 @@ impl VfsNode for MemDirectory {
 
 						let obj = guard.remove(&node_name).ok_or(io::Error::ENOENT)?;
 						if obj.get_kind() == NodeKind::Directory {
+							drop(guard);
+							self.touch().await;
 							return Ok(());
 						} else {
 							guard.insert(node_name, obj);
@@ -547,11 +1548,13 @@ impl VfsNode for MemDirectory {
 						let mut guard = self.inner.write().await;
 
 						let obj = guard.remove(&node_name).ok_or(io::Error::ENOENT)?;
-						if obj.get_kind() == NodeKind::File {
-							return Ok(());
-						} else {
+						if obj.get_kind() == NodeKind::Directory {
 							guard.insert(node_name, obj);
 							return Err(io::Error::EISDIR);
+						} else {
+							drop(guard);
+							self.touch().await;
+							return Ok(());
 						}
 					} else if let Some(directory) = self.inner.read().await.get(&node_name) {
 						return directory.traverse_unlink(components);
@@ -576,12 +1579,271 @@ impl VfsNode for MemDirectory {
 						Err(io::Error::EBADF)
 					}
 				} else {
-					let mut entries: Vec<DirectoryEntry> = Vec::new();
-					for name in self.inner.read().await.keys() {
-						entries.push(DirectoryEntry::new(name.to_string()));
+					Ok(directory_entries(&*self.inner.read().await, 0, usize::MAX))
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_rename(
+		&self,
+		old_components: &mut Vec<&str>,
+		new_components: &mut Vec<&str>,
+	) -> io::Result<()> {
+		block_on(
+			async {
+				// Both paths continue in the same subdirectory, which might be a mount point.
+				if old_components.len() > 1
+					&& new_components.len() > 1
+					&& old_components.last() == new_components.last()
+				{
+					let node_name = String::from(old_components.pop().unwrap());
+					new_components.pop();
+
+					return if let Some(directory) = self.inner.read().await.get(&node_name) {
+						directory.traverse_rename(old_components, new_components)
+					} else {
+						Err(io::Error::ENOENT)
+					};
+				}
+
+				if old_components.is_empty() || new_components.is_empty() {
+					return Err(io::Error::EINVAL);
+				}
+
+				let old_name = String::from(old_components.remove(0));
+				let new_name = String::from(new_components.remove(0));
+				let mut old_parent = old_components.clone();
+				let mut new_parent = new_components.clone();
+				let old_dir = self.traverse_mem_directory(old_components)?;
+				let new_dir = self.traverse_mem_directory(new_components)?;
+
+				if Arc::ptr_eq(&old_dir, &new_dir) {
+					let mut guard = old_dir.write().await;
+
+					if old_name == new_name {
+						return if guard.contains_key(&old_name) {
+							Ok(())
+						} else {
+							Err(io::Error::ENOENT)
+						};
+					}
+
+					let node = guard.remove(&old_name).ok_or(io::Error::ENOENT)?;
+					if let Some(target) = guard.get(&new_name) {
+						if let Err(e) = check_rename_target(node.as_ref(), target.as_ref()) {
+							guard.insert(old_name, node);
+							return Err(e);
+						}
+					}
+					guard.insert(new_name, node);
+				} else {
+					// acquire the locks in a fixed order to avoid deadlocks
+					let (mut old_guard, mut new_guard) =
+						if Arc::as_ptr(&old_dir) < Arc::as_ptr(&new_dir) {
+							let old_guard = old_dir.write().await;
+							(old_guard, new_dir.write().await)
+						} else {
+							let new_guard = new_dir.write().await;
+							(old_dir.write().await, new_guard)
+						};
+
+					let node = old_guard.remove(&old_name).ok_or(io::Error::ENOENT)?;
+					if let Some(target) = new_guard.get(&new_name) {
+						if let Err(e) = check_rename_target(node.as_ref(), target.as_ref()) {
+							old_guard.insert(old_name, node);
+							return Err(e);
+						}
+					}
+					new_guard.insert(new_name, node);
+				}
+
+				self.touch_at(&mut old_parent)?;
+				self.touch_at(&mut new_parent)
+			},
+			None,
+		)
+	}
+
+	fn traverse_symlink(&self, components: &mut Vec<&str>, target: &str) -> io::Result<()> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
+
+					if components.is_empty() {
+						let mut guard = self.inner.write().await;
+						if guard.contains_key(&node_name) {
+							return Err(io::Error::EEXIST);
+						}
+
+						guard.insert(node_name, Box::new(MemSymlink::new(target)));
+						drop(guard);
+						self.touch().await;
+						return Ok(());
+					}
+
+					if let Some(directory) = self.inner.read().await.get(&node_name) {
+						return directory.traverse_symlink(components, target);
+					}
+				}
+
+				Err(io::Error::ENOENT)
+			},
+			None,
+		)
+	}
+
+	fn traverse_mknod(&self, components: &mut Vec<&str>, mode: AccessPermission) -> io::Result<()> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
+
+					if components.is_empty() {
+						let node = MemSpecialFile::new(mode)?;
+						let mut guard = self.inner.write().await;
+						if guard.contains_key(&node_name) {
+							return Err(io::Error::EEXIST);
+						}
+
+						guard.insert(node_name, Box::new(node));
+						drop(guard);
+						self.touch().await;
+						return Ok(());
+					}
+
+					if let Some(directory) = self.inner.read().await.get(&node_name) {
+						return directory.traverse_mknod(components, mode);
+					}
+				}
+
+				Err(io::Error::ENOENT)
+			},
+			None,
+		)
+	}
+
+	fn traverse_link(
+		&self,
+		old_components: &mut Vec<&str>,
+		new_components: &mut Vec<&str>,
+	) -> io::Result<()> {
+		block_on(
+			async {
+				// Both paths continue in the same subdirectory, which might be a mount point.
+				if old_components.len() > 1
+					&& new_components.len() > 1
+					&& old_components.last() == new_components.last()
+				{
+					let node_name = String::from(old_components.pop().unwrap());
+					new_components.pop();
+
+					return if let Some(directory) = self.inner.read().await.get(&node_name) {
+						directory.traverse_link(old_components, new_components)
+					} else {
+						Err(io::Error::ENOENT)
+					};
+				}
+
+				if old_components.is_empty() || new_components.is_empty() {
+					return Err(io::Error::EPERM);
+				}
+
+				let old_name = String::from(old_components.remove(0));
+				let new_name = String::from(new_components.remove(0));
+				let mut new_parent = new_components.clone();
+				let old_dir = self.traverse_mem_directory(old_components)?;
+				let new_dir = self.traverse_mem_directory(new_components)?;
+
+				let link = old_dir
+					.read()
+					.await
+					.get(&old_name)
+					.ok_or(io::Error::ENOENT)?
+					.hard_link()?;
+
+				let mut guard = new_dir.write().await;
+				if guard.contains_key(&new_name) {
+					return Err(io::Error::EEXIST);
+				}
+				guard.insert(new_name, link);
+				drop(guard);
+
+				self.touch_at(&mut new_parent)
+			},
+			None,
+		)
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
+
+					if let Some(node) = self.inner.read().await.get(&node_name) {
+						node.traverse_readlink(components)
+					} else {
+						Err(io::Error::ENOENT)
+					}
+				} else {
+					Err(io::Error::EINVAL)
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_lookup_symlink(
+		&self,
+		components: &mut Vec<&str>,
+		follow: bool,
+	) -> io::Result<Option<String>> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
+
+					if let Some(node) = self.inner.read().await.get(&node_name) {
+						if node.get_kind() == NodeKind::Symlink {
+							if follow || !components.is_empty() {
+								return node.traverse_readlink(&mut Vec::new()).map(Some);
+							}
+						} else if !components.is_empty() {
+							return node.traverse_lookup_symlink(components, follow);
+						}
 					}
+				}
+
+				// Missing nodes are reported by the operation, which uses the path.
+				Ok(None)
+			},
+			None,
+		)
+	}
+
+	fn traverse_mem_directory(
+		&self,
+		components: &mut Vec<&str>,
+	) -> io::Result<MemDirectoryEntries> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
 
-					Ok(entries)
+					if let Some(directory) = self.inner.read().await.get(&node_name) {
+						if directory.get_kind() == NodeKind::Directory {
+							directory.traverse_mem_directory(components)
+						} else {
+							Err(io::Error::ENOTDIR)
+						}
+					} else {
+						Err(io::Error::ENOENT)
+					}
+				} else {
+					Ok(self.inner.clone())
 				}
 			},
 			None,
@@ -606,7 +1868,7 @@ impl VfsNode for MemDirectory {
 						Err(io::Error::EBADF)
 					}
 				} else {
-					Err(io::Error::ENOSYS)
+					self.get_file_attributes()
 				}
 			},
 			None,
@@ -631,7 +1893,7 @@ impl VfsNode for MemDirectory {
 						Err(io::Error::EBADF)
 					}
 				} else {
-					Err(io::Error::ENOSYS)
+					self.get_file_attributes()
 				}
 			},
 			None,
@@ -664,6 +1926,178 @@ impl VfsNode for MemDirectory {
 		)
 	}
 
+	fn traverse_detach(
+		&self,
+		components: &mut Vec<&str>,
+	) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
+
+					if components.is_empty() {
+						return self
+							.inner
+							.write()
+							.await
+							.remove(&node_name)
+							.ok_or(io::Error::ENOENT);
+					}
+
+					if let Some(directory) = self.inner.read().await.get(&node_name) {
+						return directory.traverse_detach(components);
+					}
+				}
+
+				Err(io::Error::ENOENT)
+			},
+			None,
+		)
+	}
+
+	fn traverse_setattr(&self, components: &mut Vec<&str>, attr: SetAttr) -> io::Result<()> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
+
+					if let Some(node) = self.inner.read().await.get(&node_name) {
+						node.traverse_setattr(components, attr)
+					} else {
+						Err(io::Error::ENOENT)
+					}
+				} else {
+					attr.apply(&mut *self.attr.write().await);
+					Ok(())
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
+
+					if let Some(node) = self.inner.read().await.get(&node_name) {
+						node.traverse_getxattr(components, name)
+					} else {
+						Err(io::Error::ENOENT)
+					}
+				} else {
+					self.xattrs.read().await.get(name)
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
+
+					if let Some(node) = self.inner.read().await.get(&node_name) {
+						node.traverse_setxattr(components, name, value, flags)
+					} else {
+						Err(io::Error::ENOENT)
+					}
+				} else {
+					let mut attr = self.attr.write().await;
+					self.xattrs.write().await.set(name, value, flags, &mut attr)
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
+
+					if let Some(node) = self.inner.read().await.get(&node_name) {
+						node.traverse_listxattr(components)
+					} else {
+						Err(io::Error::ENOENT)
+					}
+				} else {
+					Ok(self.xattrs.read().await.list())
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<()> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
+
+					if let Some(node) = self.inner.read().await.get(&node_name) {
+						node.traverse_removexattr(components, name)
+					} else {
+						Err(io::Error::ENOENT)
+					}
+				} else {
+					let mut attr = self.attr.write().await;
+					self.xattrs.write().await.remove(name, &mut attr)
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					let node_name = String::from(component);
+
+					if let Some(node) = self.inner.read().await.get(&node_name) {
+						// other file systems can be mounted on directories
+						if node.get_kind() == NodeKind::Directory {
+							return node.traverse_statfs(components);
+						} else if components.is_empty() {
+							return self.traverse_statfs(components);
+						} else {
+							return Err(io::Error::ENOTDIR);
+						}
+					}
+
+					Err(io::Error::ENOENT)
+				} else {
+					let block_size = BasePageSize::SIZE;
+					let total: u64 = mm::total_memory_size().try_into().unwrap();
+					let available: u64 = mm::available_memory_size().try_into().unwrap();
+
+					Ok(StatFs {
+						f_type: TMPFS_MAGIC,
+						f_bsize: block_size.try_into().unwrap(),
+						f_blocks: total / block_size,
+						f_bfree: available / block_size,
+						f_bavail: available / block_size,
+						f_namelen: 255,
+						f_frsize: block_size.try_into().unwrap(),
+						..Default::default()
+					})
+				}
+			},
+			None,
+		)
+	}
+
 	fn traverse_open(
 		&self,
 		components: &mut Vec<&str>,
@@ -687,6 +2121,7 @@ impl VfsNode for MemDirectory {
 					if components.is_empty() {
 						let file = RomFile::new(data, mode);
 						self.inner.write().await.insert(name, Box::new(file));
+						self.touch().await;
 						return Ok(());
 					}
 
@@ -1,32 +1,127 @@
+mod dev;
 #[cfg(all(feature = "fuse", feature = "pci"))]
 pub(crate) mod fuse;
+mod initramfs;
+pub(crate) mod lock;
 mod mem;
+pub(crate) mod notify;
+mod overlay;
+mod proc;
 mod uhyve;
 
 use alloc::boxed::Box;
 use alloc::string::{String, ToString};
-use alloc::sync::Arc;
+use alloc::sync::{Arc, Weak};
 use alloc::vec::Vec;
+use core::mem::MaybeUninit;
 
 use async_trait::async_trait;
-use hermit_sync::OnceCell;
-use mem::MemDirectory;
-
-use crate::fd::{AccessPermission, ObjectInterface, OpenOption, insert_object, remove_object};
-use crate::io;
-use crate::io::Write;
+use dev::DevDirectory;
+use hermit_sync::{OnceCell, TicketMutex};
+use mem::{MemDirectory, MemDirectoryEntries};
+use notify::InotifyMask;
+use overlay::OverlayDirectory;
+use proc::ProcDirectory;
+
+use crate::executor::block_on;
+use crate::fd::{AccessPermission, ObjectInterface, OpenOption, PollEvent, insert_object};
 use crate::time::{SystemTime, timespec};
+use crate::{arch, io};
 
 static FILESYSTEM: OnceCell<Filesystem> = OnceCell::new();
 
+/// Current working directory of the application
+static WORKING_DIRECTORY: TicketMutex<String> = TicketMutex::new(String::new());
+
+/// Maximum number of symbolic links, which are followed during a path lookup
+const MAX_SYMLINKS: usize = 40;
+
+/// Maximum number of entries, which are returned by a single `readdir` call
+const READDIR_PAGE_LEN: usize = 128;
+
+/// Maximum length of the name of an extended attribute
+const XATTR_NAME_MAX: usize = 255;
+
+/// Maximum size of the value of an extended attribute
+const XATTR_SIZE_MAX: usize = 0x1_0000;
+
+/// Namespaces of extended attributes
+const XATTR_NAMESPACES: [&str; 4] = ["user.", "trusted.", "security.", "system."];
+
+/// Checks the name of an extended attribute
+pub(crate) fn check_xattr_name(name: &str) -> io::Result<()> {
+	if name.is_empty() || name.len() > XATTR_NAME_MAX {
+		return Err(io::Error::ERANGE);
+	}
+
+	if XATTR_NAMESPACES
+		.iter()
+		.any(|namespace| name.len() > namespace.len() && name.starts_with(namespace))
+	{
+		Ok(())
+	} else {
+		Err(io::Error::EOPNOTSUPP)
+	}
+}
+
+/// Checks the name and the value of an extended attribute, which is set
+pub(crate) fn check_xattr(name: &str, value: &[u8]) -> io::Result<()> {
+	check_xattr_name(name)?;
+	if value.len() > XATTR_SIZE_MAX {
+		return Err(io::Error::E2BIG);
+	}
+
+	Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct DirectoryEntry {
 	pub name: String,
+	/// Inode number or 0, if it is unknown
+	pub ino: u64,
+	pub file_type: FileType,
+	/// Position of the next entry, which is passed to [`ObjectInterface::readdir`]
+	/// to continue the directory stream
+	pub offset: u64,
 }
 
 impl DirectoryEntry {
 	pub fn new(name: String) -> Self {
-		Self { name }
+		Self {
+			name,
+			ino: 0,
+			file_type: FileType::Unknown,
+			offset: 0,
+		}
+	}
+
+	/// Creates an entry with the inode number and file type of `attr`
+	pub fn with_attr(name: String, attr: &FileAttr) -> Self {
+		Self {
+			name,
+			ino: attr.st_ino,
+			file_type: FileType::from(attr.st_mode),
+			offset: 0,
+		}
+	}
+}
+
+/// Position of an opened directory, which is used by `getdents64`
+#[derive(Debug, Default)]
+struct DirectoryPosition(TicketMutex<u64>);
+
+impl DirectoryPosition {
+	/// Moves the position to `offset`. Only absolute positions are supported,
+	/// besides querying the current position.
+	pub fn seek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+		let mut pos = self.0.lock();
+		match whence {
+			SeekWhence::Set => *pos = offset.try_into().map_err(|_| io::Error::EINVAL)?,
+			SeekWhence::Cur if offset == 0 => {}
+			_ => return Err(io::Error::EINVAL),
+		}
+
+		(*pos).try_into().map_err(|_| io::Error::EOVERFLOW)
 	}
 }
 
@@ -37,6 +132,8 @@ pub(crate) enum NodeKind {
 	File,
 	/// Node represent a directory
 	Directory,
+	/// Node represent a symbolic link
+	Symlink,
 }
 
 /// VfsNode represents an internal node of the ramdisk.
@@ -78,6 +175,70 @@ pub(crate) trait VfsNode: core::fmt::Debug {
 		Err(io::Error::ENOSYS)
 	}
 
+	/// Helper function to rename a node
+	fn traverse_rename(
+		&self,
+		_old_components: &mut Vec<&str>,
+		_new_components: &mut Vec<&str>,
+	) -> io::Result<()> {
+		Err(io::Error::ENOSYS)
+	}
+
+	/// Helper function to create a symbolic link, which points to `target`
+	fn traverse_symlink(&self, _components: &mut Vec<&str>, _target: &str) -> io::Result<()> {
+		Err(io::Error::ENOSYS)
+	}
+
+	/// Helper function to create a special file of the type in `mode`
+	fn traverse_mknod(
+		&self,
+		_components: &mut Vec<&str>,
+		_mode: AccessPermission,
+	) -> io::Result<()> {
+		Err(io::Error::ENOSYS)
+	}
+
+	/// Helper function to create a hard link
+	fn traverse_link(
+		&self,
+		_old_components: &mut Vec<&str>,
+		_new_components: &mut Vec<&str>,
+	) -> io::Result<()> {
+		Err(io::Error::ENOSYS)
+	}
+
+	/// Helper function to read the target of a symbolic link
+	fn traverse_readlink(&self, _components: &mut Vec<&str>) -> io::Result<String> {
+		Err(io::Error::EINVAL)
+	}
+
+	/// Helper function to find the first symbolic link in the path. The last
+	/// component is only considered, if `follow` is set. On success, the target of
+	/// the link is returned and `components` holds the components behind the link.
+	///
+	/// File systems, which resolve symbolic links by themselves, return `None`.
+	fn traverse_lookup_symlink(
+		&self,
+		_components: &mut Vec<&str>,
+		_follow: bool,
+	) -> io::Result<Option<String>> {
+		Ok(None)
+	}
+
+	/// Creates a new hard link to this node
+	fn hard_link(&self) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		Err(io::Error::EPERM)
+	}
+
+	/// Helper function to get the entries of a directory, which is part of the RAM disk.
+	/// Nodes, which don't belong to the RAM disk, return `EXDEV`.
+	fn traverse_mem_directory(
+		&self,
+		_components: &mut Vec<&str>,
+	) -> io::Result<MemDirectoryEntries> {
+		Err(io::Error::EXDEV)
+	}
+
 	/// Helper function to get file status
 	fn traverse_lstat(&self, _components: &mut Vec<&str>) -> io::Result<FileAttr> {
 		Err(io::Error::ENOSYS)
@@ -97,6 +258,52 @@ pub(crate) trait VfsNode: core::fmt::Debug {
 		Err(io::Error::ENOSYS)
 	}
 
+	/// Helper function to detach a node from the tree. In contrast to
+	/// `traverse_unlink` and `traverse_rmdir`, the node isn't checked
+	/// and returned to the caller.
+	fn traverse_detach(
+		&self,
+		_components: &mut Vec<&str>,
+	) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		Err(io::Error::EINVAL)
+	}
+
+	/// Helper function to change the attributes of a node
+	fn traverse_setattr(&self, _components: &mut Vec<&str>, _attr: SetAttr) -> io::Result<()> {
+		Err(io::Error::ENOSYS)
+	}
+
+	/// Helper function to get the value of an extended attribute
+	fn traverse_getxattr(&self, _components: &mut Vec<&str>, _name: &str) -> io::Result<Vec<u8>> {
+		Err(io::Error::EOPNOTSUPP)
+	}
+
+	/// Helper function to create or replace an extended attribute
+	fn traverse_setxattr(
+		&self,
+		_components: &mut Vec<&str>,
+		_name: &str,
+		_value: &[u8],
+		_flags: XattrFlags,
+	) -> io::Result<()> {
+		Err(io::Error::EOPNOTSUPP)
+	}
+
+	/// Helper function to list the names of the extended attributes
+	fn traverse_listxattr(&self, _components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		Err(io::Error::EOPNOTSUPP)
+	}
+
+	/// Helper function to remove an extended attribute
+	fn traverse_removexattr(&self, _components: &mut Vec<&str>, _name: &str) -> io::Result<()> {
+		Err(io::Error::EOPNOTSUPP)
+	}
+
+	/// Helper function to get the statistics of the file system
+	fn traverse_statfs(&self, _components: &mut Vec<&str>) -> io::Result<StatFs> {
+		Err(io::Error::ENOSYS)
+	}
+
 	/// Helper function to open a file
 	fn traverse_open(
 		&self,
@@ -118,34 +325,381 @@ pub(crate) trait VfsNode: core::fmt::Debug {
 	}
 }
 
-#[derive(Debug, Clone)]
-struct DirectoryReader(Vec<DirectoryEntry>);
+#[derive(Debug)]
+struct DirectoryReader {
+	entries: Vec<DirectoryEntry>,
+	pos: DirectoryPosition,
+}
 
 impl DirectoryReader {
-	pub fn new(data: Vec<DirectoryEntry>) -> Self {
-		Self(data)
+	pub fn new(mut entries: Vec<DirectoryEntry>) -> Self {
+		for (i, entry) in entries.iter_mut().enumerate() {
+			entry.offset = (i + 1).try_into().unwrap();
+		}
+
+		Self {
+			entries,
+			pos: DirectoryPosition::default(),
+		}
 	}
 }
 
 #[async_trait]
 impl ObjectInterface for DirectoryReader {
-	async fn readdir(&self) -> io::Result<Vec<DirectoryEntry>> {
-		Ok(self.0.clone())
+	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+		self.pos.seek(offset, whence)
+	}
+
+	async fn readdir(&self, offset: u64) -> io::Result<Vec<DirectoryEntry>> {
+		let start = usize::try_from(offset).map_err(|_| io::Error::EINVAL)?;
+
+		Ok(self
+			.entries
+			.iter()
+			.skip(start)
+			.take(READDIR_PAGE_LEN)
+			.cloned()
+			.collect())
+	}
+
+	async fn fsync(&self, _datasync: bool) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Directory, which remembers the path it was opened with
+#[derive(Debug)]
+struct DirectoryHandle {
+	path: String,
+	inner: Arc<dyn ObjectInterface>,
+}
+
+impl DirectoryHandle {
+	pub fn new(path: String, inner: Arc<dyn ObjectInterface>) -> Self {
+		Self { path, inner }
+	}
+}
+
+#[async_trait]
+impl ObjectInterface for DirectoryHandle {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		self.inner.poll(event).await
+	}
+
+	async fn read(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		self.inner.read(buf).await
+	}
+
+	async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		self.inner.write(buf).await
+	}
+
+	async fn append(&self, buf: &[u8]) -> io::Result<usize> {
+		self.inner.append(buf).await
+	}
+
+	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+		self.inner.lseek(offset, whence).await
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		self.inner.fstat().await
+	}
+
+	async fn readdir(&self, offset: u64) -> io::Result<Vec<DirectoryEntry>> {
+		self.inner.readdir(offset).await
+	}
+
+	async fn flush(&self) -> io::Result<()> {
+		self.inner.flush().await
+	}
+
+	async fn fsync(&self, datasync: bool) -> io::Result<()> {
+		self.inner.fsync(datasync).await
+	}
+
+	async fn syncfs(&self) -> io::Result<()> {
+		self.inner.syncfs().await
+	}
+
+	async fn getpath(&self) -> io::Result<String> {
+		Ok(self.path.clone())
+	}
+
+	async fn setattr(&self, attr: SetAttr) -> io::Result<()> {
+		FILESYSTEM
+			.get()
+			.ok_or(io::Error::EINVAL)?
+			.setattr(&self.path, attr, true)
+	}
+
+	async fn getxattr(&self, name: &str) -> io::Result<Vec<u8>> {
+		FILESYSTEM
+			.get()
+			.ok_or(io::Error::EINVAL)?
+			.getxattr(&self.path, name, true)
+	}
+
+	async fn setxattr(&self, name: &str, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+		FILESYSTEM
+			.get()
+			.ok_or(io::Error::EINVAL)?
+			.setxattr(&self.path, name, value, flags, true)
+	}
+
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		FILESYSTEM
+			.get()
+			.ok_or(io::Error::EINVAL)?
+			.listxattr(&self.path, true)
+	}
+
+	async fn removexattr(&self, name: &str) -> io::Result<()> {
+		FILESYSTEM
+			.get()
+			.ok_or(io::Error::EINVAL)?
+			.removexattr(&self.path, name, true)
+	}
+}
+
+/// Splits a normalized absolute path into its components. As expected by the
+/// `traverse_*` functions, the first component is the last element of the vector.
+fn split_path(path: &str) -> Vec<&str> {
+	path.split('/')
+		.filter(|component| !component.is_empty())
+		.rev()
+		.collect()
+}
+
+/// Converts `path` to an absolute path, which is relative to the current
+/// working directory, and removes ".", ".." and repeated slashes.
+fn absolute_path(path: &str) -> io::Result<String> {
+	if path.is_empty() {
+		return Err(io::Error::ENOENT);
+	}
+
+	let cwd = if path.starts_with('/') {
+		None
+	} else {
+		Some(WORKING_DIRECTORY.lock().clone())
+	};
+
+	let mut normalized: Vec<&str> = Vec::new();
+	for component in cwd
+		.iter()
+		.flat_map(|cwd| cwd.split('/'))
+		.chain(path.split('/'))
+	{
+		match component {
+			"" | "." => {}
+			".." => {
+				normalized.pop();
+			}
+			component => normalized.push(component),
+		}
+	}
+
+	Ok("/".to_string() + &normalized.join("/"))
+}
+
+/// Returns true, if `path` is `dir` or lies below `dir`
+fn is_below(path: &str, dir: &str) -> bool {
+	dir == "/"
+		|| path
+			.strip_prefix(dir)
+			.is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Entry of the mount table
+#[derive(Debug, Clone)]
+pub(crate) struct MountEntry {
+	/// Device or remote file system, which is mounted
+	pub source: String,
+	/// Absolute path of the mount point
+	pub target: String,
+	/// Type of the file system
+	pub fstype: &'static str,
+	/// Mount options as comma-separated list
+	pub options: String,
+	/// File system is mounted read-only
+	pub read_only: bool,
+}
+
+#[derive(Debug)]
+struct Mount {
+	entry: MountEntry,
+	/// Files, which were opened below the mount point
+	open_files: Vec<Weak<dyn ObjectInterface>>,
+	/// Node, which was replaced by the mount
+	covered: Option<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>>,
+}
+
+impl Mount {
+	fn is_busy(&self) -> bool {
+		self.open_files.iter().any(|file| file.strong_count() > 0)
+	}
+}
+
+/// File system, which was lazily unmounted, while files were still open
+#[derive(Debug)]
+struct DetachedMount {
+	/// Root node of the file system
+	root: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	/// Files, which were opened below the former mount point
+	open_files: Vec<Weak<dyn ObjectInterface>>,
+	read_only: bool,
+}
+
+/// Magic number of anonymous inodes, as reported by `fstatfs` for objects
+/// like sockets and pipes, which don't belong to a file system
+const ANON_INODE_FS_MAGIC: i64 = 0x0904_1934;
+
+/// Converts the mount flags and file system specific options to an option string
+fn mount_options(flags: MountFlags, data: &str) -> String {
+	let mut options = if flags.contains(MountFlags::MS_RDONLY) {
+		"ro".to_string()
+	} else {
+		"rw".to_string()
+	};
+	if !data.is_empty() {
+		options = options + "," + data;
 	}
+
+	options
 }
 
 #[derive(Debug)]
 pub(crate) struct Filesystem {
 	root: MemDirectory,
+	mounts: TicketMutex<Vec<Mount>>,
+	/// Lazily unmounted file systems with open files
+	detached: TicketMutex<Vec<DetachedMount>>,
+	/// Serializes mount and unmount, so that the lookup of the mount point
+	/// and the change of the tree and the mount table are atomic
+	mount_lock: async_lock::Mutex<()>,
 }
 
 impl Filesystem {
 	pub fn new() -> Self {
+		let rootfs = Mount {
+			entry: MountEntry {
+				source: "rootfs".to_string(),
+				target: "/".to_string(),
+				fstype: "tmpfs",
+				options: mount_options(MountFlags::empty(), ""),
+				read_only: false,
+			},
+			open_files: Vec::new(),
+			covered: None,
+		};
+
 		Self {
 			root: MemDirectory::new(AccessPermission::from_bits(0o777).unwrap()),
+			mounts: TicketMutex::new(vec![rootfs]),
+			detached: TicketMutex::new(Vec::new()),
+			mount_lock: async_lock::Mutex::new(()),
 		}
 	}
 
+	/// Returns the index of the innermost mount, which contains `path`
+	fn mount_index(mounts: &[Mount], path: &str) -> usize {
+		mounts
+			.iter()
+			.enumerate()
+			.filter(|(_, mount)| is_below(path, &mount.entry.target))
+			.max_by_key(|(_, mount)| mount.entry.target.len())
+			.map(|(i, _)| i)
+			.unwrap()
+	}
+
+	/// Returns `EROFS`, if the normalized absolute `path` is part of a
+	/// read-only file system
+	fn check_writable(&self, path: &str) -> io::Result<()> {
+		let mounts = self.mounts.lock();
+		if mounts[Self::mount_index(&mounts, path)].entry.read_only {
+			Err(io::Error::EROFS)
+		} else {
+			Ok(())
+		}
+	}
+
+	/// Returns `EBUSY`, if a file system is mounted at the normalized absolute
+	/// `path`, so that the mount point cannot be removed or replaced
+	fn check_mount_point(mounts: &[Mount], path: &str) -> io::Result<()> {
+		if mounts.iter().any(|mount| mount.entry.target == path) {
+			Err(io::Error::EBUSY)
+		} else {
+			Ok(())
+		}
+	}
+
+	/// Returns `EXDEV`, if the normalized absolute paths `old_path` and
+	/// `new_path` are part of different file systems
+	fn check_same_mount(mounts: &[Mount], old_path: &str, new_path: &str) -> io::Result<()> {
+		if Self::mount_index(mounts, old_path) == Self::mount_index(mounts, new_path) {
+			Ok(())
+		} else {
+			Err(io::Error::EXDEV)
+		}
+	}
+
+	/// Returns the type of the file system, which contains the normalized
+	/// absolute `path`
+	fn fstype(&self, path: &str) -> &'static str {
+		let mounts = self.mounts.lock();
+		mounts[Self::mount_index(&mounts, path)].entry.fstype
+	}
+
+	/// Returns the attributes of the normalized absolute `path` without
+	/// following a symbolic link
+	fn lstat_resolved(&self, path: &str) -> Option<FileAttr> {
+		self.root.traverse_lstat(&mut split_path(path)).ok()
+	}
+
+	/// Remembers that `obj` was opened at the normalized absolute `path`
+	fn add_open_file(&self, path: &str, obj: &Arc<dyn ObjectInterface>) {
+		let mut mounts = self.mounts.lock();
+		let index = Self::mount_index(&mounts, path);
+		let open_files = &mut mounts[index].open_files;
+		open_files.retain(|file| file.strong_count() > 0);
+		open_files.push(Arc::downgrade(obj));
+	}
+
+	/// Converts `path` to a normalized absolute path and resolves the symbolic
+	/// links in it. The last component is only resolved, if `follow` is set.
+	fn resolve(&self, path: &str, follow: bool) -> io::Result<String> {
+		let mut path = absolute_path(path)?;
+
+		for _ in 0..MAX_SYMLINKS {
+			let components = split_path(&path);
+
+			let mut remaining = components.clone();
+			let Some(target) = self.root.traverse_lookup_symlink(&mut remaining, follow)? else {
+				return Ok(path);
+			};
+
+			// The link is the last component, which was consumed by the lookup.
+			// Relative targets are interpreted relative to the directory of the link.
+			let mut resolved: Vec<&str> = Vec::new();
+			if !target.starts_with('/') {
+				resolved.extend(components[remaining.len() + 1..].iter().rev());
+			}
+			for component in target.split('/').chain(remaining.iter().rev().copied()) {
+				match component {
+					"" | "." => {}
+					".." => {
+						resolved.pop();
+					}
+					component => resolved.push(component),
+				}
+			}
+
+			path = "/".to_string() + &resolved.join("/");
+		}
+
+		Err(io::Error::ELOOP)
+	}
+
 	/// Tries to open file at given path.
 	pub fn open(
 		&self,
@@ -154,74 +708,261 @@ impl Filesystem {
 		mode: AccessPermission,
 	) -> io::Result<Arc<dyn ObjectInterface>> {
 		debug!("Open file {} with {:?}", path, opt);
-		let mut components: Vec<&str> = path.split('/').collect();
-
-		components.reverse();
-		components.pop();
+		let path = self.resolve(path, true)?;
+		if opt.intersects(
+			OpenOption::O_WRONLY | OpenOption::O_RDWR | OpenOption::O_CREAT | OpenOption::O_TRUNC,
+		) {
+			self.check_writable(&path)?;
+		}
+		let mut components = split_path(&path);
+		let created = notify::is_active()
+			&& opt.contains(OpenOption::O_CREAT)
+			&& self.lstat_resolved(&path).is_none();
+
+		let obj = self.root.traverse_open(&mut components, opt, mode)?;
+		let obj: Arc<dyn ObjectInterface> = if opt.contains(OpenOption::O_DIRECTORY) {
+			Arc::new(DirectoryHandle::new(path.clone(), obj))
+		} else {
+			obj
+		};
 
-		self.root.traverse_open(&mut components, opt, mode)
+		self.add_open_file(&path, &obj);
+		if notify::is_active() {
+			notify::open(&path, &obj, opt, created);
+		}
+		Ok(obj)
 	}
 
 	/// Unlinks a file given by path
 	pub fn unlink(&self, path: &str) -> io::Result<()> {
 		debug!("Unlinking file {}", path);
-		let mut components: Vec<&str> = path.split('/').collect();
-
-		components.reverse();
-		components.pop();
-
-		self.root.traverse_unlink(&mut components)
+		let path = self.resolve(path, false)?;
+		self.check_writable(&path)?;
+		Self::check_mount_point(&self.mounts.lock(), &path)?;
+		let mut components = split_path(&path);
+
+		self.root.traverse_unlink(&mut components)?;
+		notify::notify(
+			&path,
+			InotifyMask::IN_DELETE | InotifyMask::IN_DELETE_SELF,
+			0,
+		);
+		Ok(())
 	}
 
 	/// Remove directory given by path
 	pub fn rmdir(&self, path: &str) -> io::Result<()> {
 		debug!("Removing directory {}", path);
-		let mut components: Vec<&str> = path.split('/').collect();
-
-		components.reverse();
-		components.pop();
-
-		self.root.traverse_rmdir(&mut components)
+		let path = self.resolve(path, false)?;
+		self.check_writable(&path)?;
+		Self::check_mount_point(&self.mounts.lock(), &path)?;
+		let mut components = split_path(&path);
+
+		self.root.traverse_rmdir(&mut components)?;
+		notify::notify(
+			&path,
+			InotifyMask::IN_DELETE | InotifyMask::IN_DELETE_SELF | InotifyMask::IN_ISDIR,
+			0,
+		);
+		Ok(())
 	}
 
 	/// Create directory given by path
 	pub fn mkdir(&self, path: &str, mode: AccessPermission) -> io::Result<()> {
 		debug!("Create directory {}", path);
-		let mut components: Vec<&str> = path.split('/').collect();
+		let path = self.resolve(path, false)?;
+		self.check_writable(&path)?;
+		let mut components = split_path(&path);
+
+		self.root.traverse_mkdir(&mut components, mode)?;
+		notify::notify(&path, InotifyMask::IN_CREATE | InotifyMask::IN_ISDIR, 0);
+		Ok(())
+	}
+
+	/// Rename a file or directory and replace the target, if it already exists
+	pub fn rename(&self, old_path: &str, new_path: &str) -> io::Result<()> {
+		debug!("Rename {} to {}", old_path, new_path);
+		let old_path = self.resolve(old_path, false)?;
+		let new_path = self.resolve(new_path, false)?;
+		self.check_writable(&old_path)?;
+		self.check_writable(&new_path)?;
+		{
+			let mounts = self.mounts.lock();
+			Self::check_mount_point(&mounts, &old_path)?;
+			Self::check_mount_point(&mounts, &new_path)?;
+			Self::check_same_mount(&mounts, &old_path, &new_path)?;
+		}
+		let mut old_components = split_path(&old_path);
+		let mut new_components = split_path(&new_path);
 
-		components.reverse();
-		components.pop();
+		// a directory cannot become a subdirectory of itself
+		if new_components.len() > old_components.len() && new_components.ends_with(&old_components)
+		{
+			return Err(io::Error::EINVAL);
+		}
+
+		let watched = notify::is_active().then(|| {
+			(
+				self.lstat_resolved(&old_path),
+				self.lstat_resolved(&new_path).is_some(),
+			)
+		});
+
+		self.root
+			.traverse_rename(&mut old_components, &mut new_components)?;
+		if let Some((attr, replaced)) = watched {
+			let is_dir =
+				attr.is_some_and(|attr| FileType::from(attr.st_mode) == FileType::Directory);
+			notify::rename(&old_path, &new_path, is_dir, replaced);
+		}
+		Ok(())
+	}
+
+	/// Create a symbolic link at `path`, which points to `target`
+	pub fn symlink(&self, target: &str, path: &str) -> io::Result<()> {
+		debug!("Create symbolic link {} -> {}", path, target);
+		let path = self.resolve(path, false)?;
+		self.check_writable(&path)?;
+		let mut components = split_path(&path);
+
+		self.root.traverse_symlink(&mut components, target)?;
+		notify::notify(&path, InotifyMask::IN_CREATE, 0);
+		Ok(())
+	}
+
+	/// Creates a special file at `path`, whose type is part of `mode`
+	#[cfg(feature = "unix")]
+	pub fn mknod(&self, path: &str, mode: AccessPermission) -> io::Result<()> {
+		debug!("Create special file {} with mode {:?}", path, mode);
+		let path = self.resolve(path, false)?;
+		self.check_writable(&path)?;
+		let mut components = split_path(&path);
+
+		self.root.traverse_mknod(&mut components, mode)?;
+		notify::notify(&path, InotifyMask::IN_CREATE, 0);
+		Ok(())
+	}
+
+	/// Create a hard link at `new_path`, which refers to the same file as `old_path`
+	pub fn link(&self, old_path: &str, new_path: &str) -> io::Result<()> {
+		debug!("Create hard link {} -> {}", new_path, old_path);
+		let old_path = self.resolve(old_path, false)?;
+		let new_path = self.resolve(new_path, false)?;
+		self.check_writable(&old_path)?;
+		self.check_writable(&new_path)?;
+		Self::check_same_mount(&self.mounts.lock(), &old_path, &new_path)?;
+		let mut old_components = split_path(&old_path);
+		let mut new_components = split_path(&new_path);
+
+		self.root
+			.traverse_link(&mut old_components, &mut new_components)?;
+		notify::notify(&new_path, InotifyMask::IN_CREATE, 0);
+		notify::notify(&old_path, InotifyMask::IN_ATTRIB, 0);
+		Ok(())
+	}
+
+	/// Changes the attributes of the node at `path`. The last component is
+	/// only resolved, if `follow` is set.
+	pub fn setattr(&self, path: &str, attr: SetAttr, follow: bool) -> io::Result<()> {
+		debug!("Set attributes {} to {:?}", path, attr);
+		let path = self.resolve(path, follow)?;
+		self.check_writable(&path)?;
+		let mut components = split_path(&path);
+
+		self.root.traverse_setattr(&mut components, attr)?;
+		notify::notify(&path, InotifyMask::IN_ATTRIB, 0);
+		Ok(())
+	}
 
-		self.root.traverse_mkdir(&mut components, mode)
+	/// Returns the value of the extended attribute `name` of the node at
+	/// `path`. The last component is only resolved, if `follow` is set.
+	pub fn getxattr(&self, path: &str, name: &str, follow: bool) -> io::Result<Vec<u8>> {
+		debug!("Get extended attribute {} of {}", name, path);
+		check_xattr_name(name)?;
+		let path = self.resolve(path, follow)?;
+		let mut components = split_path(&path);
+
+		self.root.traverse_getxattr(&mut components, name)
+	}
+
+	/// Creates or replaces the extended attribute `name` of the node at
+	/// `path`. The last component is only resolved, if `follow` is set.
+	pub fn setxattr(
+		&self,
+		path: &str,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+		follow: bool,
+	) -> io::Result<()> {
+		debug!("Set extended attribute {} of {}", name, path);
+		check_xattr(name, value)?;
+		let path = self.resolve(path, follow)?;
+		self.check_writable(&path)?;
+		let mut components = split_path(&path);
+
+		self.root
+			.traverse_setxattr(&mut components, name, value, flags)?;
+		notify::notify(&path, InotifyMask::IN_ATTRIB, 0);
+		Ok(())
+	}
+
+	/// Returns the names of the extended attributes of the node at `path`.
+	/// The last component is only resolved, if `follow` is set.
+	pub fn listxattr(&self, path: &str, follow: bool) -> io::Result<Vec<String>> {
+		debug!("List extended attributes of {}", path);
+		let path = self.resolve(path, follow)?;
+		let mut components = split_path(&path);
+
+		self.root.traverse_listxattr(&mut components)
+	}
+
+	/// Removes the extended attribute `name` of the node at `path`.
+	/// The last component is only resolved, if `follow` is set.
+	pub fn removexattr(&self, path: &str, name: &str, follow: bool) -> io::Result<()> {
+		debug!("Remove extended attribute {} of {}", name, path);
+		check_xattr_name(name)?;
+		let path = self.resolve(path, follow)?;
+		self.check_writable(&path)?;
+		let mut components = split_path(&path);
+
+		self.root.traverse_removexattr(&mut components, name)?;
+		notify::notify(&path, InotifyMask::IN_ATTRIB, 0);
+		Ok(())
+	}
+
+	/// Read the target of the symbolic link at `path`
+	pub fn readlink(&self, path: &str) -> io::Result<String> {
+		debug!("Read symbolic link {}", path);
+		let path = self.resolve(path, false)?;
+		let mut components = split_path(&path);
+
+		self.root.traverse_readlink(&mut components)
 	}
 
 	pub fn opendir(&self, path: &str) -> io::Result<Arc<dyn ObjectInterface>> {
 		debug!("Open directory {}", path);
-		Ok(Arc::new(DirectoryReader::new(self.readdir(path)?)))
+		self.open(
+			path,
+			OpenOption::O_RDONLY | OpenOption::O_DIRECTORY,
+			AccessPermission::empty(),
+		)
 	}
 
 	/// List given directory
 	pub fn readdir(&self, path: &str) -> io::Result<Vec<DirectoryEntry>> {
-		if path.trim() == "/" {
-			let mut components: Vec<&str> = Vec::new();
-			self.root.traverse_readdir(&mut components)
-		} else {
-			let mut components: Vec<&str> = path.split('/').collect();
-
-			components.reverse();
-			components.pop();
+		let path = self.resolve(path, true)?;
+		let mut components = split_path(&path);
 
-			self.root.traverse_readdir(&mut components)
-		}
+		self.root.traverse_readdir(&mut components)
 	}
 
 	/// stat
 	pub fn stat(&self, path: &str) -> io::Result<FileAttr> {
 		debug!("Getting stats {}", path);
+		let path = self.resolve(path, true)?;
 
-		let mut components: Vec<&str> = path.split('/').collect();
-		components.reverse();
-		components.pop();
+		let mut components = split_path(&path);
 
 		self.root.traverse_stat(&mut components)
 	}
@@ -229,10 +970,9 @@ impl Filesystem {
 	/// lstat
 	pub fn lstat(&self, path: &str) -> io::Result<FileAttr> {
 		debug!("Getting lstats {}", path);
+		let path = self.resolve(path, false)?;
 
-		let mut components: Vec<&str> = path.split('/').collect();
-		components.reverse();
-		components.pop();
+		let mut components = split_path(&path);
 
 		self.root.traverse_lstat(&mut components)
 	}
@@ -240,15 +980,268 @@ impl Filesystem {
 	/// Create new backing-fs at mountpoint mntpath
 	pub fn mount(
 		&self,
+		source: &str,
 		path: &str,
+		fstype: &'static str,
+		flags: MountFlags,
+		data: &str,
 		obj: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
 	) -> io::Result<()> {
-		debug!("Mounting {}", path);
+		debug!("Mounting {} of type {} at {}", source, fstype, path);
+
+		let _guard = block_on(async { Ok(self.mount_lock.lock().await) }, None)?;
+		let path = self.resolve(path, true)?;
+		let mut components = split_path(&path);
+
+		// an existing mount point has to be an empty directory, which is hidden by the mount
+		let covered = match self.root.traverse_lstat(&mut components.clone()) {
+			Ok(attr) => {
+				if (attr.st_mode & AccessPermission::S_IFMT).bits()
+					!= AccessPermission::S_IFDIR.bits()
+				{
+					return Err(io::Error::ENOTDIR);
+				}
+				if path == "/"
+					|| !self
+						.root
+						.traverse_readdir(&mut components.clone())?
+						.is_empty()
+				{
+					return Err(io::Error::EBUSY);
+				}
+
+				Some(self.root.traverse_detach(&mut components.clone())?)
+			}
+			Err(_) => None,
+		};
+
+		if let Err(err) = self.root.traverse_mount(&mut components, obj) {
+			if let Some(covered) = covered {
+				self.root
+					.traverse_mount(&mut split_path(&path), covered)
+					.unwrap();
+			}
+			return Err(err);
+		}
+
+		self.mounts.lock().push(Mount {
+			entry: MountEntry {
+				source: source.to_string(),
+				target: path,
+				fstype,
+				options: mount_options(flags, data),
+				read_only: flags.contains(MountFlags::MS_RDONLY),
+			},
+			open_files: Vec::new(),
+			covered,
+		});
+
+		Ok(())
+	}
+
+	/// Stacks an overlay onto the directory at `path`. The directory becomes the
+	/// read-only lower layer, while all changes are kept in memory. The
+	/// directory is restored, when the overlay is unmounted.
+	pub fn mount_overlay(
+		&self,
+		source: &str,
+		path: &str,
+		flags: MountFlags,
+		data: &str,
+	) -> io::Result<()> {
+		debug!("Mounting overlay {} at {}", source, path);
+
+		let _guard = block_on(async { Ok(self.mount_lock.lock().await) }, None)?;
+		let path = self.resolve(path, true)?;
+		if path == "/" {
+			return Err(io::Error::EBUSY);
+		}
+		let mut components = split_path(&path);
+
+		let attr = self.root.traverse_lstat(&mut components.clone())?;
+		if (attr.st_mode & AccessPermission::S_IFMT).bits() != AccessPermission::S_IFDIR.bits() {
+			return Err(io::Error::ENOTDIR);
+		}
+
+		let lower: Arc<dyn VfsNode + core::marker::Send + core::marker::Sync> =
+			Arc::from(self.root.traverse_detach(&mut components.clone())?);
+		let overlay = OverlayDirectory::new(Box::new(lower.clone()));
+		if let Err(err) = self.root.traverse_mount(&mut components, Box::new(overlay)) {
+			self.root
+				.traverse_mount(&mut split_path(&path), Box::new(lower))
+				.unwrap();
+			return Err(err);
+		}
+
+		self.mounts.lock().push(Mount {
+			entry: MountEntry {
+				source: source.to_string(),
+				target: path,
+				fstype: "overlay",
+				options: mount_options(flags, data),
+				read_only: flags.contains(MountFlags::MS_RDONLY),
+			},
+			open_files: Vec::new(),
+			covered: Some(Box::new(lower)),
+		});
+
+		Ok(())
+	}
+
+	/// Changes the flags and options of the file system, which is mounted at `path`
+	pub fn remount(&self, path: &str, flags: MountFlags, data: &str) -> io::Result<()> {
+		debug!("Remounting {}", path);
+		let _guard = block_on(async { Ok(self.mount_lock.lock().await) }, None)?;
+		let path = self.resolve(path, true)?;
+
+		let mut mounts = self.mounts.lock();
+		let mount = mounts
+			.iter_mut()
+			.rfind(|mount| mount.entry.target == path)
+			.ok_or(io::Error::EINVAL)?;
+		mount.entry.options = mount_options(flags, data);
+		mount.entry.read_only = flags.contains(MountFlags::MS_RDONLY);
+
+		Ok(())
+	}
+
+	/// Unmounts the file system at `path`. With `MNT_DETACH`, the file system is
+	/// removed from the tree immediately, while open files stay usable until
+	/// they are closed. Without it, `EBUSY` is returned as long as files are open.
+	pub fn umount(&self, path: &str, flags: UmountFlags) -> io::Result<()> {
+		debug!("Unmounting {}", path);
+		let _guard = block_on(async { Ok(self.mount_lock.lock().await) }, None)?;
+		let path = self.resolve(path, true)?;
+		let cwd = current_dir();
+
+		{
+			let mounts = self.mounts.lock();
+			// file systems can be stacked onto each other
+			let index = mounts
+				.iter()
+				.rposition(|mount| mount.entry.target == path)
+				.ok_or(io::Error::EINVAL)?;
+			if path == "/" {
+				return Err(io::Error::EBUSY);
+			}
+
+			if !flags.contains(UmountFlags::MNT_DETACH) {
+				let submounts = mounts.iter().any(|mount| {
+					mount.entry.target != path && is_below(&mount.entry.target, &path)
+				});
+				let busy = !flags.contains(UmountFlags::MNT_FORCE) && mounts[index].is_busy();
+				if submounts || busy || is_below(&cwd, &path) {
+					return Err(io::Error::EBUSY);
+				}
+			}
+		}
+
+		// the entry is only removed from the table, after the file system was
+		// detached from the tree
+		let root = self.root.traverse_detach(&mut split_path(&path))?;
+
+		let mount = {
+			let mut mounts = self.mounts.lock();
+			// mounts below the mount point are detached as well
+			mounts.retain(|mount| {
+				mount.entry.target == path || !is_below(&mount.entry.target, &path)
+			});
+			let index = mounts
+				.iter()
+				.rposition(|mount| mount.entry.target == path)
+				.unwrap();
+			mounts.remove(index)
+		};
+
+		if let Some(covered) = mount.covered {
+			self.root.traverse_mount(&mut split_path(&path), covered)?;
+		}
+
+		let mut detached = self.detached.lock();
+		detached.retain(|mount| mount.open_files.iter().any(|file| file.strong_count() > 0));
+		if mount.open_files.iter().any(|file| file.strong_count() > 0) {
+			detached.push(DetachedMount {
+				root,
+				open_files: mount.open_files,
+				read_only: mount.entry.read_only,
+			});
+		}
+
+		Ok(())
+	}
+
+	/// Returns the entries of the mount table
+	pub(crate) fn mounts(&self) -> Vec<MountEntry> {
+		self.mounts
+			.lock()
+			.iter()
+			.map(|mount| mount.entry.clone())
+			.collect()
+	}
+
+	/// Returns the statistics of the file system, which contains `path`
+	pub fn statfs(&self, path: &str) -> io::Result<StatFs> {
+		debug!("Getting file system statistics {}", path);
+		let path = self.resolve(path, true)?;
+		let mut components = split_path(&path);
+
+		let mut stat = self.root.traverse_statfs(&mut components)?;
+		let mounts = self.mounts.lock();
+		if mounts[Self::mount_index(&mounts, &path)].entry.read_only {
+			stat.f_flags |= ST_RDONLY;
+		}
+
+		Ok(stat)
+	}
+
+	/// Returns the statistics of the file system, which contains the file `obj`.
+	/// Objects, which don't belong to a file system, report an anonymous file system.
+	pub fn fstatfs(&self, obj: &Arc<dyn ObjectInterface>) -> io::Result<StatFs> {
+		let obj = Arc::downgrade(obj);
+		let is_open = |open_files: &[Weak<dyn ObjectInterface>]| {
+			open_files.iter().any(|file| file.ptr_eq(&obj))
+		};
+
+		let target = self
+			.mounts
+			.lock()
+			.iter()
+			.find(|mount| is_open(&mount.open_files))
+			.map(|mount| mount.entry.target.clone());
+		if let Some(target) = target {
+			return self.statfs(&target);
+		}
 
-		let mut components: Vec<&str> = path.split('/').collect();
+		if let Some(mount) = self
+			.detached
+			.lock()
+			.iter()
+			.find(|mount| is_open(&mount.open_files))
+		{
+			let mut stat = mount.root.traverse_statfs(&mut Vec::new())?;
+			if mount.read_only {
+				stat.f_flags |= ST_RDONLY;
+			}
+			return Ok(stat);
+		}
 
-		components.reverse();
-		components.pop();
+		Ok(StatFs {
+			f_type: ANON_INODE_FS_MAGIC,
+			f_bsize: 4096,
+			f_namelen: 255,
+			f_frsize: 4096,
+			..Default::default()
+		})
+	}
+
+	/// Insert the node `obj` at `path`, which must not exist
+	pub(crate) fn insert(
+		&self,
+		path: &str,
+		obj: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	) -> io::Result<()> {
+		let path = self.resolve(path, false)?;
+		let mut components = split_path(&path);
 
 		self.root.traverse_mount(&mut components, obj)
 	}
@@ -261,16 +1254,28 @@ impl Filesystem {
 		mode: AccessPermission,
 	) -> io::Result<()> {
 		debug!("Create read-only file {}", path);
+		let path = self.resolve(path, false)?;
+		self.check_writable(&path)?;
 
-		let mut components: Vec<&str> = path.split('/').collect();
-
-		components.reverse();
-		components.pop();
+		let mut components = split_path(&path);
 
 		self.root.traverse_create_file(&mut components, data, mode)
 	}
 }
 
+/// Device number of the RAM filesystems. Their inode numbers are taken from
+/// a single counter, so that all of them share the device number.
+pub(crate) const MEM_DEV: u64 = 1;
+/// Device number of the proc filesystem
+pub(crate) const PROC_DEV: u64 = 2;
+/// Device number of the device filesystem
+pub(crate) const DEVFS_DEV: u64 = 3;
+/// Device number of pipes and sockets, which aren't part of a filesystem
+pub(crate) const ANON_DEV: u64 = 4;
+/// Device number of the first virtio-fs device. The following devices are
+/// numbered consecutively, because each server has its own inode numbers.
+pub(crate) const FUSE_DEV: u64 = 0x100;
+
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
 pub struct FileAttr {
@@ -299,7 +1304,112 @@ pub struct FileAttr {
 	pub st_ctim: timespec,
 }
 
-#[derive(Debug, FromPrimitive, ToPrimitive)]
+/// Changes of file attributes. Attributes, which are `None`, are left unchanged.
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct SetAttr {
+	/// access permissions without the file type
+	pub mode: Option<AccessPermission>,
+	/// user id
+	pub uid: Option<u32>,
+	/// group id
+	pub gid: Option<u32>,
+	/// time of last access
+	pub atime: Option<timespec>,
+	/// time of last modification
+	pub mtime: Option<timespec>,
+}
+
+impl SetAttr {
+	/// Applies the changes to `attr` and updates the time of the last status change
+	pub fn apply(&self, attr: &mut FileAttr) {
+		if let Some(mode) = self.mode {
+			attr.st_mode = (attr.st_mode & AccessPermission::S_IFMT)
+				| mode.difference(AccessPermission::S_IFMT);
+		}
+		if let Some(uid) = self.uid {
+			attr.st_uid = uid;
+		}
+		if let Some(gid) = self.gid {
+			attr.st_gid = gid;
+		}
+		if let Some(atime) = self.atime {
+			attr.st_atim = atime;
+		}
+		if let Some(mtime) = self.mtime {
+			attr.st_mtim = mtime;
+		}
+
+		let microseconds = arch::kernel::systemtime::now_micros();
+		attr.st_ctim = timespec::from_usec(microseconds as i64);
+	}
+}
+
+/// Mount flag, which is reported by `statfs` for read-only file systems
+const ST_RDONLY: i64 = 1;
+
+/// Statistics of a file system, as returned by `statfs`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct StatFs {
+	/// type of the file system
+	pub f_type: i64,
+	/// optimal transfer block size
+	pub f_bsize: i64,
+	/// total data blocks in the file system
+	pub f_blocks: u64,
+	/// free blocks in the file system
+	pub f_bfree: u64,
+	/// free blocks available to unprivileged users
+	pub f_bavail: u64,
+	/// total file nodes in the file system
+	pub f_files: u64,
+	/// free file nodes in the file system
+	pub f_ffree: u64,
+	/// file system id
+	pub f_fsid: [i32; 2],
+	/// maximum length of file names
+	pub f_namelen: i64,
+	/// fragment size
+	pub f_frsize: i64,
+	/// mount flags of the file system
+	pub f_flags: i64,
+	pub f_spare: [i64; 4],
+}
+
+bitflags! {
+	/// Flags for mounting file systems
+	#[derive(Debug, Copy, Clone, Default)]
+	pub struct MountFlags: u64 {
+		const MS_RDONLY = 1;
+		const MS_NOSUID = 2;
+		const MS_NODEV = 4;
+		const MS_NOEXEC = 8;
+		const MS_SYNCHRONOUS = 16;
+		const MS_REMOUNT = 32;
+	}
+}
+
+bitflags! {
+	/// Flags for setting extended attributes
+	#[derive(Debug, Copy, Clone, Default)]
+	pub struct XattrFlags: i32 {
+		/// Fails with `EEXIST`, if the attribute already exists
+		const XATTR_CREATE = 1;
+		/// Fails with `ENODATA`, if the attribute does not exist
+		const XATTR_REPLACE = 2;
+	}
+}
+
+bitflags! {
+	/// Flags for unmounting file systems
+	#[derive(Debug, Copy, Clone, Default)]
+	pub struct UmountFlags: i32 {
+		const MNT_FORCE = 1;
+		const MNT_DETACH = 2;
+	}
+}
+
+#[derive(Debug, Copy, Clone, FromPrimitive, ToPrimitive, PartialEq, Eq)]
 pub enum FileType {
 	Unknown = 0,         // DT_UNKNOWN
 	Fifo = 1,            // DT_FIFO
@@ -312,6 +1422,14 @@ pub enum FileType {
 	Whiteout = 14,       // DT_WHT
 }
 
+impl From<AccessPermission> for FileType {
+	fn from(mode: AccessPermission) -> Self {
+		// the DT_* values are the file type bits of the mode
+		let file_type = mode.intersection(AccessPermission::S_IFMT).bits() >> 12;
+		num::FromPrimitive::from_u32(file_type).unwrap_or(FileType::Unknown)
+	}
+}
+
 #[derive(Debug, Copy, Clone, FromPrimitive, ToPrimitive, PartialEq)]
 pub enum SeekWhence {
 	Set = 0,
@@ -322,10 +1440,8 @@ pub enum SeekWhence {
 }
 
 pub(crate) fn init() {
-	const VERSION: &str = env!("CARGO_PKG_VERSION");
-	const UTC_BUILT_TIME: &str = build_time::build_time_utc!();
-
 	FILESYSTEM.set(Filesystem::new()).unwrap();
+	*WORKING_DIRECTORY.lock() = "/".to_string();
 	FILESYSTEM
 		.get()
 		.unwrap()
@@ -334,16 +1450,29 @@ pub(crate) fn init() {
 	FILESYSTEM
 		.get()
 		.unwrap()
-		.mkdir("/proc", AccessPermission::from_bits(0o777).unwrap())
-		.expect("Unable to create /proc");
-
-	if let Ok(mut file) = File::create("/proc/version") {
-		if write!(file, "HermitOS version {VERSION} # UTC {UTC_BUILT_TIME}").is_err() {
-			error!("Unable to write in /proc/version");
-		}
-	} else {
-		error!("Unable to create /proc/version");
-	}
+		.mount(
+			"proc",
+			"/proc",
+			"proc",
+			MountFlags::empty(),
+			"",
+			Box::new(ProcDirectory::new()),
+		)
+		.expect("Unable to mount /proc");
+	FILESYSTEM
+		.get()
+		.unwrap()
+		.mount(
+			"devfs",
+			"/dev",
+			"devfs",
+			MountFlags::empty(),
+			"",
+			Box::new(DevDirectory::new()),
+		)
+		.expect("Unable to mount /dev");
+
+	initramfs::init(FILESYSTEM.get().unwrap());
 
 	#[cfg(all(feature = "fuse", feature = "pci"))]
 	fuse::init();
@@ -366,6 +1495,41 @@ pub fn unlink(path: &str) -> io::Result<()> {
 	FILESYSTEM.get().ok_or(io::Error::EINVAL)?.unlink(path)
 }
 
+/// Creates a symbolic link at `path`, which points to `target`.
+pub fn symlink(target: &str, path: &str) -> io::Result<()> {
+	FILESYSTEM
+		.get()
+		.ok_or(io::Error::EINVAL)?
+		.symlink(target, path)
+}
+
+/// Creates a special file at `path`, whose type is part of `mode`
+#[cfg(feature = "unix")]
+pub(crate) fn mknod(path: &str, mode: AccessPermission) -> io::Result<()> {
+	FILESYSTEM.get().ok_or(io::Error::EINVAL)?.mknod(path, mode)
+}
+
+/// Creates a new hard link `new_path` to the file `old_path`.
+pub fn link(old_path: &str, new_path: &str) -> io::Result<()> {
+	FILESYSTEM
+		.get()
+		.ok_or(io::Error::EINVAL)?
+		.link(old_path, new_path)
+}
+
+/// Returns the target of a symbolic link.
+pub fn readlink(path: &str) -> io::Result<String> {
+	FILESYSTEM.get().ok_or(io::Error::EINVAL)?.readlink(path)
+}
+
+/// Renames a file or directory. If the target already exists, it is replaced atomically.
+pub fn rename(old_path: &str, new_path: &str) -> io::Result<()> {
+	FILESYSTEM
+		.get()
+		.ok_or(io::Error::EINVAL)?
+		.rename(old_path, new_path)
+}
+
 /// Creates a new, empty directory at the provided path
 pub fn create_dir(path: &str, mode: AccessPermission) -> io::Result<()> {
 	FILESYSTEM.get().ok_or(io::Error::EINVAL)?.mkdir(path, mode)
@@ -394,18 +1558,169 @@ pub fn open(name: &str, flags: OpenOption, mode: AccessPermission) -> io::Result
 	debug!("Open {}, {:?}, {:?}", name, flags, mode);
 
 	let fs = FILESYSTEM.get().ok_or(io::Error::EINVAL)?;
-	if let Ok(file) = fs.open(name, flags, mode) {
-		let fd = insert_object(file)?;
-		Ok(fd)
+	let file = fs.open(name, flags, mode)?;
+	insert_object(file, flags)
+}
+
+/// Changes the attributes of the file at `path`. If `follow` is set, a
+/// symbolic link is resolved, otherwise the link itself is changed.
+pub(crate) fn setattr(path: &str, attr: SetAttr, follow: bool) -> io::Result<()> {
+	FILESYSTEM
+		.get()
+		.ok_or(io::Error::EINVAL)?
+		.setattr(path, attr, follow)
+}
+
+/// Returns the value of the extended attribute `name` of the file at `path`.
+/// If `follow` is set, a symbolic link is resolved.
+pub(crate) fn getxattr(path: &str, name: &str, follow: bool) -> io::Result<Vec<u8>> {
+	FILESYSTEM
+		.get()
+		.ok_or(io::Error::EINVAL)?
+		.getxattr(path, name, follow)
+}
+
+/// Creates or replaces the extended attribute `name` of the file at `path`.
+/// If `follow` is set, a symbolic link is resolved.
+pub(crate) fn setxattr(
+	path: &str,
+	name: &str,
+	value: &[u8],
+	flags: XattrFlags,
+	follow: bool,
+) -> io::Result<()> {
+	FILESYSTEM
+		.get()
+		.ok_or(io::Error::EINVAL)?
+		.setxattr(path, name, value, flags, follow)
+}
+
+/// Returns the names of the extended attributes of the file at `path`.
+/// If `follow` is set, a symbolic link is resolved.
+pub(crate) fn listxattr(path: &str, follow: bool) -> io::Result<Vec<String>> {
+	FILESYSTEM
+		.get()
+		.ok_or(io::Error::EINVAL)?
+		.listxattr(path, follow)
+}
+
+/// Removes the extended attribute `name` of the file at `path`.
+/// If `follow` is set, a symbolic link is resolved.
+pub(crate) fn removexattr(path: &str, name: &str, follow: bool) -> io::Result<()> {
+	FILESYSTEM
+		.get()
+		.ok_or(io::Error::EINVAL)?
+		.removexattr(path, name, follow)
+}
+
+/// Truncates or extends the file at `path` to `size` bytes
+pub fn truncate(path: &str, size: usize) -> io::Result<()> {
+	let obj = FILESYSTEM.get().ok_or(io::Error::EINVAL)?.open(
+		path,
+		OpenOption::O_WRONLY,
+		AccessPermission::empty(),
+	)?;
+
+	block_on(obj.truncate(size), None)?;
+	notify::file_changed(&obj, InotifyMask::IN_MODIFY);
+	Ok(())
+}
+
+/// Mounts the file system `source` of type `fstype` at `target`. `data`
+/// contains file system specific options. With `MS_REMOUNT`, the flags and
+/// options of an existing mount are changed. An `overlay` uses the existing
+/// directory `target` as read-only lower layer.
+pub fn mount(
+	source: &str,
+	target: &str,
+	fstype: &str,
+	flags: MountFlags,
+	data: &str,
+) -> io::Result<()> {
+	let fs = FILESYSTEM.get().ok_or(io::Error::EINVAL)?;
+	if flags.contains(MountFlags::MS_REMOUNT) {
+		return fs.remount(target, flags, data);
+	}
+	if fstype == "overlay" {
+		return fs.mount_overlay(source, target, flags, data);
+	}
+
+	let (fstype, obj): (
+		&'static str,
+		Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	) = match fstype {
+		"tmpfs" | "ramfs" => (
+			"tmpfs",
+			Box::new(MemDirectory::new(
+				AccessPermission::from_bits(0o777).unwrap(),
+			)),
+		),
+		"proc" => ("proc", Box::new(ProcDirectory::new())),
+		"devfs" | "devtmpfs" => ("devfs", Box::new(DevDirectory::new())),
+		#[cfg(all(feature = "fuse", feature = "pci"))]
+		"virtiofs" => ("virtiofs", fuse::new_mount(source)?),
+		_ => return Err(io::Error::ENODEV),
+	};
+
+	fs.mount(source, target, fstype, flags, data, obj)
+}
+
+/// Unmounts the file system, which is mounted at `target`
+pub fn umount(target: &str, flags: UmountFlags) -> io::Result<()> {
+	FILESYSTEM
+		.get()
+		.ok_or(io::Error::EINVAL)?
+		.umount(target, flags)
+}
+
+/// Returns the statistics of the file system, which contains `path`
+pub fn statfs(path: &str) -> io::Result<StatFs> {
+	FILESYSTEM.get().ok_or(io::Error::EINVAL)?.statfs(path)
+}
+
+/// Returns the statistics of the file system, which contains the file `fd`
+pub(crate) fn fstatfs(fd: FileDescriptor) -> io::Result<StatFs> {
+	let obj = fd::get_object(fd)?;
+	FILESYSTEM.get().ok_or(io::Error::EINVAL)?.fstatfs(&obj)
+}
+
+/// Returns the current working directory
+pub fn current_dir() -> String {
+	let cwd = WORKING_DIRECTORY.lock();
+	if cwd.is_empty() {
+		"/".to_string()
 	} else {
-		Err(io::Error::EINVAL)
+		cwd.clone()
 	}
 }
 
+/// Changes the current working directory to `path`
+pub fn set_current_dir(path: &str) -> io::Result<()> {
+	let fs = FILESYSTEM.get().ok_or(io::Error::EINVAL)?;
+	let path = fs.resolve(path, true)?;
+	let attr = fs.stat(&path)?;
+	if (attr.st_mode & AccessPermission::S_IFMT).bits() != AccessPermission::S_IFDIR.bits() {
+		return Err(io::Error::ENOTDIR);
+	}
+
+	*WORKING_DIRECTORY.lock() = path;
+	Ok(())
+}
+
+/// Changes the current working directory to the directory, which is
+/// referenced by the file descriptor `fd`
+pub(crate) fn set_current_dir_fd(fd: FileDescriptor) -> io::Result<()> {
+	let obj = fd::get_object(fd)?;
+	let path = block_on(obj.getpath(), None)?;
+
+	*WORKING_DIRECTORY.lock() = path;
+	Ok(())
+}
+
 /// Open a directory to read the directory entries
 pub(crate) fn opendir(name: &str) -> io::Result<FileDescriptor> {
 	let obj = FILESYSTEM.get().ok_or(io::Error::EINVAL)?.opendir(name)?;
-	insert_object(obj)
+	insert_object(obj, OpenOption::O_RDONLY | OpenOption::O_DIRECTORY)
 }
 
 use crate::fd::{self, FileDescriptor};
@@ -492,6 +1807,16 @@ impl File {
 	pub fn metadata(&self) -> io::Result<Metadata> {
 		metadata(&self.path)
 	}
+
+	/// Transfers all modified data and metadata to the storage device.
+	pub fn sync_all(&self) -> io::Result<()> {
+		fd::fsync(self.fd, false)
+	}
+
+	/// Transfers all modified data to the storage device.
+	pub fn sync_data(&self) -> io::Result<()> {
+		fd::fsync(self.fd, true)
+	}
 }
 
 impl crate::io::Read for File {
@@ -509,6 +1834,86 @@ impl crate::io::Write for File {
 
 impl Drop for File {
 	fn drop(&mut self) {
-		let _ = remove_object(self.fd);
+		let _ = fd::close(self.fd);
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+
+	fn mount(target: &str) -> Mount {
+		Mount {
+			entry: MountEntry {
+				source: "none".to_string(),
+				target: target.to_string(),
+				fstype: "tmpfs",
+				options: mount_options(MountFlags::empty(), ""),
+				read_only: false,
+			},
+			open_files: Vec::new(),
+			covered: None,
+		}
+	}
+
+	#[test]
+	fn mount_points() {
+		let mounts = [mount("/"), mount("/tmp")];
+
+		assert!(matches!(
+			Filesystem::check_mount_point(&mounts, "/tmp"),
+			Err(io::Error::EBUSY)
+		));
+		assert!(matches!(
+			Filesystem::check_mount_point(&mounts, "/"),
+			Err(io::Error::EBUSY)
+		));
+		assert!(Filesystem::check_mount_point(&mounts, "/tmp/a").is_ok());
+		assert!(Filesystem::check_mount_point(&mounts, "/proc").is_ok());
+	}
+
+	#[test]
+	fn rename_across_mounts() {
+		let mounts = [mount("/"), mount("/tmp")];
+
+		assert!(Filesystem::check_same_mount(&mounts, "/tmp/a", "/tmp/b").is_ok());
+		assert!(Filesystem::check_same_mount(&mounts, "/a", "/tmpfile").is_ok());
+		assert!(matches!(
+			Filesystem::check_same_mount(&mounts, "/tmp/a", "/b"),
+			Err(io::Error::EXDEV)
+		));
+		assert!(matches!(
+			Filesystem::check_same_mount(&mounts, "/a", "/tmp/a"),
+			Err(io::Error::EXDEV)
+		));
+	}
+
+	#[test]
+	fn xattr_names() {
+		assert!(check_xattr_name("user.comment").is_ok());
+		assert!(check_xattr_name("security.selinux").is_ok());
+		assert!(matches!(
+			check_xattr_name("comment"),
+			Err(io::Error::EOPNOTSUPP)
+		));
+		assert!(matches!(
+			check_xattr_name("user."),
+			Err(io::Error::EOPNOTSUPP)
+		));
+		assert!(matches!(check_xattr_name(""), Err(io::Error::ERANGE)));
+
+		let name = format!("user.{}", "a".repeat(XATTR_NAME_MAX));
+		assert!(matches!(check_xattr_name(&name), Err(io::Error::ERANGE)));
+		assert!(check_xattr_name(&name[..XATTR_NAME_MAX]).is_ok());
+	}
+
+	#[test]
+	fn xattr_values() {
+		let value = vec![0; XATTR_SIZE_MAX + 1];
+		assert!(check_xattr("user.data", &value[..XATTR_SIZE_MAX]).is_ok());
+		assert!(matches!(
+			check_xattr("user.data", &value),
+			Err(io::Error::E2BIG)
+		));
 	}
 }
@@ -0,0 +1,752 @@
+//! Notifications about changes of the file system, as provided by `inotify`.
+//!
+//! Changes, which are made by the kernel, are reported by the operations of
+//! [`Filesystem`] and by the file descriptors of open files. virtio-fs
+//! doesn't negotiate the notification queue, so changes of the host aren't
+//! announced by the server. Watches on FUSE mounts compare the attributes of
+//! the watched nodes periodically, while an application waits for events.
+//! Accesses of files aren't reported.
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::future;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{self, AtomicBool, AtomicU32};
+use core::task::{Context, Poll, Waker};
+
+use async_trait::async_trait;
+use hermit_sync::TicketMutex;
+
+use crate::arch::kernel::core_local::core_scheduler;
+use crate::fd::{IoCtl, ObjectInterface, OpenOption, PollEvent};
+use crate::fs::{FILESYSTEM, FileAttr, FileType, Filesystem, is_below};
+use crate::time::timespec;
+use crate::{arch, io};
+
+bitflags! {
+	/// Events of an inotify watch
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	pub struct InotifyMask: u32 {
+		const IN_ACCESS = 0x0000_0001;
+		const IN_MODIFY = 0x0000_0002;
+		const IN_ATTRIB = 0x0000_0004;
+		const IN_CLOSE_WRITE = 0x0000_0008;
+		const IN_CLOSE_NOWRITE = 0x0000_0010;
+		const IN_OPEN = 0x0000_0020;
+		const IN_MOVED_FROM = 0x0000_0040;
+		const IN_MOVED_TO = 0x0000_0080;
+		const IN_CREATE = 0x0000_0100;
+		const IN_DELETE = 0x0000_0200;
+		const IN_DELETE_SELF = 0x0000_0400;
+		const IN_MOVE_SELF = 0x0000_0800;
+		const IN_UNMOUNT = 0x0000_2000;
+		const IN_Q_OVERFLOW = 0x0000_4000;
+		const IN_IGNORED = 0x0000_8000;
+		const IN_ONLYDIR = 0x0100_0000;
+		const IN_DONT_FOLLOW = 0x0200_0000;
+		const IN_EXCL_UNLINK = 0x0400_0000;
+		const IN_MASK_CREATE = 0x1000_0000;
+		const IN_MASK_ADD = 0x2000_0000;
+		const IN_ISDIR = 0x4000_0000;
+		const IN_ONESHOT = 0x8000_0000;
+	}
+}
+
+bitflags! {
+	/// Flags of `inotify_init1`
+	#[derive(Debug, Copy, Clone)]
+	pub struct InotifyFlags: i32 {
+		const IN_NONBLOCK = 0o4000;
+		const IN_CLOEXEC = 0o2_000_000;
+	}
+}
+
+impl InotifyMask {
+	/// Events, which can be requested by a watch
+	const ALL_EVENTS: Self = Self::IN_ACCESS
+		.union(Self::IN_MODIFY)
+		.union(Self::IN_ATTRIB)
+		.union(Self::IN_CLOSE_WRITE)
+		.union(Self::IN_CLOSE_NOWRITE)
+		.union(Self::IN_OPEN)
+		.union(Self::IN_MOVED_FROM)
+		.union(Self::IN_MOVED_TO)
+		.union(Self::IN_CREATE)
+		.union(Self::IN_DELETE)
+		.union(Self::IN_DELETE_SELF)
+		.union(Self::IN_MOVE_SELF);
+
+	/// Events, which are reported to the watch of the parent directory
+	const CHILD_EVENTS: Self = Self::IN_MODIFY
+		.union(Self::IN_ATTRIB)
+		.union(Self::IN_CLOSE_WRITE)
+		.union(Self::IN_CLOSE_NOWRITE)
+		.union(Self::IN_OPEN)
+		.union(Self::IN_MOVED_FROM)
+		.union(Self::IN_MOVED_TO)
+		.union(Self::IN_CREATE)
+		.union(Self::IN_DELETE);
+
+	/// Events, which are reported to the watch of the node itself
+	const SELF_EVENTS: Self = Self::IN_MODIFY
+		.union(Self::IN_ATTRIB)
+		.union(Self::IN_CLOSE_WRITE)
+		.union(Self::IN_CLOSE_NOWRITE)
+		.union(Self::IN_OPEN)
+		.union(Self::IN_DELETE_SELF)
+		.union(Self::IN_MOVE_SELF);
+}
+
+/// Size of `struct inotify_event` without the name
+const EVENT_HEADER_LEN: usize = 16;
+/// Maximum number of queued events, as `fs.inotify.max_queued_events` on Linux
+const MAX_QUEUED_EVENTS: usize = 0x4000;
+/// Interval in microseconds, in which watches on FUSE mounts are compared
+const SCAN_INTERVAL: u64 = 500_000;
+
+/// All inotify instances, which receive events
+static INSTANCES: TicketMutex<Vec<Weak<Inotify>>> = TicketMutex::new(Vec::new());
+/// Open files, which are reported by `IN_MODIFY`, `IN_ATTRIB` and `IN_CLOSE_*`
+static OPEN_FILES: TicketMutex<Vec<OpenFile>> = TicketMutex::new(Vec::new());
+/// Cookie, which connects `IN_MOVED_FROM` and `IN_MOVED_TO`
+static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+#[derive(Debug)]
+struct OpenFile {
+	file: Weak<dyn ObjectInterface>,
+	/// Normalized absolute path of the file
+	path: String,
+	writable: bool,
+}
+
+/// Returns `true`, if an inotify instance exists
+pub(crate) fn is_active() -> bool {
+	!INSTANCES.lock().is_empty()
+}
+
+fn instances() -> Vec<Arc<Inotify>> {
+	// the instances are dropped after the release of the lock, because
+	// `Inotify::drop` acquires the lock as well
+	INSTANCES.lock().iter().filter_map(Weak::upgrade).collect()
+}
+
+/// Splits the normalized absolute `path` into its parent directory and its name
+fn split_name(path: &str) -> Option<(&str, &str)> {
+	let (parent, name) = path.rsplit_once('/')?;
+	if name.is_empty() {
+		None
+	} else if parent.is_empty() {
+		Some(("/", name))
+	} else {
+		Some((parent, name))
+	}
+}
+
+/// Reports the events `mask` of the node at the normalized absolute `path`.
+/// Events of `CHILD_EVENTS` are reported to the watches of the parent
+/// directory and events of `SELF_EVENTS` to the watches of the node.
+pub(crate) fn notify(path: &str, mask: InotifyMask, cookie: u32) {
+	for inotify in instances() {
+		inotify.state.lock().notify(path, mask, cookie);
+	}
+}
+
+/// Reports the rename of `old_path` to `new_path`. Watches and open files
+/// below `old_path` move with the node.
+pub(crate) fn rename(old_path: &str, new_path: &str, is_dir: bool, replaced: bool) {
+	let isdir = if is_dir {
+		InotifyMask::IN_ISDIR
+	} else {
+		InotifyMask::empty()
+	};
+	let cookie = NEXT_COOKIE.fetch_add(1, atomic::Ordering::Relaxed);
+
+	if replaced {
+		notify(new_path, InotifyMask::IN_DELETE_SELF, 0);
+	}
+	notify(old_path, InotifyMask::IN_MOVED_FROM | isdir, cookie);
+	notify(new_path, InotifyMask::IN_MOVED_TO | isdir, cookie);
+	notify(old_path, InotifyMask::IN_MOVE_SELF, 0);
+
+	let moved = |path: &mut String| {
+		if is_below(path, old_path) {
+			*path = new_path.to_owned() + &path[old_path.len()..];
+		}
+	};
+	for inotify in instances() {
+		for watch in &mut inotify.state.lock().watches {
+			moved(&mut watch.path);
+		}
+	}
+	for file in OPEN_FILES.lock().iter_mut() {
+		moved(&mut file.path);
+	}
+}
+
+/// Reports that `obj` was opened at the normalized absolute `path` and
+/// remembers its path for the events of the file descriptors
+pub(crate) fn open(path: &str, obj: &Arc<dyn ObjectInterface>, opt: OpenOption, created: bool) {
+	let writable = opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR);
+	let isdir = if opt.contains(OpenOption::O_DIRECTORY) {
+		InotifyMask::IN_ISDIR
+	} else {
+		InotifyMask::empty()
+	};
+
+	if created {
+		notify(path, InotifyMask::IN_CREATE | isdir, 0);
+	} else if writable && opt.contains(OpenOption::O_TRUNC) {
+		notify(path, InotifyMask::IN_MODIFY, 0);
+	}
+	notify(path, InotifyMask::IN_OPEN | isdir, 0);
+
+	let mut open_files = OPEN_FILES.lock();
+	open_files.retain(|file| file.file.strong_count() > 0);
+	open_files.push(OpenFile {
+		file: Arc::downgrade(obj),
+		path: path.to_owned(),
+		writable,
+	});
+}
+
+fn open_file_path(obj: &Arc<dyn ObjectInterface>, remove: bool) -> Option<(String, bool)> {
+	if !is_active() {
+		return None;
+	}
+
+	let mut open_files = OPEN_FILES.lock();
+	let index = open_files
+		.iter()
+		.position(|file| file.file.as_ptr().cast::<()>() == Arc::as_ptr(obj).cast::<()>())?;
+	if remove {
+		let file = open_files.swap_remove(index);
+		Some((file.path, file.writable))
+	} else {
+		let file = &open_files[index];
+		Some((file.path.clone(), file.writable))
+	}
+}
+
+/// Reports `mask` for the open file `obj`
+pub(crate) fn file_changed(obj: &Arc<dyn ObjectInterface>, mask: InotifyMask) {
+	if let Some((path, _)) = open_file_path(obj, false) {
+		notify(&path, mask, 0);
+	}
+}
+
+/// Reports the release of the open file `obj`
+pub(crate) fn file_closed(obj: &Arc<dyn ObjectInterface>) {
+	if let Some((path, writable)) = open_file_path(obj, true) {
+		let mask = if writable {
+			InotifyMask::IN_CLOSE_WRITE
+		} else {
+			InotifyMask::IN_CLOSE_NOWRITE
+		};
+		notify(&path, mask, 0);
+	}
+}
+
+fn same_time(a: &timespec, b: &timespec) -> bool {
+	a.tv_sec == b.tv_sec && a.tv_nsec == b.tv_nsec
+}
+
+/// Returns the event, which describes the change from `old` to `new`
+fn compare(old: &FileAttr, new: &FileAttr) -> Option<InotifyMask> {
+	if old.st_size != new.st_size || !same_time(&old.st_mtim, &new.st_mtim) {
+		Some(InotifyMask::IN_MODIFY)
+	} else if old.st_mode.bits() != new.st_mode.bits()
+		|| old.st_uid != new.st_uid
+		|| old.st_gid != new.st_gid
+		|| old.st_nlink != new.st_nlink
+		|| !same_time(&old.st_ctim, &new.st_ctim)
+	{
+		Some(InotifyMask::IN_ATTRIB)
+	} else {
+		None
+	}
+}
+
+fn is_dir(attr: &FileAttr) -> bool {
+	FileType::from(attr.st_mode) == FileType::Directory
+}
+
+/// Attributes of a watched node on a FUSE mount
+#[derive(Debug)]
+struct Snapshot {
+	attr: FileAttr,
+	/// Attributes of the directory entries
+	entries: BTreeMap<String, FileAttr>,
+	/// Entries, which were changed by the kernel since the last scan
+	changed: BTreeSet<String>,
+	/// The node was changed by the kernel since the last scan
+	self_changed: bool,
+}
+
+impl Snapshot {
+	fn new(fs: &Filesystem, path: &str, attr: FileAttr) -> Self {
+		Self {
+			attr,
+			entries: Self::entries(fs, path, &attr),
+			changed: BTreeSet::new(),
+			self_changed: false,
+		}
+	}
+
+	fn entries(fs: &Filesystem, path: &str, attr: &FileAttr) -> BTreeMap<String, FileAttr> {
+		if !is_dir(attr) {
+			return BTreeMap::new();
+		}
+
+		fs.readdir(path)
+			.unwrap_or_default()
+			.into_iter()
+			.filter(|entry| entry.name != "." && entry.name != "..")
+			.filter_map(|entry| {
+				let attr = fs.lstat(&(path.to_owned() + "/" + &entry.name)).ok()?;
+				Some((entry.name, attr))
+			})
+			.collect()
+	}
+}
+
+#[derive(Debug)]
+struct Watch {
+	wd: i32,
+	/// Normalized absolute path of the watched node
+	path: String,
+	mask: InotifyMask,
+	/// Is only used for watches on FUSE mounts
+	snapshot: Option<Snapshot>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Event {
+	wd: i32,
+	mask: InotifyMask,
+	cookie: u32,
+	name: String,
+}
+
+impl Event {
+	fn len(&self) -> usize {
+		EVENT_HEADER_LEN + self.name_len()
+	}
+
+	/// Length of the null-terminated name, which is padded to the size of the header
+	fn name_len(&self) -> usize {
+		if self.name.is_empty() {
+			0
+		} else {
+			(self.name.len() + 1).next_multiple_of(EVENT_HEADER_LEN)
+		}
+	}
+
+	/// Writes the event as `struct inotify_event` to `buf`
+	fn encode(&self, buf: &mut [MaybeUninit<u8>]) {
+		let name_len = self.name_len();
+		buf[..4].write_copy_of_slice(&self.wd.to_ne_bytes());
+		buf[4..8].write_copy_of_slice(&self.mask.bits().to_ne_bytes());
+		buf[8..12].write_copy_of_slice(&self.cookie.to_ne_bytes());
+		buf[12..16].write_copy_of_slice(&u32::try_from(name_len).unwrap().to_ne_bytes());
+
+		let name = &mut buf[EVENT_HEADER_LEN..EVENT_HEADER_LEN + name_len];
+		name.fill(MaybeUninit::new(0));
+		name[..self.name.len()].write_copy_of_slice(self.name.as_bytes());
+	}
+}
+
+#[derive(Debug)]
+struct InotifyState {
+	watches: Vec<Watch>,
+	events: VecDeque<Event>,
+	next_wd: i32,
+	/// Time of the next comparison of the watches on FUSE mounts
+	next_scan: u64,
+	read_queue: VecDeque<Waker>,
+}
+
+impl InotifyState {
+	fn push(&mut self, wd: i32, mask: InotifyMask, cookie: u32, name: &str) {
+		let event = Event {
+			wd,
+			mask,
+			cookie,
+			name: name.to_owned(),
+		};
+		// identical events are merged, if they haven't been read yet
+		if self.events.back() == Some(&event) {
+			return;
+		}
+
+		// the last slot of the queue is reserved for the overflow event
+		match (self.events.len() + 1).cmp(&MAX_QUEUED_EVENTS) {
+			Ordering::Less => self.events.push_back(event),
+			Ordering::Equal => self.events.push_back(Event {
+				wd: -1,
+				mask: InotifyMask::IN_Q_OVERFLOW,
+				cookie: 0,
+				name: String::new(),
+			}),
+			Ordering::Greater => return,
+		}
+
+		for waker in self.read_queue.drain(..) {
+			waker.wake();
+		}
+	}
+
+	/// Reports `mask` to the watch at `index`. Returns `false`, if the watch
+	/// was removed.
+	fn deliver(&mut self, index: usize, mask: InotifyMask, cookie: u32, name: &str) -> bool {
+		let watch = &self.watches[index];
+		let wd = watch.wd;
+		let events = mask & (watch.mask | InotifyMask::IN_ISDIR);
+		let oneshot = watch.mask.contains(InotifyMask::IN_ONESHOT);
+
+		if events.intersects(InotifyMask::ALL_EVENTS) {
+			self.push(wd, events, cookie, name);
+		}
+
+		if mask.contains(InotifyMask::IN_DELETE_SELF)
+			|| (oneshot && events.intersects(InotifyMask::ALL_EVENTS))
+		{
+			self.watches.remove(index);
+			self.push(wd, InotifyMask::IN_IGNORED, 0, "");
+			false
+		} else {
+			true
+		}
+	}
+
+	fn notify(&mut self, path: &str, mask: InotifyMask, cookie: u32) {
+		let isdir = mask & InotifyMask::IN_ISDIR;
+		let child_mask = mask & InotifyMask::CHILD_EVENTS;
+		let self_mask = mask & InotifyMask::SELF_EVENTS;
+		let parent = split_name(path);
+
+		let mut i = 0;
+		while i < self.watches.len() {
+			let watch = &mut self.watches[i];
+
+			if let Some((_, name)) = parent.filter(|(parent, _)| *parent == watch.path) {
+				if child_mask.is_empty() {
+					i += 1;
+					continue;
+				}
+				if let Some(snapshot) = &mut watch.snapshot {
+					snapshot.changed.insert(name.to_owned());
+				}
+				if !self.deliver(i, child_mask | isdir, cookie, name) {
+					continue;
+				}
+			} else if watch.path == path && !self_mask.is_empty() {
+				if let Some(snapshot) = &mut watch.snapshot {
+					snapshot.self_changed = true;
+				}
+				if !self.deliver(i, self_mask, 0, "") {
+					continue;
+				}
+			}
+
+			i += 1;
+		}
+	}
+
+	/// Returns the watches on FUSE mounts, if they have to be compared now
+	fn due_scan(&mut self) -> Vec<(i32, String)> {
+		let now = arch::processor::get_timer_ticks();
+		if now < self.next_scan || !self.is_polling() {
+			return Vec::new();
+		}
+		self.next_scan = now + SCAN_INTERVAL;
+
+		self.watches
+			.iter()
+			.filter(|watch| watch.snapshot.is_some())
+			.map(|watch| (watch.wd, watch.path.clone()))
+			.collect()
+	}
+
+	/// Compares the watch `wd` on a FUSE mount with the current attributes
+	/// and directory entries, which are `None`, if the node doesn't exist anymore
+	fn update(&mut self, wd: i32, current: Option<(FileAttr, BTreeMap<String, FileAttr>)>) {
+		let Some(i) = self.watches.iter().position(|watch| watch.wd == wd) else {
+			return;
+		};
+		let Some(snapshot) = &mut self.watches[i].snapshot else {
+			return;
+		};
+		let Some((attr, new_entries)) = current else {
+			self.deliver(i, InotifyMask::IN_DELETE_SELF, 0, "");
+			return;
+		};
+
+		// new entries change the modification time of a directory, but
+		// they are reported as events of the entries
+		let changed = compare(&snapshot.attr, &attr)
+			.filter(|mask| !is_dir(&attr) || *mask != InotifyMask::IN_MODIFY)
+			.filter(|_| !snapshot.self_changed);
+		let old_entries = core::mem::take(&mut snapshot.entries);
+		let kernel_changes = core::mem::take(&mut snapshot.changed);
+		snapshot.attr = attr;
+		snapshot.self_changed = false;
+		snapshot.entries = new_entries.clone();
+
+		if let Some(mask) = changed {
+			if !self.deliver(i, mask, 0, "") {
+				return;
+			}
+		}
+
+		// changes, which were made by the kernel, have already been reported
+		let unchanged = |name: &String| !kernel_changes.contains(name);
+		let mut added: Vec<(&String, &FileAttr)> = new_entries
+			.iter()
+			.filter(|(name, _)| unchanged(name) && !old_entries.contains_key(*name))
+			.collect();
+		let mut events = Vec::new();
+
+		for (name, old) in old_entries.iter().filter(|(name, _)| unchanged(name)) {
+			let isdir = if is_dir(old) {
+				InotifyMask::IN_ISDIR
+			} else {
+				InotifyMask::empty()
+			};
+
+			if let Some(new) = new_entries.get(name) {
+				if let Some(mask) = compare(old, new) {
+					events.push((mask, 0, name.clone()));
+				}
+			} else if let Some(pos) = added.iter().position(|(_, new)| new.st_ino == old.st_ino) {
+				// the same inode under a different name was renamed
+				let (new_name, _) = added.remove(pos);
+				let cookie = NEXT_COOKIE.fetch_add(1, atomic::Ordering::Relaxed);
+				events.push((InotifyMask::IN_MOVED_FROM | isdir, cookie, name.clone()));
+				events.push((InotifyMask::IN_MOVED_TO | isdir, cookie, new_name.clone()));
+			} else {
+				events.push((InotifyMask::IN_DELETE | isdir, 0, name.clone()));
+			}
+		}
+		for (name, new) in added {
+			let isdir = if is_dir(new) {
+				InotifyMask::IN_ISDIR
+			} else {
+				InotifyMask::empty()
+			};
+			events.push((InotifyMask::IN_CREATE | isdir, 0, name.clone()));
+		}
+
+		for (mask, cookie, name) in events {
+			if !self.deliver(i, mask, cookie, &name) {
+				break;
+			}
+		}
+	}
+
+	/// Returns `true`, if a watch has to be compared periodically
+	fn is_polling(&self) -> bool {
+		self.watches.iter().any(|watch| watch.snapshot.is_some())
+	}
+
+	/// Registers the task for the next event
+	fn wait(&mut self, cx: &Context<'_>) {
+		if self.is_polling() {
+			// changes of the host are detected by repeating the comparison
+			core_scheduler().add_timer(self.next_scan, cx.waker().clone());
+		}
+		if !self
+			.read_queue
+			.iter()
+			.any(|waker| waker.will_wake(cx.waker()))
+		{
+			self.read_queue.push_back(cx.waker().clone());
+		}
+	}
+}
+
+/// An inotify instance, which reports changes of watched nodes
+#[derive(Debug)]
+pub(crate) struct Inotify {
+	state: TicketMutex<InotifyState>,
+	nonblocking: AtomicBool,
+}
+
+impl Inotify {
+	pub fn new(flags: InotifyFlags) -> Arc<Self> {
+		debug!("Create inotify instance {flags:?}");
+		let inotify = Arc::new(Self {
+			state: TicketMutex::new(InotifyState {
+				watches: Vec::new(),
+				events: VecDeque::new(),
+				next_wd: 1,
+				next_scan: 0,
+				read_queue: VecDeque::new(),
+			}),
+			nonblocking: AtomicBool::new(flags.contains(InotifyFlags::IN_NONBLOCK)),
+		});
+
+		INSTANCES.lock().push(Arc::downgrade(&inotify));
+		inotify
+	}
+}
+
+impl Inotify {
+	/// Compares the watches on FUSE mounts with the current attributes. The
+	/// state isn't locked, while the attributes are requested from the server.
+	fn scan(&self) {
+		let Some(fs) = FILESYSTEM.get() else {
+			return;
+		};
+
+		let watches = self.state.lock().due_scan();
+		for (wd, path) in watches {
+			let current = fs
+				.lstat(&path)
+				.ok()
+				.map(|attr| (attr, Snapshot::entries(fs, &path, &attr)));
+			self.state.lock().update(wd, current);
+		}
+	}
+}
+
+impl Drop for Inotify {
+	fn drop(&mut self) {
+		INSTANCES
+			.lock()
+			.retain(|inotify| inotify.strong_count() > 0);
+		if !is_active() {
+			OPEN_FILES.lock().clear();
+		}
+	}
+}
+
+#[async_trait]
+impl ObjectInterface for Inotify {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		let readable = PollEvent::POLLIN | PollEvent::POLLRDNORM;
+
+		future::poll_fn(|cx| {
+			self.scan();
+			let mut state = self.state.lock();
+
+			if !state.events.is_empty() || !event.intersects(readable) {
+				let available = if state.events.is_empty() {
+					PollEvent::empty()
+				} else {
+					readable
+				};
+				Poll::Ready(Ok(event & available))
+			} else {
+				state.wait(cx);
+				Poll::Pending
+			}
+		})
+		.await
+	}
+
+	async fn read(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		future::poll_fn(|cx| {
+			self.scan();
+			let mut state = self.state.lock();
+
+			if state.events.is_empty() {
+				if self.nonblocking.load(atomic::Ordering::Relaxed) {
+					return Poll::Ready(Err(io::Error::EAGAIN));
+				}
+				state.wait(cx);
+				return Poll::Pending;
+			}
+
+			let mut len = 0;
+			while let Some(event) = state.events.front() {
+				let event_len = event.len();
+				if len + event_len > buf.len() {
+					break;
+				}
+				event.encode(&mut buf[len..len + event_len]);
+				len += event_len;
+				state.events.pop_front();
+			}
+
+			if len == 0 {
+				// the buffer is too small for the next event
+				Poll::Ready(Err(io::Error::EINVAL))
+			} else {
+				Poll::Ready(Ok(len))
+			}
+		})
+		.await
+	}
+
+	async fn inotify_add_watch(&self, path: &str, mask: u32) -> io::Result<i32> {
+		let mask = InotifyMask::from_bits_truncate(mask);
+		if !mask.intersects(InotifyMask::ALL_EVENTS)
+			|| mask.contains(InotifyMask::IN_MASK_ADD | InotifyMask::IN_MASK_CREATE)
+		{
+			return Err(io::Error::EINVAL);
+		}
+
+		let fs = FILESYSTEM.get().ok_or(io::Error::EINVAL)?;
+		let path = fs.resolve(path, !mask.contains(InotifyMask::IN_DONT_FOLLOW))?;
+		let attr = fs.lstat(&path)?;
+		if mask.contains(InotifyMask::IN_ONLYDIR) && !is_dir(&attr) {
+			return Err(io::Error::ENOTDIR);
+		}
+		let mask = mask.difference(InotifyMask::IN_DONT_FOLLOW | InotifyMask::IN_ONLYDIR);
+
+		// the snapshot is taken without holding the state, because the
+		// attributes are requested from the server
+		let snapshot = (fs.fstype(&path) == "virtiofs").then(|| Snapshot::new(fs, &path, attr));
+
+		let mut state = self.state.lock();
+		if let Some(watch) = state.watches.iter_mut().find(|watch| watch.path == path) {
+			if mask.contains(InotifyMask::IN_MASK_CREATE) {
+				return Err(io::Error::EEXIST);
+			}
+			if mask.contains(InotifyMask::IN_MASK_ADD) {
+				watch.mask |= mask.difference(InotifyMask::IN_MASK_ADD);
+			} else {
+				watch.mask = mask;
+			}
+			return Ok(watch.wd);
+		}
+
+		let wd = state.next_wd;
+		state.next_wd += 1;
+		state.watches.push(Watch {
+			wd,
+			path,
+			mask: mask.difference(InotifyMask::IN_MASK_ADD | InotifyMask::IN_MASK_CREATE),
+			snapshot,
+		});
+
+		Ok(wd)
+	}
+
+	async fn inotify_rm_watch(&self, wd: i32) -> io::Result<()> {
+		let mut state = self.state.lock();
+		let index = state
+			.watches
+			.iter()
+			.position(|watch| watch.wd == wd)
+			.ok_or(io::Error::EINVAL)?;
+		state.watches.remove(index);
+		state.push(wd, InotifyMask::IN_IGNORED, 0, "");
+
+		Ok(())
+	}
+
+	async fn ioctl(&self, cmd: IoCtl, value: bool) -> io::Result<()> {
+		match cmd {
+			IoCtl::NonBlocking => {
+				self.nonblocking.store(value, atomic::Ordering::Relaxed);
+				Ok(())
+			}
+		}
+	}
+}
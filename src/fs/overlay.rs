@@ -0,0 +1,755 @@
+//! Implements an overlay file system, which combines a read-only lower layer
+//! with a writable upper layer in memory.
+//!
+//! Nodes of the lower layer are copied to the upper layer, before they are
+//! modified. Removed nodes of the lower layer are hidden by whiteouts, which are
+//! character devices with the device number 0/0 as on Linux.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+
+use crate::executor::block_on;
+use crate::fd::{AccessPermission, ObjectInterface, OpenOption};
+use crate::fs::mem::{MemDirectory, MemDirectoryEntries};
+use crate::fs::{
+	DirectoryEntry, DirectoryReader, FileAttr, NodeKind, SetAttr, StatFs, VfsNode, XattrFlags,
+};
+use crate::io;
+
+/// Magic number of the overlay file system, as reported by `statfs`
+const OVERLAYFS_SUPER_MAGIC: i64 = 0x794c_7630;
+
+/// Size of the buffer, which is used to copy files to the upper layer
+const COPY_BUFFER_LEN: usize = 64 * 1024;
+
+fn file_type(attr: &FileAttr) -> u32 {
+	(attr.st_mode & AccessPermission::S_IFMT).bits()
+}
+
+fn is_dir(attr: &FileAttr) -> bool {
+	file_type(attr) == AccessPermission::S_IFDIR.bits()
+}
+
+fn is_whiteout(attr: &FileAttr) -> bool {
+	file_type(attr) == AccessPermission::S_IFCHR.bits() && attr.st_rdev == 0
+}
+
+/// Marks a node of the lower layer as removed
+#[derive(Debug)]
+struct Whiteout;
+
+impl VfsNode for Whiteout {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::File
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		Ok(FileAttr {
+			st_nlink: 1,
+			st_mode: AccessPermission::S_IFCHR,
+			..Default::default()
+		})
+	}
+}
+
+/// Layer, which provides a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+	Upper,
+	Lower,
+}
+
+/// Root directory of an overlay file system
+#[derive(Debug)]
+pub(crate) struct OverlayDirectory {
+	lower: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	upper: MemDirectory,
+}
+
+impl OverlayDirectory {
+	pub fn new(lower: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>) -> Self {
+		let mode = lower
+			.get_file_attributes()
+			.map(|attr| attr.st_mode.difference(AccessPermission::S_IFMT))
+			.unwrap_or(AccessPermission::from_bits(0o755).unwrap());
+
+		Self {
+			lower,
+			upper: MemDirectory::new(mode),
+		}
+	}
+
+	/// Determines the layer, which provides the node at `components`
+	fn layer(&self, components: &[&str]) -> io::Result<Layer> {
+		// check the upper layer from the root to the node
+		for i in (0..components.len()).rev() {
+			match self.upper.traverse_lstat(&mut components[i..].to_vec()) {
+				Ok(attr) if is_whiteout(&attr) => return Err(io::Error::ENOENT),
+				Ok(attr) if i > 0 && !is_dir(&attr) => return Err(io::Error::ENOTDIR),
+				Ok(_) => {}
+				Err(_) => {
+					return self
+						.lower
+						.traverse_lstat(&mut components.to_vec())
+						.map(|_| Layer::Lower)
+						.map_err(|_| io::Error::ENOENT);
+				}
+			}
+		}
+
+		Ok(Layer::Upper)
+	}
+
+	fn node(&self, layer: Layer) -> &(dyn VfsNode + core::marker::Send + core::marker::Sync) {
+		match layer {
+			Layer::Upper => &self.upper,
+			Layer::Lower => self.lower.as_ref(),
+		}
+	}
+
+	fn lstat(&self, components: &[&str]) -> io::Result<FileAttr> {
+		let layer = self.layer(components)?;
+		self.node(layer).traverse_lstat(&mut components.to_vec())
+	}
+
+	/// Returns true, if the lower layer contains a node at `components`
+	fn in_lower(&self, components: &[&str]) -> bool {
+		self.lower.traverse_lstat(&mut components.to_vec()).is_ok()
+	}
+
+	/// Returns the merged entries of the directory at `components`
+	fn readdir(&self, components: &[&str]) -> io::Result<Vec<DirectoryEntry>> {
+		if self.layer(components)? == Layer::Lower {
+			return self.lower.traverse_readdir(&mut components.to_vec());
+		}
+
+		let upper = self.upper.traverse_readdir(&mut components.to_vec())?;
+		let names: BTreeSet<&str> = upper.iter().map(|entry| entry.name.as_str()).collect();
+
+		let mut entries = Vec::new();
+		for entry in &upper {
+			let mut child = vec![entry.name.as_str()];
+			child.extend_from_slice(components);
+			if !self
+				.upper
+				.traverse_lstat(&mut child)
+				.is_ok_and(|attr| is_whiteout(&attr))
+			{
+				entries.push(entry.clone());
+			}
+		}
+		if let Ok(lower) = self.lower.traverse_readdir(&mut components.to_vec()) {
+			entries.extend(
+				lower
+					.into_iter()
+					.filter(|entry| !names.contains(entry.name.as_str())),
+			);
+		}
+
+		Ok(entries)
+	}
+
+	/// Hides the node of the lower layer at `components`
+	fn whiteout(&self, components: &[&str]) -> io::Result<()> {
+		self.upper
+			.traverse_mount(&mut components.to_vec(), Box::new(Whiteout))
+	}
+
+	/// Hides all nodes of the lower layer in the directory `components`,
+	/// which aren't part of the upper layer
+	fn whiteout_lower_entries(&self, components: &[&str]) -> io::Result<()> {
+		let Ok(entries) = self.lower.traverse_readdir(&mut components.to_vec()) else {
+			return Ok(());
+		};
+
+		for entry in entries {
+			let mut child = vec![entry.name.as_str()];
+			child.extend_from_slice(components);
+			if self.upper.traverse_lstat(&mut child.clone()).is_err() {
+				self.whiteout(&child)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Copies the node at `components` and its parent directories to the upper layer
+	fn copy_up(&self, components: &[&str]) -> io::Result<()> {
+		if self.upper.traverse_lstat(&mut components.to_vec()).is_ok() {
+			return Ok(());
+		}
+		if let Some((_, parent)) = components.split_first() {
+			self.copy_up(parent)?;
+		}
+
+		let attr = self.lower.traverse_lstat(&mut components.to_vec())?;
+		let mode = attr.st_mode.difference(AccessPermission::S_IFMT);
+		let upper_components = components.to_vec();
+		match file_type(&attr) {
+			t if t == AccessPermission::S_IFDIR.bits() => {
+				self.upper.traverse_mkdir(&mut components.to_vec(), mode)?;
+			}
+			t if t == AccessPermission::S_IFLNK.bits() => {
+				let target = self.lower.traverse_readlink(&mut components.to_vec())?;
+				self.upper
+					.traverse_symlink(&mut components.to_vec(), &target)?;
+			}
+			t if t == AccessPermission::S_IFREG.bits() => {
+				let src = self.lower.traverse_open(
+					&mut components.to_vec(),
+					OpenOption::O_RDONLY,
+					AccessPermission::empty(),
+				)?;
+				let dst = self.upper.traverse_open(
+					&mut components.to_vec(),
+					OpenOption::O_CREAT | OpenOption::O_RDWR,
+					mode,
+				)?;
+
+				let mut buf = vec![MaybeUninit::uninit(); COPY_BUFFER_LEN];
+				loop {
+					let len = block_on(src.read(&mut buf), None)?;
+					if len == 0 {
+						break;
+					}
+
+					let mut data = unsafe { buf[..len].assume_init_ref() };
+					while !data.is_empty() {
+						let written = block_on(dst.write(data), None)?;
+						data = &data[written..];
+					}
+				}
+			}
+			// special files cannot be copied
+			_ => return Err(io::Error::EPERM),
+		}
+
+		// keep the owner and the timestamps of the lower layer
+		let owner_and_times = SetAttr {
+			uid: Some(attr.st_uid),
+			gid: Some(attr.st_gid),
+			atime: Some(attr.st_atim),
+			mtime: Some(attr.st_mtim),
+			..Default::default()
+		};
+		self.upper
+			.traverse_setattr(&mut upper_components.clone(), owner_and_times)?;
+
+		// a lower layer without extended attributes fails to list them
+		let names = self
+			.lower
+			.traverse_listxattr(&mut components.to_vec())
+			.unwrap_or_default();
+		for name in names {
+			let value = self
+				.lower
+				.traverse_getxattr(&mut components.to_vec(), &name)?;
+			self.upper.traverse_setxattr(
+				&mut upper_components.clone(),
+				&name,
+				&value,
+				XattrFlags::empty(),
+			)?;
+		}
+
+		Ok(())
+	}
+
+	/// Prepares the upper layer for the creation of a new node at `components`
+	fn prepare_create(&self, components: &[&str]) -> io::Result<()> {
+		let (_, parent) = components.split_first().ok_or(io::Error::EEXIST)?;
+		match self.layer(components) {
+			Ok(_) => return Err(io::Error::EEXIST),
+			Err(io::Error::ENOENT) => {}
+			Err(err) => return Err(err),
+		}
+		if !is_dir(&self.lstat(parent)?) {
+			return Err(io::Error::ENOTDIR);
+		}
+
+		self.copy_up(parent)?;
+		// remove a whiteout, which hides the node of the lower layer
+		if self.upper.traverse_lstat(&mut components.to_vec()).is_ok() {
+			self.upper.traverse_detach(&mut components.to_vec())?;
+		}
+
+		Ok(())
+	}
+}
+
+impl VfsNode for OverlayDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		self.upper.get_file_attributes()
+	}
+
+	fn get_object(&self) -> io::Result<Arc<dyn ObjectInterface>> {
+		Ok(Arc::new(DirectoryReader::new(self.readdir(&[])?)))
+	}
+
+	fn traverse_mkdir(&self, components: &mut Vec<&str>, mode: AccessPermission) -> io::Result<()> {
+		self.prepare_create(components)?;
+		self.upper.traverse_mkdir(&mut components.clone(), mode)?;
+		// the new directory has to hide the content of a removed directory
+		self.whiteout_lower_entries(components)
+	}
+
+	fn traverse_rmdir(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		if components.is_empty() {
+			return Err(io::Error::EBUSY);
+		}
+
+		let layer = self.layer(components)?;
+		if !is_dir(&self.lstat(components)?) {
+			return Err(io::Error::ENOTDIR);
+		}
+		if !self.readdir(components)?.is_empty() {
+			return Err(io::Error::ENOTEMPTY);
+		}
+
+		if layer == Layer::Upper {
+			self.upper.traverse_rmdir(&mut components.clone())?;
+		}
+		if self.in_lower(components) {
+			self.copy_up(&components[1..])?;
+			self.whiteout(components)?;
+		}
+
+		Ok(())
+	}
+
+	fn traverse_unlink(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		let layer = self.layer(components)?;
+		if components.is_empty() || is_dir(&self.lstat(components)?) {
+			return Err(io::Error::EISDIR);
+		}
+
+		if layer == Layer::Upper {
+			self.upper.traverse_unlink(&mut components.clone())?;
+		}
+		if self.in_lower(components) {
+			self.copy_up(&components[1..])?;
+			self.whiteout(components)?;
+		}
+
+		Ok(())
+	}
+
+	fn traverse_readdir(&self, components: &mut Vec<&str>) -> io::Result<Vec<DirectoryEntry>> {
+		if !is_dir(&self.lstat(components)?) {
+			return Err(io::Error::ENOTDIR);
+		}
+
+		self.readdir(components)
+	}
+
+	fn traverse_rename(
+		&self,
+		old_components: &mut Vec<&str>,
+		new_components: &mut Vec<&str>,
+	) -> io::Result<()> {
+		if old_components.is_empty() || new_components.is_empty() {
+			return Err(io::Error::EINVAL);
+		}
+
+		let old_attr = self.lstat(old_components)?;
+		if old_components == new_components {
+			return Ok(());
+		}
+		// directories of the lower layer would have to be copied recursively
+		if is_dir(&old_attr) && self.in_lower(old_components) {
+			return Err(io::Error::EXDEV);
+		}
+
+		match self.lstat(new_components) {
+			Ok(new_attr) => match (is_dir(&old_attr), is_dir(&new_attr)) {
+				(true, true) => {
+					if !self.readdir(new_components)?.is_empty() {
+						return Err(io::Error::ENOTEMPTY);
+					}
+				}
+				(true, false) => return Err(io::Error::ENOTDIR),
+				(false, true) => return Err(io::Error::EISDIR),
+				(false, false) => {}
+			},
+			Err(io::Error::ENOENT) => {
+				if !is_dir(&self.lstat(&new_components[1..])?) {
+					return Err(io::Error::ENOTDIR);
+				}
+			}
+			Err(err) => return Err(err),
+		}
+
+		self.copy_up(old_components)?;
+		self.copy_up(&new_components[1..])?;
+		// whiteouts and empty directories, which only contain whiteouts, are replaced
+		if let Ok(attr) = self.upper.traverse_lstat(&mut new_components.clone()) {
+			if is_whiteout(&attr) || is_dir(&attr) {
+				self.upper.traverse_detach(&mut new_components.clone())?;
+			}
+		}
+
+		self.upper
+			.traverse_rename(&mut old_components.clone(), &mut new_components.clone())?;
+		if self.in_lower(old_components) {
+			self.whiteout(old_components)?;
+		}
+		if is_dir(&old_attr) {
+			self.whiteout_lower_entries(new_components)?;
+		}
+
+		Ok(())
+	}
+
+	fn traverse_symlink(&self, components: &mut Vec<&str>, target: &str) -> io::Result<()> {
+		self.prepare_create(components)?;
+		self.upper.traverse_symlink(components, target)
+	}
+
+	fn traverse_mknod(&self, components: &mut Vec<&str>, mode: AccessPermission) -> io::Result<()> {
+		self.prepare_create(components)?;
+		self.upper.traverse_mknod(components, mode)
+	}
+
+	fn traverse_link(
+		&self,
+		old_components: &mut Vec<&str>,
+		new_components: &mut Vec<&str>,
+	) -> io::Result<()> {
+		if old_components.is_empty() || is_dir(&self.lstat(old_components)?) {
+			return Err(io::Error::EPERM);
+		}
+
+		self.prepare_create(new_components)?;
+		self.copy_up(old_components)?;
+		self.upper.traverse_link(old_components, new_components)
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		let layer = self.layer(components)?;
+		self.node(layer).traverse_readlink(components)
+	}
+
+	fn traverse_lookup_symlink(
+		&self,
+		components: &mut Vec<&str>,
+		follow: bool,
+	) -> io::Result<Option<String>> {
+		let len = components.len();
+		let limit = if follow { len } else { len.saturating_sub(1) };
+
+		for i in 1..=limit {
+			let prefix = &components[len - i..];
+			let Ok(attr) = self.lstat(prefix) else {
+				// Missing nodes are reported by the operation, which uses the path.
+				return Ok(None);
+			};
+
+			if file_type(&attr) == AccessPermission::S_IFLNK.bits() {
+				let target = self.traverse_readlink(&mut prefix.to_vec())?;
+				components.truncate(len - i);
+				return Ok(Some(target));
+			} else if !is_dir(&attr) {
+				return Ok(None);
+			}
+		}
+
+		Ok(None)
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		self.lstat(components)
+	}
+
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		let layer = self.layer(components)?;
+		self.node(layer).traverse_stat(components)
+	}
+
+	fn traverse_mount(
+		&self,
+		components: &mut Vec<&str>,
+		obj: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	) -> io::Result<()> {
+		let (_, parent) = components.split_first().ok_or(io::Error::EBUSY)?;
+		if !is_dir(&self.lstat(parent)?) {
+			return Err(io::Error::ENOTDIR);
+		}
+
+		// nodes of the lower layer are hidden by the mounted file system
+		self.copy_up(parent)?;
+		match self.upper.traverse_lstat(&mut components.clone()) {
+			Ok(attr) if is_whiteout(&attr) => {
+				self.upper.traverse_detach(&mut components.clone())?;
+			}
+			Ok(_) => return Err(io::Error::EEXIST),
+			Err(_) => {}
+		}
+
+		self.upper.traverse_mount(components, obj)
+	}
+
+	fn traverse_detach(
+		&self,
+		components: &mut Vec<&str>,
+	) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		if self.layer(components)? == Layer::Lower {
+			self.copy_up(components)?;
+		}
+
+		self.upper.traverse_detach(components)
+	}
+
+	fn traverse_setattr(&self, components: &mut Vec<&str>, attr: SetAttr) -> io::Result<()> {
+		if self.layer(components)? == Layer::Lower {
+			self.copy_up(components)?;
+		}
+
+		self.upper.traverse_setattr(components, attr)
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		let layer = self.layer(components)?;
+		self.node(layer).traverse_getxattr(components, name)
+	}
+
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		if self.layer(components)? == Layer::Lower {
+			self.copy_up(components)?;
+		}
+
+		self.upper.traverse_setxattr(components, name, value, flags)
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		let layer = self.layer(components)?;
+		self.node(layer).traverse_listxattr(components)
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<()> {
+		if self.layer(components)? == Layer::Lower {
+			self.copy_up(components)?;
+		}
+
+		self.upper.traverse_removexattr(components, name)
+	}
+
+	fn traverse_statfs(&self, _components: &mut Vec<&str>) -> io::Result<StatFs> {
+		let mut stat = self.upper.traverse_statfs(&mut Vec::new())?;
+		stat.f_type = OVERLAYFS_SUPER_MAGIC;
+
+		Ok(stat)
+	}
+
+	fn traverse_open(
+		&self,
+		components: &mut Vec<&str>,
+		opt: OpenOption,
+		mode: AccessPermission,
+	) -> io::Result<Arc<dyn ObjectInterface>> {
+		let layer = match self.layer(components) {
+			Ok(layer) => layer,
+			Err(io::Error::ENOENT) if opt.contains(OpenOption::O_CREAT) => {
+				self.prepare_create(components)?;
+				return self.upper.traverse_open(components, opt, mode);
+			}
+			Err(err) => return Err(err),
+		};
+
+		if opt.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) {
+			return Err(io::Error::EEXIST);
+		}
+		let opt = opt.difference(OpenOption::O_CREAT);
+
+		let attr = self.lstat(components)?;
+		if is_dir(&attr) {
+			return Ok(Arc::new(DirectoryReader::new(self.readdir(components)?)));
+		} else if opt.contains(OpenOption::O_DIRECTORY) {
+			return Err(io::Error::ENOTDIR);
+		}
+
+		if layer == Layer::Lower
+			&& opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR | OpenOption::O_TRUNC)
+		{
+			self.copy_up(components)?;
+			return self.upper.traverse_open(components, opt, mode);
+		}
+
+		self.node(layer).traverse_open(components, opt, mode)
+	}
+
+	fn traverse_create_file(
+		&self,
+		components: &mut Vec<&str>,
+		data: &'static [u8],
+		mode: AccessPermission,
+	) -> io::Result<()> {
+		self.prepare_create(components)?;
+		self.upper.traverse_create_file(components, data, mode)
+	}
+}
+
+/// The lower layer of an overlay is shared with the mount table, which
+/// restores it, when the overlay is unmounted.
+impl VfsNode for Arc<dyn VfsNode + core::marker::Send + core::marker::Sync> {
+	fn get_kind(&self) -> NodeKind {
+		self.as_ref().get_kind()
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		self.as_ref().get_file_attributes()
+	}
+
+	fn get_object(&self) -> io::Result<Arc<dyn ObjectInterface>> {
+		self.as_ref().get_object()
+	}
+
+	fn traverse_mkdir(&self, components: &mut Vec<&str>, mode: AccessPermission) -> io::Result<()> {
+		self.as_ref().traverse_mkdir(components, mode)
+	}
+
+	fn traverse_rmdir(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		self.as_ref().traverse_rmdir(components)
+	}
+
+	fn traverse_unlink(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		self.as_ref().traverse_unlink(components)
+	}
+
+	fn traverse_readdir(&self, components: &mut Vec<&str>) -> io::Result<Vec<DirectoryEntry>> {
+		self.as_ref().traverse_readdir(components)
+	}
+
+	fn traverse_rename(
+		&self,
+		old_components: &mut Vec<&str>,
+		new_components: &mut Vec<&str>,
+	) -> io::Result<()> {
+		self.as_ref()
+			.traverse_rename(old_components, new_components)
+	}
+
+	fn traverse_symlink(&self, components: &mut Vec<&str>, target: &str) -> io::Result<()> {
+		self.as_ref().traverse_symlink(components, target)
+	}
+
+	fn traverse_mknod(&self, components: &mut Vec<&str>, mode: AccessPermission) -> io::Result<()> {
+		self.as_ref().traverse_mknod(components, mode)
+	}
+
+	fn traverse_link(
+		&self,
+		old_components: &mut Vec<&str>,
+		new_components: &mut Vec<&str>,
+	) -> io::Result<()> {
+		self.as_ref().traverse_link(old_components, new_components)
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		self.as_ref().traverse_readlink(components)
+	}
+
+	fn traverse_lookup_symlink(
+		&self,
+		components: &mut Vec<&str>,
+		follow: bool,
+	) -> io::Result<Option<String>> {
+		self.as_ref().traverse_lookup_symlink(components, follow)
+	}
+
+	fn hard_link(&self) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		self.as_ref().hard_link()
+	}
+
+	fn traverse_mem_directory(
+		&self,
+		components: &mut Vec<&str>,
+	) -> io::Result<MemDirectoryEntries> {
+		self.as_ref().traverse_mem_directory(components)
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		self.as_ref().traverse_lstat(components)
+	}
+
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		self.as_ref().traverse_stat(components)
+	}
+
+	fn traverse_mount(
+		&self,
+		components: &mut Vec<&str>,
+		obj: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	) -> io::Result<()> {
+		self.as_ref().traverse_mount(components, obj)
+	}
+
+	fn traverse_detach(
+		&self,
+		components: &mut Vec<&str>,
+	) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		self.as_ref().traverse_detach(components)
+	}
+
+	fn traverse_setattr(&self, components: &mut Vec<&str>, attr: SetAttr) -> io::Result<()> {
+		self.as_ref().traverse_setattr(components, attr)
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		self.as_ref().traverse_getxattr(components, name)
+	}
+
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		self.as_ref()
+			.traverse_setxattr(components, name, value, flags)
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		self.as_ref().traverse_listxattr(components)
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<()> {
+		self.as_ref().traverse_removexattr(components, name)
+	}
+
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		self.as_ref().traverse_statfs(components)
+	}
+
+	fn traverse_open(
+		&self,
+		components: &mut Vec<&str>,
+		opt: OpenOption,
+		mode: AccessPermission,
+	) -> io::Result<Arc<dyn ObjectInterface>> {
+		self.as_ref().traverse_open(components, opt, mode)
+	}
+
+	fn traverse_create_file(
+		&self,
+		components: &mut Vec<&str>,
+		data: &'static [u8],
+		mode: AccessPermission,
+	) -> io::Result<()> {
+		self.as_ref().traverse_create_file(components, data, mode)
+	}
+}
@@ -0,0 +1,488 @@
+//! Implements the process information pseudo-file system, which is mounted at `/proc`.
+//!
+//! The content of a file is generated, when the file is opened. Afterwards,
+//! the file descriptor reads from this snapshot.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::mem::MaybeUninit;
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+
+use crate::executor::block_on;
+use crate::fd::{AccessPermission, FileDescriptor, ObjectInterface, OpenOption, PollEvent};
+use crate::fs::{
+	DirectoryEntry, DirectoryReader, FileAttr, NodeKind, PROC_DEV, SeekWhence, StatFs, VfsNode,
+};
+use crate::time::timespec;
+use crate::{arch, core_scheduler, env, fd, io, mm, scheduler};
+
+/// Node of the proc file system
+#[derive(Debug, Clone, Copy)]
+enum ProcNode {
+	/// `/proc`
+	Root,
+	/// `/proc/self`
+	Process,
+	/// `/proc/self/fd`
+	FileDescriptors,
+	/// Symbolic link in `/proc/self/fd`, which describes an open file descriptor
+	FileDescriptor(FileDescriptor),
+	/// File, whose content is generated by the function
+	File(fn() -> Result<String, core::fmt::Error>),
+}
+
+const ROOT_ENTRIES: &[(&str, ProcNode)] = &[
+	("cpuinfo", ProcNode::File(cpuinfo)),
+	("interrupts", ProcNode::File(interrupts)),
+	("meminfo", ProcNode::File(meminfo)),
+	("mounts", ProcNode::File(mounts)),
+	("self", ProcNode::Process),
+	("uptime", ProcNode::File(uptime)),
+	("version", ProcNode::File(version)),
+];
+
+const PROCESS_ENTRIES: &[(&str, ProcNode)] = &[
+	("cmdline", ProcNode::File(cmdline)),
+	("environ", ProcNode::File(environ)),
+	("fd", ProcNode::FileDescriptors),
+	("mounts", ProcNode::File(mounts)),
+	("status", ProcNode::File(status)),
+];
+
+/// Magic number of the proc file system, as reported by `statfs`
+const PROC_SUPER_MAGIC: i64 = 0x9fa0;
+
+impl ProcNode {
+	/// Returns the entries of a directory
+	fn entries(self) -> io::Result<Vec<(String, ProcNode)>> {
+		let entries = |entries: &[(&str, ProcNode)]| {
+			entries
+				.iter()
+				.map(|(name, node)| (name.to_string(), *node))
+				.collect()
+		};
+
+		match self {
+			ProcNode::Root => Ok(entries(ROOT_ENTRIES)),
+			ProcNode::Process => Ok(entries(PROCESS_ENTRIES)),
+			ProcNode::FileDescriptors => block_on(
+				async {
+					let object_map = core_scheduler().get_current_task_object_map();
+					let mut fds: Vec<FileDescriptor> =
+						object_map.read().await.keys().copied().collect();
+					fds.sort_unstable();
+
+					Ok(fds
+						.into_iter()
+						.map(|fd| (fd.to_string(), ProcNode::FileDescriptor(fd)))
+						.collect())
+				},
+				None,
+			),
+			_ => Err(io::Error::ENOTDIR),
+		}
+	}
+
+	/// Looks up the entry `name` of a directory
+	fn lookup(self, name: &str) -> io::Result<ProcNode> {
+		match self {
+			ProcNode::FileDescriptors => {
+				let fd: FileDescriptor = name.parse().map_err(|_| io::Error::ENOENT)?;
+				fd::get_object(fd).map_err(|_| io::Error::ENOENT)?;
+				Ok(ProcNode::FileDescriptor(fd))
+			}
+			ProcNode::Root | ProcNode::Process => self
+				.entries()?
+				.into_iter()
+				.find(|(entry, _)| entry == name)
+				.map(|(_, node)| node)
+				.ok_or(io::Error::ENOENT),
+			_ => Err(io::Error::ENOTDIR),
+		}
+	}
+
+	fn kind(self) -> NodeKind {
+		match self {
+			ProcNode::Root | ProcNode::Process | ProcNode::FileDescriptors => NodeKind::Directory,
+			ProcNode::FileDescriptor(_) => NodeKind::Symlink,
+			ProcNode::File(_) => NodeKind::File,
+		}
+	}
+
+	fn attr(self) -> FileAttr {
+		let microseconds = arch::kernel::systemtime::now_micros();
+		let t = timespec::from_usec(microseconds as i64);
+
+		let (mode, nlink) = match self.kind() {
+			NodeKind::Directory => (
+				AccessPermission::S_IFDIR | AccessPermission::from_bits(0o555).unwrap(),
+				2,
+			),
+			NodeKind::Symlink => (
+				AccessPermission::S_IFLNK | AccessPermission::from_bits(0o777).unwrap(),
+				1,
+			),
+			NodeKind::File => (
+				AccessPermission::S_IFREG | AccessPermission::from_bits(0o444).unwrap(),
+				1,
+			),
+		};
+
+		FileAttr {
+			st_dev: PROC_DEV,
+			st_nlink: nlink,
+			st_mode: mode,
+			st_blksize: 1024,
+			st_atim: t,
+			st_mtim: t,
+			st_ctim: t,
+			..Default::default()
+		}
+	}
+
+	/// Returns the target of a symbolic link
+	fn readlink(self) -> io::Result<String> {
+		let ProcNode::FileDescriptor(fd) = self else {
+			return Err(io::Error::EINVAL);
+		};
+
+		let obj = fd::get_object(fd).map_err(|_| io::Error::ENOENT)?;
+		// Only directories remember their path. All other objects are anonymous.
+		Ok(block_on(obj.getpath(), None).unwrap_or_else(|_| format!("anon_inode:[{fd}]")))
+	}
+}
+
+/// Follows `components` starting at `/proc`
+fn walk(components: &mut Vec<&str>) -> io::Result<ProcNode> {
+	let mut node = ProcNode::Root;
+	while let Some(component) = components.pop() {
+		node = node.lookup(component)?;
+	}
+
+	Ok(node)
+}
+
+fn cpuinfo() -> Result<String, core::fmt::Error> {
+	let mut s = String::new();
+	for core_id in 0..arch::get_processor_count() {
+		writeln!(s, "processor\t: {core_id}")?;
+		arch::processor::write_cpuinfo(&mut s)?;
+		writeln!(s)?;
+	}
+
+	Ok(s)
+}
+
+fn interrupts() -> Result<String, core::fmt::Error> {
+	let counters = arch::interrupts::irq_counters();
+
+	let mut s = String::from("    ");
+	for (core_id, _) in counters.iter() {
+		write!(s, " {:>10}", format!("CPU{core_id}"))?;
+	}
+	writeln!(s)?;
+
+	for irq in 0..=u8::MAX {
+		let i = usize::from(irq);
+		if counters.iter().all(|(_, counters)| counters[i] == 0) {
+			continue;
+		}
+
+		write!(s, "{irq:>3}:")?;
+		for (_, counters) in counters.iter() {
+			write!(s, " {:>10}", counters[i])?;
+		}
+		writeln!(
+			s,
+			"  {}",
+			arch::interrupts::get_irq_name(irq).unwrap_or_default()
+		)?;
+	}
+
+	Ok(s)
+}
+
+fn meminfo() -> Result<String, core::fmt::Error> {
+	let heap = mm::heap_statistics();
+	let total = mm::total_memory_size();
+	let free = mm::available_memory_size();
+
+	let mut s = String::new();
+	for (name, bytes) in [
+		("MemTotal", total),
+		("MemFree", free),
+		("MemAvailable", free),
+		("Buffers", 0),
+		("Cached", 0),
+		("SwapTotal", 0),
+		("SwapFree", 0),
+		("HeapTotal", heap.claimed),
+		("HeapUsed", heap.allocated),
+	] {
+		writeln!(s, "{:<16}{:>8} kB", format!("{name}:"), bytes / 1024)?;
+	}
+
+	Ok(s)
+}
+
+fn mounts() -> Result<String, core::fmt::Error> {
+	let mut s = String::new();
+	for entry in super::FILESYSTEM.get().unwrap().mounts() {
+		writeln!(
+			s,
+			"{} {} {} {} 0 0",
+			entry.source, entry.target, entry.fstype, entry.options
+		)?;
+	}
+
+	Ok(s)
+}
+
+fn uptime() -> Result<String, core::fmt::Error> {
+	let ticks = arch::processor::get_timer_ticks();
+	let mut s = String::new();
+	// the idle time isn't measured
+	writeln!(
+		s,
+		"{}.{:02} 0.00",
+		ticks / 1_000_000,
+		ticks % 1_000_000 / 10_000
+	)?;
+
+	Ok(s)
+}
+
+fn version() -> Result<String, core::fmt::Error> {
+	const VERSION: &str = core::env!("CARGO_PKG_VERSION");
+	const UTC_BUILT_TIME: &str = build_time::build_time_utc!();
+
+	let mut s = String::new();
+	writeln!(s, "HermitOS version {VERSION} # UTC {UTC_BUILT_TIME}")?;
+
+	Ok(s)
+}
+
+fn cmdline() -> Result<String, core::fmt::Error> {
+	let mut s = String::new();
+	for arg in
+		core::iter::once(env::application_name()).chain(env::args().iter().map(String::as_str))
+	{
+		write!(s, "{arg}\0")?;
+	}
+
+	Ok(s)
+}
+
+fn environ() -> Result<String, core::fmt::Error> {
+	let mut s = String::new();
+	for (key, value) in env::vars() {
+		write!(s, "{key}={value}\0")?;
+	}
+
+	Ok(s)
+}
+
+fn status() -> Result<String, core::fmt::Error> {
+	let heap = mm::heap_statistics();
+	let cores = arch::get_processor_count();
+
+	let mut s = String::new();
+	writeln!(s, "Name:\t{}", env::application_name())?;
+	writeln!(s, "State:\tR (running)")?;
+	writeln!(s, "VmSize:\t{:>8} kB", heap.claimed / 1024)?;
+	writeln!(s, "VmRSS:\t{:>8} kB", heap.allocated / 1024)?;
+	writeln!(s, "Threads:\t{}", scheduler::number_of_tasks())?;
+	writeln!(s, "Cpus_allowed_list:\t0-{}", cores.saturating_sub(1))?;
+
+	Ok(s)
+}
+
+/// Snapshot of a generated file
+#[derive(Debug)]
+struct ProcFileInterface {
+	/// Position within the file
+	pos: Mutex<usize>,
+	/// File content at the time of opening
+	data: Vec<u8>,
+	attr: FileAttr,
+}
+
+impl ProcFileInterface {
+	pub fn new(data: Vec<u8>, attr: FileAttr) -> Self {
+		Self {
+			pos: Mutex::new(0),
+			data,
+			attr,
+		}
+	}
+}
+
+#[async_trait]
+impl ObjectInterface for ProcFileInterface {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		Ok(event.intersection(PollEvent::POLLIN | PollEvent::POLLRDNORM))
+	}
+
+	async fn read(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		let mut pos = self.pos.lock().await;
+		let len = self.pread(buf, *pos).await?;
+		*pos += len;
+
+		Ok(len)
+	}
+
+	async fn pread(&self, buf: &mut [MaybeUninit<u8>], offset: usize) -> io::Result<usize> {
+		if offset >= self.data.len() {
+			return Ok(0);
+		}
+
+		let len = buf.len().min(self.data.len() - offset);
+		buf[..len].write_copy_of_slice(&self.data[offset..offset + len]);
+
+		Ok(len)
+	}
+
+	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+		let mut pos = self.pos.lock().await;
+
+		let new_pos = match whence {
+			SeekWhence::Set => offset,
+			SeekWhence::Cur => isize::try_from(*pos).unwrap() + offset,
+			SeekWhence::End => isize::try_from(self.data.len()).unwrap() + offset,
+			_ => return Err(io::Error::EINVAL),
+		};
+
+		*pos = new_pos.try_into().map_err(|_| io::Error::EINVAL)?;
+		Ok(new_pos)
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(self.attr)
+	}
+}
+
+/// Root directory of the proc file system
+#[derive(Debug)]
+pub(crate) struct ProcDirectory;
+
+impl ProcDirectory {
+	pub fn new() -> Self {
+		Self
+	}
+}
+
+impl VfsNode for ProcDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		Ok(ProcNode::Root.attr())
+	}
+
+	fn get_object(&self) -> io::Result<Arc<dyn ObjectInterface>> {
+		self.traverse_open(
+			&mut Vec::new(),
+			OpenOption::O_RDONLY,
+			AccessPermission::empty(),
+		)
+	}
+
+	fn traverse_readdir(&self, components: &mut Vec<&str>) -> io::Result<Vec<DirectoryEntry>> {
+		Ok(walk(components)?
+			.entries()?
+			.into_iter()
+			.map(|(name, node)| DirectoryEntry::with_attr(name, &node.attr()))
+			.collect())
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		walk(components)?.readlink()
+	}
+
+	fn traverse_lookup_symlink(
+		&self,
+		components: &mut Vec<&str>,
+		follow: bool,
+	) -> io::Result<Option<String>> {
+		let mut node = ProcNode::Root;
+		while let Some(component) = components.pop() {
+			let Ok(next) = node.lookup(component) else {
+				// Missing nodes are reported by the operation, which uses the path.
+				return Ok(None);
+			};
+			node = next;
+
+			if node.kind() == NodeKind::Symlink && (follow || !components.is_empty()) {
+				let target = node.readlink()?;
+				// anonymous objects are opened through the link itself
+				return Ok(target.starts_with('/').then_some(target));
+			}
+		}
+
+		Ok(None)
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		Ok(walk(components)?.attr())
+	}
+
+	fn traverse_statfs(&self, _components: &mut Vec<&str>) -> io::Result<StatFs> {
+		Ok(StatFs {
+			f_type: PROC_SUPER_MAGIC,
+			f_bsize: 4096,
+			f_namelen: 255,
+			f_frsize: 4096,
+			..Default::default()
+		})
+	}
+
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		let node = walk(components)?;
+		if let ProcNode::FileDescriptor(fd) = node {
+			fd::fstat(fd)
+		} else {
+			Ok(node.attr())
+		}
+	}
+
+	fn traverse_open(
+		&self,
+		components: &mut Vec<&str>,
+		opt: OpenOption,
+		_mode: AccessPermission,
+	) -> io::Result<Arc<dyn ObjectInterface>> {
+		if opt.intersects(OpenOption::O_CREAT | OpenOption::O_WRONLY | OpenOption::O_RDWR) {
+			return Err(io::Error::EACCES);
+		}
+
+		let node = walk(components)?;
+		match node {
+			ProcNode::File(generate) => {
+				if opt.contains(OpenOption::O_DIRECTORY) {
+					return Err(io::Error::ENOTDIR);
+				}
+
+				let data = generate().map_err(|_| io::Error::EIO)?;
+				Ok(Arc::new(ProcFileInterface::new(
+					data.into_bytes(),
+					node.attr(),
+				)))
+			}
+			ProcNode::FileDescriptor(fd) => fd::get_object(fd),
+			_ => {
+				let entries = node
+					.entries()?
+					.into_iter()
+					.map(|(name, node)| DirectoryEntry::with_attr(name, &node.attr()))
+					.collect();
+				Ok(Arc::new(DirectoryReader::new(entries)))
+			}
+		}
+	}
+}
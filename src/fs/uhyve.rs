@@ -17,7 +17,8 @@ use uhyve_interface::{GuestPhysAddr, GuestVirtAddr, Hypercall};
 use crate::arch::mm::paging;
 use crate::env::is_uhyve;
 use crate::fs::{
-	self, AccessPermission, FileAttr, NodeKind, ObjectInterface, OpenOption, SeekWhence, VfsNode,
+	self, AccessPermission, FileAttr, MountFlags, NodeKind, ObjectInterface, OpenOption,
+	SeekWhence, VfsNode,
 };
 use crate::io;
 use crate::syscalls::interfaces::uhyve::uhyve_hypercall;
@@ -71,6 +72,30 @@ impl UhyveFileHandleInner {
 			Err(io::Error::EINVAL)
 		}
 	}
+
+	/// Uhyve doesn't provide positional I/O. Consequently, the file offset is
+	/// moved to `offset` and restored afterwards.
+	fn pread(&mut self, buf: &mut [MaybeUninit<u8>], offset: usize) -> io::Result<usize> {
+		let pos = self.lseek(0, SeekWhence::Cur)?;
+		self.lseek(
+			offset.try_into().map_err(|_| io::Error::EINVAL)?,
+			SeekWhence::Set,
+		)?;
+		let ret = self.read(buf);
+		self.lseek(pos, SeekWhence::Set)?;
+		ret
+	}
+
+	fn pwrite(&mut self, buf: &[u8], offset: usize) -> io::Result<usize> {
+		let pos = self.lseek(0, SeekWhence::Cur)?;
+		self.lseek(
+			offset.try_into().map_err(|_| io::Error::EINVAL)?,
+			SeekWhence::Set,
+		)?;
+		let ret = self.write(buf);
+		self.lseek(pos, SeekWhence::Set)?;
+		ret
+	}
 }
 
 impl Drop for UhyveFileHandleInner {
@@ -103,9 +128,28 @@ impl ObjectInterface for UhyveFileHandle {
 		self.0.lock().await.write(buf)
 	}
 
+	async fn append(&self, buf: &[u8]) -> io::Result<usize> {
+		let mut guard = self.0.lock().await;
+		guard.lseek(0, SeekWhence::End)?;
+		guard.write(buf)
+	}
+
 	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
 		self.0.lock().await.lseek(offset, whence)
 	}
+
+	async fn pread(&self, buf: &mut [MaybeUninit<u8>], offset: usize) -> io::Result<usize> {
+		self.0.lock().await.pread(buf, offset)
+	}
+
+	async fn pwrite(&self, buf: &[u8], offset: usize) -> io::Result<usize> {
+		self.0.lock().await.pwrite(buf, offset)
+	}
+
+	async fn fsync(&self, _datasync: bool) -> io::Result<()> {
+		// Uhyve doesn't provide a sync hypercall, writes are directly passed to the host
+		Ok(())
+	}
 }
 
 impl Clone for UhyveFileHandle {
@@ -146,6 +190,10 @@ impl VfsNode for UhyveDirectory {
 		NodeKind::Directory
 	}
 
+	// uhyve-interface 0.1.2 only provides hypercalls to open, read, write,
+	// seek, close and unlink files. Nodes cannot be inspected, listed,
+	// created or removed as directories.
+
 	fn traverse_stat(&self, _components: &mut Vec<&str>) -> io::Result<FileAttr> {
 		Err(io::Error::ENOSYS)
 	}
@@ -223,7 +271,11 @@ pub(crate) fn init() {
 			.get()
 			.unwrap()
 			.mount(
+				"uhyve",
 				&mount_point,
+				"uhyve",
+				MountFlags::empty(),
+				"",
 				Box::new(UhyveDirectory::new(Some(mount_point.to_owned()))),
 			)
 			.expect("Mount failed. Duplicate mount_point?");
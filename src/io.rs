@@ -23,6 +23,28 @@ pub enum Error {
 	EADDRINUSE = crate::errno::EADDRINUSE as isize,
 	EOVERFLOW = crate::errno::EOVERFLOW as isize,
 	ENOTSOCK = crate::errno::ENOTSOCK as isize,
+	ENOTEMPTY = crate::errno::ENOTEMPTY as isize,
+	EXDEV = crate::errno::EXDEV as isize,
+	EPERM = crate::errno::EPERM as isize,
+	ELOOP = crate::errno::ELOOP as isize,
+	ERANGE = crate::errno::ERANGE as isize,
+	ESPIPE = crate::errno::ESPIPE as isize,
+	EFBIG = crate::errno::EFBIG as isize,
+	EOPNOTSUPP = crate::errno::EOPNOTSUPP as isize,
+	ENXIO = crate::errno::ENXIO as isize,
+	EACCES = crate::errno::EACCES as isize,
+	EROFS = crate::errno::EROFS as isize,
+	EBUSY = crate::errno::EBUSY as isize,
+	ENODEV = crate::errno::ENODEV as isize,
+	ENOMEM = crate::errno::ENOMEM as isize,
+	ENODATA = crate::errno::ENODATA as isize,
+	E2BIG = crate::errno::E2BIG as isize,
+	EPIPE = crate::errno::EPIPE as isize,
+	ECONNREFUSED = crate::errno::ECONNREFUSED as isize,
+	EISCONN = crate::errno::EISCONN as isize,
+	EPROTOTYPE = crate::errno::EPROTOTYPE as isize,
+	EMSGSIZE = crate::errno::EMSGSIZE as isize,
+	ENOSPC = crate::errno::ENOSPC as isize,
 }
 
 pub type Result<T> = result::Result<T, Error>;
@@ -6,6 +6,17 @@ use core::alloc::{GlobalAlloc, Layout};
 use hermit_sync::RawInterruptTicketMutex;
 use talc::{ErrOnOom, Span, Talc, Talck};
 
+/// Statistics of the heap in bytes
+#[derive(Debug, Default, Copy, Clone)]
+pub struct HeapStatistics {
+	/// Memory, which is managed by the allocator
+	pub claimed: usize,
+	/// Memory, which is allocated
+	pub allocated: usize,
+	/// Memory, which is available for allocations
+	pub available: usize,
+}
+
 pub struct LockedAllocator(Talck<RawInterruptTicketMutex, ErrOnOom>);
 
 impl LockedAllocator {
@@ -27,6 +38,17 @@ impl LockedAllocator {
 			self.0.lock().claim(arena).unwrap();
 		}
 	}
+
+	/// Returns the statistics of the heap
+	pub fn statistics(&self) -> HeapStatistics {
+		let talc = self.0.lock();
+		let counters = talc.get_counters();
+		HeapStatistics {
+			claimed: counters.claimed_bytes,
+			allocated: counters.allocated_bytes,
+			available: counters.available_bytes,
+		}
+	}
 }
 
 /// To avoid false sharing, the global memory allocator align
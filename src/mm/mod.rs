@@ -6,11 +6,9 @@ use core::ops::Range;
 
 use align_address::Align;
 use hermit_sync::Lazy;
-#[cfg(feature = "pci")]
-use memory_addresses::PhysAddr;
-use memory_addresses::VirtAddr;
+use memory_addresses::{PhysAddr, VirtAddr};
 
-use self::allocator::LockedAllocator;
+use self::allocator::{HeapStatistics, LockedAllocator};
 #[cfg(any(target_arch = "x86_64", target_arch = "riscv64"))]
 use crate::arch::mm::paging::HugePageSize;
 #[cfg(target_arch = "x86_64")]
@@ -42,6 +40,16 @@ pub(crate) fn kernel_end_address() -> VirtAddr {
 	KERNEL_ADDR_RANGE.end
 }
 
+/// Removes the initial RAM disk of the loader from the free physical memory.
+/// Must be called, before the first physical memory is allocated.
+pub(crate) fn reserve_initrd() {
+	if let Some(initrd) = env::initrd() {
+		let start = initrd.start.align_down(BasePageSize::SIZE);
+		let end = initrd.end.align_up(BasePageSize::SIZE);
+		physicalmem::reserve(PhysAddr::new(start), (end - start).try_into().unwrap());
+	}
+}
+
 #[cfg(target_os = "none")]
 pub(crate) fn init() {
 	use crate::arch::mm::paging;
@@ -49,6 +57,7 @@ pub(crate) fn init() {
 	Lazy::force(&KERNEL_ADDR_RANGE);
 
 	arch::mm::init();
+
 	arch::mm::init_page_tables();
 
 	let total_mem = physicalmem::total_memory_size();
@@ -247,6 +256,29 @@ pub(crate) fn print_information() {
 	arch::mm::virtualmem::print_information();
 }
 
+/// Returns the statistics of the kernel heap
+#[cfg(target_os = "none")]
+pub(crate) fn heap_statistics() -> HeapStatistics {
+	ALLOCATOR.statistics()
+}
+
+/// Returns the statistics of the kernel heap
+#[cfg(not(target_os = "none"))]
+pub(crate) fn heap_statistics() -> HeapStatistics {
+	HeapStatistics::default()
+}
+
+/// Returns the size of the physical memory in bytes
+pub(crate) fn total_memory_size() -> usize {
+	physicalmem::total_memory_size()
+}
+
+/// Returns the number of bytes, which are still available for allocations.
+/// As the heap is taken from the physical memory, its free space is included.
+pub(crate) fn available_memory_size() -> usize {
+	physicalmem::free_memory_size() + heap_statistics().available
+}
+
 /// Soft-deprecated in favor of `DeviceAlloc`
 pub(crate) fn allocate(size: usize, no_execution: bool) -> VirtAddr {
 	let size = size.align_up(BasePageSize::SIZE as usize);
@@ -284,7 +316,6 @@ pub(crate) fn deallocate(virtual_address: VirtAddr, size: usize) {
 }
 
 /// Maps a given physical address and size in virtual space and returns address.
-#[cfg(feature = "pci")]
 pub(crate) fn map(
 	physical_address: PhysAddr,
 	size: usize,
@@ -13,7 +13,7 @@ use core::ptr;
 use core::sync::atomic::AtomicBool;
 use core::sync::atomic::{AtomicI32, AtomicU32, Ordering};
 use core::task::Poll::Ready;
-use core::task::ready;
+use core::task::{Waker, ready};
 
 use ahash::RandomState;
 use crossbeam_utils::Backoff;
@@ -28,7 +28,7 @@ use crate::arch::switch::switch_to_task;
 #[cfg(target_arch = "x86_64")]
 use crate::arch::switch::{switch_to_fpu_owner, switch_to_task};
 use crate::arch::{get_processor_count, interrupts};
-use crate::fd::{FileDescriptor, ObjectInterface};
+use crate::fd::{Descriptor, FdFlags, FileDescriptor, ObjectInterface};
 use crate::kernel::scheduler::TaskStacks;
 use crate::scheduler::task::*;
 use crate::{arch, io};
@@ -215,8 +215,7 @@ struct NewTask {
 	prio: Priority,
 	core_id: CoreId,
 	stacks: TaskStacks,
-	object_map:
-		Arc<async_lock::RwLock<HashMap<FileDescriptor, Arc<dyn ObjectInterface>, RandomState>>>,
+	object_map: Arc<async_lock::RwLock<HashMap<FileDescriptor, Descriptor, RandomState>>>,
 }
 
 impl From<NewTask> for Task {
@@ -396,12 +395,27 @@ impl PerCoreScheduler {
 	pub fn handle_waiting_tasks(&mut self) {
 		without_interrupts(|| {
 			crate::executor::run();
-			for task in self.blocked_tasks.handle_waiting_tasks() {
+			let (tasks, timers) = self.blocked_tasks.handle_waiting_tasks();
+			for task in tasks {
 				self.ready_queue.push(task);
 			}
+			for waker in timers {
+				waker.wake();
+			}
 		});
 	}
 
+	/// Wakes `waker`, when the timer ticks reach `wakeup_time`, and returns
+	/// the key of the timer
+	pub fn add_timer(&mut self, wakeup_time: u64, waker: Waker) -> TimerKey {
+		without_interrupts(|| self.blocked_tasks.add_timer(wakeup_time, waker))
+	}
+
+	/// Removes the timer `key`, which was added on this core
+	pub fn remove_timer(&mut self, key: TimerKey) {
+		without_interrupts(|| self.blocked_tasks.remove_timer(key));
+	}
+
 	#[cfg(not(feature = "smp"))]
 	pub fn custom_wakeup(&mut self, task: TaskHandle) {
 		without_interrupts(|| {
@@ -457,7 +471,7 @@ impl PerCoreScheduler {
 	#[inline]
 	pub fn get_current_task_object_map(
 		&self,
-	) -> Arc<async_lock::RwLock<HashMap<FileDescriptor, Arc<dyn ObjectInterface>, RandomState>>> {
+	) -> Arc<async_lock::RwLock<HashMap<FileDescriptor, Descriptor, RandomState>>> {
 		without_interrupts(|| self.current_task.borrow().object_map.clone())
 	}
 
@@ -465,6 +479,14 @@ impl PerCoreScheduler {
 	/// the shared reference
 	#[inline]
 	pub async fn get_object(&self, fd: FileDescriptor) -> io::Result<Arc<dyn ObjectInterface>> {
+		self.get_descriptor(fd)
+			.await
+			.map(|descriptor| descriptor.object)
+	}
+
+	/// Map a file descriptor to their IO interface and returns
+	/// it together with the flags of the descriptor
+	pub async fn get_descriptor(&self, fd: FileDescriptor) -> io::Result<Descriptor> {
 		future::poll_fn(|cx| {
 			without_interrupts(|| {
 				let borrowed = self.current_task.borrow();
@@ -481,7 +503,7 @@ impl PerCoreScheduler {
 	/// clone the standard descriptors.
 	#[allow(dead_code)]
 	pub async fn recreate_objmap(&self) -> io::Result<()> {
-		let mut map = HashMap::<FileDescriptor, Arc<dyn ObjectInterface>, RandomState>::with_hasher(
+		let mut map = HashMap::<FileDescriptor, Descriptor, RandomState>::with_hasher(
 			RandomState::with_seeds(0, 0, 0, 0),
 		);
 
@@ -493,8 +515,8 @@ impl PerCoreScheduler {
 				let guard = ready!(pinned_obj.as_mut().poll(cx));
 				// clone standard file descriptors
 				for i in 0..3 {
-					if let Some(obj) = guard.get(&i) {
-						map.insert(i, obj.clone());
+					if let Some(descriptor) = guard.get(&i) {
+						map.insert(i, descriptor.clone());
 					}
 				}
 
@@ -510,9 +532,14 @@ impl PerCoreScheduler {
 		Ok(())
 	}
 
-	/// Insert a new IO interface and returns a file descriptor as
+	/// Insert a new IO interface and returns the lowest unused file
+	/// descriptor, which is greater than or equal to `min_fd`, as
 	/// identifier to this object
-	pub async fn insert_object(&self, obj: Arc<dyn ObjectInterface>) -> io::Result<FileDescriptor> {
+	pub async fn insert_object(
+		&self,
+		descriptor: Descriptor,
+		min_fd: FileDescriptor,
+	) -> io::Result<FileDescriptor> {
 		future::poll_fn(|cx| {
 			without_interrupts(|| {
 				let borrowed = self.current_task.borrow();
@@ -520,7 +547,7 @@ impl PerCoreScheduler {
 
 				let mut guard = ready!(pinned_obj.as_mut().poll(cx));
 				let new_fd = || -> io::Result<FileDescriptor> {
-					let mut fd: FileDescriptor = 0;
+					let mut fd: FileDescriptor = min_fd;
 					loop {
 						if !guard.contains_key(&fd) {
 							break Ok(fd);
@@ -533,65 +560,65 @@ impl PerCoreScheduler {
 				};
 
 				let fd = new_fd()?;
-				let _ = guard.insert(fd, obj.clone());
+				let _ = guard.insert(fd, descriptor.clone());
 				Ready(Ok(fd))
 			})
 		})
 		.await
 	}
 
-	/// Duplicate a IO interface and returns a new file descriptor as
-	/// identifier to the new copy
-	pub async fn dup_object(&self, fd: FileDescriptor) -> io::Result<FileDescriptor> {
+	/// Duplicate a IO interface and returns a new file descriptor, which is
+	/// greater than or equal to `min_fd`, as identifier to the new copy.
+	/// The new descriptor shares the status flags and gets the flags `flags`.
+	pub async fn dup_object(
+		&self,
+		fd: FileDescriptor,
+		min_fd: FileDescriptor,
+		flags: FdFlags,
+	) -> io::Result<FileDescriptor> {
+		let descriptor = Descriptor {
+			flags,
+			..self.get_descriptor(fd).await?
+		};
+
+		self.insert_object(descriptor, min_fd).await
+	}
+
+	pub async fn dup_object2(
+		&self,
+		fd1: FileDescriptor,
+		fd2: FileDescriptor,
+	) -> io::Result<FileDescriptor> {
 		future::poll_fn(|cx| {
 			without_interrupts(|| {
 				let borrowed = self.current_task.borrow();
 				let mut pinned_obj = core::pin::pin!(borrowed.object_map.write());
-
 				let mut guard = ready!(pinned_obj.as_mut().poll(cx));
-				let obj = (*(guard.get(&fd).ok_or(io::Error::EINVAL)?)).clone();
-
-				let new_fd = || -> io::Result<FileDescriptor> {
-					let mut fd: FileDescriptor = 0;
-					loop {
-						if !guard.contains_key(&fd) {
-							break Ok(fd);
-						} else if fd == FileDescriptor::MAX {
-							break Err(io::Error::EOVERFLOW);
-						}
-
-						fd = fd.saturating_add(1);
-					}
+				let descriptor = Descriptor {
+					flags: FdFlags::empty(),
+					..guard.get(&fd1).cloned().ok_or(io::Error::EBADF)?
 				};
 
-				let fd = new_fd()?;
-				if guard.try_insert(fd, obj).is_err() {
+				if guard.try_insert(fd2, descriptor).is_err() {
 					Ready(Err(io::Error::EMFILE))
 				} else {
-					Ready(Ok(fd))
+					Ready(Ok(fd2))
 				}
 			})
 		})
 		.await
 	}
 
-	pub async fn dup_object2(
-		&self,
-		fd1: FileDescriptor,
-		fd2: FileDescriptor,
-	) -> io::Result<FileDescriptor> {
+	/// Sets the flags of the file descriptor `fd`
+	pub async fn set_fd_flags(&self, fd: FileDescriptor, flags: FdFlags) -> io::Result<()> {
 		future::poll_fn(|cx| {
 			without_interrupts(|| {
 				let borrowed = self.current_task.borrow();
 				let mut pinned_obj = core::pin::pin!(borrowed.object_map.write());
 				let mut guard = ready!(pinned_obj.as_mut().poll(cx));
-				let obj = guard.get(&fd1).cloned().ok_or(io::Error::EBADF)?;
-
-				if guard.try_insert(fd2, obj).is_err() {
-					Ready(Err(io::Error::EMFILE))
-				} else {
-					Ready(Ok(fd2))
-				}
+				let descriptor = guard.get_mut(&fd).ok_or(io::Error::EBADF)?;
+				descriptor.flags = flags;
+				Ready(Ok(()))
 			})
 		})
 		.await
@@ -604,7 +631,12 @@ impl PerCoreScheduler {
 				let borrowed = self.current_task.borrow();
 				let mut pinned_obj = core::pin::pin!(borrowed.object_map.write());
 				let mut guard = ready!(pinned_obj.as_mut().poll(cx));
-				Ready(guard.remove(&fd).ok_or(io::Error::EBADF))
+				Ready(
+					guard
+						.remove(&fd)
+						.map(|descriptor| descriptor.object)
+						.ok_or(io::Error::EBADF),
+				)
 			})
 		})
 		.await
@@ -878,6 +910,11 @@ fn get_tid() -> TaskId {
 	}
 }
 
+/// Returns the number of tasks, which aren't finished yet
+pub(crate) fn number_of_tasks() -> u32 {
+	NO_TASKS.load(Ordering::SeqCst)
+}
+
 #[inline]
 pub(crate) fn abort() -> ! {
 	core_scheduler().exit(-1)
@@ -2,12 +2,13 @@
 
 #[cfg(not(feature = "common-os"))]
 use alloc::boxed::Box;
-use alloc::collections::{LinkedList, VecDeque};
+use alloc::collections::{BTreeMap, LinkedList, VecDeque};
 use alloc::rc::Rc;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::cell::RefCell;
 use core::num::NonZeroU64;
+use core::task::Waker;
 use core::{cmp, fmt};
 
 use ahash::RandomState;
@@ -22,7 +23,9 @@ use crate::arch::scheduler::TaskStacks;
 use crate::arch::scheduler::TaskTLS;
 use crate::executor::poll_on;
 use crate::fd::stdio::*;
-use crate::fd::{FileDescriptor, ObjectInterface, STDERR_FILENO, STDIN_FILENO, STDOUT_FILENO};
+use crate::fd::{
+	Descriptor, FileDescriptor, OpenOption, STDERR_FILENO, STDIN_FILENO, STDOUT_FILENO,
+};
 use crate::scheduler::CoreId;
 use crate::{arch, env, io};
 
@@ -395,8 +398,7 @@ pub(crate) struct Task {
 	/// Stack of the task
 	pub stacks: TaskStacks,
 	/// Mapping between file descriptor and the referenced IO interface
-	pub object_map:
-		Arc<async_lock::RwLock<HashMap<FileDescriptor, Arc<dyn ObjectInterface>, RandomState>>>,
+	pub object_map: Arc<async_lock::RwLock<HashMap<FileDescriptor, Descriptor, RandomState>>>,
 	/// Task Thread-Local-Storage (TLS)
 	#[cfg(not(feature = "common-os"))]
 	pub tls: Option<Box<TaskTLS>>,
@@ -417,9 +419,7 @@ impl Task {
 		task_status: TaskStatus,
 		task_prio: Priority,
 		stacks: TaskStacks,
-		object_map: Arc<
-			async_lock::RwLock<HashMap<FileDescriptor, Arc<dyn ObjectInterface>, RandomState>>,
-		>,
+		object_map: Arc<async_lock::RwLock<HashMap<FileDescriptor, Descriptor, RandomState>>>,
 	) -> Task {
 		debug!("Creating new task {} on core {}", tid, core_id);
 
@@ -446,14 +446,14 @@ impl Task {
 
 		/// All cores use the same mapping between file descriptor and the referenced object
 		static OBJECT_MAP: OnceCell<
-			Arc<async_lock::RwLock<HashMap<FileDescriptor, Arc<dyn ObjectInterface>, RandomState>>>,
+			Arc<async_lock::RwLock<HashMap<FileDescriptor, Descriptor, RandomState>>>,
 		> = OnceCell::new();
 
 		if core_id == 0 {
 			OBJECT_MAP
 				.set(Arc::new(async_lock::RwLock::new(HashMap::<
 					FileDescriptor,
-					Arc<dyn ObjectInterface>,
+					Descriptor,
 					RandomState,
 				>::with_hasher(
 					RandomState::with_seeds(0, 0, 0, 0),
@@ -464,23 +464,41 @@ impl Task {
 				let mut guard = objmap.write().await;
 				if env::is_uhyve() {
 					guard
-						.try_insert(STDIN_FILENO, Arc::new(UhyveStdin::new()))
+						.try_insert(
+							STDIN_FILENO,
+							Descriptor::new(Arc::new(UhyveStdin::new()), OpenOption::O_RDWR),
+						)
 						.map_err(|_| io::Error::EIO)?;
 					guard
-						.try_insert(STDOUT_FILENO, Arc::new(UhyveStdout::new()))
+						.try_insert(
+							STDOUT_FILENO,
+							Descriptor::new(Arc::new(UhyveStdout::new()), OpenOption::O_RDWR),
+						)
 						.map_err(|_| io::Error::EIO)?;
 					guard
-						.try_insert(STDERR_FILENO, Arc::new(UhyveStderr::new()))
+						.try_insert(
+							STDERR_FILENO,
+							Descriptor::new(Arc::new(UhyveStderr::new()), OpenOption::O_RDWR),
+						)
 						.map_err(|_| io::Error::EIO)?;
 				} else {
 					guard
-						.try_insert(STDIN_FILENO, Arc::new(GenericStdin::new()))
+						.try_insert(
+							STDIN_FILENO,
+							Descriptor::new(Arc::new(GenericStdin::new()), OpenOption::O_RDWR),
+						)
 						.map_err(|_| io::Error::EIO)?;
 					guard
-						.try_insert(STDOUT_FILENO, Arc::new(GenericStdout::new()))
+						.try_insert(
+							STDOUT_FILENO,
+							Descriptor::new(Arc::new(GenericStdout::new()), OpenOption::O_RDWR),
+						)
 						.map_err(|_| io::Error::EIO)?;
 					guard
-						.try_insert(STDERR_FILENO, Arc::new(GenericStderr::new()))
+						.try_insert(
+							STDERR_FILENO,
+							Descriptor::new(Arc::new(GenericStderr::new()), OpenOption::O_RDWR),
+						)
 						.map_err(|_| io::Error::EIO)?;
 				}
 
@@ -524,8 +542,25 @@ impl BlockedTask {
 	}
 }
 
+/// Wakeup time and ID of a timer, which identifies it in `remove_timer`
+pub(crate) type TimerKey = (u64, u64);
+
+/// Returns the earlier of `wakeup_time` and the wakeup time of the first timer
+fn earliest(wakeup_time: Option<u64>, timers: &BTreeMap<TimerKey, Waker>) -> Option<u64> {
+	let timer = timers.first_key_value().map(|(&(time, _), _)| time);
+
+	match (wakeup_time, timer) {
+		(Some(a), Some(b)) => Some(a.min(b)),
+		(a, b) => a.or(b),
+	}
+}
+
 pub(crate) struct BlockedTaskQueue {
 	list: LinkedList<BlockedTask>,
+	/// Wakers of timers, sorted by their wakeup time and by their ID
+	timers: BTreeMap<TimerKey, Waker>,
+	/// ID of the next timer
+	next_timer_id: u64,
 	#[cfg(any(feature = "tcp", feature = "udp"))]
 	network_wakeup_time: Option<u64>,
 }
@@ -534,6 +569,8 @@ impl BlockedTaskQueue {
 	pub const fn new() -> Self {
 		Self {
 			list: LinkedList::new(),
+			timers: BTreeMap::new(),
+			next_timer_id: 0,
 			#[cfg(any(feature = "tcp", feature = "udp"))]
 			network_wakeup_time: None,
 		}
@@ -573,7 +610,36 @@ impl BlockedTaskQueue {
 			(a, b) => a.or(b),
 		};
 
-		arch::set_oneshot_timer(time);
+		arch::set_oneshot_timer(earliest(time, &self.timers));
+	}
+
+	/// Wakes `waker`, when the timer ticks reach `wakeup_time`, and returns
+	/// the key of the timer.
+	pub fn add_timer(&mut self, wakeup_time: u64, waker: Waker) -> TimerKey {
+		let key = (wakeup_time, self.next_timer_id);
+		self.next_timer_id = self.next_timer_id.wrapping_add(1);
+		self.timers.insert(key, waker);
+
+		#[cfg(any(feature = "tcp", feature = "udp"))]
+		let next = match (
+			self.list.front().and_then(|t| t.wakeup_time),
+			self.network_wakeup_time,
+		) {
+			(Some(a), Some(b)) => Some(a.min(b)),
+			(a, b) => a.or(b),
+		};
+		#[cfg(not(any(feature = "tcp", feature = "udp")))]
+		let next = self.list.front().and_then(|t| t.wakeup_time);
+
+		arch::set_oneshot_timer(earliest(next, &self.timers));
+
+		key
+	}
+
+	/// Removes the timer `key`, if it hasn't expired yet. The one-shot timer
+	/// isn't reprogrammed, an early interrupt only finds no elapsed timer.
+	pub fn remove_timer(&mut self, key: TimerKey) {
+		self.timers.remove(&key);
 	}
 
 	/// Blocks the given task for `wakeup_time` ticks, or indefinitely if None is given.
@@ -599,17 +665,20 @@ impl BlockedTaskQueue {
 			let mut cursor = self.list.cursor_front_mut();
 			let set_oneshot_timer = || {
 				#[cfg(not(any(feature = "tcp", feature = "udp")))]
-				arch::set_oneshot_timer(wakeup_time);
+				arch::set_oneshot_timer(earliest(wakeup_time, &self.timers));
 				#[cfg(any(feature = "tcp", feature = "udp"))]
 				match self.network_wakeup_time {
 					Some(time) => {
 						if time > wt {
-							arch::set_oneshot_timer(wakeup_time);
+							arch::set_oneshot_timer(earliest(wakeup_time, &self.timers));
 						} else {
-							arch::set_oneshot_timer(self.network_wakeup_time);
+							arch::set_oneshot_timer(earliest(
+								self.network_wakeup_time,
+								&self.timers,
+							));
 						}
 					}
-					_ => arch::set_oneshot_timer(wakeup_time),
+					_ => arch::set_oneshot_timer(earliest(wakeup_time, &self.timers)),
 				}
 			};
 
@@ -654,27 +723,31 @@ impl BlockedTaskQueue {
 				// next task's wakeup time (if any).
 				#[cfg(any(feature = "tcp", feature = "udp"))]
 				if first_task {
-					arch::set_oneshot_timer(cursor.current().map_or_else(
-						|| self.network_wakeup_time,
-						|node| match node.wakeup_time {
-							Some(wt) => {
-								if let Some(timer) = self.network_wakeup_time {
-									if wt < timer { Some(wt) } else { Some(timer) }
-								} else {
-									Some(wt)
+					arch::set_oneshot_timer(earliest(
+						cursor.current().map_or_else(
+							|| self.network_wakeup_time,
+							|node| match node.wakeup_time {
+								Some(wt) => {
+									if let Some(timer) = self.network_wakeup_time {
+										if wt < timer { Some(wt) } else { Some(timer) }
+									} else {
+										Some(wt)
+									}
 								}
-							}
-							None => self.network_wakeup_time,
-						},
+								None => self.network_wakeup_time,
+							},
+						),
+						&self.timers,
 					));
 				}
 				#[cfg(not(any(feature = "tcp", feature = "udp")))]
 				if first_task {
-					arch::set_oneshot_timer(
+					arch::set_oneshot_timer(earliest(
 						cursor
 							.current()
 							.map_or_else(|| None, |node| node.wakeup_time),
-					);
+						&self.timers,
+					));
 				}
 
 				// Wake it up.
@@ -690,11 +763,12 @@ impl BlockedTaskQueue {
 		unreachable!();
 	}
 
-	/// Wakes up all tasks whose wakeup time has elapsed.
+	/// Wakes up all tasks whose wakeup time has elapsed and returns them
+	/// together with the wakers of the elapsed timers.
 	///
 	/// Should be called by the One-Shot Timer interrupt handler when the wakeup time for
 	/// at least one task has elapsed.
-	pub fn handle_waiting_tasks(&mut self) -> Vec<Rc<RefCell<Task>>> {
+	pub fn handle_waiting_tasks(&mut self) -> (Vec<Rc<RefCell<Task>>>, Vec<Waker>) {
 		// Get the current time.
 		let time = arch::processor::get_timer_ticks();
 
@@ -709,6 +783,16 @@ impl BlockedTaskQueue {
 
 		let mut tasks = vec![];
 
+		// Remove all elapsed timers.
+		let mut wakers = Vec::new();
+		while let Some(entry) = self.timers.first_entry() {
+			if time < entry.key().0 {
+				break;
+			}
+
+			wakers.push(entry.remove());
+		}
+
 		// Loop through all blocked tasks.
 		let mut cursor = self.list.cursor_front_mut();
 		while let Some(node) = cursor.current() {
@@ -725,30 +809,34 @@ impl BlockedTaskQueue {
 		}
 
 		#[cfg(any(feature = "tcp", feature = "udp"))]
-		arch::set_oneshot_timer(cursor.current().map_or_else(
-			|| self.network_wakeup_time,
-			|node| match node.wakeup_time {
-				Some(wt) => {
-					if let Some(timer) = self.network_wakeup_time {
-						if wt < timer { Some(wt) } else { Some(timer) }
-					} else {
-						Some(wt)
+		arch::set_oneshot_timer(earliest(
+			cursor.current().map_or_else(
+				|| self.network_wakeup_time,
+				|node| match node.wakeup_time {
+					Some(wt) => {
+						if let Some(timer) = self.network_wakeup_time {
+							if wt < timer { Some(wt) } else { Some(timer) }
+						} else {
+							Some(wt)
+						}
 					}
-				}
-				None => self.network_wakeup_time,
-			},
+					None => self.network_wakeup_time,
+				},
+			),
+			&self.timers,
 		));
 		#[cfg(not(any(feature = "tcp", feature = "udp")))]
-		arch::set_oneshot_timer(
+		arch::set_oneshot_timer(earliest(
 			cursor
 				.current()
 				.map_or_else(|| None, |node| node.wakeup_time),
-		);
+			&self.timers,
+		));
 
 		for task in tasks.iter().cloned() {
 			Self::wakeup_task(task);
 		}
 
-		tasks
+		(tasks, wakers)
 	}
 }
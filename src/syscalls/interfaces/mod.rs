@@ -16,7 +16,7 @@ pub trait SyscallInterface: Send + Sync {
 	fn get_application_parameters(&self) -> (i32, *const *const u8, *const *const u8) {
 		let mut argv = Vec::new();
 
-		let name = Box::leak(Box::new("bin\0")).as_ptr();
+		let name = Box::leak(format!("{}\0", env::application_name()).into_boxed_str()).as_ptr();
 		argv.push(name);
 
 		let args = env::args();
@@ -1,11 +1,32 @@
+//! Anonymous and file-backed memory mappings.
+//!
+//! The pages of a file mapping are loaded by the page fault handler on the
+//! first access. All mappings of a file share one set of pages, so that
+//! changes of shared mappings are visible in the other mappings. Pages of
+//! read-only files, whose content is page-aligned in memory, and pages of the
+//! DAX window of virtio-fs devices are mapped without copy. Private mappings
+//! receive a copy of a page on the first write. Shared mappings mark the pages
+//! as dirty on the first write, which are written back to the file by `msync`
+//! and `munmap`.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::mem::{self, MaybeUninit};
+use core::slice;
+
 use align_address::Align;
-use memory_addresses::VirtAddr;
+use hermit_sync::TicketMutex;
+use memory_addresses::{PhysAddr, VirtAddr};
 
-use crate::arch;
 #[cfg(target_arch = "x86_64")]
 use crate::arch::mm::paging::PageTableEntryFlagsExt;
 use crate::arch::mm::paging::{BasePageSize, PageSize, PageTableEntryFlags};
-
+use crate::executor::{block_on, poll_on};
+use crate::fd::{self, FileDescriptor, MmapSource, ObjectInterface};
+use crate::fs::FileAttr;
+use crate::fs::notify::{self, InotifyMask};
+use crate::{arch, io};
 bitflags! {
 	#[repr(transparent)]
 	#[derive(Debug, Copy, Clone, Default)]
@@ -21,6 +42,593 @@ bitflags! {
 	}
 }
 
+bitflags! {
+	/// Flags of `sys_mmap_file`
+	#[derive(Debug, Copy, Clone, Default)]
+	pub struct MapFlags: i32 {
+		/// Changes are written back to the file
+		const MAP_SHARED = 0x01;
+		/// Changes are private to the mapping
+		const MAP_PRIVATE = 0x02;
+	}
+}
+
+bitflags! {
+	/// Flags of `sys_msync`
+	#[derive(Debug, Copy, Clone, Default)]
+	pub struct MsyncFlags: i32 {
+		const MS_ASYNC = 1;
+		const MS_INVALIDATE = 2;
+		const MS_SYNC = 4;
+	}
+}
+
+const PAGE_SIZE: usize = BasePageSize::SIZE as usize;
+
+/// Physical page of a file
+#[derive(Debug, Copy, Clone)]
+struct Frame {
+	addr: PhysAddr,
+	/// Is set, if the page belongs to the DAX window of the device
+	window: bool,
+}
+
+/// Pages of a file, which are shared by all mappings of the file
+#[derive(Debug)]
+struct FilePages {
+	/// Object, from which the pages are loaded
+	obj: Arc<dyn ObjectInterface>,
+	source: MmapSource,
+	/// Loaded pages indexed by their number in the file. Pages of static
+	/// files are mapped directly and aren't stored.
+	frames: BTreeMap<usize, Frame>,
+	/// Pages, which were changed through shared mappings since they were
+	/// written back
+	dirty: BTreeSet<usize>,
+}
+
+impl Drop for FilePages {
+	fn drop(&mut self) {
+		for (index, frame) in mem::take(&mut self.frames) {
+			release_frame(&self.obj, index, frame);
+		}
+	}
+}
+
+type SharedPages = Arc<TicketMutex<FilePages>>;
+
+/// Device and inode of a file
+type FileKey = (u64, u64);
+
+/// Page sets of the mapped files
+static FILE_PAGES: TicketMutex<BTreeMap<FileKey, Weak<TicketMutex<FilePages>>>> =
+	TicketMutex::new(BTreeMap::new());
+
+/// Mapping of a file, which was created by `sys_mmap_file`
+#[derive(Debug, Clone)]
+struct FileMapping {
+	obj: Arc<dyn ObjectInterface>,
+	pages: SharedPages,
+	/// Offset of the first page in the file
+	offset: usize,
+	/// Length in bytes, a multiple of the page size
+	len: usize,
+	shared: bool,
+	prot: MemoryProtection,
+	/// Addresses of the pages of a private mapping, which were copied on write
+	copies: Arc<TicketMutex<BTreeSet<usize>>>,
+}
+
+impl FileMapping {
+	/// Returns the part of the mapping at `start`, which covers `from..to`
+	fn slice(&self, start: usize, from: usize, to: usize) -> Self {
+		Self {
+			offset: self.offset + (from - start),
+			len: to - from,
+			..self.clone()
+		}
+	}
+
+	/// Returns the number of the page at `addr` in the file
+	fn page_index(&self, start: usize, addr: usize) -> usize {
+		(self.offset + (addr - start)) / PAGE_SIZE
+	}
+}
+
+/// File mappings indexed by their start address
+static FILE_MAPPINGS: TicketMutex<BTreeMap<usize, FileMapping>> = TicketMutex::new(BTreeMap::new());
+
+/// Removes the file mappings, which overlap `start..end`, from the table.
+/// The lock of the table isn't held during the I/O of the callers.
+fn take_mappings(start: usize, end: usize) -> Vec<(usize, FileMapping)> {
+	let mut mappings = FILE_MAPPINGS.lock();
+	let overlapping: Vec<usize> = mappings
+		.range(..end)
+		.filter(|(addr, mapping)| **addr + mapping.len > start)
+		.map(|(addr, _)| *addr)
+		.collect();
+
+	overlapping
+		.into_iter()
+		.map(|addr| (addr, mappings.remove(&addr).unwrap()))
+		.collect()
+}
+
+/// Returns the file mappings, which overlap `start..end`
+fn clone_mappings(start: usize, end: usize) -> Vec<(usize, FileMapping)> {
+	FILE_MAPPINGS
+		.lock()
+		.range(..end)
+		.filter(|(addr, mapping)| **addr + mapping.len > start)
+		.map(|(addr, mapping)| (*addr, mapping.clone()))
+		.collect()
+}
+
+/// Returns the page table flags of a page with the protection `prot`. The
+/// page is only writable, if `writable` is set and `prot` permits writes.
+fn page_table_flags(prot: MemoryProtection, writable: bool) -> PageTableEntryFlags {
+	let mut flags = PageTableEntryFlags::empty();
+	flags.normal();
+	if writable && prot.contains(MemoryProtection::Write) {
+		flags.writable();
+	}
+	if !prot.contains(MemoryProtection::Exec) {
+		flags.execute_disable();
+	}
+	flags
+}
+
+/// Maps `frame` temporarily and calls `f` with its content
+fn with_frame<T>(frame: PhysAddr, f: impl FnOnce(&mut [MaybeUninit<u8>]) -> T) -> io::Result<T> {
+	let tmp = arch::mm::virtualmem::allocate(PAGE_SIZE).map_err(|_| io::Error::ENOMEM)?;
+	arch::mm::paging::map::<BasePageSize>(
+		tmp,
+		frame,
+		1,
+		page_table_flags(MemoryProtection::Read | MemoryProtection::Write, true),
+	);
+	let result = f(unsafe { slice::from_raw_parts_mut(tmp.as_mut_ptr(), PAGE_SIZE) });
+	arch::mm::paging::unmap::<BasePageSize>(tmp, 1);
+	arch::mm::virtualmem::deallocate(tmp, PAGE_SIZE);
+
+	Ok(result)
+}
+
+/// Fills `buf` with the file content at `offset`. Bytes beyond the end of
+/// the file are zeroed.
+async fn read_pages(
+	obj: &Arc<dyn ObjectInterface>,
+	offset: usize,
+	buf: &mut [MaybeUninit<u8>],
+) -> io::Result<()> {
+	let mut pos = 0;
+	while pos < buf.len() {
+		let len = obj.pread(&mut buf[pos..], offset + pos).await?;
+		if len == 0 {
+			break;
+		}
+		pos += len;
+	}
+	buf[pos..].fill(MaybeUninit::new(0));
+
+	Ok(())
+}
+
+/// Releases a page, which was loaded by `load_page`
+fn release_frame(obj: &Arc<dyn ObjectInterface>, index: usize, frame: Frame) {
+	if frame.window {
+		// the page fault handler may release the last reference of a page set
+		let _ = poll_on(async {
+			obj.unmap_window(index * PAGE_SIZE).await;
+			Ok(())
+		});
+	} else {
+		arch::mm::physicalmem::deallocate(frame.addr, PAGE_SIZE);
+	}
+}
+
+/// Returns the page `index` of the page set and loads it, if necessary.
+/// The lock of the page set isn't held during the I/O.
+fn load_page(pages: &SharedPages, index: usize) -> io::Result<Frame> {
+	let (obj, source) = {
+		let guard = pages.lock();
+		if let Some(frame) = guard.frames.get(&index) {
+			return Ok(*frame);
+		}
+		(guard.obj.clone(), guard.source)
+	};
+	let offset = index * PAGE_SIZE;
+
+	let window = match source {
+		MmapSource::Static(data) if offset + PAGE_SIZE <= data.len() => {
+			let addr = VirtAddr::from_ptr(data[offset..].as_ptr());
+			return Ok(Frame {
+				addr: arch::mm::paging::virtual_to_physical(addr).unwrap(),
+				window: false,
+			});
+		}
+		// if the window cannot be used, the page is copied
+		MmapSource::Window => poll_on(obj.map_window(offset)).ok(),
+		_ => None,
+	};
+	let frame = if let Some(addr) = window {
+		Frame {
+			addr: arch::mm::paging::virtual_to_physical(addr).unwrap(),
+			window: true,
+		}
+	} else {
+		let addr = arch::mm::physicalmem::allocate(PAGE_SIZE).map_err(|_| io::Error::ENOMEM)?;
+		let result = with_frame(addr, |buf| poll_on(read_pages(&obj, offset, buf)));
+		if let Err(err) = result.and_then(|result| result) {
+			arch::mm::physicalmem::deallocate(addr, PAGE_SIZE);
+			return Err(err);
+		}
+		Frame {
+			addr,
+			window: false,
+		}
+	};
+
+	let mut guard = pages.lock();
+	if let Some(existing) = guard.frames.get(&index).copied() {
+		// another task loaded the page in the meantime
+		drop(guard);
+		release_frame(&obj, index, frame);
+		return Ok(existing);
+	}
+	guard.frames.insert(index, frame);
+
+	Ok(frame)
+}
+
+/// Maps the page at `page` of the mapping at `start` after a page fault
+fn map_page(start: usize, mapping: &FileMapping, page: usize, write: bool) -> io::Result<()> {
+	let index = mapping.page_index(start, page);
+	let frame = load_page(&mapping.pages, index)?;
+	let virtual_address = VirtAddr::new(page as u64);
+
+	if write && !mapping.shared {
+		let copy = arch::mm::physicalmem::allocate(PAGE_SIZE).map_err(|_| io::Error::ENOMEM)?;
+		let result = with_frame(copy, |dst| {
+			with_frame(frame.addr, |src| dst.copy_from_slice(src))
+		});
+		if let Err(err) = result.and_then(|result| result) {
+			arch::mm::physicalmem::deallocate(copy, PAGE_SIZE);
+			return Err(err);
+		}
+
+		mapping.copies.lock().insert(page);
+		arch::mm::paging::map::<BasePageSize>(
+			virtual_address,
+			copy,
+			1,
+			page_table_flags(mapping.prot, true),
+		);
+		return Ok(());
+	}
+
+	// The lock is held during the mapping, so that `write_back` cannot miss
+	// the changes of a page, which is marked as dirty.
+	let mut pages = mapping.pages.lock();
+	let writable = if !mapping.shared {
+		false
+	} else if frame.window {
+		// changes are written directly to the file
+		true
+	} else {
+		if write {
+			pages.dirty.insert(index);
+		}
+		pages.dirty.contains(&index)
+	};
+	arch::mm::paging::map::<BasePageSize>(
+		virtual_address,
+		frame.addr,
+		1,
+		page_table_flags(mapping.prot, writable),
+	);
+	drop(pages);
+
+	Ok(())
+}
+
+/// Resolves a page fault at `addr` by loading the page of a file mapping.
+/// Returns `false`, if the address doesn't belong to a file mapping or the
+/// access isn't permitted.
+///
+/// The fault is handled on the stack of the exception handler. Therefore, the
+/// pages are loaded with `poll_on`, which doesn't switch to another task.
+/// Buffers of system calls are loaded by [`prefault`] in advance, because the
+/// handler would wait forever for an object, which the system call locked.
+pub(crate) fn handle_page_fault(addr: usize, write: bool) -> bool {
+	let Some((start, mapping)) = FILE_MAPPINGS
+		.lock()
+		.range(..=addr)
+		.next_back()
+		.filter(|(start, mapping)| addr < **start + mapping.len)
+		.map(|(start, mapping)| (*start, mapping.clone()))
+	else {
+		return false;
+	};
+
+	let access = if write {
+		MemoryProtection::Write
+	} else {
+		MemoryProtection::Read
+	};
+	if !mapping.prot.contains(access) {
+		return false;
+	}
+
+	let page = addr.align_down(PAGE_SIZE);
+	match map_page(start, &mapping, page, write) {
+		Ok(()) => true,
+		Err(err) => {
+			error!("Unable to load the page {page:#x} of a file mapping: {err:?}");
+			false
+		}
+	}
+}
+
+/// Loads the pages of file mappings in `addr..addr + len`, before a system
+/// call uses them as buffer. Afterwards, a page fault in the buffer only maps
+/// the loaded page and doesn't access the file. Fails with `EFAULT`, if a page
+/// cannot be loaded.
+pub(crate) fn prefault(addr: usize, len: usize) -> io::Result<()> {
+	if len == 0 {
+		return Ok(());
+	}
+	let end = addr.checked_add(len).ok_or(io::Error::EFAULT)?;
+
+	for (start, mapping) in clone_mappings(addr, end) {
+		let from = addr.max(start).align_down(PAGE_SIZE);
+		let to = end.min(start + mapping.len);
+		for page in (from..to).step_by(PAGE_SIZE) {
+			load_page(&mapping.pages, mapping.page_index(start, page))
+				.map_err(|_| io::Error::EFAULT)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Maps the pages `dirty` of the page set read-only in all shared mappings,
+/// so that the next write marks them as dirty again
+fn write_protect(pages: &SharedPages, dirty: &[(usize, PhysAddr)]) {
+	let mappings = FILE_MAPPINGS.lock();
+	for (addr, mapping) in mappings.iter() {
+		if !mapping.shared || !Arc::ptr_eq(&mapping.pages, pages) {
+			continue;
+		}
+
+		for (index, frame) in dirty {
+			let Some(offset) = (index * PAGE_SIZE)
+				.checked_sub(mapping.offset)
+				.filter(|offset| *offset < mapping.len)
+			else {
+				continue;
+			};
+			let virtual_address = VirtAddr::new((addr + offset) as u64);
+			if arch::mm::paging::virtual_to_physical(virtual_address) == Some(*frame) {
+				arch::mm::paging::map::<BasePageSize>(
+					virtual_address,
+					*frame,
+					1,
+					page_table_flags(mapping.prot, false),
+				);
+			}
+		}
+	}
+}
+
+/// Writes the dirty pages of a shared mapping at `start` in the range
+/// `from..to` back to the file. The file isn't extended.
+async fn write_back(start: usize, mapping: &FileMapping, from: usize, to: usize) -> io::Result<()> {
+	if !mapping.shared || from >= to {
+		return Ok(());
+	}
+
+	let first = mapping.page_index(start, from);
+	let last = mapping.page_index(start, to);
+	let dirty: Vec<(usize, PhysAddr)> = {
+		let mut pages = mapping.pages.lock();
+		let indices: Vec<usize> = pages.dirty.range(first..last).copied().collect();
+		indices
+			.into_iter()
+			.map(|index| {
+				pages.dirty.remove(&index);
+				(index, pages.frames[&index].addr)
+			})
+			.collect()
+	};
+	if dirty.is_empty() {
+		return Ok(());
+	}
+	write_protect(&mapping.pages, &dirty);
+
+	let mut written = 0;
+	let result = async {
+		let size: usize = mapping.obj.fstat().await?.st_size.try_into().unwrap();
+		let mut page = vec![MaybeUninit::new(0u8); PAGE_SIZE];
+
+		for (index, frame) in &dirty {
+			let offset = index * PAGE_SIZE;
+			if offset < size {
+				let len = PAGE_SIZE.min(size - offset);
+				with_frame(*frame, |data| page[..len].copy_from_slice(&data[..len]))?;
+				let data = unsafe { page[..len].assume_init_ref() };
+
+				let mut pos = 0;
+				while pos < len {
+					pos += mapping.obj.pwrite(&data[pos..], offset + pos).await?;
+				}
+			}
+			written += 1;
+		}
+
+		Ok(())
+	}
+	.await;
+
+	if written > 0 {
+		notify::file_changed(&mapping.obj, InotifyMask::IN_MODIFY);
+	}
+	if result.is_err() {
+		// the pages are written again by the next `msync` or `munmap`
+		mapping
+			.pages
+			.lock()
+			.dirty
+			.extend(dirty[written..].iter().map(|(index, _)| *index));
+	}
+
+	result
+}
+
+/// Removes the pages `from..to` of a mapping and releases the copied pages
+fn unmap_pages(mapping: &FileMapping, from: usize, to: usize) {
+	if from >= to {
+		return;
+	}
+
+	let mut copies = mapping.copies.lock();
+	for addr in (from..to).step_by(PAGE_SIZE) {
+		let virtual_address = VirtAddr::new(addr as u64);
+		let Some(physical_address) = arch::mm::paging::virtual_to_physical(virtual_address) else {
+			continue;
+		};
+		arch::mm::paging::unmap::<BasePageSize>(virtual_address, 1);
+		if copies.remove(&addr) {
+			arch::mm::physicalmem::deallocate(physical_address, PAGE_SIZE);
+		}
+	}
+	arch::mm::virtualmem::deallocate(VirtAddr::new(from as u64), to - from);
+}
+
+/// Returns the page set of the file `attr` and creates it, if the file
+/// isn't mapped yet
+fn file_pages(obj: &Arc<dyn ObjectInterface>, source: MmapSource, attr: &FileAttr) -> SharedPages {
+	let key = (attr.st_dev, attr.st_ino);
+	let mut files = FILE_PAGES.lock();
+	if let Some(pages) = files.get(&key).and_then(Weak::upgrade) {
+		return pages;
+	}
+
+	files.retain(|_, pages| pages.strong_count() > 0);
+	let pages = Arc::new(TicketMutex::new(FilePages {
+		obj: obj.clone(),
+		source,
+		frames: BTreeMap::new(),
+		dirty: BTreeSet::new(),
+	}));
+	files.insert(key, Arc::downgrade(&pages));
+	pages
+}
+
+fn mmap_file(
+	size: usize,
+	prot: MemoryProtection,
+	flags: MapFlags,
+	fd: FileDescriptor,
+	offset: isize,
+) -> io::Result<VirtAddr> {
+	let shared = match (
+		flags.contains(MapFlags::MAP_SHARED),
+		flags.contains(MapFlags::MAP_PRIVATE),
+	) {
+		(true, false) => true,
+		(false, true) => false,
+		_ => return Err(io::Error::EINVAL),
+	};
+	let offset = usize::try_from(offset).map_err(|_| io::Error::EINVAL)?;
+	if size == 0 || offset % PAGE_SIZE != 0 {
+		return Err(io::Error::EINVAL);
+	}
+	let len = size.align_up(PAGE_SIZE);
+
+	let obj = fd::get_object(fd)?;
+	let source = block_on(obj.mmap(), None)?;
+	// a read-only file cannot be changed through a shared mapping
+	if shared && prot.contains(MemoryProtection::Write) && matches!(source, MmapSource::Static(_)) {
+		return Err(io::Error::EACCES);
+	}
+	let attr = block_on(obj.fstat(), None)?;
+	let pages = file_pages(&obj, source, &attr);
+
+	// the pages are mapped by the page fault handler
+	let virtual_address = arch::mm::virtualmem::allocate(len).map_err(|_| io::Error::ENOMEM)?;
+	FILE_MAPPINGS.lock().insert(
+		virtual_address.as_usize(),
+		FileMapping {
+			obj,
+			pages,
+			offset,
+			len,
+			shared,
+			prot,
+			copies: Arc::new(TicketMutex::new(BTreeSet::new())),
+		},
+	);
+
+	Ok(virtual_address)
+}
+
+/// Changes the protection of the file mappings in `start..end`. Returns
+/// `false`, if the range doesn't belong to a file mapping.
+fn mprotect_file(start: usize, end: usize, prot: MemoryProtection) -> io::Result<bool> {
+	let mut mappings = FILE_MAPPINGS.lock();
+	for (addr, mapping) in mappings.range(..end) {
+		if *addr + mapping.len > start
+			&& mapping.shared
+			&& prot.contains(MemoryProtection::Write)
+			&& matches!(mapping.pages.lock().source, MmapSource::Static(_))
+		{
+			return Err(io::Error::EACCES);
+		}
+	}
+
+	let mut found = false;
+	for (addr, mapping) in mappings.range_mut(..end) {
+		let mapping_end = *addr + mapping.len;
+		if mapping_end <= start {
+			continue;
+		}
+		found = true;
+		mapping.prot = prot;
+
+		let copies = mapping.copies.lock();
+		let pages = mapping.pages.lock();
+		for page in (start.max(*addr)..end.min(mapping_end)).step_by(PAGE_SIZE) {
+			let virtual_address = VirtAddr::new(page as u64);
+			let Some(physical_address) = arch::mm::paging::virtual_to_physical(virtual_address)
+			else {
+				continue;
+			};
+
+			let copied = copies.contains(&page);
+			if !copied && !prot.contains(MemoryProtection::Read) {
+				// the page is loaded again, if the access is permitted later
+				arch::mm::paging::unmap::<BasePageSize>(virtual_address, 1);
+				continue;
+			}
+
+			let index = mapping.page_index(*addr, page);
+			let writable = copied
+				|| (mapping.shared
+					&& (pages.dirty.contains(&index)
+						|| pages.frames.get(&index).is_some_and(|frame| frame.window)));
+			arch::mm::paging::map::<BasePageSize>(
+				virtual_address,
+				physical_address,
+				1,
+				page_table_flags(prot, writable),
+			);
+		}
+	}
+
+	Ok(found)
+}
+
 /// Creates a new virtual memory mapping of the `size` specified with
 /// protection bits specified in `prot_flags`.
 #[hermit_macro::system]
@@ -58,6 +666,29 @@ pub extern "C" fn sys_munmap(ptr: *mut u8, size: usize) -> i32 {
 	let virtual_address = VirtAddr::from_ptr(ptr);
 	let size = size.align_up(BasePageSize::SIZE as usize);
 
+	let start = virtual_address.as_usize();
+	let mappings = take_mappings(start, start + size);
+	if !mappings.is_empty() {
+		for (addr, mapping) in mappings {
+			let from = start.max(addr);
+			let to = (start + size).min(addr + mapping.len);
+			if let Err(err) = block_on(write_back(addr, &mapping, from, to), None) {
+				warn!("Unable to write back the mapping at {addr:#x}: {err:?}");
+			}
+			unmap_pages(&mapping, from, to);
+
+			let mut remaining = FILE_MAPPINGS.lock();
+			if addr < from {
+				remaining.insert(addr, mapping.slice(addr, addr, from));
+			}
+			if to < addr + mapping.len {
+				remaining.insert(to, mapping.slice(addr, to, addr + mapping.len));
+			}
+		}
+
+		return 0;
+	}
+
 	if let Some(phys_addr) = arch::mm::paging::virtual_to_physical(virtual_address) {
 		arch::mm::paging::unmap::<BasePageSize>(
 			virtual_address,
@@ -78,6 +709,13 @@ pub extern "C" fn sys_munmap(ptr: *mut u8, size: usize) -> i32 {
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
 pub extern "C" fn sys_mprotect(ptr: *mut u8, size: usize, prot_flags: MemoryProtection) -> i32 {
+	let start = ptr.addr();
+	match mprotect_file(start, start + size.align_up(PAGE_SIZE), prot_flags) {
+		Ok(true) => return 0,
+		Ok(false) => {}
+		Err(err) => return -num::ToPrimitive::to_i32(&err).unwrap(),
+	}
+
 	let count = size / BasePageSize::SIZE as usize;
 	let mut flags = PageTableEntryFlags::empty();
 	flags.normal().writable();
@@ -99,3 +737,56 @@ pub extern "C" fn sys_mprotect(ptr: *mut u8, size: usize, prot_flags: MemoryProt
 		0
 	}
 }
+
+/// Maps `size` bytes of the file `fd`, starting at the page-aligned `offset`,
+/// with the protection bits `prot_flags`. `flags` has to contain either
+/// `MAP_SHARED` or `MAP_PRIVATE`.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_mmap_file(
+	size: usize,
+	prot_flags: MemoryProtection,
+	flags: i32,
+	fd: FileDescriptor,
+	offset: isize,
+	ret: &mut *mut u8,
+) -> i32 {
+	let flags = MapFlags::from_bits_truncate(flags);
+
+	match mmap_file(size, prot_flags, flags, fd, offset) {
+		Ok(virtual_address) => {
+			*ret = virtual_address.as_mut_ptr();
+			0
+		}
+		Err(err) => -num::ToPrimitive::to_i32(&err).unwrap(),
+	}
+}
+
+/// Writes the changes of shared file mappings in the range of `size` bytes
+/// at `ptr` back to the files. With `MS_SYNC`, the files are synchronized
+/// with the storage as well.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_msync(ptr: *mut u8, size: usize, flags: i32) -> i32 {
+	let Some(flags) = MsyncFlags::from_bits(flags) else {
+		return -crate::errno::EINVAL;
+	};
+	let start = ptr.addr();
+	if start % PAGE_SIZE != 0 || flags.contains(MsyncFlags::MS_ASYNC | MsyncFlags::MS_SYNC) {
+		return -crate::errno::EINVAL;
+	}
+	let end = start + size.align_up(PAGE_SIZE);
+
+	let mappings = clone_mappings(start, end);
+	let mut ret = Ok(());
+	for (addr, mapping) in &mappings {
+		let from = start.max(*addr);
+		let to = end.min(*addr + mapping.len);
+		ret = ret.and_then(|()| block_on(write_back(*addr, mapping, from, to), None));
+		if flags.contains(MsyncFlags::MS_SYNC) && mapping.shared {
+			ret = ret.and_then(|()| block_on(mapping.obj.fsync(false), None));
+		}
+	}
+
+	ret.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
@@ -1,5 +1,7 @@
 #![allow(clippy::result_unit_err)]
 
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 #[cfg(all(target_os = "none", not(feature = "common-os")))]
 use core::alloc::{GlobalAlloc, Layout};
 use core::ffi::{CStr, c_char};
@@ -11,6 +13,8 @@ use hermit_sync::Lazy;
 pub use self::condvar::*;
 pub use self::entropy::*;
 pub use self::futex::*;
+#[cfg(feature = "mmap")]
+pub(crate) use self::mmap::{handle_page_fault, prefault};
 pub use self::processor::*;
 #[cfg(feature = "newlib")]
 pub use self::recmutex::*;
@@ -20,15 +24,20 @@ pub use self::system::*;
 pub use self::tasks::*;
 pub use self::timer::*;
 use crate::executor::block_on;
+use crate::fd::epoll::{EpollEvent, EpollFlags, EpollOp};
+use crate::fd::timerfd::{TimerClock, TimerFdFlags, TimerSetFlags};
 use crate::fd::{
-	AccessPermission, EventFlags, FileDescriptor, IoCtl, OpenOption, PollFd, dup_object,
-	dup_object2, get_object, isatty, remove_object,
+	AccessPermission, EventFlags, FallocateFlags, FdFlags, FileDescriptor, IoCtl, OpenOption,
+	PollFd, dup_object, dup_object2, get_object, isatty,
 };
-use crate::fs::{self, FileAttr};
+use crate::fs::lock::{FileLock, LockOwner, LockType};
+use crate::fs::notify::InotifyFlags;
+use crate::fs::{self, FileAttr, MountFlags, SeekWhence, SetAttr, StatFs, UmountFlags, XattrFlags};
 #[cfg(all(target_os = "none", not(feature = "common-os")))]
 use crate::mm::ALLOCATOR;
 use crate::syscalls::interfaces::SyscallInterface;
-use crate::{env, io};
+use crate::time::{itimerspec, timespec};
+use crate::{arch, env, io};
 
 mod condvar;
 mod entropy;
@@ -40,7 +49,7 @@ mod processor;
 #[cfg(feature = "newlib")]
 mod recmutex;
 mod semaphore;
-#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock"))]
+#[cfg(any(feature = "tcp", feature = "udp", feature = "vsock", feature = "unix"))]
 pub mod socket;
 mod spinlock;
 mod system;
@@ -285,6 +294,109 @@ pub unsafe extern "C" fn sys_unlink(name: *const c_char) -> i32 {
 	fs::unlink(name).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
 }
 
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_rename(oldpath: *const c_char, newpath: *const c_char) -> i32 {
+	let Ok(oldpath) = unsafe { CStr::from_ptr(oldpath) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+	let Ok(newpath) = unsafe { CStr::from_ptr(newpath) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+
+	fs::rename(oldpath, newpath).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+/// `getcwd` copies the absolute path of the current working directory
+/// including the terminating null byte to `buf`.
+/// Returns `-ERANGE`, if the buffer is too small.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_getcwd(buf: *mut c_char, size: usize) -> i32 {
+	if buf.is_null() {
+		return -crate::errno::EFAULT;
+	}
+
+	let cwd = fs::current_dir();
+	if cwd.len() >= size {
+		return -crate::errno::ERANGE;
+	}
+
+	unsafe {
+		core::ptr::copy_nonoverlapping(cwd.as_ptr(), buf.cast(), cwd.len());
+		*buf.add(cwd.len()) = 0;
+	}
+
+	0
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_chdir(path: *const c_char) -> i32 {
+	let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+
+	fs::set_current_dir(path).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_fchdir(fd: FileDescriptor) -> i32 {
+	fs::set_current_dir_fd(fd).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_symlink(target: *const c_char, linkpath: *const c_char) -> i32 {
+	let Ok(target) = unsafe { CStr::from_ptr(target) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+	let Ok(linkpath) = unsafe { CStr::from_ptr(linkpath) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+
+	fs::symlink(target, linkpath).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_link(oldpath: *const c_char, newpath: *const c_char) -> i32 {
+	let Ok(oldpath) = unsafe { CStr::from_ptr(oldpath) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+	let Ok(newpath) = unsafe { CStr::from_ptr(newpath) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+
+	fs::link(oldpath, newpath).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+/// `readlink` places the contents of the symbolic link `name` in the buffer `buf`.
+/// The content is not null-terminated and truncated, if the buffer is too small.
+/// On success, the number of bytes placed in `buf` is returned.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_readlink(name: *const c_char, buf: *mut u8, bufsiz: usize) -> isize {
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return (-crate::errno::EINVAL).try_into().unwrap();
+	};
+	if buf.is_null() || bufsiz == 0 {
+		return (-crate::errno::EINVAL).try_into().unwrap();
+	}
+
+	match fs::readlink(name) {
+		Ok(target) => {
+			let len = target.len().min(bufsiz);
+			unsafe {
+				core::ptr::copy_nonoverlapping(target.as_ptr(), buf, len);
+			}
+			len.try_into().unwrap()
+		}
+		Err(e) => -num::ToPrimitive::to_isize(&e).unwrap(),
+	}
+}
+
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn sys_mkdir(name: *const c_char, mode: u32) -> i32 {
@@ -349,217 +461,975 @@ pub unsafe extern "C" fn sys_fstat(fd: FileDescriptor, stat: *mut FileAttr) -> i
 	)
 }
 
-#[hermit_macro::system]
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn sys_opendir(name: *const c_char) -> FileDescriptor {
-	if let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() {
-		crate::fs::opendir(name).unwrap_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap())
+/// Special file descriptor, which refers to the current working directory
+const AT_FDCWD: FileDescriptor = -100;
+/// Symbolic links aren't followed by the `*at` functions
+const AT_SYMLINK_NOFOLLOW: i32 = 0x100;
+/// Timestamp is set to the current time
+const UTIME_NOW: i32 = (1 << 30) - 1;
+/// Timestamp is left unchanged
+const UTIME_OMIT: i32 = (1 << 30) - 2;
+
+/// Returns the path of `path` relative to the directory `dirfd`
+fn at_path(dirfd: FileDescriptor, path: &str) -> io::Result<String> {
+	if dirfd == AT_FDCWD || path.starts_with('/') {
+		Ok(path.to_string())
 	} else {
-		-crate::errno::EINVAL
+		let dir = block_on(get_object(dirfd)?.getpath(), None)?;
+		Ok(dir + "/" + path)
+	}
+}
+
+/// Converts the owner of `chown` to attribute changes. An id of -1 is left unchanged.
+fn owner_attr(uid: u32, gid: u32) -> SetAttr {
+	SetAttr {
+		uid: (uid != u32::MAX).then_some(uid),
+		gid: (gid != u32::MAX).then_some(gid),
+		..Default::default()
+	}
+}
+
+/// Converts the timestamps of `utimensat` to attribute changes. Without
+/// timestamps, both times are set to the current time.
+unsafe fn utime_attr(times: *const timespec) -> io::Result<SetAttr> {
+	let microseconds = arch::kernel::systemtime::now_micros();
+	let now = timespec::from_usec(microseconds as i64);
+	let convert = |time: timespec| match time.tv_nsec {
+		UTIME_NOW => Ok(Some(now)),
+		UTIME_OMIT => Ok(None),
+		0..1_000_000_000 => Ok(Some(time)),
+		_ => Err(io::Error::EINVAL),
+	};
+
+	if times.is_null() {
+		Ok(SetAttr {
+			atime: Some(now),
+			mtime: Some(now),
+			..Default::default()
+		})
+	} else {
+		let times = unsafe { core::slice::from_raw_parts(times, 2) };
+		Ok(SetAttr {
+			atime: convert(times[0])?,
+			mtime: convert(times[1])?,
+			..Default::default()
+		})
 	}
 }
 
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn sys_open(name: *const c_char, flags: i32, mode: u32) -> FileDescriptor {
-	let Some(flags) = OpenOption::from_bits(flags) else {
+pub unsafe extern "C" fn sys_chmod(name: *const c_char, mode: u32) -> i32 {
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
 		return -crate::errno::EINVAL;
 	};
 	let Some(mode) = AccessPermission::from_bits(mode) else {
 		return -crate::errno::EINVAL;
 	};
+	let attr = SetAttr {
+		mode: Some(mode),
+		..Default::default()
+	};
 
-	if let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() {
-		crate::fs::open(name, flags, mode)
-			.unwrap_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap())
-	} else {
-		-crate::errno::EINVAL
-	}
+	fs::setattr(name, attr, true).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
 }
 
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
-pub extern "C" fn sys_close(fd: FileDescriptor) -> i32 {
-	let obj = remove_object(fd);
-	obj.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |_| 0)
+pub extern "C" fn sys_fchmod(fd: FileDescriptor, mode: u32) -> i32 {
+	let Some(mode) = AccessPermission::from_bits(mode) else {
+		return -crate::errno::EINVAL;
+	};
+	let attr = SetAttr {
+		mode: Some(mode),
+		..Default::default()
+	};
+
+	crate::fd::fsetattr(fd, attr).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
 }
 
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn sys_read(fd: FileDescriptor, buf: *mut u8, len: usize) -> isize {
-	let slice = unsafe { core::slice::from_raw_parts_mut(buf.cast(), len) };
-	crate::fd::read(fd, slice).map_or_else(
-		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
-		|v| v.try_into().unwrap(),
-	)
+pub unsafe extern "C" fn sys_chown(name: *const c_char, uid: u32, gid: u32) -> i32 {
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+
+	fs::setattr(name, owner_attr(uid, gid), true)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
 }
 
-/// `read()` attempts to read `nbyte` of data to the object referenced by the
-/// descriptor `fd` from a buffer. `read()` performs the same
-/// action, but scatters the input data from the `iovcnt` buffers specified by the
-/// members of the iov array: `iov[0], iov[1], ..., iov[iovcnt-1]`.
-///
-/// ```
-/// struct iovec {
-///     char   *iov_base;  /* Base address. */
-///     size_t iov_len;    /* Length. */
-/// };
-/// ```
-///
-/// Each `iovec` entry specifies the base address and length of an area in memory from
-/// which data should be written.  `readv()` will always fill an completely
-/// before proceeding to the next.
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn sys_readv(fd: i32, iov: *const iovec, iovcnt: usize) -> isize {
-	if !(0..=IOV_MAX).contains(&iovcnt) {
-		return (-crate::errno::EINVAL).try_into().unwrap();
-	}
-
-	let mut read_bytes: isize = 0;
-	let iovec_buffers = unsafe { core::slice::from_raw_parts(iov, iovcnt) };
-
-	for iovec_buf in iovec_buffers {
-		let buf = unsafe {
-			core::slice::from_raw_parts_mut(iovec_buf.iov_base.cast(), iovec_buf.iov_len)
-		};
-
-		let len = crate::fd::read(fd, buf).map_or_else(
-			|e| -num::ToPrimitive::to_isize(&e).unwrap(),
-			|v| v.try_into().unwrap(),
-		);
+pub unsafe extern "C" fn sys_lchown(name: *const c_char, uid: u32, gid: u32) -> i32 {
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
 
-		if len < 0 {
-			return len;
-		}
+	fs::setattr(name, owner_attr(uid, gid), false)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
 
-		read_bytes += len;
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_fchown(fd: FileDescriptor, uid: u32, gid: u32) -> i32 {
+	crate::fd::fsetattr(fd, owner_attr(uid, gid))
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
 
-		if len < iovec_buf.iov_len.try_into().unwrap() {
-			return read_bytes;
+/// Copies the value of an extended attribute or a list of names to `buf`
+/// and returns its length. With `size` 0, only the length is returned.
+unsafe fn copy_xattr(data: io::Result<Vec<u8>>, buf: *mut u8, size: usize) -> isize {
+	match data {
+		Ok(data) if size == 0 => data.len().try_into().unwrap(),
+		Ok(data) if data.len() > size => (-crate::errno::ERANGE).try_into().unwrap(),
+		Ok(data) => {
+			unsafe {
+				ptr::copy_nonoverlapping(data.as_ptr(), buf, data.len());
+			}
+			data.len().try_into().unwrap()
 		}
+		Err(e) => -num::ToPrimitive::to_isize(&e).unwrap(),
 	}
+}
 
-	read_bytes
+/// Converts the names of extended attributes to a list of null-terminated strings
+fn xattr_list(names: io::Result<Vec<String>>) -> io::Result<Vec<u8>> {
+	Ok(names?
+		.into_iter()
+		.flat_map(|name| name.into_bytes().into_iter().chain([0]))
+		.collect())
 }
 
-unsafe fn write(fd: FileDescriptor, buf: *const u8, len: usize) -> isize {
-	let slice = unsafe { core::slice::from_raw_parts(buf, len) };
-	crate::fd::write(fd, slice).map_or_else(
-		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
-		|v| v.try_into().unwrap(),
-	)
+/// Returns the value of the extended attribute, which is passed to `setxattr`
+unsafe fn xattr_value<'a>(value: *const u8, size: usize) -> &'a [u8] {
+	if size == 0 {
+		&[]
+	} else {
+		unsafe { core::slice::from_raw_parts(value, size) }
+	}
 }
 
+/// `getxattr` copies the value of the extended attribute `name` of the file
+/// `path` to `value` and returns its size. With `size` 0, only the size is returned.
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn sys_write(fd: FileDescriptor, buf: *const u8, len: usize) -> isize {
-	unsafe { write(fd, buf, len) }
+pub unsafe extern "C" fn sys_getxattr(
+	path: *const c_char,
+	name: *const c_char,
+	value: *mut u8,
+	size: usize,
+) -> isize {
+	let (Ok(path), Ok(name)) = (
+		unsafe { CStr::from_ptr(path) }.to_str(),
+		unsafe { CStr::from_ptr(name) }.to_str(),
+	) else {
+		return (-crate::errno::EINVAL).try_into().unwrap();
+	};
+
+	unsafe { copy_xattr(fs::getxattr(path, name, true), value, size) }
 }
 
-/// `write()` attempts to write `nbyte` of data to the object referenced by the
-/// descriptor `fd` from a buffer. `writev()` performs the same
-/// action, but gathers the output data from the `iovcnt` buffers specified by the
-/// members of the iov array: `iov[0], iov[1], ..., iov[iovcnt-1]`.
-///
-/// ```
-/// struct iovec {
-///     char   *iov_base;  /* Base address. */
-///     size_t iov_len;    /* Length. */
-/// };
-/// ```
-///
-/// Each `iovec` entry specifies the base address and length of an area in memory from
-/// which data should be written.  `writev()` will always write a
-/// complete area before proceeding to the next.
+/// `lgetxattr` is like `getxattr`, but doesn't follow a symbolic link
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn sys_writev(fd: FileDescriptor, iov: *const iovec, iovcnt: usize) -> isize {
-	if !(0..=IOV_MAX).contains(&iovcnt) {
+pub unsafe extern "C" fn sys_lgetxattr(
+	path: *const c_char,
+	name: *const c_char,
+	value: *mut u8,
+	size: usize,
+) -> isize {
+	let (Ok(path), Ok(name)) = (
+		unsafe { CStr::from_ptr(path) }.to_str(),
+		unsafe { CStr::from_ptr(name) }.to_str(),
+	) else {
 		return (-crate::errno::EINVAL).try_into().unwrap();
-	}
+	};
 
-	let mut written_bytes: isize = 0;
-	let iovec_buffers = unsafe { core::slice::from_raw_parts(iov, iovcnt) };
+	unsafe { copy_xattr(fs::getxattr(path, name, false), value, size) }
+}
 
-	for iovec_buf in iovec_buffers {
-		let buf = unsafe { core::slice::from_raw_parts(iovec_buf.iov_base, iovec_buf.iov_len) };
+/// `fgetxattr` is like `getxattr` for the open file `fd`
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_fgetxattr(
+	fd: FileDescriptor,
+	name: *const c_char,
+	value: *mut u8,
+	size: usize,
+) -> isize {
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return (-crate::errno::EINVAL).try_into().unwrap();
+	};
 
-		let len = crate::fd::write(fd, buf).map_or_else(
-			|e| -num::ToPrimitive::to_isize(&e).unwrap(),
-			|v| v.try_into().unwrap(),
-		);
+	unsafe { copy_xattr(crate::fd::fgetxattr(fd, name), value, size) }
+}
 
-		if len < 0 {
-			return len;
-		}
+/// `setxattr` creates or replaces the extended attribute `name` of the file
+/// `path`. `flags` may contain either `XATTR_CREATE` or `XATTR_REPLACE`.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_setxattr(
+	path: *const c_char,
+	name: *const c_char,
+	value: *const u8,
+	size: usize,
+	flags: i32,
+) -> i32 {
+	let (Ok(path), Ok(name), Some(flags)) = (
+		unsafe { CStr::from_ptr(path) }.to_str(),
+		unsafe { CStr::from_ptr(name) }.to_str(),
+		XattrFlags::from_bits(flags),
+	) else {
+		return -crate::errno::EINVAL;
+	};
+	let value = unsafe { xattr_value(value, size) };
 
-		written_bytes += len;
+	fs::setxattr(path, name, value, flags, true)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
 
-		if len < iovec_buf.iov_len.try_into().unwrap() {
-			return written_bytes;
-		}
-	}
+/// `lsetxattr` is like `setxattr`, but doesn't follow a symbolic link
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_lsetxattr(
+	path: *const c_char,
+	name: *const c_char,
+	value: *const u8,
+	size: usize,
+	flags: i32,
+) -> i32 {
+	let (Ok(path), Ok(name), Some(flags)) = (
+		unsafe { CStr::from_ptr(path) }.to_str(),
+		unsafe { CStr::from_ptr(name) }.to_str(),
+		XattrFlags::from_bits(flags),
+	) else {
+		return -crate::errno::EINVAL;
+	};
+	let value = unsafe { xattr_value(value, size) };
 
-	written_bytes
+	fs::setxattr(path, name, value, flags, false)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
 }
 
+/// `fsetxattr` is like `setxattr` for the open file `fd`
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn sys_ioctl(
+pub unsafe extern "C" fn sys_fsetxattr(
 	fd: FileDescriptor,
-	cmd: i32,
-	argp: *mut core::ffi::c_void,
+	name: *const c_char,
+	value: *const u8,
+	size: usize,
+	flags: i32,
 ) -> i32 {
-	const FIONBIO: i32 = 0x8008_667eu32 as i32;
-
-	if cmd == FIONBIO {
-		let value = unsafe { *(argp as *const i32) };
+	let (Ok(name), Some(flags)) = (
+		unsafe { CStr::from_ptr(name) }.to_str(),
+		XattrFlags::from_bits(flags),
+	) else {
+		return -crate::errno::EINVAL;
+	};
+	let value = unsafe { xattr_value(value, size) };
 
-		let obj = get_object(fd);
-		obj.map_or_else(
-			|e| -num::ToPrimitive::to_i32(&e).unwrap(),
-			|v| {
-				block_on((*v).ioctl(IoCtl::NonBlocking, value != 0), None)
-					.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
-			},
-		)
-	} else {
-		-crate::errno::EINVAL
-	}
+	crate::fd::fsetxattr(fd, name, value, flags)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
 }
 
-/// manipulate file descriptor
+/// `listxattr` copies the null-terminated names of the extended attributes
+/// of the file `path` to `list` and returns their size. With `size` 0, only
+/// the size is returned.
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
-pub extern "C" fn sys_fcntl(fd: i32, cmd: i32, arg: i32) -> i32 {
-	const F_SETFD: i32 = 2;
-	const F_SETFL: i32 = 4;
-	const FD_CLOEXEC: i32 = 1;
+pub unsafe extern "C" fn sys_listxattr(
+	path: *const c_char,
+	list: *mut c_char,
+	size: usize,
+) -> isize {
+	let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+		return (-crate::errno::EINVAL).try_into().unwrap();
+	};
 
-	if cmd == F_SETFD && arg == FD_CLOEXEC {
-		0
-	} else if cmd == F_SETFL && arg == OpenOption::O_NONBLOCK.bits() {
-		let obj = get_object(fd);
-		obj.map_or_else(
-			|e| -num::ToPrimitive::to_i32(&e).unwrap(),
-			|v| {
-				block_on((*v).ioctl(IoCtl::NonBlocking, true), None)
-					.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
-			},
-		)
-	} else {
-		-crate::errno::EINVAL
-	}
+	unsafe { copy_xattr(xattr_list(fs::listxattr(path, true)), list.cast(), size) }
 }
 
+/// `llistxattr` is like `listxattr`, but doesn't follow a symbolic link
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
-pub extern "C" fn sys_lseek(fd: FileDescriptor, offset: isize, whence: i32) -> isize {
-	crate::fd::lseek(fd, offset, num::FromPrimitive::from_i32(whence).unwrap())
-		.map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
-}
-
+pub unsafe extern "C" fn sys_llistxattr(
+	path: *const c_char,
+	list: *mut c_char,
+	size: usize,
+) -> isize {
+	let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+		return (-crate::errno::EINVAL).try_into().unwrap();
+	};
+
+	unsafe { copy_xattr(xattr_list(fs::listxattr(path, false)), list.cast(), size) }
+}
+
+/// `flistxattr` is like `listxattr` for the open file `fd`
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_flistxattr(
+	fd: FileDescriptor,
+	list: *mut c_char,
+	size: usize,
+) -> isize {
+	unsafe { copy_xattr(xattr_list(crate::fd::flistxattr(fd)), list.cast(), size) }
+}
+
+/// `removexattr` removes the extended attribute `name` of the file `path`
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_removexattr(path: *const c_char, name: *const c_char) -> i32 {
+	let (Ok(path), Ok(name)) = (
+		unsafe { CStr::from_ptr(path) }.to_str(),
+		unsafe { CStr::from_ptr(name) }.to_str(),
+	) else {
+		return -crate::errno::EINVAL;
+	};
+
+	fs::removexattr(path, name, true)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+/// `lremovexattr` is like `removexattr`, but doesn't follow a symbolic link
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_lremovexattr(path: *const c_char, name: *const c_char) -> i32 {
+	let (Ok(path), Ok(name)) = (
+		unsafe { CStr::from_ptr(path) }.to_str(),
+		unsafe { CStr::from_ptr(name) }.to_str(),
+	) else {
+		return -crate::errno::EINVAL;
+	};
+
+	fs::removexattr(path, name, false)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+/// `fremovexattr` is like `removexattr` for the open file `fd`
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_fremovexattr(fd: FileDescriptor, name: *const c_char) -> i32 {
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+
+	crate::fd::fremovexattr(fd, name)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+/// `utimensat` changes the access and modification time of the file `name`,
+/// which is relative to the directory `dirfd`. If `name` is null, the file
+/// `dirfd` itself is changed.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_utimensat(
+	dirfd: FileDescriptor,
+	name: *const c_char,
+	times: *const timespec,
+	flags: i32,
+) -> i32 {
+	if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+		return -crate::errno::EINVAL;
+	}
+	let attr = match unsafe { utime_attr(times) } {
+		Ok(attr) => attr,
+		Err(e) => return -num::ToPrimitive::to_i32(&e).unwrap(),
+	};
+
+	if name.is_null() {
+		return crate::fd::fsetattr(dirfd, attr)
+			.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0);
+	}
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+
+	at_path(dirfd, name)
+		.and_then(|path| fs::setattr(&path, attr, flags & AT_SYMLINK_NOFOLLOW == 0))
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_futimens(fd: FileDescriptor, times: *const timespec) -> i32 {
+	unsafe { utime_attr(times) }
+		.and_then(|attr| crate::fd::fsetattr(fd, attr))
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_statfs(name: *const c_char, buf: *mut StatFs) -> i32 {
+	if buf.is_null() {
+		return -crate::errno::EINVAL;
+	}
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+
+	fs::statfs(name).map_or_else(
+		|e| -num::ToPrimitive::to_i32(&e).unwrap(),
+		|v| unsafe {
+			*buf = v;
+			0
+		},
+	)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_fstatfs(fd: FileDescriptor, buf: *mut StatFs) -> i32 {
+	if buf.is_null() {
+		return -crate::errno::EINVAL;
+	}
+
+	fs::fstatfs(fd).map_or_else(
+		|e| -num::ToPrimitive::to_i32(&e).unwrap(),
+		|v| unsafe {
+			*buf = v;
+			0
+		},
+	)
+}
+
+/// Mounts the file system `source` of type `fstype` at `target`. `data`
+/// is a null-terminated string with file system specific options and
+/// can be null.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_mount(
+	source: *const c_char,
+	target: *const c_char,
+	fstype: *const c_char,
+	flags: u64,
+	data: *const core::ffi::c_void,
+) -> i32 {
+	let Ok(source) = unsafe { CStr::from_ptr(source) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+	let Ok(target) = unsafe { CStr::from_ptr(target) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+	let Ok(fstype) = unsafe { CStr::from_ptr(fstype) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+	let data = if data.is_null() {
+		""
+	} else if let Ok(data) = unsafe { CStr::from_ptr(data.cast()) }.to_str() {
+		data
+	} else {
+		return -crate::errno::EINVAL;
+	};
+
+	fs::mount(
+		source,
+		target,
+		fstype,
+		MountFlags::from_bits_truncate(flags),
+		data,
+	)
+	.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_umount2(target: *const c_char, flags: i32) -> i32 {
+	let Ok(target) = unsafe { CStr::from_ptr(target) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+	let Some(flags) = UmountFlags::from_bits(flags) else {
+		return -crate::errno::EINVAL;
+	};
+
+	fs::umount(target, flags).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_umount(target: *const c_char) -> i32 {
+	unsafe { sys_umount2(target, 0) }
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_opendir(name: *const c_char) -> FileDescriptor {
+	if let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() {
+		crate::fs::opendir(name).unwrap_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap())
+	} else {
+		-crate::errno::EINVAL
+	}
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_open(name: *const c_char, flags: i32, mode: u32) -> FileDescriptor {
+	let Some(flags) = OpenOption::from_bits(flags) else {
+		return -crate::errno::EINVAL;
+	};
+	let Some(mode) = AccessPermission::from_bits(mode) else {
+		return -crate::errno::EINVAL;
+	};
+
+	if let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() {
+		crate::fs::open(name, flags, mode)
+			.unwrap_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap())
+	} else {
+		-crate::errno::EINVAL
+	}
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_close(fd: FileDescriptor) -> i32 {
+	crate::fd::close(fd).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_fsync(fd: FileDescriptor) -> i32 {
+	crate::fd::fsync(fd, false).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_fdatasync(fd: FileDescriptor) -> i32 {
+	crate::fd::fsync(fd, true).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_syncfs(fd: FileDescriptor) -> i32 {
+	crate::fd::syncfs(fd).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_read(fd: FileDescriptor, buf: *mut u8, len: usize) -> isize {
+	let slice = unsafe { core::slice::from_raw_parts_mut(buf.cast(), len) };
+	crate::fd::read(fd, slice).map_or_else(
+		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+		|v| v.try_into().unwrap(),
+	)
+}
+
+/// `read()` attempts to read `nbyte` of data to the object referenced by the
+/// descriptor `fd` from a buffer. `read()` performs the same
+/// action, but scatters the input data from the `iovcnt` buffers specified by the
+/// members of the iov array: `iov[0], iov[1], ..., iov[iovcnt-1]`.
+///
+/// ```
+/// struct iovec {
+///     char   *iov_base;  /* Base address. */
+///     size_t iov_len;    /* Length. */
+/// };
+/// ```
+///
+/// Each `iovec` entry specifies the base address and length of an area in memory from
+/// which data should be written.  `readv()` will always fill an completely
+/// before proceeding to the next.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_readv(fd: i32, iov: *const iovec, iovcnt: usize) -> isize {
+	if !(0..=IOV_MAX).contains(&iovcnt) {
+		return (-crate::errno::EINVAL).try_into().unwrap();
+	}
+
+	let mut read_bytes: isize = 0;
+	let iovec_buffers = unsafe { core::slice::from_raw_parts(iov, iovcnt) };
+
+	for iovec_buf in iovec_buffers {
+		let buf = unsafe {
+			core::slice::from_raw_parts_mut(iovec_buf.iov_base.cast(), iovec_buf.iov_len)
+		};
+
+		let len = crate::fd::read(fd, buf).map_or_else(
+			|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+			|v| v.try_into().unwrap(),
+		);
+
+		if len < 0 {
+			return len;
+		}
+
+		read_bytes += len;
+
+		if len < iovec_buf.iov_len.try_into().unwrap() {
+			return read_bytes;
+		}
+	}
+
+	read_bytes
+}
+
+unsafe fn write(fd: FileDescriptor, buf: *const u8, len: usize) -> isize {
+	let slice = unsafe { core::slice::from_raw_parts(buf, len) };
+	crate::fd::write(fd, slice).map_or_else(
+		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+		|v| v.try_into().unwrap(),
+	)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_write(fd: FileDescriptor, buf: *const u8, len: usize) -> isize {
+	unsafe { write(fd, buf, len) }
+}
+
+/// `write()` attempts to write `nbyte` of data to the object referenced by the
+/// descriptor `fd` from a buffer. `writev()` performs the same
+/// action, but gathers the output data from the `iovcnt` buffers specified by the
+/// members of the iov array: `iov[0], iov[1], ..., iov[iovcnt-1]`.
+///
+/// ```
+/// struct iovec {
+///     char   *iov_base;  /* Base address. */
+///     size_t iov_len;    /* Length. */
+/// };
+/// ```
+///
+/// Each `iovec` entry specifies the base address and length of an area in memory from
+/// which data should be written.  `writev()` will always write a
+/// complete area before proceeding to the next.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_writev(fd: FileDescriptor, iov: *const iovec, iovcnt: usize) -> isize {
+	if !(0..=IOV_MAX).contains(&iovcnt) {
+		return (-crate::errno::EINVAL).try_into().unwrap();
+	}
+
+	let mut written_bytes: isize = 0;
+	let iovec_buffers = unsafe { core::slice::from_raw_parts(iov, iovcnt) };
+
+	for iovec_buf in iovec_buffers {
+		let buf = unsafe { core::slice::from_raw_parts(iovec_buf.iov_base, iovec_buf.iov_len) };
+
+		let len = crate::fd::write(fd, buf).map_or_else(
+			|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+			|v| v.try_into().unwrap(),
+		);
+
+		if len < 0 {
+			return len;
+		}
+
+		written_bytes += len;
+
+		if len < iovec_buf.iov_len.try_into().unwrap() {
+			return written_bytes;
+		}
+	}
+
+	written_bytes
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_ioctl(
+	fd: FileDescriptor,
+	cmd: i32,
+	argp: *mut core::ffi::c_void,
+) -> i32 {
+	const FIONBIO: i32 = 0x8008_667eu32 as i32;
+
+	if cmd == FIONBIO {
+		let value = unsafe { *(argp as *const i32) };
+
+		let status = match crate::fd::get_status_flags(fd) {
+			Ok(status) if value != 0 => status | OpenOption::O_NONBLOCK,
+			Ok(status) => status - OpenOption::O_NONBLOCK,
+			Err(e) => return -num::ToPrimitive::to_i32(&e).unwrap(),
+		};
+		crate::fd::set_status_flags(fd, status)
+			.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+	} else {
+		-crate::errno::EINVAL
+	}
+}
+
+/// Describes a record lock of `fcntl`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Flock {
+	/// Type of the lock: `F_RDLCK`, `F_WRLCK` or `F_UNLCK`
+	pub l_type: i16,
+	/// Interpretation of `l_start`: `SEEK_SET`, `SEEK_CUR` or `SEEK_END`
+	pub l_whence: i16,
+	/// Starting offset of the lock
+	pub l_start: i64,
+	/// Number of locked bytes, 0 locks until the end of the file
+	pub l_len: i64,
+	/// Process, which holds a conflicting lock (`F_GETLK`)
+	pub l_pid: i32,
+}
+
+/// Converts the description `flock` of a record lock of `fd` into a lock of `owner`
+fn file_lock(fd: FileDescriptor, flock: &Flock, owner: LockOwner) -> io::Result<FileLock> {
+	let kind = num::FromPrimitive::from_i16(flock.l_type).ok_or(io::Error::EINVAL)?;
+	let base: i64 = match num::FromPrimitive::from_i16(flock.l_whence) {
+		Some(SeekWhence::Set) => 0,
+		Some(SeekWhence::Cur) => crate::fd::lseek(fd, 0, SeekWhence::Cur)?
+			.try_into()
+			.map_err(|_| io::Error::EOVERFLOW)?,
+		Some(SeekWhence::End) => crate::fd::fstat(fd)?
+			.st_size
+			.try_into()
+			.map_err(|_| io::Error::EOVERFLOW)?,
+		_ => return Err(io::Error::EINVAL),
+	};
+
+	let start = flock
+		.l_start
+		.checked_add(base)
+		.ok_or(io::Error::EOVERFLOW)?;
+	let (start, end) = match flock.l_len {
+		0 => (start, None),
+		len if len > 0 => (
+			start,
+			Some(start.checked_add(len).ok_or(io::Error::EOVERFLOW)?),
+		),
+		// a negative length locks the bytes before `start`
+		len => (
+			start.checked_add(len).ok_or(io::Error::EINVAL)?,
+			Some(start),
+		),
+	};
+
+	let start = u64::try_from(start).map_err(|_| io::Error::EINVAL)?;
+	let end = end.map_or(Ok(u64::MAX), |end| {
+		u64::try_from(end).map_err(|_| io::Error::EINVAL)
+	})?;
+
+	Ok(FileLock {
+		kind,
+		owner,
+		flock: false,
+		start,
+		end,
+		pid: if owner == LockOwner::Process { 0 } else { -1 },
+	})
+}
+
+/// Handles the lock commands of `fcntl`. Record locks belong to the process
+/// and open file description locks to the open file.
+fn fcntl_lock(fd: FileDescriptor, cmd: i32, flock: &mut Flock) -> io::Result<()> {
+	const F_GETLK: i32 = 5;
+	const F_SETLK: i32 = 6;
+	const F_SETLKW: i32 = 7;
+	const F_OFD_GETLK: i32 = 36;
+	const F_OFD_SETLK: i32 = 37;
+	const F_OFD_SETLKW: i32 = 38;
+
+	if !matches!(
+		cmd,
+		F_GETLK | F_SETLK | F_SETLKW | F_OFD_GETLK | F_OFD_SETLK | F_OFD_SETLKW
+	) {
+		return Err(io::Error::EINVAL);
+	}
+
+	let owner = match cmd {
+		F_GETLK | F_SETLK | F_SETLKW => LockOwner::Process,
+		_ if flock.l_pid != 0 => return Err(io::Error::EINVAL),
+		_ => crate::fd::lock_owner(&get_object(fd)?),
+	};
+	let lock = file_lock(fd, flock, owner)?;
+
+	match cmd {
+		F_GETLK | F_OFD_GETLK => {
+			if let Some(other) = crate::fd::getlk(fd, lock)? {
+				flock.l_type = num::ToPrimitive::to_i16(&other.kind).unwrap();
+				flock.l_whence = num::ToPrimitive::to_i16(&SeekWhence::Set).unwrap();
+				flock.l_start = other.start.try_into().map_err(|_| io::Error::EOVERFLOW)?;
+				flock.l_len = if other.end == u64::MAX {
+					0
+				} else {
+					(other.end - other.start)
+						.try_into()
+						.map_err(|_| io::Error::EOVERFLOW)?
+				};
+				flock.l_pid = other.pid;
+			} else {
+				flock.l_type = num::ToPrimitive::to_i16(&LockType::Unlock).unwrap();
+			}
+			Ok(())
+		}
+		F_SETLK | F_OFD_SETLK => crate::fd::setlk(fd, lock, false),
+		_ => crate::fd::setlk(fd, lock, true),
+	}
+}
+
+/// manipulate file descriptor
+///
+/// The lock commands take a pointer to a [`Flock`] and are handled by
+/// [`sys_fcntl_lock`]. They fail with `EINVAL`.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_fcntl(fd: i32, cmd: i32, arg: i32) -> i32 {
+	const F_DUPFD: i32 = 0;
+	const F_GETFD: i32 = 1;
+	const F_SETFD: i32 = 2;
+	const F_GETFL: i32 = 3;
+	const F_SETFL: i32 = 4;
+	const F_DUPFD_CLOEXEC: i32 = 1030;
+
+	match cmd {
+		F_DUPFD | F_DUPFD_CLOEXEC => {
+			let flags = if cmd == F_DUPFD_CLOEXEC {
+				FdFlags::FD_CLOEXEC
+			} else {
+				FdFlags::empty()
+			};
+			dup_object(fd, arg, flags).unwrap_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap())
+		}
+		F_GETFD => crate::fd::get_fd_flags(fd).map_or_else(
+			|e| -num::ToPrimitive::to_i32(&e).unwrap(),
+			|flags| flags.bits(),
+		),
+		F_SETFD => crate::fd::set_fd_flags(fd, FdFlags::from_bits_truncate(arg))
+			.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0),
+		F_GETFL => crate::fd::get_status_flags(fd).map_or_else(
+			|e| -num::ToPrimitive::to_i32(&e).unwrap(),
+			|flags| flags.bits(),
+		),
+		F_SETFL => crate::fd::set_status_flags(fd, OpenOption::from_bits_truncate(arg))
+			.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0),
+		_ => -crate::errno::EINVAL,
+	}
+}
+
+/// Handles the lock commands of `fcntl`: `F_GETLK`, `F_SETLK`, `F_SETLKW` and
+/// their open file description variants `F_OFD_GETLK`, `F_OFD_SETLK` and
+/// `F_OFD_SETLKW`. `flock` describes the lock and receives the conflicting
+/// lock of `F_GETLK`.
+///
+/// # Safety
+///
+/// `flock` has to be null or point to a valid [`Flock`].
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_fcntl_lock(fd: i32, cmd: i32, flock: *mut Flock) -> i32 {
+	let Some(flock) = (unsafe { flock.as_mut() }) else {
+		return -crate::errno::EFAULT;
+	};
+
+	fcntl_lock(fd, cmd, flock).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+/// Applies or removes an advisory lock on the open file `fd`.
+/// `operation` is `LOCK_SH`, `LOCK_EX` or `LOCK_UN`, optionally combined
+/// with `LOCK_NB`.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_flock(fd: FileDescriptor, operation: i32) -> i32 {
+	const LOCK_SH: i32 = 1;
+	const LOCK_EX: i32 = 2;
+	const LOCK_NB: i32 = 4;
+	const LOCK_UN: i32 = 8;
+
+	let kind = match operation & !LOCK_NB {
+		LOCK_SH => LockType::Read,
+		LOCK_EX => LockType::Write,
+		LOCK_UN => LockType::Unlock,
+		_ => return -crate::errno::EINVAL,
+	};
+
+	crate::fd::flock(fd, kind, operation & LOCK_NB == 0)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_lseek(fd: FileDescriptor, offset: isize, whence: i32) -> isize {
+	let Some(whence) = num::FromPrimitive::from_i32(whence) else {
+		return (-crate::errno::EINVAL).try_into().unwrap();
+	};
+
+	crate::fd::lseek(fd, offset, whence)
+		.unwrap_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap())
+}
+
+/// `pread()` attempts to read `len` bytes at position `offset` from the object
+/// referenced by the descriptor `fd`. The file offset is not changed.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_pread(
+	fd: FileDescriptor,
+	buf: *mut u8,
+	len: usize,
+	offset: isize,
+) -> isize {
+	let Ok(offset) = usize::try_from(offset) else {
+		return (-crate::errno::EINVAL).try_into().unwrap();
+	};
+
+	let slice = unsafe { core::slice::from_raw_parts_mut(buf.cast(), len) };
+	crate::fd::pread(fd, slice, offset).map_or_else(
+		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+		|v| v.try_into().unwrap(),
+	)
+}
+
+/// `pwrite()` attempts to write `len` bytes at position `offset` to the object
+/// referenced by the descriptor `fd`. The file offset is not changed.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_pwrite(
+	fd: FileDescriptor,
+	buf: *const u8,
+	len: usize,
+	offset: isize,
+) -> isize {
+	let Ok(offset) = usize::try_from(offset) else {
+		return (-crate::errno::EINVAL).try_into().unwrap();
+	};
+
+	let slice = unsafe { core::slice::from_raw_parts(buf, len) };
+	crate::fd::pwrite(fd, slice, offset).map_or_else(
+		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+		|v| v.try_into().unwrap(),
+	)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_truncate(path: *const c_char, size: isize) -> i32 {
+	let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+	let Ok(size) = usize::try_from(size) else {
+		return -crate::errno::EINVAL;
+	};
+
+	fs::truncate(path, size).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_ftruncate(fd: FileDescriptor, size: isize) -> i32 {
+	let Ok(size) = usize::try_from(size) else {
+		return -crate::errno::EINVAL;
+	};
+
+	crate::fd::ftruncate(fd, size).map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+/// `fallocate()` allocates, deallocates or zeroes the byte range starting at
+/// `offset` with `len` bytes of the file referenced by the descriptor `fd`.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_fallocate(fd: FileDescriptor, mode: i32, offset: isize, len: isize) -> i32 {
+	let Some(mode) = FallocateFlags::from_bits(mode) else {
+		return -crate::errno::EOPNOTSUPP;
+	};
+	let (Ok(offset), Ok(len)) = (usize::try_from(offset), usize::try_from(len)) else {
+		return -crate::errno::EINVAL;
+	};
+
+	crate::fd::fallocate(fd, mode, offset, len)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct Dirent64 {
@@ -587,52 +1457,75 @@ pub unsafe extern "C" fn sys_getdents64(
 	}
 
 	const ALIGN_DIRENT: usize = core::mem::align_of::<Dirent64>();
-	let mut dirp: *mut Dirent64 = dirp;
-	let mut offset: i64 = 0;
-	let obj = get_object(fd);
-	obj.map_or_else(
-		|_| (-crate::errno::EINVAL).into(),
-		|v| {
-			block_on((*v).readdir(), None).map_or_else(
-				|e| -num::ToPrimitive::to_i64(&e).unwrap(),
-				|v| {
-					for i in v.iter() {
-						let len = i.name.len();
-						let aligned_len = ((core::mem::size_of::<Dirent64>() + len + 1)
-							+ (ALIGN_DIRENT - 1)) & (!(ALIGN_DIRENT - 1));
-						if offset as usize + aligned_len >= count {
-							return (-crate::errno::EINVAL).into();
-						}
-
-						let dir = unsafe { &mut *dirp };
-
-						dir.d_ino = 0;
-						dir.d_type = 0;
-						dir.d_reclen = aligned_len.try_into().unwrap();
-						offset += i64::try_from(aligned_len).unwrap();
-						dir.d_off = offset;
-
-						// copy null-terminated filename
-						let s = ptr::from_mut(&mut dir.d_name).cast::<u8>();
-						unsafe {
-							core::ptr::copy_nonoverlapping(i.name.as_ptr(), s, len);
-							s.add(len).write_bytes(0, 1);
-						}
-
-						dirp = unsafe { dirp.byte_add(aligned_len) };
+	let Ok(obj) = get_object(fd) else {
+		return (-crate::errno::EINVAL).into();
+	};
+
+	let getdents = async {
+		// the position of the directory stream is stored as file offset
+		let mut pos: u64 = obj
+			.lseek(0, SeekWhence::Cur)
+			.await?
+			.try_into()
+			.map_err(|_| io::Error::EINVAL)?;
+		let mut offset: usize = 0;
+
+		'stream: loop {
+			let entries = obj.readdir(pos).await?;
+			if entries.is_empty() {
+				break;
+			}
+
+			for i in entries.iter() {
+				let len = i.name.len();
+				let aligned_len = ((core::mem::size_of::<Dirent64>() + len + 1)
+					+ (ALIGN_DIRENT - 1))
+					& (!(ALIGN_DIRENT - 1));
+				if offset + aligned_len > count {
+					if offset == 0 {
+						// the buffer is too small for a single entry
+						return Err(io::Error::EINVAL);
 					}
+					break 'stream;
+				}
 
-					offset
-				},
-			)
-		},
+				let dir = unsafe { &mut *dirp.byte_add(offset) };
+
+				dir.d_ino = i.ino;
+				dir.d_type = num::ToPrimitive::to_u8(&i.file_type).unwrap();
+				dir.d_reclen = aligned_len.try_into().unwrap();
+				dir.d_off = i.offset.try_into().unwrap_or(i64::MAX);
+
+				// copy null-terminated filename
+				let s = ptr::from_mut(&mut dir.d_name).cast::<u8>();
+				unsafe {
+					core::ptr::copy_nonoverlapping(i.name.as_ptr(), s, len);
+					s.add(len).write_bytes(0, 1);
+				}
+
+				offset += aligned_len;
+				pos = i.offset;
+			}
+		}
+
+		obj.lseek(
+			pos.try_into().map_err(|_| io::Error::EOVERFLOW)?,
+			SeekWhence::Set,
+		)
+		.await?;
+		Ok(offset)
+	};
+
+	block_on(getdents, None).map_or_else(
+		|e| -num::ToPrimitive::to_i64(&e).unwrap(),
+		|offset| offset.try_into().unwrap(),
 	)
 }
 
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
 pub extern "C" fn sys_dup(fd: i32) -> i32 {
-	dup_object(fd).unwrap_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap())
+	dup_object(fd, 0, FdFlags::empty()).unwrap_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap())
 }
 
 #[hermit_macro::system]
@@ -691,6 +1584,219 @@ pub extern "C" fn sys_eventfd(initval: u64, flags: i16) -> i32 {
 	}
 }
 
+/// `pipe2` creates a pipe and stores the file descriptor of its read end in
+/// `fds[0]` and the one of its write end in `fds[1]`. `flags` may contain
+/// `O_NONBLOCK` and `O_CLOEXEC`.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_pipe2(fds: *mut [FileDescriptor; 2], flags: i32) -> i32 {
+	let (Some(fds), Some(flags)) = (unsafe { fds.as_mut() }, OpenOption::from_bits(flags)) else {
+		return -crate::errno::EINVAL;
+	};
+
+	crate::fd::pipe(flags).map_or_else(
+		|e| -num::ToPrimitive::to_i32(&e).unwrap(),
+		|(read_fd, write_fd)| {
+			*fds = [read_fd, write_fd];
+			0
+		},
+	)
+}
+
+/// `pipe` is like `pipe2` without flags
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_pipe(fds: *mut [FileDescriptor; 2]) -> i32 {
+	unsafe { sys_pipe2(fds, 0) }
+}
+
+/// `inotify_init1` creates an inotify instance and returns its file descriptor.
+/// `flags` may contain `IN_NONBLOCK` and `IN_CLOEXEC`.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_inotify_init1(flags: i32) -> i32 {
+	let Some(flags) = InotifyFlags::from_bits(flags) else {
+		return -crate::errno::EINVAL;
+	};
+
+	crate::fd::inotify_init(flags).unwrap_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap())
+}
+
+/// `inotify_add_watch` watches the events `mask` of the file `path` and
+/// returns the watch descriptor
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_inotify_add_watch(
+	fd: FileDescriptor,
+	path: *const c_char,
+	mask: u32,
+) -> i32 {
+	let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+		return -crate::errno::EINVAL;
+	};
+
+	crate::fd::inotify_add_watch(fd, path, mask)
+		.unwrap_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap())
+}
+
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_inotify_rm_watch(fd: FileDescriptor, wd: i32) -> i32 {
+	crate::fd::inotify_rm_watch(fd, wd)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+/// `epoll_create1` creates an epoll instance and returns its file descriptor.
+/// `flags` may contain `EPOLL_CLOEXEC`.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_epoll_create1(flags: i32) -> i32 {
+	let Some(flags) = EpollFlags::from_bits(flags) else {
+		return -crate::errno::EINVAL;
+	};
+
+	crate::fd::epoll_create(flags).unwrap_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap())
+}
+
+/// `epoll_create` is like `epoll_create1` without flags. `size` is ignored,
+/// but must be positive.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_epoll_create(size: i32) -> i32 {
+	if size <= 0 {
+		return -crate::errno::EINVAL;
+	}
+
+	sys_epoll_create1(0)
+}
+
+/// `epoll_ctl` adds (`EPOLL_CTL_ADD`), modifies (`EPOLL_CTL_MOD`) or removes
+/// (`EPOLL_CTL_DEL`) the entry of `fd` in the interest list of the epoll
+/// instance `epfd`. `event` is ignored by `EPOLL_CTL_DEL`.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_epoll_ctl(
+	epfd: FileDescriptor,
+	op: i32,
+	fd: FileDescriptor,
+	event: *mut EpollEvent,
+) -> i32 {
+	let Some(op) = num::FromPrimitive::from_i32(op) else {
+		return -crate::errno::EINVAL;
+	};
+	let event = if op == EpollOp::Del {
+		None
+	} else {
+		let Some(event) = (unsafe { event.as_ref() }) else {
+			return -crate::errno::EFAULT;
+		};
+		Some(*event)
+	};
+
+	crate::fd::epoll_ctl(epfd, op, fd, event)
+		.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+}
+
+/// `epoll_wait` waits for events of the epoll instance `epfd`, stores up to
+/// `maxevents` of them in `events` and returns their number. A negative
+/// `timeout` in milliseconds waits indefinitely.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_epoll_wait(
+	epfd: FileDescriptor,
+	events: *mut EpollEvent,
+	maxevents: i32,
+	timeout: i32,
+) -> i32 {
+	if maxevents <= 0 || events.is_null() {
+		return -crate::errno::EINVAL;
+	}
+
+	let slice = unsafe { core::slice::from_raw_parts_mut(events, maxevents.try_into().unwrap()) };
+	let timeout = if timeout >= 0 {
+		Some(core::time::Duration::from_millis(
+			timeout.try_into().unwrap(),
+		))
+	} else {
+		None
+	};
+
+	crate::fd::epoll_wait(epfd, slice, timeout).map_or_else(
+		|e| -num::ToPrimitive::to_i32(&e).unwrap(),
+		|v| v.try_into().unwrap(),
+	)
+}
+
+/// `timerfd_create` creates a timer and returns its file descriptor. `clock_id`
+/// is `CLOCK_MONOTONIC` or `CLOCK_REALTIME`. `flags` may contain
+/// `TFD_NONBLOCK` and `TFD_CLOEXEC`.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_timerfd_create(clock_id: clockid_t, flags: i32) -> i32 {
+	let clock = match clock_id {
+		CLOCK_MONOTONIC => TimerClock::Monotonic,
+		CLOCK_REALTIME => TimerClock::Realtime,
+		_ => return -crate::errno::EINVAL,
+	};
+	let Some(flags) = TimerFdFlags::from_bits(flags) else {
+		return -crate::errno::EINVAL;
+	};
+
+	crate::fd::timerfd_create(clock, flags)
+		.unwrap_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap())
+}
+
+/// `timerfd_settime` arms the timer `fd` with `new_value` or disarms it, if
+/// `new_value->it_value` is zero. The expiration time is absolute, if `flags`
+/// contains `TFD_TIMER_ABSTIME`. The previous setting is stored in
+/// `old_value`, if it isn't null.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_timerfd_settime(
+	fd: FileDescriptor,
+	flags: i32,
+	new_value: *const itimerspec,
+	old_value: *mut itimerspec,
+) -> i32 {
+	let Some(flags) = TimerSetFlags::from_bits(flags) else {
+		return -crate::errno::EINVAL;
+	};
+	let Some(new_value) = (unsafe { new_value.as_ref() }) else {
+		return -crate::errno::EFAULT;
+	};
+
+	crate::fd::timerfd_settime(fd, flags, *new_value).map_or_else(
+		|e| -num::ToPrimitive::to_i32(&e).unwrap(),
+		|old| {
+			if let Some(old_value) = unsafe { old_value.as_mut() } {
+				*old_value = old;
+			}
+			0
+		},
+	)
+}
+
+/// `timerfd_gettime` stores the interval of the timer `fd` and the time until
+/// its next expiration in `curr_value`
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_timerfd_gettime(
+	fd: FileDescriptor,
+	curr_value: *mut itimerspec,
+) -> i32 {
+	let Some(curr_value) = (unsafe { curr_value.as_mut() }) else {
+		return -crate::errno::EFAULT;
+	};
+
+	crate::fd::timerfd_gettime(fd).map_or_else(
+		|e| -num::ToPrimitive::to_i32(&e).unwrap(),
+		|value| {
+			*curr_value = value;
+			0
+		},
+	)
+}
+
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
 pub extern "C" fn sys_image_start_addr() -> usize {
@@ -6,27 +6,31 @@ use core::mem::size_of;
 #[allow(unused_imports)]
 use core::ops::DerefMut;
 
-use cfg_if::cfg_if;
 #[cfg(any(feature = "tcp", feature = "udp"))]
 use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
 use crate::errno::*;
 #[cfg(any(feature = "tcp", feature = "udp"))]
 use crate::executor::network::{NIC, NetworkState};
+#[cfg(feature = "unix")]
+use crate::fd::remove_object;
 #[cfg(feature = "tcp")]
 use crate::fd::socket::tcp;
 #[cfg(feature = "udp")]
 use crate::fd::socket::udp;
+#[cfg(feature = "unix")]
+use crate::fd::socket::unix::{self, UnixEndpoint};
 #[cfg(feature = "vsock")]
 use crate::fd::socket::vsock::{self, VsockEndpoint, VsockListenEndpoint};
 use crate::fd::{
-	Endpoint, ListenEndpoint, ObjectInterface, SocketOption, get_object, insert_object,
+	Endpoint, ListenEndpoint, ObjectInterface, OpenOption, SocketOption, get_object, insert_object,
 };
 use crate::syscalls::{IoCtl, block_on};
 
 pub const AF_INET: i32 = 0;
 pub const AF_INET6: i32 = 1;
 pub const AF_VSOCK: i32 = 2;
+pub const AF_UNIX: i32 = 3;
 pub const IPPROTO_IP: i32 = 0;
 pub const IPPROTO_IPV6: i32 = 41;
 pub const IPPROTO_TCP: i32 = 6;
@@ -80,6 +84,21 @@ bitflags! {
 	}
 }
 
+impl SockType {
+	/// Returns the flags of the file descriptor of a new socket
+	fn open_option(self) -> OpenOption {
+		let mut flags = OpenOption::O_RDWR;
+		if self.contains(SockType::SOCK_NONBLOCK) {
+			flags |= OpenOption::O_NONBLOCK;
+		}
+		if self.contains(SockType::SOCK_CLOEXEC) {
+			flags |= OpenOption::O_CLOEXEC;
+		}
+
+		flags
+	}
+}
+
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
 pub struct in_addr {
@@ -149,6 +168,74 @@ impl From<VsockEndpoint> for sockaddr_vm {
 	}
 }
 
+#[cfg(feature = "unix")]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct sockaddr_un {
+	pub sun_len: u8,
+	pub sun_family: sa_family_t,
+	pub sun_path: [c_char; 104],
+}
+
+/// Length of an unnamed Unix domain socket address
+#[cfg(feature = "unix")]
+const SUN_PATH_OFFSET: usize = core::mem::offset_of!(sockaddr_un, sun_path);
+
+/// Reads the Unix domain socket address `addr`, which consists of `len` bytes.
+/// A path ends with the first null byte and a name in the abstract namespace
+/// starts with a null byte.
+#[cfg(feature = "unix")]
+unsafe fn unix_endpoint_from(addr: *const sockaddr, len: socklen_t) -> Option<UnixEndpoint> {
+	let len = usize::try_from(len).ok()?;
+	if !(SUN_PATH_OFFSET..=size_of::<sockaddr_un>()).contains(&len) {
+		return None;
+	}
+
+	let path = unsafe {
+		core::slice::from_raw_parts(
+			addr.cast::<u8>().add(SUN_PATH_OFFSET),
+			len - SUN_PATH_OFFSET,
+		)
+	};
+	match path {
+		[] => Some(UnixEndpoint::Unnamed),
+		[0, name @ ..] => Some(UnixEndpoint::Abstract(name.to_vec())),
+		path => {
+			let end = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+			let path = core::str::from_utf8(&path[..end]).ok()?;
+			Some(UnixEndpoint::Path(path.into()))
+		}
+	}
+}
+
+/// Copies `endpoint` to `addr` and stores the length of the address in
+/// `addrlen`. As on Linux, the address is truncated, if `addrlen` is too small.
+#[cfg(feature = "unix")]
+unsafe fn unix_endpoint_to(endpoint: &UnixEndpoint, addr: *mut sockaddr, addrlen: &mut socklen_t) {
+	let mut sun = sockaddr_un {
+		sun_len: 0,
+		sun_family: AF_UNIX.try_into().unwrap(),
+		sun_path: [0; 104],
+	};
+
+	let (name, start, terminator): (&[u8], usize, usize) = match endpoint {
+		UnixEndpoint::Unnamed => (&[], 0, 0),
+		UnixEndpoint::Path(path) => (path.as_bytes(), 0, 1),
+		UnixEndpoint::Abstract(name) => (name, 1, 0),
+	};
+	for (dst, src) in sun.sun_path[start..].iter_mut().zip(name) {
+		*dst = *src as c_char;
+	}
+
+	let len = (SUN_PATH_OFFSET + start + name.len() + terminator).min(size_of::<sockaddr_un>());
+	sun.sun_len = len.try_into().unwrap();
+	let copied = len.min((*addrlen).try_into().unwrap());
+	unsafe {
+		core::ptr::copy_nonoverlapping((&raw const sun).cast::<u8>(), addr.cast::<u8>(), copied);
+	}
+	*addrlen = len.try_into().unwrap();
+}
+
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
 pub struct sockaddr_in {
@@ -422,6 +509,24 @@ pub extern "C" fn sys_socket(domain: i32, type_: SockType, protocol: i32) -> i32
 		return -EINVAL;
 	}
 
+	#[cfg(feature = "unix")]
+	if domain == AF_UNIX && type_.intersects(SockType::SOCK_STREAM | SockType::SOCK_DGRAM) {
+		let socket: Arc<dyn ObjectInterface> = if type_.contains(SockType::SOCK_DGRAM) {
+			Arc::new(async_lock::RwLock::new(unix::DatagramSocket::new()))
+		} else {
+			Arc::new(async_lock::RwLock::new(unix::Socket::new()))
+		};
+
+		if type_.contains(SockType::SOCK_NONBLOCK) {
+			block_on(socket.ioctl(IoCtl::NonBlocking, true), None).unwrap();
+		}
+
+		return match insert_object(socket, type_.open_option()) {
+			Ok(fd) => fd,
+			Err(e) => -num::ToPrimitive::to_i32(&e).unwrap(),
+		};
+	}
+
 	#[cfg(feature = "vsock")]
 	if domain == AF_VSOCK && type_.intersects(SockType::SOCK_STREAM) {
 		let socket = Arc::new(async_lock::RwLock::new(vsock::Socket::new()));
@@ -430,7 +535,7 @@ pub extern "C" fn sys_socket(domain: i32, type_: SockType, protocol: i32) -> i32
 			block_on(socket.ioctl(IoCtl::NonBlocking, true), None).unwrap();
 		}
 
-		let fd = insert_object(socket).expect("FD is already used");
+		let fd = insert_object(socket, type_.open_option()).expect("FD is already used");
 
 		return fd;
 	}
@@ -452,7 +557,7 @@ pub extern "C" fn sys_socket(domain: i32, type_: SockType, protocol: i32) -> i32
 					block_on(socket.ioctl(IoCtl::NonBlocking, true), None).unwrap();
 				}
 
-				let fd = insert_object(socket).expect("FD is already used");
+				let fd = insert_object(socket, type_.open_option()).expect("FD is already used");
 
 				return fd;
 			}
@@ -467,7 +572,7 @@ pub extern "C" fn sys_socket(domain: i32, type_: SockType, protocol: i32) -> i32
 					block_on(socket.ioctl(IoCtl::NonBlocking, true), None).unwrap();
 				}
 
-				let fd = insert_object(socket).expect("FD is already used");
+				let fd = insert_object(socket, type_.open_option()).expect("FD is already used");
 
 				return fd;
 			}
@@ -477,6 +582,68 @@ pub extern "C" fn sys_socket(domain: i32, type_: SockType, protocol: i32) -> i32
 	-EINVAL
 }
 
+/// `socketpair` creates two connected sockets and stores their file
+/// descriptors in `sv`. Only Unix domain sockets are supported.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_socketpair(
+	domain: i32,
+	type_: SockType,
+	protocol: i32,
+	sv: *mut [i32; 2],
+) -> i32 {
+	debug!(
+		"sys_socketpair: domain {}, type {:?}, protocol {}",
+		domain, type_, protocol
+	);
+
+	if sv.is_null() || protocol != 0 {
+		return -EINVAL;
+	}
+
+	#[cfg(feature = "unix")]
+	if domain == AF_UNIX && type_.intersects(SockType::SOCK_STREAM | SockType::SOCK_DGRAM) {
+		let (first, second): (Arc<dyn ObjectInterface>, Arc<dyn ObjectInterface>) =
+			if type_.contains(SockType::SOCK_DGRAM) {
+				let (first, second) = unix::DatagramSocket::pair();
+				(
+					Arc::new(async_lock::RwLock::new(first)),
+					Arc::new(async_lock::RwLock::new(second)),
+				)
+			} else {
+				let (first, second) = unix::Socket::pair();
+				(
+					Arc::new(async_lock::RwLock::new(first)),
+					Arc::new(async_lock::RwLock::new(second)),
+				)
+			};
+
+		if type_.contains(SockType::SOCK_NONBLOCK) {
+			block_on(first.ioctl(IoCtl::NonBlocking, true), None).unwrap();
+			block_on(second.ioctl(IoCtl::NonBlocking, true), None).unwrap();
+		}
+
+		let first_fd = match insert_object(first, type_.open_option()) {
+			Ok(fd) => fd,
+			Err(e) => return -num::ToPrimitive::to_i32(&e).unwrap(),
+		};
+		return match insert_object(second, type_.open_option()) {
+			Ok(second_fd) => {
+				unsafe {
+					sv.write([first_fd, second_fd]);
+				}
+				0
+			}
+			Err(e) => {
+				let _ = remove_object(first_fd);
+				-num::ToPrimitive::to_i32(&e).unwrap()
+			}
+		};
+	}
+
+	-EOPNOTSUPP
+}
+
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn sys_accept(fd: i32, addr: *mut sockaddr, addrlen: *mut socklen_t) -> i32 {
@@ -489,7 +656,7 @@ pub unsafe extern "C" fn sys_accept(fd: i32, addr: *mut sockaddr, addrlen: *mut
 				|(obj, endpoint)| match endpoint {
 					#[cfg(any(feature = "tcp", feature = "udp"))]
 					Endpoint::Ip(endpoint) => {
-						let new_fd = insert_object(obj).unwrap();
+						let new_fd = insert_object(obj, OpenOption::O_RDWR).unwrap();
 
 						if !addr.is_null() && !addrlen.is_null() {
 							let addrlen = unsafe { &mut *addrlen };
@@ -516,7 +683,7 @@ pub unsafe extern "C" fn sys_accept(fd: i32, addr: *mut sockaddr, addrlen: *mut
 					}
 					#[cfg(feature = "vsock")]
 					Endpoint::Vsock(endpoint) => {
-						let new_fd = insert_object(v.clone()).unwrap();
+						let new_fd = insert_object(v.clone(), OpenOption::O_RDWR).unwrap();
 
 						if !addr.is_null() && !addrlen.is_null() {
 							let addrlen = unsafe { &mut *addrlen };
@@ -528,6 +695,16 @@ pub unsafe extern "C" fn sys_accept(fd: i32, addr: *mut sockaddr, addrlen: *mut
 							}
 						}
 
+						new_fd
+					}
+					#[cfg(feature = "unix")]
+					Endpoint::Unix(endpoint) => {
+						let new_fd = insert_object(obj, OpenOption::O_RDWR).unwrap();
+
+						if !addr.is_null() && !addrlen.is_null() {
+							unsafe { unix_endpoint_to(&endpoint, addr, &mut *addrlen) };
+						}
+
 						new_fd
 					}
 				},
@@ -589,6 +766,14 @@ pub unsafe extern "C" fn sys_bind(fd: i32, name: *const sockaddr, namelen: sockl
 				block_on((*v).bind(ListenEndpoint::Vsock(endpoint)), None)
 					.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
 			}
+			#[cfg(feature = "unix")]
+			AF_UNIX => {
+				let Some(endpoint) = (unsafe { unix_endpoint_from(name, namelen) }) else {
+					return -crate::errno::EINVAL;
+				};
+				block_on((*v).bind(ListenEndpoint::Unix(endpoint)), None)
+					.map_or_else(|e| -num::ToPrimitive::to_i32(&e).unwrap(), |()| 0)
+			}
 			_ => -crate::errno::EINVAL,
 		},
 	)
@@ -625,6 +810,13 @@ pub unsafe extern "C" fn sys_connect(fd: i32, name: *const sockaddr, namelen: so
 			}
 			Endpoint::Vsock(VsockEndpoint::from(unsafe { *name.cast::<sockaddr_vm>() }))
 		}
+		#[cfg(feature = "unix")]
+		AF_UNIX => {
+			let Some(endpoint) = (unsafe { unix_endpoint_from(name, namelen) }) else {
+				return -crate::errno::EINVAL;
+			};
+			Endpoint::Unix(endpoint)
+		}
 		_ => {
 			return -crate::errno::EINVAL;
 		}
@@ -686,6 +878,10 @@ pub unsafe extern "C" fn sys_getsockname(
 								return -crate::errno::EINVAL;
 							}
 						}
+						#[cfg(feature = "unix")]
+						Endpoint::Unix(endpoint) => unsafe {
+							unix_endpoint_to(&endpoint, addr, addrlen);
+						},
 					}
 				} else {
 					return -crate::errno::EINVAL;
@@ -825,6 +1021,10 @@ pub unsafe extern "C" fn sys_getpeername(
 								return -crate::errno::EINVAL;
 							}
 						}
+						#[cfg(feature = "unix")]
+						Endpoint::Unix(endpoint) => unsafe {
+							unix_endpoint_to(&endpoint, addr, addrlen);
+						},
 					}
 				} else {
 					return -crate::errno::EINVAL;
@@ -904,35 +1104,37 @@ pub unsafe extern "C" fn sys_sendto(
 	addr: *const sockaddr,
 	addr_len: socklen_t,
 ) -> isize {
-	let endpoint;
-
 	if addr.is_null() || addr_len == 0 {
 		return (-crate::errno::EINVAL).try_into().unwrap();
 	}
 
-	cfg_if! {
-		if #[cfg(any(feature = "tcp", feature = "udp"))] {
-			let sa_family = unsafe { i32::from((*addr).sa_family) };
+	let sa_family = unsafe { i32::from((*addr).sa_family) };
 
-			if sa_family == AF_INET {
-				if addr_len < size_of::<sockaddr_in>().try_into().unwrap() {
-					return (-crate::errno::EINVAL).try_into().unwrap();
-				}
-
-				endpoint = Some(Endpoint::Ip(IpEndpoint::from(unsafe {*(addr.cast::<sockaddr_in>())})));
-			} else if sa_family == AF_INET6 {
-				if addr_len < size_of::<sockaddr_in6>().try_into().unwrap() {
-					return (-crate::errno::EINVAL).try_into().unwrap();
-				}
+	let endpoint = match sa_family {
+		#[cfg(any(feature = "tcp", feature = "udp"))]
+		AF_INET => {
+			if addr_len < size_of::<sockaddr_in>().try_into().unwrap() {
+				return (-crate::errno::EINVAL).try_into().unwrap();
+			}
 
-				endpoint = Some(Endpoint::Ip(IpEndpoint::from(unsafe { *(addr.cast::<sockaddr_in6>()) })));
-			} else {
-				endpoint = None;
+			Some(Endpoint::Ip(IpEndpoint::from(unsafe {
+				*(addr.cast::<sockaddr_in>())
+			})))
+		}
+		#[cfg(any(feature = "tcp", feature = "udp"))]
+		AF_INET6 => {
+			if addr_len < size_of::<sockaddr_in6>().try_into().unwrap() {
+				return (-crate::errno::EINVAL).try_into().unwrap();
 			}
-		} else {
-			endpoint = None;
+
+			Some(Endpoint::Ip(IpEndpoint::from(unsafe {
+				*(addr.cast::<sockaddr_in6>())
+			})))
 		}
-	}
+		#[cfg(feature = "unix")]
+		AF_UNIX => unsafe { unix_endpoint_from(addr, addr_len) }.map(Endpoint::Unix),
+		_ => None,
+	};
 
 	if let Some(endpoint) = endpoint {
 		let slice = unsafe { core::slice::from_raw_parts(buf, len) };
@@ -996,6 +1198,10 @@ pub unsafe extern "C" fn sys_recvfrom(
 									}
 								}
 							},
+							#[cfg(feature = "unix")]
+							Endpoint::Unix(endpoint) => unsafe {
+								unix_endpoint_to(&endpoint, addr, addrlen);
+							},
 							#[cfg(feature = "vsock")]
 							_ => {
 								return (-crate::errno::EINVAL).try_into().unwrap();
@@ -1009,3 +1215,96 @@ pub unsafe extern "C" fn sys_recvfrom(
 		},
 	)
 }
+
+#[cfg(all(test, not(target_os = "none"), feature = "unix"))]
+mod tests {
+	use super::*;
+
+	fn sockaddr(path: &[u8]) -> (sockaddr_un, socklen_t) {
+		let mut sun = sockaddr_un {
+			sun_len: 0,
+			sun_family: AF_UNIX.try_into().unwrap(),
+			sun_path: [0; 104],
+		};
+		for (dst, src) in sun.sun_path.iter_mut().zip(path) {
+			*dst = *src as c_char;
+		}
+		let len = (SUN_PATH_OFFSET + path.len()).try_into().unwrap();
+		(sun, len)
+	}
+
+	fn parse(path: &[u8]) -> Option<UnixEndpoint> {
+		let (sun, len) = sockaddr(path);
+		unsafe { unix_endpoint_from((&raw const sun).cast(), len) }
+	}
+
+	#[test]
+	fn sockaddr_un_from() {
+		assert_eq!(parse(b""), Some(UnixEndpoint::Unnamed));
+		assert_eq!(
+			parse(b"/tmp/socket\0"),
+			Some(UnixEndpoint::Path("/tmp/socket".into()))
+		);
+		// the path may be passed without terminator
+		assert_eq!(
+			parse(b"/tmp/socket"),
+			Some(UnixEndpoint::Path("/tmp/socket".into()))
+		);
+		assert_eq!(
+			parse(b"\0name\0"),
+			Some(UnixEndpoint::Abstract(b"name\0".to_vec()))
+		);
+		assert_eq!(parse(&[0xff, 0xfe]), None);
+
+		let (sun, _) = sockaddr(b"/tmp/socket");
+		let too_long = (size_of::<sockaddr_un>() + 1).try_into().unwrap();
+		assert_eq!(
+			unsafe { unix_endpoint_from((&raw const sun).cast(), too_long) },
+			None
+		);
+		let too_short = (SUN_PATH_OFFSET - 1).try_into().unwrap();
+		assert_eq!(
+			unsafe { unix_endpoint_from((&raw const sun).cast(), too_short) },
+			None
+		);
+	}
+
+	#[test]
+	fn sockaddr_un_to() {
+		let endpoint = UnixEndpoint::Path("/tmp/socket".into());
+		let mut sun = sockaddr(b"").0;
+		let mut len: socklen_t = size_of::<sockaddr_un>().try_into().unwrap();
+		unsafe { unix_endpoint_to(&endpoint, (&raw mut sun).cast(), &mut len) };
+		assert_eq!(usize::try_from(len).unwrap(), SUN_PATH_OFFSET + 12);
+		assert_eq!(
+			unsafe { unix_endpoint_from((&raw const sun).cast(), len) },
+			Some(endpoint)
+		);
+
+		let endpoint = UnixEndpoint::Abstract(b"name".to_vec());
+		let mut len: socklen_t = size_of::<sockaddr_un>().try_into().unwrap();
+		unsafe { unix_endpoint_to(&endpoint, (&raw mut sun).cast(), &mut len) };
+		assert_eq!(usize::try_from(len).unwrap(), SUN_PATH_OFFSET + 5);
+		assert_eq!(
+			unsafe { unix_endpoint_from((&raw const sun).cast(), len) },
+			Some(endpoint)
+		);
+
+		// the address is truncated, but the full length is returned
+		let endpoint = UnixEndpoint::Path("/tmp/socket".into());
+		let mut sun = sockaddr(b"").0;
+		let mut len: socklen_t = (SUN_PATH_OFFSET + 4).try_into().unwrap();
+		unsafe { unix_endpoint_to(&endpoint, (&raw mut sun).cast(), &mut len) };
+		assert_eq!(usize::try_from(len).unwrap(), SUN_PATH_OFFSET + 12);
+		assert_eq!(
+			sun.sun_path[..5],
+			[
+				b'/' as c_char,
+				b't' as c_char,
+				b'm' as c_char,
+				b'p' as c_char,
+				0
+			]
+		);
+	}
+}
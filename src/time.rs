@@ -66,6 +66,13 @@ impl timespec {
 	}
 }
 
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct itimerspec {
+	pub it_interval: timespec,
+	pub it_value: timespec,
+}
+
 #[derive(Copy, Clone, Debug, Default)]
 pub struct SystemTime(timespec);
 